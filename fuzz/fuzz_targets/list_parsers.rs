@@ -0,0 +1,22 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde::de::value::Error;
+use serde::de::Visitor;
+
+use recipe_app::parser;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(input) = std::str::from_utf8(data) {
+        let _ = parser::parse_python_list(input);
+        let _: Result<Vec<String>, Error> = parser::StringArrayVisitor.visit_str(input);
+        let _: Result<Vec<f32>, Error> = parser::FloatArrayVisitor::default().visit_str(input);
+        let decimal_comma = parser::FloatArrayVisitor {
+            options: parser::FloatOptions {
+                decimal_comma: true,
+                ..Default::default()
+            },
+        };
+        let _: Result<Vec<f32>, Error> = decimal_comma.visit_str(input);
+    }
+});
@@ -0,0 +1,306 @@
+use crate::cancel::CancellationToken;
+use crate::derived::{self, DerivedProperty};
+use crate::server::Server;
+use crate::settings::Settings;
+use crate::views;
+use neo4rs::{Graph, Query};
+use std::error::Error;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+/// Number of tags kept in `User.favorite_tags`.
+const FAVORITE_TAGS: i64 = 5;
+
+/// Stores per-user rating statistics on User nodes: how many recipes they
+/// rated, their average rating and the tags they rate most often. Returns
+/// the number of users updated.
+pub async fn users(
+    graph: &Graph,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_id: i64 = i64::MIN;
+    let mut updated = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(
+            "MATCH (u:User) WHERE u.id > $after \
+             WITH u ORDER BY u.id LIMIT $limit \
+             OPTIONAL MATCH (u)-[x:RATED]->(:Recipe) \
+             WITH u, count(x) AS ratings_count, avg(x.rating) AS avg_rating \
+             OPTIONAL MATCH (u)-[:RATED]->(:Recipe)-[:TAGGED]->(t:Tag) \
+             WITH u, ratings_count, avg_rating, t.name AS tag, count(t) AS uses \
+             ORDER BY uses DESC \
+             WITH u, ratings_count, avg_rating, \
+                  [tag IN collect(tag) WHERE tag IS NOT NULL][..$favorites] AS favorite_tags \
+             SET u.ratings_count = ratings_count, u.avg_rating = avg_rating, \
+                 u.favorite_tags = favorite_tags \
+             RETURN max(u.id) AS last_id, count(u) AS users"
+                .to_string(),
+        )
+        .param("after", last_id)
+        .param("limit", batch_size as i64)
+        .param("favorites", FAVORITE_TAGS);
+
+        let mut result = graph.execute(query).await?;
+        let Some(row) = result.next().await? else {
+            break;
+        };
+        let users: i64 = row.get("users")?;
+        if users == 0 {
+            break;
+        }
+
+        last_id = row.get("last_id")?;
+        updated += users as usize;
+        println!("analyzed {} users", updated);
+    }
+
+    Ok(updated)
+}
+
+/// Stores on every Ingredient node how many recipes contain it, so "top
+/// ingredients" reads one property instead of counting relationships.
+/// Returns the number of ingredients updated.
+pub async fn ingredient_counts(
+    graph: &Graph,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_name = String::new();
+    let mut updated = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(
+            "MATCH (i:Ingredient) WHERE i.name > $after \
+             WITH i ORDER BY i.name LIMIT $limit \
+             SET i.recipe_count = size([(:Recipe)-[:CONTAINS]->(i) | 1]) \
+             RETURN max(i.name) AS last_name, count(i) AS ingredients"
+                .to_string(),
+        )
+        .param("after", last_name.clone())
+        .param("limit", batch_size as i64);
+
+        let mut result = graph.execute(query).await?;
+        let Some(row) = result.next().await? else {
+            break;
+        };
+        let ingredients: i64 = row.get("ingredients")?;
+        if ingredients == 0 {
+            break;
+        }
+
+        last_name = row.get("last_name")?;
+        updated += ingredients as usize;
+        println!("counted recipes for {} ingredients", updated);
+    }
+
+    Ok(updated)
+}
+
+/// Days in a trending window written as `90d`, `12w` or plain days.
+pub fn window_days(text: &str) -> Result<u32, String> {
+    let invalid = || {
+        format!(
+            "invalid window: {} (expected days like 90d or weeks like 12w)",
+            text
+        )
+    };
+    let (number, scale) = match text.strip_suffix('w') {
+        Some(weeks) => (weeks, 7),
+        None => (text.strip_suffix('d').unwrap_or(text), 1),
+    };
+    match number.parse::<u32>() {
+        Ok(days) if days > 0 => days.checked_mul(scale).ok_or_else(invalid),
+        _ => Err(invalid()),
+    }
+}
+
+/// Stores on every Recipe a time-decayed popularity score: each rating in
+/// the `window_days` before `as_of` (YYYY-MM-DD) counts 1 when made that
+/// day and less the older it is, down to 0 at the window's start. Recipes
+/// with none score 0, so a rerun clears old scores. Ratings with no
+/// readable date are left out. Returns the number of recipes updated.
+pub async fn trending(
+    graph: &Graph,
+    window_days: u32,
+    as_of: &str,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_id: i64 = i64::MIN;
+    let mut updated = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(
+            "MATCH (r:Recipe) WHERE r.id > $after \
+             WITH r ORDER BY r.id LIMIT $limit \
+             OPTIONAL MATCH (:User)-[x:RATED]->(r) \
+             WHERE x.date =~ '\\d{4}-\\d{2}-\\d{2}.*' \
+             WITH r, duration.inDays(date(left(x.date, 10)), date($as_of)).days AS age \
+             WITH r, sum(CASE WHEN 0 <= age < $window \
+                              THEN 1.0 - toFloat(age) / $window ELSE 0.0 END) AS score \
+             SET r.trending_score = score, r.trending_as_of = $as_of \
+             RETURN max(r.id) AS last_id, count(r) AS recipes"
+                .to_string(),
+        )
+        .param("after", last_id)
+        .param("limit", batch_size as i64)
+        .param("window", window_days as i64)
+        .param("as_of", as_of);
+
+        let mut result = graph.execute(query).await?;
+        let Some(row) = result.next().await? else {
+            break;
+        };
+        let recipes: i64 = row.get("recipes")?;
+        if recipes == 0 {
+            break;
+        }
+
+        last_id = row.get("last_id")?;
+        updated += recipes as usize;
+        println!("scored {} recipes", updated);
+    }
+
+    Ok(updated)
+}
+
+/// A whole-graph analytics pass that reads and writes nothing another pass
+/// depends on, so any set of them can run at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Pass {
+    Users,
+    IngredientCounts,
+    Views,
+    Backfill(DerivedProperty),
+}
+
+impl Pass {
+    pub fn name(&self) -> String {
+        match self {
+            Pass::Users => "users".to_string(),
+            Pass::IngredientCounts => "ingredient counts".to_string(),
+            Pass::Views => "views".to_string(),
+            Pass::Backfill(property) => format!("backfill {}", property.name()),
+        }
+    }
+
+    /// Runs the pass, returning how many nodes it updated.
+    pub async fn run(
+        self,
+        graph: &Graph,
+        server: &Server,
+        settings: &Settings,
+        batch_size: usize,
+        cancel: &CancellationToken,
+    ) -> Result<usize, Box<dyn Error>> {
+        match self {
+            Pass::Users => users(graph, batch_size, cancel).await,
+            Pass::IngredientCounts => ingredient_counts(graph, batch_size, cancel).await,
+            Pass::Views => views::materialize(graph, server, batch_size, cancel).await,
+            Pass::Backfill(property) => {
+                derived::backfill(graph, server, settings, property, batch_size, cancel).await
+            }
+        }
+    }
+}
+
+/// Every pass `analyze all` runs.
+pub fn passes() -> Vec<Pass> {
+    let mut passes = vec![Pass::Users, Pass::IngredientCounts, Pass::Views];
+    passes.extend(derived::ALL.iter().copied().map(Pass::Backfill));
+    passes
+}
+
+#[derive(Debug)]
+pub struct PassReport {
+    pub pass: Pass,
+    /// Nodes updated, or why the pass failed
+    pub outcome: Result<usize, String>,
+    pub elapsed: Duration,
+}
+
+/// Prints a table of `reports`, then fails if any pass did.
+pub fn print_reports(reports: &[PassReport]) -> Result<(), Box<dyn Error>> {
+    println!("{:<28} {:>10} {:>10}", "pass", "updated", "seconds");
+    let mut failed = 0;
+    for report in reports {
+        let seconds = report.elapsed.as_secs_f64();
+        match &report.outcome {
+            Ok(updated) => println!(
+                "{:<28} {:>10} {:>10.1}",
+                report.pass.name(),
+                updated,
+                seconds
+            ),
+            Err(err) => {
+                failed += 1;
+                println!(
+                    "{:<28} {:>10} {:>10.1}  {}",
+                    report.pass.name(),
+                    "failed",
+                    seconds,
+                    err
+                );
+            }
+        }
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} passes failed", failed, reports.len()).into());
+    }
+    Ok(())
+}
+
+/// Runs `passes` at most `jobs` at a time over clones of `graph`, which
+/// share its connection pool. A failed pass does not stop the others, but
+/// cancelling `cancel` stops them all at their next batch; the
+/// reports come back in the order the passes were given.
+pub async fn all(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    passes: &[Pass],
+    jobs: usize,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Vec<PassReport> {
+    let permits = Arc::new(Semaphore::new(jobs.max(1)));
+    let mut running = JoinSet::new();
+
+    for (index, pass) in passes.iter().copied().enumerate() {
+        let graph = graph.clone();
+        let (server, settings) = (server.clone(), settings.clone());
+        let permits = permits.clone();
+        let cancel = cancel.clone();
+        running.spawn(async move {
+            let _permit = permits.acquire_owned().await;
+            let started = Instant::now();
+            let outcome = pass
+                .run(&graph, &server, &settings, batch_size, &cancel)
+                .await
+                .map_err(|err| err.to_string());
+            let report = PassReport {
+                pass,
+                outcome,
+                elapsed: started.elapsed(),
+            };
+            (index, report)
+        });
+    }
+
+    let mut reports: Vec<(usize, PassReport)> = Vec::with_capacity(passes.len());
+    while let Some(joined) = running.join_next().await {
+        match joined {
+            Ok(report) => reports.push(report),
+            Err(err) => panic!("analytics pass panicked: {}", err),
+        }
+    }
+    reports.sort_by_key(|(index, _)| *index);
+    reports.into_iter().map(|(_, report)| report).collect()
+}
@@ -0,0 +1,278 @@
+#[cfg(feature = "neo4j")]
+use crate::cancel::CancellationToken;
+use crate::settings::Settings;
+use crate::Recipe;
+#[cfg(feature = "neo4j")]
+use crate::{export, ingest, mapping, names, schema};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use ring::digest::{Context, SHA256};
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+const BLOCK: usize = 512;
+
+/// Name of the checksum list inside the archive, in `sha256sum` format so
+/// `sha256sum -c MANIFEST.sha256` verifies an extracted copy.
+pub const MANIFEST: &str = "MANIFEST.sha256";
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+fn sha256(bytes: &[u8]) -> String {
+    hex(ring::digest::digest(&SHA256, bytes).as_ref())
+}
+
+/// Hashes everything written through it.
+struct HashingWriter<W> {
+    inner: W,
+    context: Context,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.context.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes `value` as a NUL-terminated octal field filling `field`.
+fn octal(field: &mut [u8], value: u64) {
+    let digits = format!("{:0width$o}", value, width = field.len() - 1);
+    field[..digits.len()].copy_from_slice(digits.as_bytes());
+}
+
+/// A ustar header for a regular file. Owner, group and mtime are zeroed so
+/// the same contents always produce the same archive bytes.
+fn header(name: &str, size: u64) -> Result<[u8; BLOCK], Box<dyn Error>> {
+    if name.len() > 100 {
+        return Err(format!("archive entry name too long: {}", name).into());
+    }
+    let mut header = [0u8; BLOCK];
+    header[..name.len()].copy_from_slice(name.as_bytes());
+    octal(&mut header[100..108], 0o644);
+    octal(&mut header[108..116], 0);
+    octal(&mut header[116..124], 0);
+    octal(&mut header[124..136], size);
+    octal(&mut header[136..148], 0);
+    header[156] = b'0';
+    header[257..263].copy_from_slice(b"ustar\0");
+    header[263..265].copy_from_slice(b"00");
+
+    // The checksum is computed with its own field read as spaces
+    header[148..156].copy_from_slice(b"        ");
+    let checksum: u64 = header.iter().map(|byte| u64::from(*byte)).sum();
+    let digits = format!("{:06o}\0 ", checksum);
+    header[148..156].copy_from_slice(digits.as_bytes());
+    Ok(header)
+}
+
+fn append(
+    tar: &mut dyn Write,
+    name: &str,
+    source: &mut dyn Read,
+    size: u64,
+) -> Result<(), Box<dyn Error>> {
+    tar.write_all(&header(name, size)?)?;
+    let copied = io::copy(source, tar)?;
+    if copied != size {
+        return Err(format!("{} changed while archiving", name).into());
+    }
+    let padding = (BLOCK - (size as usize % BLOCK)) % BLOCK;
+    tar.write_all(&vec![0; padding])?;
+    Ok(())
+}
+
+fn open_archive(path: &Path) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    let name = path.to_string_lossy();
+    if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        let file = BufWriter::new(File::create(path)?);
+        Ok(Box::new(GzEncoder::new(file, Compression::default())))
+    } else if name.ends_with(".tar") {
+        Ok(Box::new(BufWriter::new(File::create(path)?)))
+    } else {
+        // zstd is not among the crate's dependencies
+        Err(format!("archive must end in .tar.gz, .tgz or .tar: {}", name).into())
+    }
+}
+
+/// What `archive create` bundles and where.
+#[cfg(feature = "neo4j")]
+pub struct ArchiveOptions {
+    /// Recipe CSV, `-` for stdin
+    pub input: String,
+    pub out: PathBuf,
+    /// Column mapping the input is read through, bundled as `mapping.toml`
+    pub mapping: Option<PathBuf>,
+    pub names: names::NameCleanup,
+    /// `--name-style` and `--name-fillers` as given, for `ingest.json`
+    pub name_style: String,
+    pub name_fillers: Option<String>,
+}
+
+/// Reads and normalizes the recipes in `options.input` as `export` does,
+/// then bundles them with the schema, an `ingest.json` recording how they
+/// were read and the mapping, if any. Returns the manifest.
+#[cfg(feature = "neo4j")]
+pub fn create_from_input(
+    options: &ArchiveOptions,
+    settings: &Settings,
+    cancel: &CancellationToken,
+) -> Result<String, Box<dyn Error>> {
+    let mapping = match &options.mapping {
+        Some(path) => Some(mapping::ColumnMapping::load(path)?),
+        None => None,
+    };
+    let ingest = serde_json::json!({
+        "tool": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "input": options.input,
+        "input_sha256": match options.input.as_str() {
+            "-" => None,
+            path => Some(file_sha256(Path::new(path))?),
+        },
+        "mapping": options.mapping,
+        "name_style": options.name_style,
+        "name_fillers": options.name_fillers,
+        "nutrition_storage": settings.precision.storage.name(),
+        "nutrition_layout": settings.layout.name(),
+        "precision": settings.precision.digits,
+    });
+    let mut files = vec![
+        ("schema.md", schema::model().to_markdown().into_bytes()),
+        ("ingest.json", serde_json::to_vec_pretty(&ingest)?),
+    ];
+    if let Some(mapping) = &mapping {
+        files.push((
+            "mapping.toml",
+            toml::to_string_pretty(mapping)?.into_bytes(),
+        ));
+    }
+
+    let recipes = ingest::read_recipes(&options.input, mapping.as_ref(), settings)?.map(|result| {
+        cancel.check()?;
+        let mut recipe = result?;
+        export::normalize(&mut recipe, &options.names);
+        Ok(recipe)
+    });
+    create(&options.out, recipes, &files, settings)
+}
+
+/// Bundles the normalized recipes as `recipes.jsonl` with the small
+/// metadata `files` (name and contents) into a tar archive at `out`, ending
+/// with a SHA-256 manifest of every entry. Recipes are written with the
+/// precision and nutrition storage of `settings`. Returns the manifest.
+pub fn create(
+    out: &Path,
+    recipes: impl Iterator<Item = Result<Recipe, Box<dyn Error>>>,
+    files: &[(&str, Vec<u8>)],
+    settings: &Settings,
+) -> Result<String, Box<dyn Error>> {
+    // The tar header needs the size up front, so the corpus is staged on
+    // disk rather than held in memory
+    let staged = PathBuf::from(format!("{}.recipes.partial", out.display()));
+    let result = write_archive(out, &staged, recipes, files, settings);
+    let _ = fs::remove_file(&staged);
+    if result.is_err() {
+        let _ = fs::remove_file(out);
+    }
+    result
+}
+
+fn write_archive(
+    out: &Path,
+    staged: &Path,
+    recipes: impl Iterator<Item = Result<Recipe, Box<dyn Error>>>,
+    files: &[(&str, Vec<u8>)],
+    settings: &Settings,
+) -> Result<String, Box<dyn Error>> {
+    let mut tar = open_archive(out)?;
+    let mut jsonl = HashingWriter {
+        inner: BufWriter::new(File::create(staged)?),
+        context: Context::new(&SHA256),
+    };
+    for recipe in recipes {
+        let recipe = recipe?;
+        settings.scope(|| serde_json::to_writer(&mut jsonl, &recipe))?;
+        jsonl.write_all(b"\n")?;
+    }
+    jsonl.flush()?;
+    let mut manifest = format!("{}  recipes.jsonl\n", hex(jsonl.context.finish().as_ref()));
+
+    let size = fs::metadata(staged)?.len();
+    append(
+        &mut tar,
+        "recipes.jsonl",
+        &mut BufReader::new(File::open(staged)?),
+        size,
+    )?;
+    for (name, contents) in files {
+        manifest.push_str(&format!("{}  {}\n", sha256(contents), name));
+        append(
+            &mut tar,
+            name,
+            &mut contents.as_slice(),
+            contents.len() as u64,
+        )?;
+    }
+    append(
+        &mut tar,
+        MANIFEST,
+        &mut manifest.as_bytes(),
+        manifest.len() as u64,
+    )?;
+    // End of archive: two empty blocks
+    tar.write_all(&[0; 2 * BLOCK])?;
+    tar.flush()?;
+    Ok(manifest)
+}
+
+/// SHA-256 of a file's contents, read in chunks.
+pub fn file_sha256(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut context = Context::new(&SHA256);
+    let mut buffer = [0u8; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        context.update(&buffer[..read]);
+    }
+    Ok(hex(context.finish().as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::precision::{Precision, Storage};
+
+    #[test]
+    fn recipes_are_written_with_the_run_precision() {
+        let mut recipe = crate::testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.nutrition = vec![51.04, 0.0, 2.0];
+        let settings = Settings {
+            precision: Precision {
+                storage: Storage::Fixed,
+                digits: Some(1),
+            },
+            ..Settings::default()
+        };
+        let dir = std::env::temp_dir().join(format!("archive-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let out = dir.join("precision.tar");
+
+        create(&out, std::iter::once(Ok(recipe)), &[], &settings).unwrap();
+
+        let tar = String::from_utf8_lossy(&fs::read(&out).unwrap()).into_owned();
+        assert!(tar.contains(r#""nutrition":[510,0,20]"#), "{}", tar);
+    }
+}
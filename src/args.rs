@@ -0,0 +1,1120 @@
+use clap::parser::MatchesError;
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
+use recipe_app::edgelist::Projection;
+use recipe_app::export::ExportOptions;
+use recipe_app::ingest::{LoadOptions, SpellingOptions};
+use recipe_app::output::{self, Format};
+use recipe_app::settings::Settings;
+use recipe_app::{
+    analyze, audit, brands, config, conflicts, derived, hours, ingredient, keys, mapping, migrate,
+    ml, names, nutrition, parser, precision, pricing, query, scrub, searches, sink, truncation,
+    verify,
+};
+use std::any::Any;
+use std::error::Error;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// The parsed command line: the subcommand path and its positional values
+/// (`["run", "report", "<id>"]`), and the options of the innermost
+/// subcommand, global ones included, as their value parsers typed them.
+#[derive(Debug)]
+pub struct Args {
+    positional: Vec<String>,
+    matches: ArgMatches,
+    /// Options for those not given here, from a pipeline's [global] table
+    defaults: Option<Box<Args>>,
+}
+
+fn option(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name).long(name).value_name("VALUE").help(help)
+}
+
+fn switch(name: &'static str, help: &'static str) -> Arg {
+    Arg::new(name)
+        .long(name)
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
+fn input(help: &'static str) -> Arg {
+    option("input", help).value_name("PATH")
+}
+
+/// An input file given as a positional argument, interchangeable with
+/// `--input`.
+fn input_path() -> Arg {
+    Arg::new("path")
+        .value_name("INPUT")
+        .conflicts_with("input")
+        .help("Recipe CSV to read, `-` for stdin [default: data/RAW_recipes.csv]")
+}
+
+fn window() -> [Arg; 2] {
+    [
+        option("skip", "Skip the first N recipes of the input")
+            .value_parser(value_parser!(usize))
+            .value_name("N"),
+        option("limit", "Read at most N recipes after --skip")
+            .value_parser(value_parser!(usize))
+            .value_name("N"),
+    ]
+}
+
+fn names() -> [Arg; 2] {
+    [
+        option("name-style", "Display name casing: title, sentence or raw")
+            .value_parser(value_parser!(names::NameStyle)),
+        option(
+            "name-fillers",
+            "Comma-separated extra prefixes to strip from display names",
+        ),
+    ]
+}
+
+/// Options shared by `load` and `validate`, which clean recipes the same way.
+fn cleaning() -> Vec<Arg> {
+    let mut args = vec![
+        input_path(),
+        input("Recipe CSV to read, `-` for stdin"),
+        option("mapping", "Column mapping written by `init`").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+        option(
+            "overflow",
+            "What to do with over-long text: truncate, reject or externalize",
+        ).value_parser(value_parser!(truncation::OverflowPolicy)),
+        option("overflow-dir", "Directory for externalized text").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+        option("max-name", "Longest recipe name kept").value_parser(value_parser!(usize)).value_name("CHARS"),
+        option("max-description", "Longest description kept").value_parser(value_parser!(usize)).value_name("CHARS"),
+        option("max-step", "Longest step kept").value_parser(value_parser!(usize)).value_name("CHARS"),
+        switch(
+            "spell-correct",
+            "Correct rare ingredient spellings towards common ones",
+        ),
+        option(
+            "spell-confidence",
+            "Minimum confidence for a spelling correction",
+        ).value_parser(value_parser!(f64)),
+        option(
+            "spell-whitelist",
+            "Words the spell checker must leave alone",
+        ).value_parser(value_parser!(PathBuf))
+        .value_name("PATH"),
+        option("prices", "Price list for estimated costs").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+        option("warnings-out", "Write cleanup warnings as JSON lines").value_name("PATH"),
+        switch("no-step-repair", "Keep fragmented steps as they are"),
+        option(
+            "scrub",
+            "Redact descriptions and steps: comma-separated email, url, phone, name, profanity, or all",
+        ).value_parser(scrub::categories)
+        .value_name("CATEGORIES"),
+        option(
+            "scrub-patterns",
+            "Extra scrubbing patterns by category, as TOML",
+        ).value_parser(value_parser!(PathBuf))
+        .value_name("PATH")
+        .requires("scrub"),
+        option(
+            "on-duplicate-id",
+            "first-wins, last-wins, error or merge-fields [default: first-wins]",
+        ).value_parser(value_parser!(conflicts::ConflictPolicy)),
+        switch("strict", "Stop at the first row that fails instead of skipping it"),
+        option(
+            "error-report",
+            "Where skipped rows are listed, as .json or .csv [default: <runs-dir>/<run>.errors.json for load]",
+        )
+        .value_name("PATH"),
+    ];
+    args.extend(window());
+    args.extend(names());
+    args
+}
+
+/// `--algorithms`: comma-separated `ml::Algorithm` names.
+fn algorithms(list: &str) -> Result<Vec<ml::Algorithm>, String> {
+    list.split(',').map(|name| name.trim().parse()).collect()
+}
+
+/// A YYYY-MM-DD date, checked and kept as written.
+fn date(text: &str) -> Result<String, String> {
+    query::check_date(text)?;
+    Ok(text.to_string())
+}
+
+/// The "Examples:" section `--help` prints after a subcommand's options,
+/// one invocation per line.
+fn examples(lines: &[&str]) -> String {
+    let mut text = String::from("Examples:");
+    for line in lines {
+        text.push_str(&format!("\n  {} {}", env!("CARGO_PKG_NAME"), line));
+    }
+    text
+}
+
+/// Every command and option the binary accepts.
+pub fn command() -> Command {
+    let batch_size = || {
+        option(
+            "batch-size",
+            "Records per graph transaction [default: from the profile]",
+        )
+        .value_parser(value_parser!(usize))
+        .value_name("N")
+    };
+
+    Command::new(env!("CARGO_PKG_NAME"))
+        .version(env!("CARGO_PKG_VERSION"))
+        .about("Loads the Food.com recipe dump into Neo4j and analyzes it")
+        .subcommand_required(true)
+        .arg_required_else_help(true)
+        .args(
+            [
+                option("config", "Config file with connection profiles").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                option("profile", "Profile to use from the config file").value_name("NAME"),
+                option(
+                    "uri",
+                    "Neo4j URI, overriding the profile [default: bolt://localhost:7687]",
+                )
+                .env("NEO4J_URI"),
+                option("user", "Neo4j user, overriding the profile").env("NEO4J_USER"),
+                option(
+                    "password",
+                    "Neo4j password, overriding the profile and keyring",
+                )
+                .env("NEO4J_PASSWORD")
+                .hide_env_values(true),
+                option(
+                    "timeout",
+                    "Stop long-running commands after this many seconds",
+                ).value_parser(value_parser!(f64))
+                .value_name("SECONDS"),
+                option("nan", "NaN nutrition values: missing or reject").value_parser(value_parser!(parser::NanPolicy)),
+                switch("decimal-comma", "Read numbers written with a decimal comma"),
+                option("brands", "Brand lexicon for ingredient cleanup").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                option(
+                    "ingredient-rules",
+                    "Plural exceptions, aliases and descriptors for ingredient names",
+                ).value_parser(value_parser!(PathBuf))
+                .value_name("PATH"),
+                option("nutrition-storage", "Store nutrition as float or fixed").value_parser(value_parser!(precision::Storage)),
+                option(
+                    "nutrition-layout",
+                    "named: require 7 nutrition values and store each by name; raw: keep any array",
+                ).value_parser(value_parser!(nutrition::Layout)),
+                option("precision", "Decimal digits kept for nutrition").value_parser(value_parser!(u32)).value_name("DIGITS"),
+                option(
+                    "id-namespace",
+                    "Namespace for generated node keys, overriding the profile [default: food.com]",
+                )
+                .value_name("NAME"),
+            ]
+            .map(|arg| arg.global(true)),
+        )
+        .subcommand(
+            Command::new("load")
+                .visible_alias("ingest")
+                .about("Clean recipes and write them to the graph")
+                .after_long_help(examples(&["load data/RAW_recipes.csv", "load --profile prod --checkpoint load.ckpt --workers 4", "load --limit 1000 --dry-run --compare", "load --output jsonl --out recipes.jsonl"]))
+                .args(cleaning())
+                .args([
+                    option(
+                        "type-policy",
+                        "On graph property type conflicts: fail or coerce",
+                    ).value_parser(value_parser!(audit::TypePolicy)),
+                    option("runs-dir", "Directory for run logs").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    switch(
+                        "materialize-views",
+                        "Refresh the materialized views after loading",
+                    ),
+                    batch_size(),
+                    option(
+                        "output",
+                        "Where recipes go: neo4j, or jsonl or cypher to write a file instead",
+                    ).value_parser(value_parser!(sink::Output)),
+                    option(
+                        "out",
+                        "File for --output jsonl or cypher, `-` for stdout \
+                         [default: recipes.jsonl or recipes.cypher]",
+                    )
+                    .value_name("PATH"),
+                    switch(
+                        "steps-as-nodes",
+                        "Write steps as ordered Step nodes instead of a list property",
+                    ),
+                    switch(
+                        "single-statement",
+                        "Send each batch as one Cypher statement, one round trip per batch",
+                    ),
+                    option(
+                        "workers",
+                        "Batches written at once, each on its own connection",
+                    ).value_parser(value_parser!(usize))
+                    .value_name("N"),
+                    option(
+                        "stall-timeout",
+                        "Reconnect and resume from the checkpoint when no batch commits for this many minutes",
+                    ).value_parser(value_parser!(f64))
+                    .value_name("MINUTES"),
+                    option(
+                        "checkpoint",
+                        "Record committed rows here and skip them when rerun; rows of a batch \
+                         that failed are skipped too once a later batch commits, and are \
+                         listed in the error report",
+                    ).value_parser(value_parser!(PathBuf))
+                    .value_name("PATH"),
+                    switch("dry-run", "Write nothing; needs --compare"),
+                    switch(
+                        "compare",
+                        "With --dry-run, count by label what the load would create, update or leave unchanged in the graph",
+                    ),
+                    option(
+                        "active-hours",
+                        "Only write during this daily window, in UTC, pausing between batches outside it",
+                    ).value_parser(value_parser!(hours::ActiveHours))
+                    .value_name("HH:MM-HH:MM"),
+                    switch(
+                        "profile-pipeline",
+                        "Print the time spent reading, parsing, normalizing, serializing and committing",
+                    ),
+                    option(
+                        "autocomplete-index",
+                        "Also write a name index for `query autocomplete`",
+                    )
+                    .value_name("PATH"),
+                ]),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Parse and clean recipes without writing to the graph")
+                .after_long_help(examples(&["validate data/RAW_recipes.csv", "validate --strict --warnings-out warnings.jsonl"]))
+                .args(cleaning()),
+        )
+        .subcommand(
+            Command::new("export")
+                .about("Write normalized recipes as JSON lines, CSV, Markdown or an edge list")
+                .after_long_help(examples(&["export --format csv --out recipes.csv", "export --format edgelist --projection ingredient-ingredient --weighted", "export --format voice --recipe-id 137739 --out -"]))
+                .args([
+                    input_path(),
+                    input("Recipe CSV to read, `-` for stdin"),
+                    option(
+                        "format",
+                        "jsonl, csv, markdown, cypher, cypher-stream, graphml, voice, csr, \
+                         edgelist, nutrition-label or nutrition-label-svg",
+                    ).value_parser(value_parser!(Format)),
+                    option(
+                        "projection",
+                        "Network for edgelist: recipe-ingredient, ingredient-ingredient or \
+                         user-recipe, read from interactions [default: recipe-ingredient]",
+                    ).value_parser(value_parser!(Projection)),
+                    switch(
+                        "weighted",
+                        "Give edgelist edges a weight: lines, shared recipes or the rating",
+                    ),
+                    option("recipe-id", "Export only this recipe").value_parser(value_parser!(i32)).value_name("ID"),
+                    option(
+                        "transaction-size",
+                        "Statements per :begin/:commit block with cypher-stream [default: 1000]",
+                    ).value_parser(value_parser!(usize))
+                    .value_name("N"),
+                    option("out", "Output file, `-` for stdout").value_name("PATH"),
+                    switch("compress", "Gzip the output"),
+                ])
+                .args(window())
+                .args(names()),
+        )
+        .subcommand(
+            Command::new("quickstart")
+                .about("Load 200 sample recipes into an empty database and suggest queries")
+                .after_long_help(examples(&["quickstart", "quickstart --batch-size 50"]))
+                .arg(batch_size()),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Propose a column mapping for a new CSV")
+                .after_long_help(examples(&["init --from new_recipes.csv --out mapping.toml", "init --from new_recipes.csv --yes"]))
+                .args([
+                    option("from", "CSV to inspect").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    option("out", "Where to write the mapping").value_name("PATH"),
+                    switch("yes", "Accept the proposal without asking"),
+                ]),
+        )
+        .subcommand(
+            Command::new("auth")
+                .about("Manage the Neo4j password in the OS keyring")
+                .after_long_help(examples(&["auth login", "auth logout"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("login").about("Save a password read from stdin"))
+                .subcommand(Command::new("logout").about("Remove the saved password")),
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Describe the graph model")
+                .after_long_help(examples(&["schema describe --format mermaid"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("describe").arg(option("format", "markdown or mermaid"))),
+        )
+        .subcommand(
+            Command::new("load-interactions")
+                .about("Load user reviews and ratings")
+                .after_long_help(examples(&["load-interactions data/RAW_interactions.csv --checkpoint interactions.ckpt"]))
+                .args([
+                    input("Interactions CSV [default: data/RAW_interactions.csv]"),
+                    option("checkpoint", "Resume file").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    batch_size(),
+                    switch(
+                        "create-missing-recipes",
+                        "Create placeholder recipes for unknown recipe ids instead of skipping",
+                    ),
+                ]),
+        )
+        .subcommand(
+            Command::new("ml")
+                .about("Train/test splits and recommender evaluation")
+                .after_long_help(examples(&["ml split --by user --test-fraction 0.2 --seed 7 --train train.csv --test test.csv", "ml evaluate --train train.csv --test test.csv --k 10"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("split").args([
+                    input("Interactions CSV [default: data/RAW_interactions.csv]"),
+                    option(
+                        "by",
+                        "Hold out whole users or random interactions: user or random",
+                    ).value_parser(value_parser!(ml::SplitBy)),
+                    option("test-fraction", "Share of interactions held out").value_parser(value_parser!(f64)),
+                    option("seed", "Random seed").value_parser(value_parser!(u64)),
+                    option("train", "Train split output").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    option("test", "Test split output").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    switch("label-graph", "Mark held-out reviews in the graph"),
+                    batch_size(),
+                ]))
+                .subcommand(Command::new("evaluate").args([
+                    option("train", "Train split").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    option("test", "Test split").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    option("k", "Recommendations scored per user").value_parser(value_parser!(usize)),
+                    option("min-rating", "Lowest rating counted as relevant").value_parser(value_parser!(i32)),
+                    option("seed", "Random seed").value_parser(value_parser!(u64)),
+                    option("algorithms", "Comma-separated algorithms to compare").value_parser(algorithms),
+                ])),
+        )
+        .subcommand(
+            Command::new("run")
+                .about("Inspect recorded load runs")
+                .after_long_help(examples(&["run report load-1718000000-4242-0"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("report").args([
+                    Arg::new("run-id").value_name("RUN_ID").required(true),
+                    option("runs-dir", "Directory of run logs").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    option("out", "Output file, `-` for stdout").value_name("PATH"),
+                ])),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Check the graph against its source")
+                .after_long_help(examples(&["graph capabilities", "graph verify --sample 1%", "graph repair --dedupe-relationships --dry-run"]))
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("capabilities")
+                        .about("Show the server's version, edition and the features used"),
+                )
+                .subcommand(Command::new("verify").args([
+                    input("Recipe CSV the graph was loaded from"),
+                    option("sample", "Share of recipes to check, such as 1%").value_parser(verify::parse_fraction),
+                    option("delay-ms", "Pause between batches").value_parser(value_parser!(u64)).value_name("MS"),
+                    batch_size(),
+                ]))
+                .subcommand(
+                    Command::new("repair")
+                        .about("Fix problems older loads left in the graph")
+                        .args([
+                            switch(
+                                "dedupe-relationships",
+                                "Collapse parallel relationships of one type between the same nodes",
+                            ),
+                            switch("dry-run", "Count what would change without changing it"),
+                            batch_size(),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("analyze")
+                .about("Analytics passes over the graph or the input")
+                .after_long_help(examples(&["analyze all --jobs 2", "analyze trending --window 4w", "analyze suggest-tags --out suggestions.csv"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("users").arg(batch_size()))
+                .subcommand(
+                    Command::new("trending")
+                        .about("Score recipes by recent ratings, older ones counting less")
+                        .args([
+                            option("window", "Ratings counted, as days or weeks [default: 90d]").value_parser(analyze::window_days)
+                                .value_name("DURATION"),
+                            option(
+                                "as-of",
+                                "Day to score for, as YYYY-MM-DD [default: today]",
+                            ).value_parser(date)
+                            .value_name("DATE"),
+                            batch_size(),
+                        ]),
+                )
+                .subcommand(Command::new("all").args([
+                    option("jobs", "Passes run at once").value_parser(value_parser!(usize)).value_name("N"),
+                    batch_size(),
+                ]))
+                .subcommand(
+                    Command::new("quantity-norms").args([
+                        input("Recipe CSV to read, `-` for stdin"),
+                        option("min-recipes", "Fewest recipes an ingredient needs a norm").value_parser(value_parser!(usize))
+                            .value_name("N"),
+                        option("tolerance", "Flag amounts this many times off the median").value_parser(value_parser!(f64)),
+                        option("out", "Norms CSV output, `-` for stdout").value_name("PATH"),
+                        option("anomalies-out", "Anomalies as JSON lines").value_name("PATH"),
+                    ]),
+                )
+                .subcommand(
+                    Command::new("suggest-tags").args([
+                        input("Recipe CSV to read, `-` for stdin"),
+                        option("max-tags", "Suggest for recipes with this many tags or fewer").value_parser(value_parser!(usize))
+                            .value_name("N"),
+                        option(
+                            "min-support",
+                            "Tagged recipes an ingredient or title word needs to count",
+                        ).value_parser(value_parser!(u32))
+                        .value_name("N"),
+                        option("min-confidence", "Lowest confidence suggested, 0 to 1").value_parser(value_parser!(f64)),
+                        option("per-recipe", "Most suggestions per recipe").value_parser(value_parser!(usize)).value_name("N"),
+                        option("out", "Suggestions CSV output, `-` for stdout").value_name("PATH"),
+                        switch("graph", "Also write SUGGESTED_TAG relationships"),
+                        batch_size(),
+                    ]),
+                )
+                .subcommand(
+                    Command::new("components")
+                        .about("Find ingredients that are other recipes in the corpus")
+                        .args([
+                            input("Recipe CSV to read, `-` for stdin"),
+                            option("out", "Components CSV output, `-` for stdout")
+                                .value_name("PATH"),
+                            switch("graph", "Also write USES_COMPONENT relationships"),
+                            batch_size(),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("descriptors")
+                        .about("Suggest descriptor words to drop from ingredient names")
+                        .args([
+                            input("Recipe CSV to read, `-` for stdin"),
+                            option("out", "Stoplist TOML output, `-` for stdout")
+                                .value_name("PATH"),
+                            option(
+                                "min-heads",
+                                "Distinct ingredients a word must come before [default: 20]",
+                            ).value_parser(value_parser!(usize))
+                            .value_name("N"),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("materialize")
+                .about("Precompute graph views")
+                .after_long_help(examples(&["materialize views"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("views").arg(batch_size())),
+        )
+        .subcommand(
+            Command::new("query")
+                .about("Look up recipes")
+                .after_long_help(examples(&["query random --tag vegetarian --max-minutes 30", "query by-ingredient chicken garlic", "query save quick-dinner --filter \"tag:main-dish max-minutes:30\""]))
+                .subcommand_required(true)
+                .subcommand(Command::new("random").args([
+                    input("Recipe CSV to read, `-` for stdin"),
+                    option("tag", "Only recipes with this tag"),
+                    option("max-minutes", "Only recipes this quick").value_parser(value_parser!(i32)),
+                    option(
+                        "max-cost",
+                        "Only recipes this cheap per serving, or in all when servings are \
+                         unknown; needs --prices",
+                    ).value_parser(value_parser!(f64)),
+                    option("prices", "Price list for estimated costs").value_parser(value_parser!(PathBuf)).value_name("PATH"),
+                    switch("beginner-friendly", "Only easy-to-follow recipes"),
+                    option("count", "Recipes to pick").value_parser(value_parser!(usize)).value_name("N"),
+                    option("seed", "Random seed").value_parser(value_parser!(u64)),
+                    switch("json", "Print recipes as JSON"),
+                ]))
+                .subcommand(
+                    Command::new("autocomplete")
+                        .about("Complete a recipe name from its first letters")
+                        .args([
+                            Arg::new("prefix").value_name("PREFIX").required(true),
+                            option("index", "Index written by `load --autocomplete-index`").value_parser(value_parser!(PathBuf))
+                                .value_name("PATH"),
+                            input("Recipe CSV to index when there is no --index")
+                                .conflicts_with("index"),
+                            option("count", "Completions to show").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print completions as JSON"),
+                        ])
+                        .args(names()),
+                )
+                .subcommand(
+                    Command::new("by-ingredient")
+                        .about("Recipes in the graph containing every ingredient named")
+                        .args([
+                            Arg::new("ingredients")
+                                .value_name("INGREDIENT")
+                                .help("Ingredients the recipe must contain")
+                                .action(ArgAction::Append)
+                                .required(true),
+                            option("max-minutes", "Only recipes this quick").value_parser(value_parser!(i64)),
+                            option("count", "Recipes to show [default: 20]").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print recipes as JSON"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("similar")
+                        .about("Recipes in the graph sharing the most ingredients with one")
+                        .args([
+                            Arg::new("recipe_id").value_name("RECIPE_ID").required(true),
+                            option("count", "Recipes to show [default: 20]").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print recipes as JSON"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("rotd")
+                        .about("The recipe of the day, the same on every run for a date")
+                        .args([
+                            option("date", "Day to pick for, as YYYY-MM-DD [default: today]").value_parser(date)
+                                .value_name("DATE"),
+                            option("tag", "Only recipes with this tag"),
+                            option("max-minutes", "Only recipes this quick").value_parser(value_parser!(i64)),
+                            switch("json", "Print the recipe as JSON"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("save")
+                        .about("Save a search under a name, replacing any saved before")
+                        .args([
+                            Arg::new("name").value_name("NAME").required(true),
+                            option(
+                                "filter",
+                                "Terms such as \"tag:healthy max-minutes:30 ingredient:chicken\"; \
+                                 also max-calories and min-rating",
+                            ).value_parser(value_parser!(searches::Filter))
+                            .required(true),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Run a saved search")
+                        .args([
+                            Arg::new("name").value_name("NAME").required(true),
+                            option("count", "Recipes to show [default: 20]").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print recipes as JSON"),
+                        ]),
+                )
+                .subcommand(Command::new("searches").about("List saved searches"))
+                .subcommand(
+                    Command::new("trending")
+                        .about("Recipes rated most recently, from analyze trending")
+                        .args([
+                            option("tag", "Only recipes with this tag"),
+                            option("count", "Recipes to show [default: 20]").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print recipes as JSON"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("nutrition")
+                        .about("Recipes in the graph within a calorie budget")
+                        .args([
+                            option("max-calories", "Most calories per serving").value_parser(value_parser!(f32)).required(true),
+                            option("count", "Recipes to show [default: 20]").value_parser(value_parser!(usize)).value_name("N"),
+                            switch("json", "Print recipes as JSON"),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("fixtures")
+                .about("Synthetic test data")
+                .after_long_help(examples(&["fixtures generate --count 500 --seed 1 --out fixtures.csv"]))
+                .subcommand_required(true)
+                .subcommand(Command::new("generate").args([
+                    option("count", "Recipes to generate").value_parser(value_parser!(usize)).value_name("N"),
+                    option("seed", "Random seed").value_parser(value_parser!(u64)),
+                    option("out", "Output file, `-` for stdout").value_name("PATH"),
+                ])),
+        )
+        .subcommand(
+            Command::new("archive")
+                .about("Reproducible dataset snapshots")
+                .after_long_help(examples(&["archive create --out snapshot.tar.gz data/RAW_recipes.csv"]))
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("create")
+                        .args([
+                            option("out", "Archive to write: .tar.gz, .tgz or .tar")
+                                .value_name("PATH")
+                                .required(true),
+                            input("Recipe CSV to read, `-` for stdin"),
+                            option("mapping", "Column mapping written by `init`").value_parser(value_parser!(PathBuf))
+                                .value_name("PATH"),
+                        ])
+                        .args(names()),
+                ),
+        )
+        .subcommand(
+            Command::new("enrich")
+                .about("Attach external data to graph nodes")
+                .after_long_help(examples(&["enrich wikidata --rate 1", "enrich pp-recipes data/PP_recipes.csv"]))
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("wikidata")
+                        .about("Link Ingredient nodes to Wikidata items")
+                        .args([
+                            option("cache", "Lookup cache [default: wikidata_cache.json]").value_parser(value_parser!(PathBuf))
+                                .value_name("PATH"),
+                            option("rate", "Most API requests per second [default: 1]").value_parser(value_parser!(f64)),
+                            switch("refresh", "Look up every ingredient again"),
+                            batch_size(),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("pp-recipes")
+                        .about("Add technique and calorie-level data from PP_recipes.csv")
+                        .args([
+                            input_path(),
+                            input("Preprocessed recipes CSV [default: data/PP_recipes.csv]"),
+                            batch_size(),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("translate")
+                .about("Translate recipe names, descriptions and steps into another language")
+                .after_long_help(examples(&["translate --to es", "translate --to fr --command ./translate.sh"]))
+                .args([
+                    option("to", "Language to translate into, such as es")
+                        .value_name("LANG")
+                        .required(true),
+                    option("from", "Language the recipes are in [default: en]")
+                        .value_name("LANG"),
+                    option(
+                        "url",
+                        "LibreTranslate server [default: http://localhost:5000]",
+                    )
+                    .value_name("URL"),
+                    option("api-key", "LibreTranslate API key")
+                        .env("LIBRETRANSLATE_API_KEY")
+                        .hide_env_values(true),
+                    option(
+                        "command",
+                        "Translate with this shell command instead: JSON {from, to, texts} \
+                         on stdin, a JSON array of translations on stdout",
+                    )
+                    .value_name("COMMAND")
+                    .conflicts_with_all(["url", "api-key"]),
+                    option("cache", "Translation cache [default: translation_cache.json]").value_parser(value_parser!(PathBuf))
+                        .value_name("PATH"),
+                    switch("refresh", "Translate recipes that already have a translation"),
+                    batch_size(),
+                ]),
+        )
+        .subcommand(
+            Command::new("migrate")
+                .about("Rewrite the graph from one model version to another in place")
+                .after_long_help(examples(&["migrate --from-model v2 --to-model v3"]))
+                .args([
+                    option(
+                        "from-model",
+                        "Model the graph is at: v1 (nutrition array), v2 (named nutrition) \
+                         or v3 (surrogate keys)",
+                    ).value_parser(value_parser!(migrate::Model))
+                    .value_name("VERSION")
+                    .required(true),
+                    option("to-model", "Model to migrate to [default: v3]").value_parser(value_parser!(migrate::Model)).value_name("VERSION"),
+                    batch_size(),
+                ]),
+        )
+        .subcommand(
+            Command::new("pipeline")
+                .about("Run multi-step loads declared in a file")
+                .after_long_help(examples(&["pipeline run nightly.toml --dry-run"]))
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("run")
+                        .about("Run each load and enricher of a pipeline file in order")
+                        .args([
+                            Arg::new("pipeline")
+                                .value_name("PATH")
+                                .help("Pipeline TOML")
+                                .required(true),
+                            switch("dry-run", "Print the commands without running them"),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("backfill")
+                .about("Compute a derived property for recipes missing it")
+                .after_long_help(examples(&["backfill --property difficulty"]))
+                .args([
+                    option("property", "Property to compute").value_parser(value_parser!(derived::DerivedProperty)).required(true),
+                    batch_size(),
+                ]),
+        )
+}
+
+impl Args {
+    fn new(command: &Command, matches: &ArgMatches) -> Args {
+        let mut positional = Vec::new();
+        let mut command = command;
+        let mut matches = matches;
+        loop {
+            for arg in command.get_positionals() {
+                if let Some(values) = matches.get_many::<String>(arg.get_id().as_str()) {
+                    positional.extend(values.cloned());
+                }
+            }
+            match matches.subcommand() {
+                Some((name, inner)) => {
+                    positional.push(name.to_string());
+                    command = command
+                        .find_subcommand(name)
+                        .expect("clap only matches defined subcommands");
+                    matches = inner;
+                }
+                None => break,
+            }
+        }
+        Args {
+            positional,
+            matches: matches.clone(),
+            defaults: None,
+        }
+    }
+
+    /// Parses the process arguments, printing usage and exiting on errors
+    /// and `--help`.
+    pub fn from_env() -> Args {
+        let mut command = command();
+        let matches = command.get_matches_mut();
+        Args::new(&command, &matches)
+    }
+
+    /// Parses `args`, the program name first, returning usage errors as
+    /// text instead of exiting.
+    pub fn try_parse_from(args: impl IntoIterator<Item = String>) -> Result<Args, String> {
+        let mut command = command();
+        let matches = command
+            .try_get_matches_from_mut(args)
+            .map_err(|err| err.to_string().trim_end().to_string())?;
+        Ok(Args::new(&command, &matches))
+    }
+
+    /// Takes the options of `other` that were not given here.
+    pub fn with_defaults(mut self, other: Args) -> Args {
+        self.defaults = Some(Box::new(other));
+        self
+    }
+
+    pub fn positional(&self) -> Vec<&str> {
+        self.positional.iter().map(String::as_str).collect()
+    }
+
+    /// `--name` as given here, or `None` when it was not or this command
+    /// has no such option. Asking for another type than the option's value
+    /// parser gives is a bug and panics, as `ArgMatches::get_one` does.
+    fn given<T: Any + Clone + Send + Sync + 'static>(&self, name: &str) -> Option<&T> {
+        match self.matches.try_get_one::<T>(name) {
+            Ok(value) => value,
+            Err(MatchesError::UnknownArgument { .. }) => None,
+            Err(err) => panic!("--{}: {}", name, err),
+        }
+    }
+
+    fn lookup<T: Any + Clone + Send + Sync + 'static>(&self, name: &str) -> Option<&T> {
+        self.given(name)
+            .or_else(|| self.defaults.as_deref()?.lookup(name))
+    }
+
+    /// The value of the option `name`, typed by its value parser.
+    pub fn get<T: Any + Clone + Send + Sync + 'static>(&self, name: &str) -> Option<T> {
+        self.lookup(name).cloned()
+    }
+
+    /// The value of a text option.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.lookup::<String>(name).map(String::as_str)
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.given::<bool>(name) == Some(&true)
+            || self.defaults.as_ref().is_some_and(|other| other.flag(name))
+    }
+
+    /// The run's settings from the global options and the profile they
+    /// select. `--uri`, `--user` and `--password` (or their environment
+    /// variables) win over the profile.
+    pub fn settings(&self) -> Result<Settings, Box<dyn Error>> {
+        let mut profile = config::load(
+            &self
+                .get::<PathBuf>("config")
+                .unwrap_or_else(|| "recipe_app.toml".into()),
+            self.text("profile"),
+        )?;
+        for (name, field) in [
+            ("uri", &mut profile.uri),
+            ("user", &mut profile.user),
+            ("password", &mut profile.password),
+        ] {
+            if let Some(value) = self.get(name) {
+                *field = Some(value);
+            }
+        }
+        let namespace = self
+            .text("id-namespace")
+            .or(profile.id_namespace.as_deref())
+            .unwrap_or(keys::DEFAULT_NAMESPACE)
+            .to_string();
+        let mut settings = Settings {
+            profile,
+            floats: parser::FloatOptions {
+                nan: self.get("nan").unwrap_or_default(),
+                decimal_comma: self.flag("decimal-comma"),
+            },
+            layout: self.get("nutrition-layout").unwrap_or_default(),
+            precision: precision::Precision {
+                storage: self.get("nutrition-storage").unwrap_or_default(),
+                digits: self.get("precision"),
+            },
+            namespace,
+            ..Settings::default()
+        };
+        if let Some(path) = self.get::<PathBuf>("brands") {
+            settings.lexicon = brands::Lexicon::load(&path)?;
+        }
+        if let Some(path) = self.get::<PathBuf>("ingredient-rules") {
+            settings.rules = ingredient::Rules::load(&path)?;
+        }
+        Ok(settings)
+    }
+
+    /// `--batch-size`, else the profile's batch size, else `default`.
+    pub fn batch_size(&self, settings: &Settings, default: usize) -> usize {
+        self.get("batch-size")
+            .or(settings.profile.batch_size)
+            .unwrap_or(default)
+    }
+
+    /// `--skip` and `--limit` as the number of input recipes to pass over
+    /// and the most to read after them.
+    pub fn window(&self) -> (usize, usize) {
+        (
+            self.get("skip").unwrap_or(0),
+            self.get("limit").unwrap_or(usize::MAX),
+        )
+    }
+
+    /// `--input`, else `default`.
+    pub fn input<'a>(&'a self, default: &'a str) -> &'a str {
+        self.text("input").unwrap_or(default)
+    }
+
+    /// `--out`, else stdout.
+    pub fn sink(&self) -> Result<Box<dyn std::io::Write>, Box<dyn Error>> {
+        output::open_sink(self.text("out").unwrap_or("-"), false)
+    }
+
+    pub fn names(&self) -> names::NameCleanup {
+        let fillers: Vec<String> = self
+            .text("name-fillers")
+            .map(|list| list.split(',').map(str::to_string).collect())
+            .unwrap_or_default();
+        names::NameCleanup::new(self.get("name-style").unwrap_or_default(), &fillers)
+    }
+
+    pub fn prices(
+        &self,
+        settings: &Settings,
+    ) -> Result<Option<pricing::PriceList>, Box<dyn Error>> {
+        match self.get::<PathBuf>("prices") {
+            Some(path) => Ok(Some(pricing::PriceList::load(&path, settings)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// `load` and `validate`'s options, reading `input` unless it is
+    /// `None`.
+    pub fn load_options(
+        &self,
+        input: Option<&str>,
+        settings: &Settings,
+    ) -> Result<LoadOptions, Box<dyn Error>> {
+        let mapping = match self.get::<PathBuf>("mapping") {
+            Some(path) => Some(mapping::ColumnMapping::load(&path)?),
+            None => None,
+        };
+        let mut policy = self.get("overflow").unwrap_or_default();
+        if let (truncation::OverflowPolicy::Externalize(dir), Some(path)) =
+            (&mut policy, self.get("overflow-dir"))
+        {
+            *dir = path;
+        }
+        let truncation = truncation::TruncationConfig {
+            max_name: self.get("max-name"),
+            max_description: self.get("max-description"),
+            max_step: self.get("max-step"),
+            policy,
+        };
+
+        let spelling = self.flag("spell-correct").then(|| SpellingOptions {
+            confidence: self.get("spell-confidence").unwrap_or(0.95),
+            whitelist: self.get("spell-whitelist"),
+        });
+
+        let scrub = match self.get::<Vec<scrub::Category>>("scrub") {
+            Some(categories) => Some(match self.get::<PathBuf>("scrub-patterns") {
+                Some(path) => scrub::Scrubber::load(&categories, &path)?,
+                None => scrub::Scrubber::new(&categories),
+            }),
+            None => None,
+        };
+
+        let (skip, limit) = self.window();
+        Ok(LoadOptions {
+            input: input
+                .unwrap_or(self.input("data/RAW_recipes.csv"))
+                .to_string(),
+            skip,
+            limit,
+            mapping,
+            truncation,
+            prices: self.prices(settings)?,
+            warnings_out: self.get("warnings-out"),
+            type_policy: self.get("type-policy").unwrap_or_default(),
+            runs_dir: self.get("runs-dir").unwrap_or_else(|| "runs".into()),
+            spelling,
+            scrub,
+            repair_steps: !self.flag("no-step-repair"),
+            conflicts: self.get("on-duplicate-id").unwrap_or_default(),
+            names: self.names(),
+            materialize_views: self.flag("materialize-views"),
+            batch_size: self.batch_size(settings, 500),
+            steps_as_nodes: self.flag("steps-as-nodes"),
+            single_statement: self.flag("single-statement"),
+            workers: self.get("workers").unwrap_or(1),
+            stall_timeout: self
+                .get("stall-timeout")
+                .map(|minutes: f64| Duration::from_secs_f64(minutes * 60.0)),
+            profile_pipeline: self.flag("profile-pipeline"),
+            strict: self.flag("strict"),
+            error_report: self.get("error-report"),
+            checkpoint: self.get("checkpoint"),
+            active_hours: self.get::<hours::ActiveHours>("active-hours"),
+            compare: match (self.flag("dry-run"), self.flag("compare")) {
+                (true, true) => true,
+                (false, false) => false,
+                _ => return Err("--dry-run and --compare are only used together".into()),
+            },
+            output: self.get::<sink::Output>("output").unwrap_or_default(),
+            out: self.get("out"),
+            autocomplete_index: self.get("autocomplete-index"),
+        })
+    }
+
+    /// `export`'s options, reading `input` unless it is `None`.
+    pub fn export_options(&self, input: Option<&str>) -> Result<ExportOptions, Box<dyn Error>> {
+        let format = self.get("format").unwrap_or(Format::Jsonl);
+        let projection = match (format, self.get("projection")) {
+            (Format::Edgelist, projection) => projection.unwrap_or(Projection::RecipeIngredient),
+            (_, Some(_)) => return Err("--projection needs --format edgelist".into()),
+            (_, None) => Projection::RecipeIngredient,
+        };
+        let input = input.unwrap_or(self.input(projection.default_input()));
+        let (skip, limit) = self.window();
+        Ok(ExportOptions {
+            input: input.into(),
+            format,
+            projection,
+            weighted: self.flag("weighted"),
+            transaction_size: self.get("transaction-size"),
+            skip,
+            limit,
+            recipe_id: self.get("recipe-id"),
+            names: self.names(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Args, String> {
+        Args::try_parse_from(
+            std::iter::once(env!("CARGO_PKG_NAME"))
+                .chain(line.split_whitespace())
+                .map(str::to_string),
+        )
+    }
+
+    #[test]
+    fn command_definition_is_consistent() {
+        command().debug_assert();
+    }
+
+    #[test]
+    fn options_are_typed_by_their_value_parsers() {
+        let args = parse("query random --count 3 --max-minutes 30 --tag quick --json").unwrap();
+        assert_eq!(args.positional(), ["query", "random"]);
+        assert_eq!(args.get::<usize>("count"), Some(3));
+        assert_eq!(args.get::<i32>("max-minutes"), Some(30));
+        assert_eq!(args.text("tag"), Some("quick"));
+        assert!(args.flag("json"));
+        // Options another command defines are absent here
+        assert_eq!(args.get::<usize>("workers"), None);
+        assert!(!args.flag("dry-run"));
+
+        let args = parse("ml evaluate --algorithms popularity,random").unwrap();
+        assert_eq!(
+            args.get::<Vec<ml::Algorithm>>("algorithms"),
+            Some(vec![ml::Algorithm::Popularity, ml::Algorithm::Random])
+        );
+    }
+
+    #[test]
+    fn invalid_values_are_usage_errors() {
+        for line in [
+            "query random --count many",
+            "export --format pdf",
+            "load --on-duplicate-id newest",
+            "analyze trending --window forever",
+            "query rotd --date 2024-13-40",
+        ] {
+            assert!(parse(line).is_err(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn defaults_fill_options_not_given() {
+        let global = parse("--profile prod --decimal-comma --precision 2 validate").unwrap();
+        let args = parse("--precision 3 load").unwrap().with_defaults(global);
+        assert_eq!(args.text("profile"), Some("prod"));
+        assert!(args.flag("decimal-comma"));
+        assert_eq!(args.get::<u32>("precision"), Some(3));
+    }
+
+    #[test]
+    fn load_options_read_the_typed_values() {
+        let args = parse(
+            "load in.csv --skip 10 --limit 5 --workers 2 --stall-timeout 0.5 \
+             --overflow externalize --overflow-dir long --name-style raw",
+        )
+        .unwrap();
+        let options = args
+            .load_options(Some("in.csv"), &Settings::default())
+            .unwrap();
+        assert_eq!(options.input, "in.csv");
+        assert_eq!((options.skip, options.limit), (10, 5));
+        assert_eq!(options.workers, 2);
+        assert_eq!(options.stall_timeout, Some(Duration::from_secs(30)));
+        assert_eq!(
+            options.truncation.policy,
+            truncation::OverflowPolicy::Externalize("long".into())
+        );
+        assert_eq!(options.names.style, names::NameStyle::Raw);
+        assert_eq!(options.batch_size, 500);
+    }
+}
@@ -0,0 +1,160 @@
+use neo4rs::{BoltFloat, BoltInteger, BoltList, BoltString, BoltType, Graph, Query};
+use std::collections::HashMap;
+use std::error::Error;
+use std::str::FromStr;
+
+/// Nodes sampled when reading the types already in the graph.
+const SAMPLE: i64 = 100;
+
+/// What to do when an incoming property's type differs from the type the
+/// same property already has in the graph.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum TypePolicy {
+    /// Stop the load before writing a mixed-type property
+    #[default]
+    Fail,
+    /// Convert incoming values to the graph's type where that is lossless
+    Coerce,
+}
+
+impl FromStr for TypePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "fail" => Ok(TypePolicy::Fail),
+            "coerce" => Ok(TypePolicy::Coerce),
+            other => Err(format!("unknown type policy: {}", other)),
+        }
+    }
+}
+
+/// The type name of a value, or `None` where it says nothing about the
+/// property's type (nulls and empty lists).
+fn kind(value: &BoltType) -> Option<String> {
+    match value {
+        BoltType::String(_) => Some("string".to_string()),
+        BoltType::Boolean(_) => Some("boolean".to_string()),
+        BoltType::Integer(_) => Some("integer".to_string()),
+        BoltType::Float(_) => Some("float".to_string()),
+        BoltType::List(list) => list
+            .value
+            .iter()
+            .find_map(kind)
+            .map(|inner| format!("list<{}>", inner)),
+        BoltType::Null(_) => None,
+        _ => Some("other".to_string()),
+    }
+}
+
+fn coerce(value: BoltType, target: &str) -> Option<BoltType> {
+    match (value, target) {
+        (BoltType::Null(null), _) => Some(BoltType::Null(null)),
+        (BoltType::Integer(int), "string") => {
+            Some(BoltType::String(BoltString::from(int.value.to_string())))
+        }
+        (BoltType::Float(float), "string") => {
+            Some(BoltType::String(BoltString::from(float.value.to_string())))
+        }
+        (BoltType::Integer(int), "float") => {
+            Some(BoltType::Float(BoltFloat::new(int.value as f64)))
+        }
+        (BoltType::Float(float), "integer") if float.value.fract() == 0.0 => {
+            Some(BoltType::Integer(BoltInteger::new(float.value as i64)))
+        }
+        (BoltType::String(string), "integer") => string
+            .value
+            .trim()
+            .parse::<i64>()
+            .ok()
+            .map(|value| BoltType::Integer(BoltInteger::new(value))),
+        (BoltType::String(string), "float") => string
+            .value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .map(|value| BoltType::Float(BoltFloat::new(value))),
+        (BoltType::List(list), target) => {
+            let inner = target.strip_prefix("list<")?.strip_suffix('>')?;
+            let items = list
+                .value
+                .into_iter()
+                .map(|item| coerce(item, inner))
+                .collect::<Option<Vec<_>>>()?;
+            Some(BoltType::List(BoltList::from(items)))
+        }
+        (value, target) if kind(&value).as_deref() == Some(target) => Some(value),
+        _ => None,
+    }
+}
+
+/// The property types already on a label's nodes, checked against every
+/// value before it is written.
+#[derive(Debug, Default)]
+pub struct TypeAudit {
+    label: String,
+    existing: HashMap<String, String>,
+    policy: TypePolicy,
+}
+
+impl TypeAudit {
+    /// Samples existing `label` nodes. Where a property already has mixed
+    /// types the most common one wins.
+    pub async fn sample(
+        graph: &Graph,
+        label: &str,
+        policy: TypePolicy,
+    ) -> Result<TypeAudit, Box<dyn Error>> {
+        let query = Query::new(format!(
+            "MATCH (n:{}) WITH n LIMIT $sample RETURN properties(n) AS properties",
+            label
+        ))
+        .param("sample", SAMPLE);
+        let mut result = graph.execute(query).await?;
+
+        let mut counts: HashMap<String, HashMap<String, usize>> = HashMap::new();
+        while let Some(row) = result.next().await? {
+            let properties: HashMap<String, BoltType> = row.get("properties")?;
+            for (name, value) in properties {
+                if let Some(kind) = kind(&value) {
+                    *counts.entry(name).or_default().entry(kind).or_insert(0) += 1;
+                }
+            }
+        }
+
+        let existing = counts
+            .into_iter()
+            .filter_map(|(name, kinds)| {
+                let (kind, _) = kinds.into_iter().max_by_key(|(_, count)| *count)?;
+                Some((name, kind))
+            })
+            .collect();
+        Ok(TypeAudit {
+            label: label.to_string(),
+            existing,
+            policy,
+        })
+    }
+
+    /// Returns `value` ready to write as `name`: unchanged when the types
+    /// agree, converted under `Coerce`, or an error describing the clash.
+    pub fn check(&self, name: &str, value: BoltType) -> Result<BoltType, String> {
+        let (Some(existing), Some(incoming)) = (self.existing.get(name), kind(&value)) else {
+            return Ok(value);
+        };
+        if *existing == incoming {
+            return Ok(value);
+        }
+
+        let clash = format!(
+            "{}.{} is {} in the graph but {} in the input",
+            self.label, name, existing, incoming
+        );
+        match self.policy {
+            TypePolicy::Fail => Err(format!("{} (use --type-policy coerce to convert)", clash)),
+            TypePolicy::Coerce => {
+                coerce(value, existing).ok_or_else(|| format!("{}, and cannot be converted", clash))
+            }
+        }
+    }
+}
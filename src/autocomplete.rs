@@ -0,0 +1,183 @@
+use crate::names::NameCleanup;
+use crate::output;
+use crate::settings::Settings;
+use csv::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{BufReader, Write};
+use std::path::Path;
+
+/// A distinct display name and how many recipes carry it.
+#[derive(Debug, Serialize, Deserialize)]
+struct Name {
+    display: String,
+    /// The first recipe with this name
+    id: i32,
+    recipes: u32,
+}
+
+/// One completion for a typed prefix.
+#[derive(Debug, Serialize)]
+pub struct Completion<'a> {
+    pub name: &'a str,
+    pub id: i32,
+    pub recipes: u32,
+}
+
+/// Lowercases `name` and collapses its whitespace, the form both names and
+/// typed prefixes are compared in.
+fn fold(name: &str) -> String {
+    name.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Byte offsets where each word of a folded name starts.
+fn word_starts(folded: &str) -> impl Iterator<Item = usize> + '_ {
+    std::iter::once(0).chain(folded.match_indices(' ').map(|(at, _)| at + 1))
+}
+
+/// A prefix index over recipe names. Every word start of every distinct
+/// name is an entry, sorted by the text from there on, so "chick" finds
+/// "Chicken Curry" and "Easy Chicken Pie" with one binary search.
+///
+/// Saved as JSON at ingest time so interactive lookups skip reading the
+/// corpus.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    names: Vec<Name>,
+    /// `(name, offset)` into the folded name, in sorted order
+    entries: Vec<(u32, u32)>,
+    #[serde(skip)]
+    folded: Vec<String>,
+}
+
+/// Prints `completions` one per line, as JSON or as id, recipe count and
+/// name.
+pub fn print(completions: &[Completion], json: bool) -> Result<(), Box<dyn Error>> {
+    for completion in completions {
+        if json {
+            println!("{}", serde_json::to_string(completion)?);
+        } else {
+            println!(
+                "{}\t{} recipes\t{}",
+                completion.id, completion.recipes, completion.name
+            );
+        }
+    }
+    Ok(())
+}
+
+impl Index {
+    /// Builds the index from the display names `names` gives the recipes
+    /// in `input`.
+    pub fn from_input(
+        input: &Path,
+        settings: &Settings,
+        names: &NameCleanup,
+    ) -> Result<Index, Box<dyn Error>> {
+        let mut display_names = Vec::new();
+        let rdr = Reader::from_reader(output::open_source(input)?);
+        for result in settings.recipes(rdr) {
+            let recipe = result?;
+            display_names.push((recipe.id, names.display(&recipe.name)));
+        }
+        Ok(Index::build(display_names))
+    }
+
+    /// Builds the index from `(recipe id, display name)` pairs.
+    pub fn build(recipes: impl IntoIterator<Item = (i32, String)>) -> Index {
+        let mut index = Index::default();
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        for (id, display) in recipes {
+            let folded = fold(&display);
+            if folded.is_empty() {
+                continue;
+            }
+            match seen.get(&folded) {
+                Some(&at) => index.names[at].recipes += 1,
+                None => {
+                    seen.insert(folded.clone(), index.names.len());
+                    index.names.push(Name {
+                        display,
+                        id,
+                        recipes: 1,
+                    });
+                    index.folded.push(folded);
+                }
+            }
+        }
+
+        for (at, folded) in index.folded.iter().enumerate() {
+            for start in word_starts(folded) {
+                index.entries.push((at as u32, start as u32));
+            }
+        }
+        let mut entries = std::mem::take(&mut index.entries);
+        entries.sort_by(|a, b| index.key(*a).cmp(index.key(*b)));
+        index.entries = entries;
+        index
+    }
+
+    fn key(&self, (name, offset): (u32, u32)) -> &str {
+        &self.folded[name as usize][offset as usize..]
+    }
+
+    /// Up to `count` names with a word starting with `prefix`. Names that
+    /// start with it come first, then names shared by more recipes, then
+    /// shorter names.
+    pub fn complete(&self, prefix: &str, count: usize) -> Vec<Completion<'_>> {
+        let prefix = fold(prefix);
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let start = self
+            .entries
+            .partition_point(|entry| self.key(*entry) < prefix.as_str());
+
+        // A name can match under several of its words
+        let mut first_word: HashMap<u32, bool> = HashMap::new();
+        for &(name, offset) in &self.entries[start..] {
+            if !self.key((name, offset)).starts_with(&prefix) {
+                break;
+            }
+            *first_word.entry(name).or_default() |= offset == 0;
+        }
+        let mut matches: Vec<(bool, &Name)> = first_word
+            .into_iter()
+            .map(|(name, first)| (first, &self.names[name as usize]))
+            .collect();
+        matches.sort_by(|(a_first, a), (b_first, b)| {
+            b_first
+                .cmp(a_first)
+                .then(b.recipes.cmp(&a.recipes))
+                .then(a.display.len().cmp(&b.display.len()))
+                .then(a.display.cmp(&b.display))
+        });
+
+        matches
+            .into_iter()
+            .take(count)
+            .map(|(_, name)| Completion {
+                name: &name.display,
+                id: name.id,
+                recipes: name.recipes,
+            })
+            .collect()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let mut out = output::open_sink(path, false)?;
+        serde_json::to_writer(&mut out, self)?;
+        out.flush()?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Index, Box<dyn Error>> {
+        let mut index: Index = serde_json::from_reader(BufReader::new(output::open_source(path)?))?;
+        index.folded = index.names.iter().map(|name| fold(&name.display)).collect();
+        Ok(index)
+    }
+}
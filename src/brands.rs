@@ -0,0 +1,237 @@
+use crate::output;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Brand names stripped from the front of an ingredient ("kraft shredded
+/// cheddar" -> "shredded cheddar", brand "kraft").
+const BRANDS: &[&str] = &[
+    "betty crocker",
+    "bush's",
+    "campbell's",
+    "duncan hines",
+    "french's",
+    "ghirardelli",
+    "goya",
+    "heinz",
+    "hellmann's",
+    "hershey's",
+    "hormel",
+    "jimmy dean",
+    "kellogg's",
+    "kikkoman",
+    "knorr",
+    "kraft",
+    "land o lakes",
+    "lipton",
+    "mccormick",
+    "nestle",
+    "old el paso",
+    "oscar mayer",
+    "philadelphia",
+    "pillsbury",
+    "progresso",
+    "quaker",
+    "swanson",
+];
+
+/// Products known by their brand, with the generic ingredient they stand
+/// for. The product name becomes the brand.
+const PRODUCTS: &[(&str, &str)] = &[
+    ("bisquick", "baking mix"),
+    ("cool whip", "whipped topping"),
+    ("crisco", "shortening"),
+    ("jell-o", "gelatin"),
+    ("jello", "gelatin"),
+    ("miracle whip", "salad dressing"),
+    ("pam", "cooking spray"),
+    ("ro-tel", "diced tomatoes and green chilies"),
+    ("rotel", "diced tomatoes and green chilies"),
+    ("tabasco", "hot sauce"),
+    ("velveeta", "processed cheese"),
+];
+
+/// Names rewritten to their usual generic form once any brand is gone.
+const GENERIC: &[(&str, &str)] = &[
+    ("shredded cheddar", "cheddar cheese"),
+    ("shredded mozzarella", "mozzarella cheese"),
+    ("cheddar", "cheddar cheese"),
+];
+
+/// A per-deployment brand lexicon, read from TOML:
+///
+/// ```toml
+/// builtin = true            # keep the bundled entries (default)
+/// brands = ["aldi"]
+/// [products]
+/// "nutella" = "hazelnut spread"
+/// [generic]
+/// "shredded parmesan" = "parmesan cheese"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct LexiconFile {
+    pub builtin: Option<bool>,
+    pub brands: Vec<String>,
+    pub products: HashMap<String, String>,
+    pub generic: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lexicon {
+    /// Longest first, so "old el paso" wins over a shorter prefix
+    brands: Vec<String>,
+    products: HashMap<String, String>,
+    generic: HashMap<String, String>,
+}
+
+fn owned(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(from, to)| (from.to_string(), to.to_string()))
+        .collect()
+}
+
+impl Lexicon {
+    pub fn builtin() -> Lexicon {
+        Lexicon::from_file(LexiconFile::default())
+    }
+
+    pub fn load(path: &Path) -> Result<Lexicon, Box<dyn Error>> {
+        let file: LexiconFile = toml::from_str(&output::read_text(path)?)?;
+        Ok(Lexicon::from_file(file))
+    }
+
+    fn from_file(file: LexiconFile) -> Lexicon {
+        let mut lexicon = Lexicon::default();
+        if file.builtin.unwrap_or(true) {
+            lexicon.brands = BRANDS.iter().map(|brand| brand.to_string()).collect();
+            lexicon.products = owned(PRODUCTS);
+            lexicon.generic = owned(GENERIC);
+        }
+        let lower = |text: String| text.trim().to_lowercase();
+        lexicon.brands.extend(file.brands.into_iter().map(lower));
+        lexicon.products.extend(
+            file.products
+                .into_iter()
+                .map(|(from, to)| (lower(from), lower(to))),
+        );
+        lexicon.generic.extend(
+            file.generic
+                .into_iter()
+                .map(|(from, to)| (lower(from), lower(to))),
+        );
+        lexicon
+            .brands
+            .sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        lexicon.brands.dedup();
+        lexicon
+    }
+
+    /// Splits an ingredient name into its generic name and brand, if any.
+    pub fn extract(&self, name: &str) -> (String, Option<String>) {
+        let lower = name.trim().to_lowercase();
+        if let Some(generic) = self.products.get(&lower) {
+            return (generic.clone(), Some(lower));
+        }
+
+        let brand = self.brands.iter().find(|brand| {
+            lower
+                .strip_prefix(brand.as_str())
+                .is_some_and(|rest| rest.starts_with(' '))
+        });
+        let (rest, brand) = match brand {
+            Some(brand) => (lower[brand.len()..].trim().to_string(), Some(brand.clone())),
+            None => return (name.trim().to_string(), None),
+        };
+
+        let generic = self
+            .products
+            .get(&rest)
+            .or_else(|| self.generic.get(&rest))
+            .cloned()
+            .unwrap_or(rest);
+        (generic, brand)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn extract(name: &str) -> (String, Option<String>) {
+        Lexicon::builtin().extract(name)
+    }
+
+    fn branded(name: &str, brand: &str) -> (String, Option<String>) {
+        (name.to_string(), Some(brand.to_string()))
+    }
+
+    #[test]
+    fn brand_prefixes_are_split_off() {
+        assert_eq!(
+            extract("Kraft Shredded Cheddar"),
+            branded("cheddar cheese", "kraft")
+        );
+        assert_eq!(
+            extract("old el paso taco seasoning"),
+            branded("taco seasoning", "old el paso")
+        );
+        // A product after the brand still becomes its generic name
+        assert_eq!(
+            extract("kraft velveeta"),
+            branded("processed cheese", "kraft")
+        );
+    }
+
+    #[test]
+    fn products_stand_for_their_generic_ingredient() {
+        assert_eq!(extract("Velveeta"), branded("processed cheese", "velveeta"));
+        assert_eq!(
+            extract(" cool whip "),
+            branded("whipped topping", "cool whip")
+        );
+    }
+
+    #[test]
+    fn lines_without_a_brand_are_kept_as_written() {
+        assert_eq!(extract("  Fresh Basil "), ("Fresh Basil".to_string(), None));
+        // A brand must be a whole word
+        assert_eq!(
+            extract("krafty cheese"),
+            ("krafty cheese".to_string(), None)
+        );
+        assert_eq!(extract("kraft"), ("kraft".to_string(), None));
+    }
+
+    #[test]
+    fn text_that_lowercases_longer_is_cut_after_the_brand() {
+        assert_eq!(extract("KRAFT İİ"), branded("i\u{307}i\u{307}", "kraft"));
+        assert_eq!(
+            extract("İstanbul spice"),
+            ("İstanbul spice".to_string(), None)
+        );
+    }
+
+    #[test]
+    fn lexicon_files_extend_or_replace_the_builtin_entries() {
+        let path = std::env::temp_dir().join(format!("brands-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "builtin = false\nbrands = [\"Aldi\"]\n[products]\n\"Nutella\" = \"Hazelnut Spread\"\n",
+        )
+        .unwrap();
+        let lexicon = Lexicon::load(&path).unwrap();
+
+        assert_eq!(lexicon.extract("aldi flour"), branded("flour", "aldi"));
+        assert_eq!(
+            lexicon.extract("nutella"),
+            branded("hazelnut spread", "nutella")
+        );
+        assert_eq!(
+            lexicon.extract("kraft cheddar"),
+            ("kraft cheddar".to_string(), None)
+        );
+    }
+}
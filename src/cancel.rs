@@ -0,0 +1,74 @@
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+#[cfg(feature = "neo4j")]
+use std::time::Duration;
+
+const RUNNING: u8 = 0;
+const CANCELLED: u8 = 1;
+const TIMED_OUT: u8 = 2;
+
+/// Why a long-running operation stopped early.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Cancelled {
+    ByRequest,
+    TimedOut,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cancelled::ByRequest => f.write_str("cancelled"),
+            Cancelled::TimedOut => f.write_str("timed out"),
+        }
+    }
+}
+
+impl Error for Cancelled {}
+
+/// Shared between a caller and the operation it started. Operations check
+/// it between records or batches, so a cancelled load stops at a boundary
+/// where its run log or checkpoint is consistent rather than mid-write.
+/// Clones observe the same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<AtomicU8>,
+}
+
+impl CancellationToken {
+    pub fn new() -> CancellationToken {
+        CancellationToken::default()
+    }
+
+    fn set(&self, state: u8) {
+        // The first reason wins
+        let _ = self
+            .state
+            .compare_exchange(RUNNING, state, Ordering::SeqCst, Ordering::SeqCst);
+    }
+
+    pub fn cancel(&self) {
+        self.set(CANCELLED);
+    }
+
+    /// Cancels with `Cancelled::TimedOut` once `timeout` has passed. Must be
+    /// called inside a Tokio runtime.
+    #[cfg(feature = "neo4j")]
+    pub fn cancel_after(&self, timeout: Duration) {
+        let token = self.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            token.set(TIMED_OUT);
+        });
+    }
+
+    /// `Err` once cancelled, for use with `?` at each checkpoint.
+    pub fn check(&self) -> Result<(), Cancelled> {
+        match self.state.load(Ordering::SeqCst) {
+            RUNNING => Ok(()),
+            TIMED_OUT => Err(Cancelled::TimedOut),
+            _ => Err(Cancelled::ByRequest),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+/// The number of input rows a previous run committed, or 0 when `path`
+/// does not exist yet.
+pub fn read(path: &Path) -> Result<usize, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(0);
+    }
+    Ok(fs::read_to_string(path)?.trim().parse()?)
+}
+
+/// Records that every input row up to `rows` is committed.
+pub fn write(path: &Path, rows: usize) -> Result<(), Box<dyn Error>> {
+    fs::write(path, rows.to_string())?;
+    Ok(())
+}
+
+/// Tracks batches that finish out of order, so the checkpoint only moves
+/// past a row once every batch before it has finished too.
+#[derive(Debug, Default)]
+pub struct Watermark {
+    next: usize,
+    settled: usize,
+    /// Finished batches past the first unfinished one, by sequence number,
+    /// with their last row
+    pending: BTreeMap<usize, Option<usize>>,
+    row: Option<usize>,
+}
+
+impl Watermark {
+    /// The sequence number of a batch about to be sent.
+    pub fn issue(&mut self) -> usize {
+        self.next += 1;
+        self.next - 1
+    }
+
+    /// Marks batch `seq` finished, with the last row it committed or
+    /// `None` when it failed. Returns the row the checkpoint can move to
+    /// when this moved it. A failed batch holds the checkpoint back only
+    /// until a later batch commits; its rows are left to the error report.
+    pub fn finish(&mut self, seq: usize, last_row: Option<usize>) -> Option<usize> {
+        self.pending.insert(seq, last_row);
+        let before = self.row;
+        while let Some(last_row) = self.pending.remove(&self.settled) {
+            self.row = last_row.or(self.row);
+            self.settled += 1;
+        }
+        (self.row != before).then_some(self.row).flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn waits_for_earlier_batches_that_finish_late() {
+        let mut watermark = Watermark::default();
+        let [first, second, third] = [(); 3].map(|_| watermark.issue());
+
+        assert_eq!(watermark.finish(third, Some(300)), None);
+        assert_eq!(watermark.finish(second, Some(200)), None);
+        assert_eq!(watermark.finish(first, Some(100)), Some(300));
+    }
+
+    #[test]
+    fn failed_batches_hold_back_until_a_later_one_commits() {
+        let mut watermark = Watermark::default();
+        let [first, second, third] = [(); 3].map(|_| watermark.issue());
+
+        assert_eq!(watermark.finish(first, Some(100)), Some(100));
+        assert_eq!(watermark.finish(second, None), None);
+        assert_eq!(watermark.finish(third, Some(300)), Some(300));
+    }
+
+    #[test]
+    fn a_failed_first_batch_moves_nothing() {
+        let mut watermark = Watermark::default();
+        let [first, second] = [(); 2].map(|_| watermark.issue());
+
+        assert_eq!(watermark.finish(second, Some(200)), None);
+        assert_eq!(watermark.finish(first, None), Some(200));
+    }
+
+    #[test]
+    fn rows_round_trip_through_the_file() {
+        let path = std::env::temp_dir().join(format!("checkpoint-{}", std::process::id()));
+        assert_eq!(read(&path).unwrap(), 0);
+        write(&path, 4200).unwrap();
+        assert_eq!(read(&path).unwrap(), 4200);
+        fs::remove_file(&path).unwrap();
+    }
+}
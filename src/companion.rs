@@ -0,0 +1,171 @@
+use crate::cancel::CancellationToken;
+use crate::output;
+use crate::parser;
+use csv::Reader;
+use neo4rs::{BoltType, Graph, Query};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// The cooking techniques behind PP_recipes.csv's `techniques` flags, in
+/// vector order (Majumder et al., 2019).
+pub const TECHNIQUES: [&str; 58] = [
+    "bake",
+    "barbecue",
+    "blanch",
+    "blend",
+    "boil",
+    "braise",
+    "brine",
+    "broil",
+    "caramelize",
+    "combine",
+    "crock pot",
+    "crush",
+    "deglaze",
+    "devein",
+    "dice",
+    "distill",
+    "drain",
+    "emulsify",
+    "ferment",
+    "freeze",
+    "fry",
+    "grate",
+    "griddle",
+    "grill",
+    "knead",
+    "leaven",
+    "marinate",
+    "mash",
+    "melt",
+    "microwave",
+    "parboil",
+    "pickle",
+    "poach",
+    "pour",
+    "pressure cook",
+    "puree",
+    "refrigerate",
+    "roast",
+    "saute",
+    "scald",
+    "scramble",
+    "shred",
+    "simmer",
+    "skillet",
+    "slow cook",
+    "smoke",
+    "smooth",
+    "soak",
+    "sous-vide",
+    "steam",
+    "stew",
+    "strain",
+    "tenderize",
+    "thicken",
+    "toast",
+    "toss",
+    "whip",
+    "whisk",
+];
+
+/// `calorie_level` 0, 1 and 2, the dataset's tertiles of calories per
+/// serving.
+const CALORIE_LEVELS: [&str; 3] = ["low", "medium", "high"];
+
+/// The columns of one PP_recipes.csv row this importer uses. The token
+/// columns only make sense with the dataset's own vocabulary and are left
+/// out.
+#[derive(Debug, Deserialize)]
+struct PreprocessedRecipe {
+    id: i32,
+    techniques: String,
+    calorie_level: usize,
+}
+
+impl PreprocessedRecipe {
+    fn to_row(&self) -> Result<HashMap<String, BoltType>, Box<dyn Error>> {
+        let flags = parser::parse_python_list(&self.techniques)
+            .map_err(|err| format!("recipe {}: techniques: {}", self.id, err))?;
+        if flags.len() != TECHNIQUES.len() {
+            return Err(format!(
+                "recipe {}: expected {} technique flags, found {}",
+                self.id,
+                TECHNIQUES.len(),
+                flags.len()
+            )
+            .into());
+        }
+        let techniques: Vec<String> = TECHNIQUES
+            .iter()
+            .zip(&flags)
+            .filter(|(_, flag)| flag.trim() == "1")
+            .map(|(technique, _)| technique.to_string())
+            .collect();
+        let calorie_level = CALORIE_LEVELS
+            .get(self.calorie_level)
+            .ok_or_else(|| format!("recipe {}: unknown calorie level", self.id))?;
+
+        let mut row = HashMap::new();
+        row.insert("id".to_string(), self.id.into());
+        row.insert("techniques".to_string(), techniques.into());
+        row.insert(
+            "calorie_level".to_string(),
+            calorie_level.to_string().into(),
+        );
+        Ok(row)
+    }
+}
+
+async fn write_batch(
+    graph: &Graph,
+    rows: Vec<HashMap<String, BoltType>>,
+) -> Result<usize, Box<dyn Error>> {
+    let query = Query::new(
+        "UNWIND $rows AS row MATCH (r:Recipe {id: row.id}) \
+         SET r.techniques = row.techniques, r.calorie_level = row.calorie_level \
+         RETURN count(r) AS matched"
+            .to_string(),
+    )
+    .param("rows", rows);
+    let mut result = graph.execute(query).await?;
+    match result.next().await? {
+        Some(row) => Ok(row.get::<i64>("matched")? as usize),
+        None => Ok(0),
+    }
+}
+
+/// Joins PP_recipes.csv to the Recipe nodes by id, setting `techniques` to
+/// the names of the flagged techniques and `calorie_level` to its bucket.
+/// Rows for recipes not in the graph are skipped. Returns the number of
+/// recipes enriched.
+pub async fn import_preprocessed(
+    graph: &Graph,
+    input: &Path,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut rdr = Reader::from_reader(output::open_source(input)?);
+    let mut rows = Vec::with_capacity(batch_size);
+    let (mut read, mut enriched) = (0, 0);
+
+    for result in rdr.deserialize() {
+        let recipe: PreprocessedRecipe = result?;
+        rows.push(recipe.to_row()?);
+        read += 1;
+        if rows.len() == batch_size {
+            cancel.check()?;
+            enriched += write_batch(graph, std::mem::take(&mut rows)).await?;
+            println!("{} of {} preprocessed recipes joined", enriched, read);
+        }
+    }
+    if !rows.is_empty() {
+        cancel.check()?;
+        enriched += write_batch(graph, rows).await?;
+    }
+
+    println!("{} of {} preprocessed recipes joined", enriched, read);
+    Ok(enriched)
+}
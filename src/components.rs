@@ -0,0 +1,139 @@
+use crate::cancel::CancellationToken;
+use crate::interner::{Interner, Symbol};
+use crate::output;
+use crate::settings::Settings;
+use crate::Recipe;
+#[cfg(feature = "neo4j")]
+use neo4rs::{BoltType, Graph, Query};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+/// A recipe whose ingredient names another recipe in the corpus, as
+/// lasagna lists "marinara sauce".
+#[derive(Debug, Clone, Serialize)]
+pub struct Component {
+    pub recipe_id: i32,
+    pub component_id: i32,
+    /// The ingredient line that names the component
+    pub ingredient: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub components: Vec<Component>,
+    /// Ingredients naming a title more than one recipe has, left unlinked
+    pub ambiguous: usize,
+}
+
+/// `analyze components`: finds the components of the recipes in `input`
+/// and writes them to `out` as CSV.
+pub fn detect_file(
+    input: &Path,
+    out: Box<dyn Write>,
+    settings: &Settings,
+    cancel: &CancellationToken,
+) -> Result<Report, Box<dyn Error>> {
+    let report = detect(settings.recipes_in(input, cancel)?, settings)?;
+    output::write_csv(out, &report.components)?;
+    eprintln!(
+        "{} ingredients are other recipes; {} name a title several recipes share",
+        report.components.len(),
+        report.ambiguous
+    );
+    Ok(report)
+}
+
+/// Finds ingredients that are the title of another recipe. Titles and
+/// ingredients are compared in canonical form, so "Marinara Sauce" matches
+/// "marinara sauces". A title shared by several recipes could mean any of
+/// them, so ingredients naming one are counted as ambiguous instead.
+pub fn detect(
+    recipes: impl IntoIterator<Item = Result<Recipe, Box<dyn Error>>>,
+    settings: &Settings,
+) -> Result<Report, Box<dyn Error>> {
+    let mut report = Report::default();
+    let mut strings = Interner::default();
+    // canonical title -> recipe ids with it
+    let mut titles: HashMap<Symbol, Vec<i32>> = HashMap::new();
+    // (recipe id, canonical ingredient name, line)
+    let mut lines: Vec<(i32, Symbol, String)> = Vec::new();
+
+    for recipe in recipes {
+        let recipe = recipe?;
+        let title = settings.rules.canonical(&recipe.name);
+        if !title.is_empty() {
+            titles
+                .entry(strings.intern(&title))
+                .or_default()
+                .push(recipe.id);
+        }
+        for line in &recipe.ingredients {
+            let name = settings.parse_ingredient(line).name;
+            lines.push((recipe.id, strings.intern(&name), line.clone()));
+        }
+    }
+
+    let mut linked = HashSet::new();
+    for (recipe_id, name, line) in lines {
+        match titles.get(&name).map(Vec::as_slice) {
+            Some([component_id])
+                if *component_id != recipe_id && linked.insert((recipe_id, *component_id)) =>
+            {
+                report.components.push(Component {
+                    recipe_id,
+                    component_id: *component_id,
+                    ingredient: line,
+                })
+            }
+            Some(ids) if ids.len() > 1 => report.ambiguous += 1,
+            _ => {}
+        }
+    }
+    Ok(report)
+}
+
+/// Writes `components` as `(:Recipe)-[:USES_COMPONENT {ingredient}]->(:Recipe)`
+/// relationships, `batch_size` at a time. Pairs with either recipe missing
+/// from the graph are skipped. Returns the number of components sent.
+#[cfg(feature = "neo4j")]
+pub async fn write(
+    graph: &Graph,
+    components: &[Component],
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut written = 0;
+    for batch in components.chunks(batch_size.max(1)) {
+        cancel.check()?;
+        let rows: Vec<HashMap<String, BoltType>> = batch
+            .iter()
+            .map(|component| {
+                let mut row = HashMap::new();
+                row.insert("recipe_id".to_string(), component.recipe_id.into());
+                row.insert("component_id".to_string(), component.component_id.into());
+                row.insert(
+                    "ingredient".to_string(),
+                    component.ingredient.clone().into(),
+                );
+                row
+            })
+            .collect();
+        graph
+            .run(
+                Query::new(
+                    "UNWIND $rows AS row MATCH (r:Recipe {id: row.recipe_id}) \
+                     MATCH (c:Recipe {id: row.component_id}) \
+                     MERGE (r)-[u:USES_COMPONENT]->(c) SET u.ingredient = row.ingredient"
+                        .to_string(),
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        written += batch.len();
+        println!("{} components written", written);
+    }
+    Ok(written)
+}
@@ -0,0 +1,110 @@
+#[cfg(feature = "neo4j")]
+use crate::credentials;
+use crate::output;
+#[cfg(feature = "neo4j")]
+use neo4rs::{ConfigBuilder, Graph};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// Connection and batch settings for one environment, read from a named
+/// table in the config file:
+///
+/// ```toml
+/// [profiles.dev]
+/// uri = "bolt://localhost:7687"
+/// batch_size = 500
+///
+/// [profiles.prod]
+/// uri = "neo4j+s://xxxx.databases.neo4j.io"
+/// user = "neo4j"
+/// database = "recipes"
+/// batch_size = 5000
+/// id_namespace = "food.com"
+/// ```
+///
+/// Anything left out falls back to the built-in defaults.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    pub uri: Option<String>,
+    pub user: Option<String>,
+    /// Prefer `auth login` or `NEO4J_PASSWORD` over storing this in the file
+    pub password: Option<String>,
+    /// The Neo4j database the profile reads and writes
+    pub database: Option<String>,
+    pub batch_size: Option<usize>,
+    /// Namespace for generated node keys; see `keys::surrogate`
+    pub id_namespace: Option<String>,
+}
+
+/// Where `Profile::connect` goes when the profile names no URI.
+#[cfg(feature = "neo4j")]
+const DEFAULT_URI: &str = "bolt://localhost:7687";
+
+impl Profile {
+    /// The Neo4j user, `neo4j` unless the profile names another.
+    pub fn user(&self) -> &str {
+        self.user.as_deref().unwrap_or("neo4j")
+    }
+
+    /// Connects to the profile's database, `DEFAULT_URI` unless it names
+    /// one, with its password, else the one saved by `auth login`. See
+    /// `server::detect` for what the server supports.
+    #[cfg(feature = "neo4j")]
+    pub async fn connect(&self) -> Result<Graph, Box<dyn Error>> {
+        let password = self
+            .password
+            .clone()
+            .or_else(|| credentials::lookup(self.user()))
+            .ok_or_else(|| {
+                format!(
+                    "no Neo4j password for {}: pass --password, set NEO4J_PASSWORD, add one to \
+                     the config profile or save one with `auth login`",
+                    self.user()
+                )
+            })?;
+        let mut builder = ConfigBuilder::default()
+            .uri(self.uri.as_deref().unwrap_or(DEFAULT_URI))
+            .user(self.user())
+            .password(password);
+        if let Some(database) = &self.database {
+            builder = builder.db(database.as_str());
+        }
+        Ok(Graph::connect(builder.build()?).await?)
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct ConfigFile {
+    profiles: HashMap<String, Profile>,
+}
+
+/// Reads profile `name` from `path`. Without an explicit `--profile`, a
+/// missing file or a file with no `default` profile is not an error.
+pub fn load(path: &Path, name: Option<&str>) -> Result<Profile, Box<dyn Error>> {
+    let text = match (output::read_text(path), name) {
+        (Ok(text), _) => text,
+        (Err(_), None) if !path.exists() => return Ok(Profile::default()),
+        (Err(err), _) => return Err(err),
+    };
+    let mut file: ConfigFile = toml::from_str(&text)?;
+    match (file.profiles.remove(name.unwrap_or("default")), name) {
+        (Some(profile), _) => Ok(profile),
+        (None, None) => Ok(Profile::default()),
+        (None, Some(name)) => {
+            let mut known: Vec<&String> = file.profiles.keys().collect();
+            known.sort();
+            let known: Vec<&str> = known.into_iter().map(String::as_str).collect();
+            Err(format!(
+                "no profile {} in {} (have: {})",
+                name,
+                path.display(),
+                known.join(", ")
+            )
+            .into())
+        }
+    }
+}
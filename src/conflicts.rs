@@ -0,0 +1,309 @@
+use crate::Recipe;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::str::FromStr;
+
+/// Which row wins when one recipe id appears more than once in an input.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConflictPolicy {
+    /// Keep the first row and skip the rest
+    #[default]
+    FirstWins,
+    /// Keep the last row
+    LastWins,
+    /// Refuse the input before anything is written
+    Error,
+    /// Start from the first row and fill its empty fields from later ones
+    MergeFields,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "first-wins" => Ok(ConflictPolicy::FirstWins),
+            "last-wins" => Ok(ConflictPolicy::LastWins),
+            "error" => Ok(ConflictPolicy::Error),
+            "merge-fields" => Ok(ConflictPolicy::MergeFields),
+            other => Err(format!("unknown conflict policy: {}", other)),
+        }
+    }
+}
+
+impl ConflictPolicy {
+    /// Whether the policy needs `duplicate_ids` from a first pass over the
+    /// input. `FirstWins` can decide as rows stream past.
+    pub fn needs_scan(&self) -> bool {
+        *self != ConflictPolicy::FirstWins
+    }
+}
+
+/// A recipe id seen more than once, as listed in the run report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Conflict {
+    pub id: i32,
+    /// Data rows with the id, counting from 1 after the header
+    pub rows: Vec<usize>,
+    /// "first", "last" or "merged"
+    pub kept: String,
+}
+
+/// Counts the ids that appear more than once.
+pub fn duplicate_ids(ids: impl IntoIterator<Item = i32>) -> HashMap<i32, usize> {
+    let mut counts: HashMap<i32, usize> = HashMap::new();
+    for id in ids {
+        *counts.entry(id).or_insert(0) += 1;
+    }
+    counts.retain(|_, count| *count > 1);
+    counts
+}
+
+/// Fills the empty fields of `base` from `other`. Tags are combined.
+fn merge(base: &mut Recipe, other: Recipe) {
+    if base.name.trim().is_empty() {
+        base.name = other.name;
+    }
+    if base.description.trim().is_empty() {
+        base.description = other.description;
+    }
+    if base.ingredients.is_empty() {
+        base.ingredients = other.ingredients;
+    }
+    if base.minutes == 0 {
+        base.minutes = other.minutes;
+    }
+    if base.steps.is_empty() {
+        base.steps = other.steps;
+    }
+    if base.nutrition.iter().all(|value| value.is_nan()) {
+        base.nutrition = other.nutrition;
+    }
+    for tag in other.tags {
+        if !base.tags.contains(&tag) {
+            base.tags.push(tag);
+        }
+    }
+    base.servings = base.servings.or(other.servings);
+    base.source_url = base.source_url.take().or(other.source_url);
+    base.author = base.author.take().or(other.author);
+    base.license = base.license.take().or(other.license);
+}
+
+/// Applies a `ConflictPolicy` to recipes in input order, holding back rows
+/// until the policy can tell which to write.
+pub struct Resolver {
+    policy: ConflictPolicy,
+    /// Occurrences still to come for each duplicated id
+    remaining: HashMap<i32, usize>,
+    /// The first row of every id so far
+    seen: HashMap<i32, usize>,
+    merging: HashMap<i32, Recipe>,
+    conflicts: BTreeMap<i32, Conflict>,
+}
+
+impl Resolver {
+    /// `duplicates` comes from `duplicate_ids` over the same rows, and may
+    /// be empty for `FirstWins`. Fails for `Error` when there are any.
+    pub fn new(
+        policy: ConflictPolicy,
+        duplicates: HashMap<i32, usize>,
+    ) -> Result<Resolver, Box<dyn Error>> {
+        if policy == ConflictPolicy::Error && !duplicates.is_empty() {
+            let mut ids: Vec<i32> = duplicates.keys().copied().collect();
+            ids.sort();
+            let shown: Vec<String> = ids.iter().take(10).map(i32::to_string).collect();
+            return Err(format!(
+                "{} recipe ids appear more than once: {}{}",
+                ids.len(),
+                shown.join(", "),
+                if ids.len() > shown.len() { ", ..." } else { "" }
+            )
+            .into());
+        }
+        Ok(Resolver {
+            policy,
+            remaining: duplicates,
+            seen: HashMap::new(),
+            merging: HashMap::new(),
+            conflicts: BTreeMap::new(),
+        })
+    }
+
+    fn record(&mut self, id: i32, row: usize) {
+        let kept = match self.policy {
+            ConflictPolicy::FirstWins | ConflictPolicy::Error => "first",
+            ConflictPolicy::LastWins => "last",
+            ConflictPolicy::MergeFields => "merged",
+        };
+        let first = self.seen[&id];
+        let conflict = self.conflicts.entry(id).or_insert_with(|| Conflict {
+            id,
+            rows: vec![first],
+            kept: kept.to_string(),
+        });
+        if row != first {
+            conflict.rows.push(row);
+        }
+    }
+
+    /// The recipe to write for data row `row`, if its id is settled.
+    pub fn resolve(
+        &mut self,
+        row: usize,
+        recipe: Recipe,
+    ) -> Result<Option<Recipe>, Box<dyn Error>> {
+        let id = recipe.id;
+        let repeated = self.seen.contains_key(&id);
+        self.seen.entry(id).or_insert(row);
+        if !repeated && !self.remaining.contains_key(&id) {
+            return Ok(Some(recipe));
+        }
+        self.record(id, row);
+
+        match self.policy {
+            ConflictPolicy::FirstWins => Ok((!repeated).then_some(recipe)),
+            ConflictPolicy::Error => Err(format!("recipe id {} appears more than once", id).into()),
+            ConflictPolicy::LastWins | ConflictPolicy::MergeFields => {
+                let remaining = self.remaining.entry(id).or_insert(1);
+                *remaining = remaining.saturating_sub(1);
+                let last = *remaining == 0;
+
+                let recipe = if self.policy == ConflictPolicy::MergeFields {
+                    match self.merging.remove(&id) {
+                        Some(mut base) => {
+                            merge(&mut base, recipe);
+                            base
+                        }
+                        None => recipe,
+                    }
+                } else {
+                    recipe
+                };
+                if last {
+                    self.remaining.remove(&id);
+                    Ok(Some(recipe))
+                } else {
+                    if self.policy == ConflictPolicy::MergeFields {
+                        self.merging.insert(id, recipe);
+                    }
+                    Ok(None)
+                }
+            }
+        }
+    }
+
+    /// The duplicated ids seen, in id order.
+    pub fn conflicts(self) -> Vec<Conflict> {
+        self.conflicts.into_values().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn recipe(id: i32, description: &str, tags: &[&str]) -> Recipe {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.id = id;
+        recipe.description = description.to_string();
+        recipe.tags = tags.iter().map(|tag| tag.to_string()).collect();
+        recipe
+    }
+
+    /// Rows 1 to 4: id 1 three times, with id 2 in between.
+    fn rows() -> Vec<Recipe> {
+        vec![
+            recipe(1, "", &["x"]),
+            recipe(2, "two", &[]),
+            recipe(1, "third", &["x", "y"]),
+            recipe(1, "fourth", &["z"]),
+        ]
+    }
+
+    fn resolve(policy: ConflictPolicy) -> (Vec<(i32, String)>, Vec<Conflict>) {
+        let recipes = rows();
+        let duplicates = if policy.needs_scan() {
+            duplicate_ids(recipes.iter().map(|recipe| recipe.id))
+        } else {
+            HashMap::new()
+        };
+        let mut resolver = Resolver::new(policy, duplicates).unwrap();
+        let mut written = Vec::new();
+        for (index, recipe) in recipes.into_iter().enumerate() {
+            if let Some(recipe) = resolver.resolve(index + 1, recipe).unwrap() {
+                written.push((recipe.id, recipe.description));
+            }
+        }
+        (written, resolver.conflicts())
+    }
+
+    fn written(rows: &[(i32, &str)]) -> Vec<(i32, String)> {
+        rows.iter()
+            .map(|&(id, description)| (id, description.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn first_wins_keeps_the_first_row_without_a_scan() {
+        assert!(!ConflictPolicy::FirstWins.needs_scan());
+        let (written_rows, conflicts) = resolve(ConflictPolicy::FirstWins);
+        assert_eq!(written_rows, written(&[(1, ""), (2, "two")]));
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].id, 1);
+        assert_eq!(conflicts[0].rows, [1, 3, 4]);
+        assert_eq!(conflicts[0].kept, "first");
+    }
+
+    #[test]
+    fn last_wins_writes_the_last_row_when_it_arrives() {
+        let (written_rows, conflicts) = resolve(ConflictPolicy::LastWins);
+        assert_eq!(written_rows, written(&[(2, "two"), (1, "fourth")]));
+        assert_eq!(conflicts[0].rows, [1, 3, 4]);
+        assert_eq!(conflicts[0].kept, "last");
+    }
+
+    #[test]
+    fn merge_fields_fills_empty_fields_and_combines_tags() {
+        let recipes = rows();
+        let duplicates = duplicate_ids(recipes.iter().map(|recipe| recipe.id));
+        let mut resolver = Resolver::new(ConflictPolicy::MergeFields, duplicates).unwrap();
+        let mut written = Vec::new();
+        for (index, recipe) in recipes.into_iter().enumerate() {
+            written.extend(resolver.resolve(index + 1, recipe).unwrap());
+        }
+
+        assert_eq!(written.len(), 2);
+        let merged = &written[1];
+        assert_eq!(merged.id, 1);
+        assert_eq!(merged.description, "third");
+        assert_eq!(merged.tags, ["x", "y", "z"]);
+        assert_eq!(resolver.conflicts()[0].kept, "merged");
+    }
+
+    #[test]
+    fn error_refuses_inputs_with_duplicates() {
+        let duplicates = duplicate_ids([3, 1, 2, 1, 3]);
+        assert_eq!(duplicates, HashMap::from([(1, 2), (3, 2)]));
+        let error = Resolver::new(ConflictPolicy::Error, duplicates)
+            .err()
+            .unwrap();
+        assert_eq!(
+            error.to_string(),
+            "2 recipe ids appear more than once: 1, 3"
+        );
+
+        // A duplicate the scan missed still fails when it arrives
+        let mut resolver = Resolver::new(ConflictPolicy::Error, HashMap::new()).unwrap();
+        assert!(resolver.resolve(1, recipe(5, "", &[])).unwrap().is_some());
+        assert!(resolver.resolve(2, recipe(5, "", &[])).is_err());
+    }
+
+    #[test]
+    fn policies_are_parsed_by_name() {
+        assert_eq!("merge-fields".parse(), Ok(ConflictPolicy::MergeFields));
+        assert!("newest".parse::<ConflictPolicy>().is_err());
+    }
+}
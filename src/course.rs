@@ -0,0 +1,159 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Course {
+    Breakfast,
+    Lunch,
+    Dinner,
+    Dessert,
+    Snack,
+}
+
+pub const COURSES: [Course; 5] = [
+    Course::Breakfast,
+    Course::Lunch,
+    Course::Dinner,
+    Course::Dessert,
+    Course::Snack,
+];
+
+impl Course {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Course::Breakfast => "breakfast",
+            Course::Lunch => "lunch",
+            Course::Dinner => "dinner",
+            Course::Dessert => "dessert",
+            Course::Snack => "snack",
+        }
+    }
+
+    fn tags(&self) -> &'static [&'static str] {
+        match self {
+            Course::Breakfast => &["breakfast", "brunch"],
+            Course::Lunch => &["lunch", "sandwiches", "salads"],
+            Course::Dinner => &["main-dish", "dinner-party", "one-dish-meal"],
+            Course::Dessert => &["desserts", "cakes", "cookies-and-brownies", "pies", "candy"],
+            Course::Snack => &["snacks", "appetizers", "finger-food", "dips"],
+        }
+    }
+
+    fn title_words(&self) -> &'static [&'static str] {
+        match self {
+            Course::Breakfast => &[
+                "pancake",
+                "waffle",
+                "omelet",
+                "omelette",
+                "muffin",
+                "granola",
+                "oatmeal",
+                "frittata",
+                "scone",
+                "breakfast",
+            ],
+            Course::Lunch => &["sandwich", "wrap", "salad", "soup", "panini", "burger"],
+            Course::Dinner => &[
+                "stew",
+                "casserole",
+                "roast",
+                "lasagna",
+                "curry",
+                "chili",
+                "pasta",
+                "steak",
+            ],
+            Course::Dessert => &[
+                "cake",
+                "cookie",
+                "pie",
+                "brownie",
+                "pudding",
+                "tart",
+                "fudge",
+                "cheesecake",
+                "cobbler",
+                "dessert",
+            ],
+            Course::Snack => &["dip", "chip", "bite", "popcorn", "salsa", "snack"],
+        }
+    }
+
+    fn ingredients(&self) -> &'static [&'static str] {
+        match self {
+            Course::Breakfast => &["bacon", "oats", "maple syrup", "rolled oats", "sausage"],
+            Course::Lunch => &["bread", "lettuce", "deli ham", "tortillas", "mayonnaise"],
+            Course::Dinner => &[
+                "chicken breasts",
+                "ground beef",
+                "pork chops",
+                "beef",
+                "salmon",
+                "pasta",
+                "rice",
+            ],
+            Course::Dessert => &[
+                "powdered sugar",
+                "cocoa",
+                "chocolate chips",
+                "semi-sweet chocolate chips",
+                "vanilla",
+                "cream cheese",
+                "brown sugar",
+                "whipped cream",
+            ],
+            Course::Snack => &["tortilla chips", "crackers", "popcorn", "sour cream"],
+        }
+    }
+}
+
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+fn best<F: Fn(Course) -> usize>(score: F) -> Option<Course> {
+    COURSES
+        .iter()
+        .map(|course| (score(*course), *course))
+        .filter(|(hits, _)| *hits > 0)
+        .max_by_key(|(hits, _)| *hits)
+        .map(|(_, course)| course)
+}
+
+/// Classifies a recipe by its tags first, then words in its title, and
+/// finally by which course its ingredients are typical of.
+pub fn classify(name: &str, tags: &[String], ingredients: &[String]) -> Option<Course> {
+    let by_tags = best(|course| {
+        tags.iter()
+            .filter(|tag| course.tags().contains(&tag.as_str()))
+            .count()
+    });
+    if by_tags.is_some() {
+        return by_tags;
+    }
+
+    let title = name.to_lowercase();
+    let words: Vec<&str> = title
+        .split(|c: char| !c.is_alphanumeric())
+        .map(|word| word.strip_suffix('s').unwrap_or(word))
+        .collect();
+    let by_title = best(|course| {
+        course
+            .title_words()
+            .iter()
+            .filter(|keyword| words.contains(keyword))
+            .count()
+    });
+    if by_title.is_some() {
+        return by_title;
+    }
+
+    best(|course| {
+        ingredients
+            .iter()
+            .filter(|ingredient| course.ingredients().contains(&ingredient.as_str()))
+            .count()
+    })
+}
@@ -0,0 +1,269 @@
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::process::{Command, ExitStatus, Stdio};
+
+/// The service name passwords are filed under in the keyring.
+const SERVICE: &str = "recipe_app";
+
+/// The tool the OS keyring is reached through.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Backend {
+    /// The Secret Service, via `secret-tool`, everywhere but macOS
+    SecretTool,
+    /// The login keychain, via `security`, on macOS
+    Security,
+}
+
+impl Backend {
+    fn current() -> Backend {
+        Backend::for_os(std::env::consts::OS)
+    }
+
+    fn for_os(os: &str) -> Backend {
+        match os {
+            "macos" => Backend::Security,
+            _ => Backend::SecretTool,
+        }
+    }
+
+    fn tool(&self) -> &'static str {
+        match self {
+            Backend::SecretTool => "secret-tool",
+            Backend::Security => "security",
+        }
+    }
+
+    /// Arguments that save `user`'s password. `secret-tool` reads it from
+    /// stdin; `security` is given `-w` last with no value, so it prompts
+    /// on the terminal itself.
+    fn store_args<'a>(&self, user: &'a str) -> Vec<&'a str> {
+        match self {
+            Backend::SecretTool => vec![
+                "store",
+                "--label",
+                "recipe_app Neo4j password",
+                "service",
+                SERVICE,
+                "user",
+                user,
+            ],
+            Backend::Security => vec![
+                "add-generic-password",
+                "-U",
+                "-s",
+                SERVICE,
+                "-a",
+                user,
+                "-w",
+            ],
+        }
+    }
+
+    /// Arguments that print `user`'s password.
+    fn lookup_args<'a>(&self, user: &'a str) -> Vec<&'a str> {
+        match self {
+            Backend::SecretTool => vec!["lookup", "service", SERVICE, "user", user],
+            Backend::Security => {
+                vec!["find-generic-password", "-s", SERVICE, "-a", user, "-w"]
+            }
+        }
+    }
+
+    fn remove_args<'a>(&self, user: &'a str) -> Vec<&'a str> {
+        match self {
+            Backend::SecretTool => vec!["clear", "service", SERVICE, "user", user],
+            Backend::Security => vec!["delete-generic-password", "-s", SERVICE, "-a", user],
+        }
+    }
+
+    fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(self.tool());
+        command.args(args);
+        command
+    }
+
+    fn status(&self, args: &[&str]) -> Result<ExitStatus, String> {
+        self.command(args)
+            .status()
+            .map_err(|err| missing_tool(self.tool(), err))
+    }
+}
+
+fn missing_tool(tool: &str, err: std::io::Error) -> String {
+    format!("cannot reach the keyring through {}: {}", tool, err)
+}
+
+/// The password a lookup printed, or `None` when it failed or printed
+/// nothing, so callers fall back to asking for one.
+fn found(success: bool, stdout: Vec<u8>) -> Option<String> {
+    let password = String::from_utf8(stdout).ok()?;
+    let password = password.trim_end_matches(['\r', '\n']);
+    (success && !password.is_empty()).then(|| password.to_string())
+}
+
+/// Turns terminal echo off for as long as it lives, when stdin is a
+/// terminal.
+struct EchoOff {
+    #[cfg(unix)]
+    saved: Option<libc::termios>,
+}
+
+impl EchoOff {
+    #[cfg(unix)]
+    fn new() -> EchoOff {
+        // SAFETY: termios is plain data, filled in by tcgetattr before use
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        // SAFETY: `termios` is a valid, writable struct for tcgetattr to
+        // fill; when stdin is not a terminal it fails and writes nothing
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return EchoOff { saved: None };
+        }
+        let mut quiet = termios;
+        quiet.c_lflag &= !libc::ECHO;
+        quiet.c_lflag |= libc::ECHONL;
+        // SAFETY: `quiet` is a copy of the settings tcgetattr just read for
+        // this descriptor, with only the echo flags changed
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &quiet) };
+        EchoOff {
+            saved: Some(termios),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn new() -> EchoOff {
+        EchoOff {}
+    }
+}
+
+impl Drop for EchoOff {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Some(saved) = &self.saved {
+            // SAFETY: `saved` is what tcgetattr returned for this
+            // descriptor, so this puts the terminal back as it was
+            unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, saved) };
+        }
+    }
+}
+
+/// Reads a password from stdin after printing `prompt`, without echoing
+/// it when stdin is a terminal.
+fn read_password(prompt: &str) -> Result<String, Box<dyn Error>> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    let mut password = String::new();
+    {
+        let _echo = EchoOff::new();
+        io::stdin().lock().read_line(&mut password)?;
+    }
+    let password = password.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err("no password given".into());
+    }
+    Ok(password.to_string())
+}
+
+/// Asks for `user`'s password and saves it in the OS keyring: the Secret
+/// Service via `secret-tool` on Linux, the login keychain via `security`
+/// on macOS. `security` prompts on the terminal itself, as a password on
+/// its command line would be readable by any local user through `ps`.
+pub fn store(user: &str) -> Result<(), Box<dyn Error>> {
+    let backend = Backend::current();
+    let args = backend.store_args(user);
+    let status = match backend {
+        Backend::Security => {
+            eprintln!("Neo4j password for {}:", user);
+            backend.status(&args)?
+        }
+        Backend::SecretTool => {
+            let password = read_password(&format!("Neo4j password for {}: ", user))?;
+            let mut child = backend
+                .command(&args)
+                .stdin(Stdio::piped())
+                .spawn()
+                .map_err(|err| missing_tool(backend.tool(), err))?;
+            child
+                .stdin
+                .take()
+                .ok_or("secret-tool has no stdin")?
+                .write_all(password.as_bytes())?;
+            child.wait()?
+        }
+    };
+    if status.success() {
+        Ok(())
+    } else {
+        Err(format!("keyring refused the password for {}", user).into())
+    }
+}
+
+/// The stored password for `user`, or `None` when there is none or no
+/// keyring is available.
+pub fn lookup(user: &str) -> Option<String> {
+    let backend = Backend::current();
+    let output = backend.command(&backend.lookup_args(user)).output().ok()?;
+    found(output.status.success(), output.stdout)
+}
+
+pub fn remove(user: &str) -> Result<(), Box<dyn Error>> {
+    let backend = Backend::current();
+    if backend.status(&backend.remove_args(user))?.success() {
+        Ok(())
+    } else {
+        Err(format!("no stored password for {}", user).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn macos_uses_the_keychain_and_everything_else_the_secret_service() {
+        assert_eq!(Backend::for_os("macos"), Backend::Security);
+        assert_eq!(Backend::for_os("linux"), Backend::SecretTool);
+        assert_eq!(Backend::for_os("freebsd"), Backend::SecretTool);
+        assert_eq!(Backend::Security.tool(), "security");
+        assert_eq!(Backend::SecretTool.tool(), "secret-tool");
+    }
+
+    #[test]
+    fn passwords_never_go_on_the_command_line() {
+        // `-w` with no value after it makes `security` prompt
+        assert_eq!(Backend::Security.store_args("neo4j").last(), Some(&"-w"));
+        assert_eq!(
+            Backend::SecretTool.store_args("neo4j")[3..],
+            ["service", SERVICE, "user", "neo4j"]
+        );
+    }
+
+    #[test]
+    fn every_command_names_the_service_and_user() {
+        for backend in [Backend::SecretTool, Backend::Security] {
+            for args in [
+                backend.store_args("ann"),
+                backend.lookup_args("ann"),
+                backend.remove_args("ann"),
+            ] {
+                assert!(args.contains(&SERVICE), "{:?}", args);
+                assert!(args.contains(&"ann"), "{:?}", args);
+            }
+        }
+    }
+
+    #[test]
+    fn lookups_fall_back_to_none_without_a_password() {
+        assert_eq!(
+            found(true, b"secret\n".to_vec()),
+            Some("secret".to_string())
+        );
+        assert_eq!(
+            found(true, b"pa ss\r\n".to_vec()),
+            Some("pa ss".to_string())
+        );
+        // Not stored, or the keyring is locked
+        assert_eq!(found(false, b"secret\n".to_vec()), None);
+        assert_eq!(found(true, b"\n".to_vec()), None);
+        assert_eq!(found(true, vec![0xff, 0xfe]), None);
+    }
+}
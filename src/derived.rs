@@ -0,0 +1,360 @@
+#[cfg(feature = "neo4j")]
+use crate::cancel::CancellationToken;
+use crate::course::{self, Course};
+#[cfg(feature = "neo4j")]
+use crate::graph;
+#[cfg(feature = "neo4j")]
+use crate::keys;
+use crate::quantity::{IngredientLine, Quantity};
+use crate::readability;
+use crate::rows::Value;
+#[cfg(feature = "neo4j")]
+use crate::server::Server;
+use crate::settings::Settings;
+use crate::Recipe;
+#[cfg(feature = "neo4j")]
+use neo4rs::{BoltType, Graph, Query};
+use serde::Deserialize;
+#[cfg(feature = "neo4j")]
+use std::collections::HashMap;
+#[cfg(feature = "neo4j")]
+use std::error::Error;
+use std::str::FromStr;
+
+/// Properties computed from other recipe fields rather than read from the CSV.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DerivedProperty {
+    Difficulty,
+    TimeBucket,
+    Completeness,
+    MainIngredient,
+    Course,
+    AvgWordsPerStep,
+    ReadingLevel,
+    ImperativeDensity,
+}
+
+pub const ALL: [DerivedProperty; 8] = [
+    DerivedProperty::Difficulty,
+    DerivedProperty::TimeBucket,
+    DerivedProperty::Completeness,
+    DerivedProperty::MainIngredient,
+    DerivedProperty::Course,
+    DerivedProperty::AvgWordsPerStep,
+    DerivedProperty::ReadingLevel,
+    DerivedProperty::ImperativeDensity,
+];
+
+/// The recipe fields the derived properties depend on. Everything here can
+/// be rebuilt from graph properties when backfilling.
+#[derive(Debug, Clone)]
+pub struct DerivedInput {
+    pub name: String,
+    pub description: String,
+    pub minutes: i64,
+    pub steps: Vec<String>,
+    pub ingredients: Vec<IngredientLine>,
+    pub nutrition_len: usize,
+    pub tags: Vec<String>,
+}
+
+impl DerivedInput {
+    pub fn from_recipe(recipe: &Recipe, settings: &Settings) -> DerivedInput {
+        DerivedInput {
+            name: recipe.name.clone(),
+            description: recipe.description.clone(),
+            minutes: i64::from(recipe.minutes),
+            steps: recipe.steps.clone(),
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|line| settings.parse_ingredient(line))
+                .collect(),
+            nutrition_len: recipe.nutrition.len(),
+            tags: recipe.tags.clone(),
+        }
+    }
+}
+
+/// A CONTAINS relationship as read back by `backfill`.
+#[derive(Debug, Deserialize)]
+struct ContainedIngredient {
+    name: String,
+    quantity_min: Option<f64>,
+    quantity_max: Option<f64>,
+    unit: Option<String>,
+    to_taste: Option<bool>,
+}
+
+impl From<ContainedIngredient> for IngredientLine {
+    fn from(contained: ContainedIngredient) -> IngredientLine {
+        let quantity = match (contained.quantity_min, contained.quantity_max) {
+            _ if contained.to_taste == Some(true) => Some(Quantity::ToTaste),
+            (Some(min), Some(max)) if min != max => Some(Quantity::Range { min, max }),
+            (Some(value), _) => Some(Quantity::Amount { value }),
+            _ => None,
+        };
+        IngredientLine {
+            name: contained.name,
+            quantity,
+            unit: contained.unit,
+            brand: None,
+            variant: None,
+            optional: false,
+            garnish: false,
+        }
+    }
+}
+
+impl FromStr for DerivedProperty {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALL.iter()
+            .find(|property| property.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown derived property: {}", s))
+    }
+}
+
+impl DerivedProperty {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DerivedProperty::Difficulty => "difficulty",
+            DerivedProperty::TimeBucket => "time_bucket",
+            DerivedProperty::Completeness => "completeness",
+            DerivedProperty::MainIngredient => "main_ingredient",
+            DerivedProperty::Course => "course",
+            DerivedProperty::AvgWordsPerStep => "avg_words_per_step",
+            DerivedProperty::ReadingLevel => "reading_level",
+            DerivedProperty::ImperativeDensity => "imperative_density",
+        }
+    }
+
+    pub fn compute(&self, input: &DerivedInput) -> Value {
+        match self {
+            DerivedProperty::Difficulty => difficulty(input).into(),
+            DerivedProperty::TimeBucket => time_bucket(input.minutes).into(),
+            DerivedProperty::Completeness => completeness(input).into(),
+            DerivedProperty::MainIngredient => main_ingredient(input).into(),
+            DerivedProperty::Course => course(input).map(|course| course.name()).into(),
+            DerivedProperty::AvgWordsPerStep => {
+                readability::avg_words_per_step(&input.steps).into()
+            }
+            DerivedProperty::ReadingLevel => readability::reading_level(&input.steps).into(),
+            DerivedProperty::ImperativeDensity => {
+                readability::imperative_density(&input.steps).into()
+            }
+        }
+    }
+}
+
+pub fn difficulty(input: &DerivedInput) -> &'static str {
+    let score = input.steps.len() + input.ingredients.len() + (input.minutes.max(0) as usize / 30);
+    match score {
+        0..=12 => "easy",
+        13..=24 => "medium",
+        _ => "hard",
+    }
+}
+
+pub fn time_bucket(minutes: i64) -> &'static str {
+    match minutes {
+        i64::MIN..=15 => "under-15",
+        16..=30 => "15-30",
+        31..=60 => "30-60",
+        61..=120 => "1-2h",
+        _ => "over-2h",
+    }
+}
+
+/// Fraction of the expected fields that are actually populated.
+pub fn completeness(input: &DerivedInput) -> f64 {
+    let checks = [
+        input.minutes > 0,
+        !input.steps.is_empty(),
+        !input.ingredients.is_empty(),
+        !input.description.trim().is_empty(),
+        input.nutrition_len == 7,
+    ];
+    let present = checks.iter().filter(|check| **check).count();
+    present as f64 / checks.len() as f64
+}
+
+pub fn course(input: &DerivedInput) -> Option<Course> {
+    let ingredients: Vec<String> = input
+        .ingredients
+        .iter()
+        .map(|line| line.name.clone())
+        .collect();
+    course::classify(&input.name, &input.tags, &ingredients)
+}
+
+/// Links the recipes in `$ids` to `(:Course)` nodes from their `course`
+/// property, given the course keys by name as `$keys`.
+#[cfg(feature = "neo4j")]
+pub const LINK_COURSE: &str = "MATCH (r:Recipe) WHERE r.id IN $ids AND r.course IS NOT NULL \
+     MERGE (c:Course {key: $keys[r.course]}) ON CREATE SET c.name = r.course \
+     MERGE (r)-[:IN_COURSE]->(c)";
+
+/// `LINK_COURSE` for one recipe bound as `row`, with its id and course
+/// key, for scripts that cannot pass parameters.
+pub const LINK_COURSE_ROW: &str = "MATCH (r:Recipe {id: row.id}) WHERE r.course IS NOT NULL \
+     MERGE (c:Course {key: row.key}) ON CREATE SET c.name = r.course \
+     MERGE (r)-[:IN_COURSE]->(c)";
+
+/// Links the recipes in `ids` to `(:Course)` nodes from their `course`
+/// property, keyed in `namespace`.
+#[cfg(feature = "neo4j")]
+pub fn link_course(ids: Vec<BoltType>, namespace: &str) -> Query {
+    Query::new(LINK_COURSE.to_string())
+        .param("ids", ids)
+        .param("keys", keys::course_keys(namespace))
+}
+
+/// Pantry items that are rarely what a dish is "about".
+const STAPLES: &[&str] = &[
+    "salt",
+    "pepper",
+    "black pepper",
+    "water",
+    "sugar",
+    "butter",
+    "flour",
+    "all-purpose flour",
+    "olive oil",
+    "vegetable oil",
+    "oil",
+    "eggs",
+    "egg",
+    "milk",
+    "garlic",
+    "onion",
+    "baking soda",
+    "baking powder",
+    "vanilla",
+    "cooking spray",
+];
+
+fn singular(word: &str) -> &str {
+    word.strip_suffix("es")
+        .filter(|stem| stem.ends_with("o"))
+        .or_else(|| word.strip_suffix('s'))
+        .unwrap_or(word)
+}
+
+/// Picks the ingredient a recipe is built around: title mentions weigh
+/// most, then large quantities, then position in the list. Staples only win
+/// when nothing else is present.
+pub fn main_ingredient(input: &DerivedInput) -> Option<String> {
+    let title: Vec<&str> = input
+        .name
+        .split(|c: char| !c.is_alphanumeric())
+        .map(singular)
+        .collect();
+    let count = input.ingredients.len() as f64;
+
+    let scored = input.ingredients.iter().enumerate().map(|(index, line)| {
+        let words: Vec<&str> = line.name.split_whitespace().map(singular).collect();
+        let mentions = words.iter().filter(|word| title.contains(word)).count() as f64;
+        let weight = match line.unit.as_deref() {
+            Some("pound" | "pounds" | "lb" | "lbs" | "kg") => 3.0,
+            Some("cup" | "cups" | "can" | "cans" | "package" | "packages") => 1.0,
+            _ => 0.0,
+        };
+        let position = 3.0 * (count - index as f64) / count;
+        let staple = if STAPLES.contains(&line.name.as_str()) {
+            -20.0
+        } else {
+            0.0
+        };
+        (10.0 * mentions + weight + position + staple, line)
+    });
+
+    scored
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, line)| line.name.clone())
+}
+
+/// Recomputes `property` for every recipe already in the graph, reading back
+/// only the properties it depends on. Returns the number of recipes updated.
+#[cfg(feature = "neo4j")]
+pub async fn backfill(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    property: DerivedProperty,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_id: i64 = i64::MIN;
+    let mut updated = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(format!(
+            "MATCH (r:Recipe) WHERE r.id > $after \
+             WITH r ORDER BY r.id LIMIT $limit \
+             OPTIONAL MATCH (r)-[c:CONTAINS]->(i:Ingredient) \
+             WITH r, c, i ORDER BY c.order \
+             WITH r, collect(CASE WHEN i IS NULL THEN NULL ELSE {{ \
+                 name: i.name, quantity_min: c.quantity_min, quantity_max: c.quantity_max, \
+                 unit: c.unit, to_taste: c.to_taste}} END) AS ingredients \
+             CALL {{ WITH r OPTIONAL MATCH (r)-[:TAGGED]->(t:Tag) RETURN collect(t.name) AS tags }} \
+             RETURN r.id AS id, r.name AS name, r.description AS description, tags, \
+                    r.minutes AS minutes, {} AS steps, \
+                    size(coalesce(r.nutrition, [])) AS nutrition_len, ingredients \
+             ORDER BY id",
+            graph::steps(server)
+        ))
+        .param("after", last_id)
+        .param("limit", batch_size as i64);
+
+        let mut result = graph.execute(query).await?;
+        let mut rows: Vec<HashMap<String, BoltType>> = Vec::new();
+
+        while let Some(row) = result.next().await? {
+            let id: i64 = row.get("id")?;
+            let ingredients: Vec<ContainedIngredient> = row.get("ingredients")?;
+            let input = DerivedInput {
+                name: row.get::<Option<String>>("name")?.unwrap_or_default(),
+                description: row
+                    .get::<Option<String>>("description")?
+                    .unwrap_or_default(),
+                minutes: row.get::<Option<i64>>("minutes")?.unwrap_or(0),
+                steps: row.get("steps")?,
+                ingredients: ingredients.into_iter().map(IngredientLine::from).collect(),
+                nutrition_len: row.get::<i64>("nutrition_len")? as usize,
+                tags: row.get("tags")?,
+            };
+
+            let mut values = HashMap::new();
+            values.insert("id".to_string(), BoltType::from(id));
+            values.insert("value".to_string(), property.compute(&input).into());
+            rows.push(values);
+            last_id = id;
+        }
+
+        if rows.is_empty() {
+            break;
+        }
+
+        updated += rows.len();
+        let ids: Vec<BoltType> = rows
+            .iter()
+            .filter_map(|row| row.get("id").cloned())
+            .collect();
+        let update = Query::new(format!(
+            "UNWIND $rows AS row MATCH (r:Recipe {{id: row.id}}) SET r.{} = row.value",
+            property.name()
+        ))
+        .param("rows", rows);
+        graph.run(update).await?;
+        if property == DerivedProperty::Course {
+            graph.run(link_course(ids, &settings.namespace)).await?;
+        }
+        println!("backfilled {} on {} recipes", property.name(), updated);
+    }
+
+    Ok(updated)
+}
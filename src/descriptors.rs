@@ -0,0 +1,141 @@
+use crate::cancel::CancellationToken;
+use crate::interner::{Interner, Symbol};
+use crate::settings::Settings;
+use crate::Recipe;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Write;
+use std::path::Path;
+
+/// Heads listed as examples for each descriptor.
+const EXAMPLES: usize = 5;
+
+/// A word that comes before many different ingredients, as "fresh" does
+/// in "fresh basil" and "fresh ginger": likely a descriptor rather than
+/// part of a name.
+#[derive(Debug, Clone, Serialize)]
+pub struct Descriptor {
+    pub word: String,
+    /// Distinct last words of the names it comes before
+    pub heads: usize,
+    /// Ingredient lines it appears in before the last word
+    pub lines: usize,
+    /// The heads it comes before most often
+    pub examples: Vec<String>,
+}
+
+#[derive(Default)]
+struct Counts {
+    /// Lines by head
+    heads: HashMap<Symbol, usize>,
+    lines: usize,
+    /// Lines whose whole name is the word
+    alone: usize,
+}
+
+/// `analyze descriptors`: finds the descriptors in `input`'s ingredient
+/// names and writes them to `out` as a stoplist.
+pub fn detect_file(
+    input: &Path,
+    mut out: Box<dyn std::io::Write>,
+    settings: &Settings,
+    min_heads: usize,
+    cancel: &CancellationToken,
+) -> Result<Vec<Descriptor>, Box<dyn Error>> {
+    let found = detect(settings.recipes_in(input, cancel)?, settings, min_heads)?;
+    out.write_all(stoplist(&found).as_bytes())?;
+    out.flush()?;
+    eprintln!(
+        "{} candidate descriptors come before at least {} ingredients",
+        found.len(),
+        min_heads
+    );
+    Ok(found)
+}
+
+/// Counts, for each word before the last in the corpus's ingredient names,
+/// the distinct last words it comes before, and returns those with at
+/// least `min_heads`, most first. Words that are an ingredient on their
+/// own in at least `min_heads` lines, as "lemon" is beside "lemon juice"
+/// and "lemon zest", are left out. Names are parsed with `settings`'
+/// rules, so descriptors already in them are not suggested again.
+pub fn detect(
+    recipes: impl IntoIterator<Item = Result<Recipe, Box<dyn Error>>>,
+    settings: &Settings,
+    min_heads: usize,
+) -> Result<Vec<Descriptor>, Box<dyn Error>> {
+    let mut strings = Interner::default();
+    let mut counts: HashMap<Symbol, Counts> = HashMap::new();
+
+    for recipe in recipes {
+        for line in &recipe?.ingredients {
+            let name = settings.parse_ingredient(line).name;
+            let words: Vec<&str> = name.split_whitespace().collect();
+            let Some((last, before)) = words.split_last() else {
+                continue;
+            };
+            if before.is_empty() {
+                counts.entry(strings.intern(last)).or_default().alone += 1;
+                continue;
+            }
+            let head = strings.intern(last);
+            let mut seen = HashSet::new();
+            for word in before {
+                if word.chars().any(|c| c.is_ascii_digit()) || !seen.insert(*word) {
+                    continue;
+                }
+                let entry = counts.entry(strings.intern(word)).or_default();
+                *entry.heads.entry(head).or_default() += 1;
+                entry.lines += 1;
+            }
+        }
+    }
+
+    let mut found: Vec<Descriptor> = counts
+        .into_iter()
+        .filter(|(_, counts)| counts.heads.len() >= min_heads.max(1) && counts.alone < min_heads)
+        .map(|(word, counts)| {
+            let mut heads: Vec<(Symbol, usize)> = counts.heads.into_iter().collect();
+            heads.sort_by(|a, b| {
+                b.1.cmp(&a.1)
+                    .then_with(|| strings.resolve(a.0).cmp(strings.resolve(b.0)))
+            });
+            Descriptor {
+                word: strings.resolve(word).to_string(),
+                heads: heads.len(),
+                lines: counts.lines,
+                examples: heads
+                    .iter()
+                    .take(EXAMPLES)
+                    .map(|(head, _)| strings.resolve(*head).to_string())
+                    .collect(),
+            }
+        })
+        .collect();
+    found.sort_by(|a, b| b.heads.cmp(&a.heads).then_with(|| a.word.cmp(&b.word)));
+    Ok(found)
+}
+
+/// `descriptors` as an ingredient rules file for `--ingredient-rules`, one
+/// per line with its counts, for a reviewer to prune before use.
+pub fn stoplist(descriptors: &[Descriptor]) -> String {
+    let mut out = String::from(
+        "# Candidate descriptors from `analyze descriptors`. Delete any that\n\
+         # are part of an ingredient's name, such as \"green\" in \"green onion\",\n\
+         # then pass this file with --ingredient-rules.\n\
+         descriptors = [\n",
+    );
+    for descriptor in descriptors {
+        let _ = writeln!(
+            out,
+            "    {:?}, # {} heads, {} lines: {}",
+            descriptor.word,
+            descriptor.heads,
+            descriptor.lines,
+            descriptor.examples.join(", ")
+        );
+    }
+    out.push_str("]\n");
+    out
+}
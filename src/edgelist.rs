@@ -0,0 +1,343 @@
+use crate::cancel::CancellationToken;
+use crate::interner::{Interner, Symbol};
+use crate::output::OutputWriter;
+use crate::settings::Settings;
+use crate::Recipe;
+use serde::Deserialize;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+/// Which network an edge list describes.
+///
+/// Each line is `source<TAB>target`, followed by `<TAB>weight` when
+/// weights are asked for, with no header, so `networkx.read_edgelist` and
+/// igraph's `Read_Ncol` take it as is. Recipes are `recipe:ID` and users
+/// `user:ID`; ingredients are their parsed names with spaces written as
+/// underscores, the names the graph's Ingredient nodes carry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Projection {
+    /// Recipe to ingredient; the weight is the recipe's lines naming it
+    RecipeIngredient,
+    /// User to recipe, from the interactions CSV; the weight is the rating
+    UserRecipe,
+    /// Ingredient to ingredient, undirected; the weight is the recipes
+    /// using both
+    IngredientIngredient,
+}
+
+impl Projection {
+    /// The file `export` reads the projection from unless given one.
+    pub fn default_input(self) -> &'static str {
+        match self {
+            Projection::UserRecipe => "data/RAW_interactions.csv",
+            _ => "data/RAW_recipes.csv",
+        }
+    }
+}
+
+impl FromStr for Projection {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "recipe-ingredient" => Ok(Projection::RecipeIngredient),
+            "user-recipe" => Ok(Projection::UserRecipe),
+            "ingredient-ingredient" => Ok(Projection::IngredientIngredient),
+            other => Err(format!(
+                "unknown projection: {} (expected recipe-ingredient, user-recipe or \
+                 ingredient-ingredient)",
+                other
+            )),
+        }
+    }
+}
+
+/// `name` as a node id: whitespace would split it in whitespace-delimited
+/// readers.
+fn node(name: &str) -> String {
+    name.split_whitespace().collect::<Vec<_>>().join("_")
+}
+
+fn write_edge(
+    sink: &mut dyn Write,
+    source: &str,
+    target: &str,
+    weight: Option<impl std::fmt::Display>,
+) -> std::io::Result<()> {
+    match weight {
+        Some(weight) => writeln!(sink, "{}\t{}\t{}", source, target, weight),
+        None => writeln!(sink, "{}\t{}", source, target),
+    }
+}
+
+/// The recipe-to-ingredient edges, one per recipe and ingredient as they
+/// are read.
+struct RecipeIngredientWriter {
+    sink: Box<dyn Write>,
+    weighted: bool,
+    settings: Settings,
+}
+
+impl OutputWriter for RecipeIngredientWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let mut lines: Vec<(String, usize)> = Vec::new();
+        for line in &recipe.ingredients {
+            let name = node(&self.settings.parse_ingredient(line).name);
+            match lines.iter_mut().find(|(known, _)| *known == name) {
+                Some((_, count)) => *count += 1,
+                None => lines.push((name, 1)),
+            }
+        }
+        let source = format!("recipe:{}", recipe.id);
+        for (name, count) in lines {
+            write_edge(
+                &mut self.sink,
+                &source,
+                &name,
+                self.weighted.then_some(count),
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// The ingredient co-occurrence network, counted over every recipe and
+/// written at the end, each pair once with its names in order.
+struct IngredientIngredientWriter {
+    sink: Box<dyn Write>,
+    weighted: bool,
+    names: Interner,
+    pairs: HashMap<(Symbol, Symbol), usize>,
+    settings: Settings,
+}
+
+impl OutputWriter for IngredientIngredientWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let names: BTreeSet<String> = recipe
+            .ingredients
+            .iter()
+            .map(|line| node(&self.settings.parse_ingredient(line).name))
+            .collect();
+        let symbols: Vec<Symbol> = names.iter().map(|name| self.names.intern(name)).collect();
+        for (index, first) in symbols.iter().enumerate() {
+            for second in &symbols[index + 1..] {
+                *self.pairs.entry((*first, *second)).or_default() += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        let names = &self.names;
+        let mut pairs: Vec<(&str, &str, usize)> = self
+            .pairs
+            .iter()
+            .map(|(&(first, second), &count)| (names.resolve(first), names.resolve(second), count))
+            .collect();
+        pairs.sort_unstable();
+        for (first, second, count) in pairs {
+            write_edge(
+                &mut self.sink,
+                first,
+                second,
+                self.weighted.then_some(count),
+            )?;
+        }
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// An edge-list writer for a projection read from recipes; user-recipe
+/// edges come from interactions instead, through [`ratings`].
+pub fn writer(
+    sink: Box<dyn Write>,
+    projection: Projection,
+    weighted: bool,
+    settings: &Settings,
+) -> Result<Box<dyn OutputWriter>, String> {
+    let settings = settings.clone();
+    match projection {
+        Projection::RecipeIngredient => Ok(Box::new(RecipeIngredientWriter {
+            sink,
+            weighted,
+            settings,
+        })),
+        Projection::IngredientIngredient => Ok(Box::new(IngredientIngredientWriter {
+            sink,
+            weighted,
+            names: Interner::default(),
+            pairs: HashMap::new(),
+            settings,
+        })),
+        Projection::UserRecipe => {
+            Err("user-recipe edges are read from interactions, not recipes".to_string())
+        }
+    }
+}
+
+/// The columns of RAW_interactions.csv an edge needs.
+#[derive(Deserialize)]
+struct Rating {
+    user_id: i64,
+    recipe_id: i32,
+    rating: i32,
+}
+
+/// Writes a user-to-recipe edge for each row of the interactions CSV in
+/// `source`, after skipping `skip` rows and for at most `limit`, keeping
+/// only `recipe_id`'s when it is given. Returns the number of edges.
+pub fn ratings(
+    source: impl Read,
+    mut sink: Box<dyn Write>,
+    weighted: bool,
+    skip: usize,
+    limit: usize,
+    recipe_id: Option<i32>,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut written = 0;
+    for result in csv::Reader::from_reader(source)
+        .deserialize()
+        .skip(skip)
+        .take(limit)
+    {
+        cancel.check()?;
+        let rating: Rating = result?;
+        if recipe_id.is_some_and(|id| id != rating.recipe_id) {
+            continue;
+        }
+        write_edge(
+            &mut sink,
+            &format!("user:{}", rating.user_id),
+            &format!("recipe:{}", rating.recipe_id),
+            weighted.then_some(rating.rating),
+        )?;
+        written += 1;
+    }
+    sink.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// A sink whose bytes can be read after the writer owning it is done.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Shared {
+        fn text(&self) -> String {
+            String::from_utf8(self.0.borrow().clone()).unwrap()
+        }
+    }
+
+    fn edges(projection: Projection, weighted: bool, ingredients: &[&[&str]]) -> String {
+        let sink = Shared::default();
+        let mut out = writer(
+            Box::new(sink.clone()),
+            projection,
+            weighted,
+            &Settings::default(),
+        )
+        .unwrap();
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        for (id, lines) in ingredients.iter().enumerate() {
+            recipe.id = id as i32 + 1;
+            recipe.ingredients = lines.iter().map(|line| line.to_string()).collect();
+            out.write_recipe(&recipe).unwrap();
+        }
+        out.finish().unwrap();
+        sink.text()
+    }
+
+    #[test]
+    fn recipe_edges_count_repeated_lines() {
+        assert_eq!(
+            edges(
+                Projection::RecipeIngredient,
+                true,
+                &[&["2 cups all purpose flour", "1 egg", "1 egg yolk", "2 eggs"]]
+            ),
+            "recipe:1\tall_purpose_flour\t1\nrecipe:1\tegg\t2\nrecipe:1\tegg_yolk\t1\n"
+        );
+        assert_eq!(
+            edges(Projection::RecipeIngredient, false, &[&["salt"]]),
+            "recipe:1\tsalt\n"
+        );
+    }
+
+    #[test]
+    fn ingredient_pairs_are_undirected_and_counted_once_per_recipe() {
+        assert_eq!(
+            edges(
+                Projection::IngredientIngredient,
+                true,
+                &[
+                    &["salt", "butter", "1 cup butter"],
+                    &["butter", "sugar", "salt"]
+                ]
+            ),
+            "butter\tsalt\t2\nbutter\tsugar\t1\nsalt\tsugar\t1\n"
+        );
+    }
+
+    #[test]
+    fn ratings_are_user_recipe_edges() {
+        let csv = "user_id,recipe_id,date,rating,review\n\
+                   7,1,2010-01-01,5,good\n\
+                   8,2,2010-01-02,4,ok\n\
+                   9,1,2010-01-03,3,meh\n";
+        let sink = Shared::default();
+        let written = ratings(
+            csv.as_bytes(),
+            Box::new(sink.clone()),
+            true,
+            0,
+            usize::MAX,
+            Some(1),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+        assert_eq!(written, 2);
+        assert_eq!(sink.text(), "user:7\trecipe:1\t5\nuser:9\trecipe:1\t3\n");
+    }
+
+    #[test]
+    fn user_recipe_is_not_a_recipe_projection() {
+        assert_eq!(
+            "user-recipe".parse::<Projection>(),
+            Ok(Projection::UserRecipe)
+        );
+        assert!("users".parse::<Projection>().is_err());
+        assert!(writer(
+            Box::new(Shared::default()),
+            Projection::UserRecipe,
+            false,
+            &Settings::default()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,367 @@
+use crate::cancel::Cancelled;
+use csv::{Reader, StringRecord};
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+/// Why one input row could not be loaded. The load moves on to the next
+/// row unless `--strict` is set.
+#[derive(Debug, thiserror::Error)]
+pub enum RowError {
+    /// The CSV record itself is broken: a wrong field count or bad UTF-8
+    #[error("unreadable row: {0}")]
+    Read(#[from] csv::Error),
+    /// A field does not fit the `Recipe` model
+    #[error("{message}")]
+    Parse {
+        column: Option<String>,
+        value: Option<String>,
+        message: String,
+    },
+    /// A cleanup pass failed, such as writing an externalized field
+    #[error("cleanup failed: {0}")]
+    Clean(String),
+    /// The recipe's batch could not be written to the graph
+    #[error("write failed: {0}")]
+    Write(String),
+}
+
+impl RowError {
+    /// The deserialize error for `record`, naming the column and raw value
+    /// that failed when csv reports them.
+    pub fn parse(err: csv::Error, headers: &StringRecord, record: &StringRecord) -> RowError {
+        let csv::ErrorKind::Deserialize { err, .. } = err.kind() else {
+            return RowError::Read(err);
+        };
+        let field = err.field().map(|field| field as usize);
+        RowError::Parse {
+            column: field
+                .and_then(|field| headers.get(field))
+                .map(str::to_string),
+            value: field
+                .and_then(|field| record.get(field))
+                .map(str::to_string),
+            message: err.kind().to_string(),
+        }
+    }
+
+    fn stage(&self) -> &'static str {
+        match self {
+            RowError::Read(_) => "read",
+            RowError::Parse { .. } => "parse",
+            RowError::Clean(_) => "clean",
+            RowError::Write(_) => "write",
+        }
+    }
+}
+
+/// A data row of the input and the source lines it spans, which are more
+/// than one when a quoted field such as a description holds line breaks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Row {
+    /// Counting from 1 after the header
+    pub number: usize,
+    /// The row's first line, counting the header as line 1
+    pub line: Option<u64>,
+    /// The row's last line, when its fields could be read
+    pub end_line: Option<u64>,
+}
+
+/// Lines csv's record positions fall short of the source by. A record's
+/// position is where the previous line break started, which with CRLF line
+/// ends is still on the previous line.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LineShift(u64);
+
+impl LineShift {
+    /// Measured from `reader` just after it read `headers`.
+    pub fn after_header<R: Read>(reader: &Reader<R>, headers: &StringRecord) -> LineShift {
+        let header_lines = 1 + breaks(headers) as u64;
+        LineShift((header_lines + 1).saturating_sub(reader.position().line()))
+    }
+}
+
+fn breaks(record: &StringRecord) -> usize {
+    record.iter().map(|field| field.matches('\n').count()).sum()
+}
+
+impl Row {
+    /// Data row `number`, read as `record`. Lines come from the position
+    /// csv gives the record or its error, moved by `shift`, plus the line
+    /// breaks inside its fields.
+    pub fn read(number: usize, record: &Result<StringRecord, csv::Error>, shift: LineShift) -> Row {
+        match record {
+            Ok(record) => {
+                let line = record.position().map(|position| position.line() + shift.0);
+                Row {
+                    number,
+                    line,
+                    end_line: line.map(|line| line + breaks(record) as u64),
+                }
+            }
+            Err(err) => Row {
+                number,
+                line: err.position().map(|position| position.line() + shift.0),
+                end_line: None,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Row {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}", self.number)?;
+        match (self.line, self.end_line) {
+            (Some(line), Some(end_line)) if end_line > line => {
+                write!(f, " (lines {}-{})", line, end_line)
+            }
+            (Some(line), _) => write!(f, " (line {})", line),
+            (None, _) => Ok(()),
+        }
+    }
+}
+
+/// One skipped row, as written to the error report.
+#[derive(Debug, Serialize)]
+pub struct RowFailure {
+    /// Data row, counting from 1 after the header
+    pub row: usize,
+    /// First and last source line of the row, counting the header as line 1
+    pub line: Option<u64>,
+    pub end_line: Option<u64>,
+    pub recipe_id: Option<i32>,
+    pub stage: &'static str,
+    pub column: Option<String>,
+    pub error: String,
+    /// The field as read, when one column is to blame
+    pub value: Option<String>,
+}
+
+/// The rows a lenient run skipped.
+#[derive(Debug, Default)]
+pub struct ErrorReport {
+    failures: Vec<RowFailure>,
+}
+
+impl ErrorReport {
+    /// Records `error` for `row` and lets the run go on, or fails with it
+    /// when `strict` is set.
+    pub fn record(
+        &mut self,
+        row: Row,
+        recipe_id: Option<i32>,
+        error: RowError,
+        strict: bool,
+    ) -> Result<(), LoadError> {
+        if strict {
+            return Err(LoadError::Row {
+                row,
+                recipe_id,
+                error,
+            });
+        }
+        eprintln!("{}: {}", row, error);
+        let (column, value) = match &error {
+            RowError::Parse { column, value, .. } => (column.clone(), value.clone()),
+            _ => (None, None),
+        };
+        self.failures.push(RowFailure {
+            row: row.number,
+            line: row.line,
+            end_line: row.end_line,
+            recipe_id,
+            stage: error.stage(),
+            column,
+            error: error.to_string(),
+            value,
+        });
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.failures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    /// Writes the failures as CSV when `path` ends in `.csv`, otherwise as
+    /// a JSON array.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        if path.extension().is_some_and(|extension| extension == "csv") {
+            let mut out = csv::Writer::from_path(path)?;
+            for failure in &self.failures {
+                out.serialize(failure)?;
+            }
+            out.flush()?;
+        } else {
+            fs::write(path, serde_json::to_string_pretty(&self.failures)?)?;
+        }
+        Ok(())
+    }
+}
+
+/// Ends a lenient run that skipped rows, so the process exits with a
+/// status of its own.
+#[derive(Debug, thiserror::Error)]
+#[error("{failed} rows could not be loaded{}", .report.as_ref().map(|path| format!("; see {}", path)).unwrap_or_default())]
+pub struct RowsFailed {
+    pub failed: usize,
+    pub report: Option<String>,
+}
+
+/// No batch committed within the watchdog's limit, so the connection is
+/// taken to be stuck; see `graph::Writers::with_stall_timeout`.
+#[derive(Debug, thiserror::Error)]
+#[error("no batch committed in {}s", .after.as_secs())]
+pub struct Stalled {
+    pub after: Duration,
+}
+
+/// Why `load` or `validate` stopped.
+#[derive(Debug, thiserror::Error)]
+pub enum LoadError {
+    /// A row failed under `--strict`
+    #[error("{row}: {error}")]
+    Row {
+        row: Row,
+        recipe_id: Option<i32>,
+        #[source]
+        error: RowError,
+    },
+    /// A lenient run skipped rows
+    #[error(transparent)]
+    RowsFailed(#[from] RowsFailed),
+    #[error(transparent)]
+    Stalled(#[from] Stalled),
+    /// A resumed load stalled before committing anything
+    #[error("{0}, again without progress; giving up")]
+    StalledAgain(Stalled),
+    #[error(transparent)]
+    Cancelled(#[from] Cancelled),
+    /// Options that do not go together
+    #[error("{0}")]
+    Options(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// Anything else, such as a database or report error
+    #[error(transparent)]
+    Other(Box<dyn Error>),
+}
+
+impl From<Box<dyn Error>> for LoadError {
+    /// Recovers the errors `load` acts on from sinks and helpers that
+    /// return them boxed.
+    fn from(err: Box<dyn Error>) -> LoadError {
+        let err = match err.downcast::<Stalled>() {
+            Ok(stalled) => return LoadError::Stalled(*stalled),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<Cancelled>() {
+            Ok(cancelled) => return LoadError::Cancelled(*cancelled),
+            Err(err) => err,
+        };
+        match err.downcast::<io::Error>() {
+            Ok(err) => LoadError::Io(*err),
+            Err(err) => LoadError::Other(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::output;
+
+    /// Every data row of `path`, as `load` reads it.
+    fn rows(path: &Path) -> Vec<Row> {
+        let mut rdr = Reader::from_reader(output::open_source(path).unwrap());
+        let headers = rdr.headers().unwrap().clone();
+        let shift = LineShift::after_header(&rdr, &headers);
+        rdr.records()
+            .enumerate()
+            .map(|(row, record)| Row::read(row + 1, &record, shift))
+            .collect()
+    }
+
+    fn fixture(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("recipe_app_{}_{}", std::process::id(), name));
+        fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn boxed_errors_load_acts_on_stay_typed() {
+        let stalled: Box<dyn Error> = Stalled {
+            after: Duration::from_secs(30),
+        }
+        .into();
+        assert!(matches!(
+            LoadError::from(stalled),
+            LoadError::Stalled(Stalled { after }) if after.as_secs() == 30
+        ));
+        let cancelled: Box<dyn Error> = Cancelled::TimedOut.into();
+        assert!(matches!(
+            LoadError::from(cancelled),
+            LoadError::Cancelled(Cancelled::TimedOut)
+        ));
+        let other: Box<dyn Error> = "no such profile".into();
+        let other = LoadError::from(other);
+        assert!(matches!(other, LoadError::Other(_)));
+        assert_eq!(other.to_string(), "no such profile");
+    }
+
+    #[test]
+    fn quoted_line_breaks_widen_the_row() {
+        let path = fixture(
+            "multiline.csv",
+            b"id,description\n1,\"first\nsecond\nthird\"\n2,plain\n",
+        );
+        let rows = rows(&path);
+
+        assert_eq!(rows[0].to_string(), "row 1 (lines 2-4)");
+        assert_eq!(rows[1].to_string(), "row 2 (line 5)");
+    }
+
+    #[test]
+    fn byte_order_marks_do_not_shift_lines() {
+        let path = fixture(
+            "bom_rows.csv",
+            b"\xEF\xBB\xBFid,description\r\n1,pie\r\n2,tart\r\n",
+        );
+        let rows = rows(&path);
+
+        assert_eq!(rows[0].to_string(), "row 1 (line 2)");
+        assert_eq!(rows[1].to_string(), "row 2 (line 3)");
+    }
+
+    #[test]
+    fn crlf_line_ends_and_headers_with_line_breaks_count() {
+        let path = fixture(
+            "crlf_rows.csv",
+            b"id,\"long\r\ndescription\"\r\n1,\"a\r\nb\"\r\n2,x,extra\r\n3,c\r\n",
+        );
+        let rows = rows(&path);
+
+        assert_eq!(rows[0].to_string(), "row 1 (lines 3-4)");
+        assert_eq!(rows[1].to_string(), "row 2 (line 5)");
+        assert_eq!(rows[2].to_string(), "row 3 (line 6)");
+    }
+
+    #[test]
+    fn unreadable_rows_keep_their_first_line() {
+        let path = fixture("ragged.csv", b"id,description\n1,\"a\nb\"\n2,x,extra\n");
+        let rows = rows(&path);
+
+        assert_eq!(rows[1].end_line, None);
+        assert_eq!(rows[1].to_string(), "row 2 (line 4)");
+    }
+}
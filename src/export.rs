@@ -0,0 +1,176 @@
+//! The `export` pipeline: reading recipes, filling in the fields every
+//! export carries and writing them in one of the `output` formats or as an
+//! edge list. The binary only turns its options into `ExportOptions`.
+
+use crate::cancel::CancellationToken;
+use crate::edgelist::{self, Projection};
+use crate::output::{self, Format};
+use crate::settings::Settings;
+use crate::{hashing, names, servings, Recipe};
+use csv::Reader;
+use std::error::Error;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// What `export` reads and how it writes it. The defaults are the command
+/// line's: every recipe of the Kaggle dump as JSON lines.
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// Recipe CSV, or the interactions CSV for `Projection::UserRecipe`;
+    /// `-` for stdin
+    pub input: PathBuf,
+    pub format: Format,
+    /// The network written with `Format::Edgelist`
+    pub projection: Projection,
+    pub weighted: bool,
+    /// Statements per transaction block with `Format::CypherStream`
+    pub transaction_size: Option<usize>,
+    pub skip: usize,
+    pub limit: usize,
+    /// Export only this recipe
+    pub recipe_id: Option<i32>,
+    pub names: names::NameCleanup,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        ExportOptions {
+            input: PathBuf::from("data/RAW_recipes.csv"),
+            format: Format::Jsonl,
+            projection: Projection::RecipeIngredient,
+            weighted: false,
+            transaction_size: None,
+            skip: 0,
+            limit: usize::MAX,
+            recipe_id: None,
+            names: names::NameCleanup::default(),
+        }
+    }
+}
+
+/// The derived fields every export carries alongside the source columns.
+pub fn normalize(recipe: &mut Recipe, names: &names::NameCleanup) {
+    recipe.content_hash = Some(hashing::content_hash(recipe));
+    recipe.name_display = Some(names.display(&recipe.name));
+    servings::infer(recipe);
+}
+
+/// Writes the recipes `options` selects to `sink`, read and written with
+/// `settings`, checking `cancel` before each one. A cancelled export
+/// returns `Cancelled` with what was written so far left unfinished.
+pub fn export(
+    options: &ExportOptions,
+    settings: &Settings,
+    sink: Box<dyn Write>,
+    cancel: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let source = output::open_source(&options.input)?;
+    if options.format == Format::Edgelist && options.projection == Projection::UserRecipe {
+        edgelist::ratings(
+            source,
+            sink,
+            options.weighted,
+            options.skip,
+            options.limit,
+            options.recipe_id,
+            cancel,
+        )?;
+        return Ok(());
+    }
+    let mut writer = match (options.format, options.transaction_size) {
+        (Format::CypherStream, Some(size)) => output::cypher_stream(sink, size, settings),
+        (Format::Edgelist, _) => {
+            edgelist::writer(sink, options.projection, options.weighted, settings)?
+        }
+        (format, _) => output::writer(format, sink, settings),
+    };
+
+    for result in settings
+        .recipes(Reader::from_reader(source))
+        .skip(options.skip)
+        .take(options.limit)
+    {
+        cancel.check()?;
+        let mut recipe: Recipe = result?;
+        if options.recipe_id.is_some_and(|id| id != recipe.id) {
+            continue;
+        }
+        normalize(&mut recipe, &options.names);
+        writer.write_recipe(&recipe)?;
+    }
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cancel, testkit};
+    use std::sync::{Arc, Mutex};
+
+    /// A sink the test can read back once the writer is done with it.
+    #[derive(Clone, Default)]
+    struct Shared(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn corpus_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("export_{}_{}.csv", std::process::id(), name));
+        std::fs::write(&path, testkit::corpus_csv(name).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn exports_the_selected_recipe() {
+        let recipes = testkit::recipes("small_corpus").unwrap();
+        let options = ExportOptions {
+            input: corpus_file("small_corpus"),
+            recipe_id: Some(recipes[1].id),
+            ..ExportOptions::default()
+        };
+        let out = Shared::default();
+
+        export(
+            &options,
+            &Settings::default(),
+            Box::new(out.clone()),
+            &CancellationToken::new(),
+        )
+        .unwrap();
+
+        let text = String::from_utf8(out.0.lock().unwrap().clone()).unwrap();
+        let exported: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0]["id"], recipes[1].id);
+        assert!(exported[0]["content_hash"].is_string());
+    }
+
+    #[test]
+    fn cancelled_exports_stop() {
+        let options = ExportOptions {
+            input: corpus_file("small_corpus"),
+            ..ExportOptions::default()
+        };
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let err = export(
+            &options,
+            &Settings::default(),
+            Box::new(Shared::default()),
+            &cancel,
+        )
+        .unwrap_err();
+        assert!(err.is::<cancel::Cancelled>(), "{}", err);
+    }
+}
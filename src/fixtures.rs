@@ -0,0 +1,158 @@
+use crate::parser::python_list;
+use csv::Writer;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use std::error::Error;
+use std::io::Write;
+
+/// Column layout of RAW_recipes.csv.
+pub const HEADER: [&str; 12] = [
+    "name",
+    "id",
+    "minutes",
+    "contributor_id",
+    "submitted",
+    "tags",
+    "nutrition",
+    "n_steps",
+    "steps",
+    "description",
+    "ingredients",
+    "n_ingredients",
+];
+
+const ADJECTIVES: &[&str] = &[
+    "easy",
+    "spicy",
+    "creamy",
+    "grandma's",
+    "quick",
+    "healthy",
+    "crispy",
+    "slow cooker",
+];
+const DISHES: &[&str] = &[
+    "chicken soup",
+    "banana bread",
+    "beef stew",
+    "pasta salad",
+    "apple pie",
+    "veggie curry",
+    "pancakes",
+    "chili",
+    "fried rice",
+    "tacos",
+];
+const INGREDIENTS: &[&str] = &[
+    "salt",
+    "butter",
+    "sugar",
+    "onion",
+    "garlic",
+    "eggs",
+    "flour",
+    "olive oil",
+    "milk",
+    "water",
+    "black pepper",
+    "chicken breasts",
+    "lemon juice",
+    "brown sugar",
+    "baking soda",
+    "carrots",
+    "tomatoes",
+    "parmesan cheese",
+    "cinnamon",
+    "rice",
+];
+const TAGS: &[&str] = &[
+    "60-minutes-or-less",
+    "time-to-make",
+    "course",
+    "main-ingredient",
+    "preparation",
+    "dinner-party",
+    "vegetarian",
+    "easy",
+    "desserts",
+    "main-dish",
+    "american",
+    "mexican",
+];
+const STEPS: &[&str] = &[
+    "preheat oven to 350 degrees",
+    "mix the dry ingredients in a large bowl",
+    "add the eggs and stir until combined",
+    "cook over medium heat for 10 minutes",
+    "season with salt and pepper",
+    "pour into a greased pan",
+    "bake for 25 minutes or until golden",
+    "let cool before serving",
+];
+
+fn pick<'a>(rng: &mut StdRng, pool: &[&'a str], min: usize, max: usize) -> Vec<&'a str> {
+    let count = rng.gen_range(min..=max);
+    pool.choose_multiple(rng, count).copied().collect()
+}
+
+fn row(rng: &mut StdRng, id: usize) -> Vec<String> {
+    let name = format!(
+        "{} {}",
+        ADJECTIVES.choose(rng).copied().unwrap_or_default(),
+        DISHES.choose(rng).copied().unwrap_or_default()
+    );
+    let tags = pick(rng, TAGS, 2, 6);
+    let steps = pick(rng, STEPS, 2, 7);
+    let ingredients = pick(rng, INGREDIENTS, 3, 10);
+    let nutrition: Vec<String> = (0..7)
+        .map(|field| {
+            let max = if field == 0 { 900.0 } else { 120.0 };
+            format!("{:.1}", rng.gen_range(0.0..max))
+        })
+        .collect();
+    let submitted = format!(
+        "{}-{:02}-{:02}",
+        rng.gen_range(2000..2019),
+        rng.gen_range(1..=12),
+        rng.gen_range(1..=28)
+    );
+
+    vec![
+        name.clone(),
+        id.to_string(),
+        rng.gen_range(5..240).to_string(),
+        rng.gen_range(1000..100000).to_string(),
+        submitted,
+        python_list(&tags),
+        format!("[{}]", nutrition.join(", ")),
+        steps.len().to_string(),
+        python_list(&steps),
+        format!("a synthetic {} for tests", name),
+        python_list(&ingredients),
+        ingredients.len().to_string(),
+    ]
+}
+
+/// Writes `count` synthetic rows to the file at `out`, or to stdout for
+/// `-`.
+pub fn generate_to(out: &str, count: usize, seed: u64) -> Result<(), Box<dyn Error>> {
+    match out {
+        "-" => generate(std::io::stdout().lock(), count, seed),
+        path => generate(std::fs::File::create(path)?, count, seed),
+    }
+}
+
+/// Writes `count` synthetic rows in the RAW_recipes.csv layout. The same
+/// `seed` always produces the same file.
+pub fn generate<W: Write>(out: W, count: usize, seed: u64) -> Result<(), Box<dyn Error>> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut wtr = Writer::from_writer(out);
+
+    wtr.write_record(HEADER)?;
+    for index in 0..count {
+        wtr.write_record(row(&mut rng, 100000 + index))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
@@ -0,0 +1,551 @@
+use crate::audit::{TypeAudit, TypePolicy};
+use crate::derived;
+use crate::errors::Stalled;
+use crate::keys;
+use crate::rows::{self, Rows, Table};
+use crate::schema;
+use crate::server::Server;
+use crate::settings::Settings;
+use crate::Recipe;
+use neo4rs::{BoltType, Graph, Query};
+use std::collections::HashSet;
+use std::error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+/// Creates the schema's indexes and uniqueness constraints where missing,
+/// so the loaders' MATCH and MERGE lookups by id, key and name stay fast as
+/// the graph grows and a rerun cannot duplicate a recipe or generated node.
+/// Generated nodes written before surrogate keys get theirs first, in
+/// `settings`' namespace.
+pub async fn create_indexes(graph: &Graph, settings: &Settings) -> Result<(), Box<dyn Error>> {
+    keys::assign_missing(graph, &settings.namespace, 1000).await?;
+    for index in schema::model().indexes {
+        graph
+            .run(Query::new(index.drop_replaced_statement()))
+            .await?;
+        graph.run(Query::new(index.create_statement())).await?;
+    }
+    Ok(())
+}
+
+/// One row as it is built: each value beside its property name.
+type Row = Vec<(&'static str, BoltType)>;
+
+/// One table of a batch, stored a column at a time: a list of values per
+/// property instead of a map per row, so a row costs its values and no
+/// key strings, in memory and on the wire. `unwind` turns the columns back
+/// into rows on the server.
+#[derive(Debug, Default)]
+struct Frame {
+    columns: Vec<(&'static str, Vec<BoltType>)>,
+    len: usize,
+}
+
+impl Frame {
+    /// Appends `row`. A column the row lacks gets a null, as a missing map
+    /// key would read, and a column new to the frame gets nulls for the
+    /// rows before it.
+    fn push(&mut self, row: Row) {
+        for (name, value) in row {
+            let index = match self.columns.iter().position(|(column, _)| *column == name) {
+                Some(index) => index,
+                None => {
+                    self.columns.push((name, Vec::with_capacity(self.len + 1)));
+                    self.columns.len() - 1
+                }
+            };
+            let values = &mut self.columns[index].1;
+            values.resize(self.len, None::<i64>.into());
+            values.push(value);
+        }
+        self.len += 1;
+        for (_, values) in &mut self.columns {
+            values.resize(self.len, None::<i64>.into());
+        }
+    }
+
+    fn extend(&mut self, rows: Vec<Row>) {
+        for row in rows {
+            self.push(row);
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn column(&self, name: &str) -> Option<&[BoltType]> {
+        self.columns
+            .iter()
+            .find(|(column, _)| *column == name)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// Cypher binding each row to `row` as a map, for a query bound with
+    /// `bind`.
+    fn unwind(&self) -> String {
+        self.unwind_prefixed("")
+    }
+
+    /// `unwind` with every parameter name starting with `prefix`, so
+    /// frames can share a query.
+    fn unwind_prefixed(&self, prefix: &str) -> String {
+        let fields: Vec<String> = self
+            .columns
+            .iter()
+            .map(|(name, _)| format!("{1}: ${0}{1}[n]", prefix, name))
+            .collect();
+        format!(
+            "UNWIND range(0, ${}rows - 1) AS n WITH {{{}}} AS row",
+            prefix,
+            fields.join(", ")
+        )
+    }
+
+    /// `query` with a list parameter per column and the row count as
+    /// `$rows`.
+    fn bind(&self, query: Query) -> Query {
+        self.bind_prefixed("", query)
+    }
+
+    /// `bind` for a query made with `unwind_prefixed(prefix)`.
+    fn bind_prefixed(&self, prefix: &str, query: Query) -> Query {
+        self.columns.iter().fold(
+            query.param(&format!("{}rows", prefix), self.len as i64),
+            |query, (name, values)| query.param(&format!("{}{}", prefix, name), values.clone()),
+        )
+    }
+}
+
+/// The steps of the recipe bound to `r`, from the `steps` property or, for
+/// recipes loaded with `--steps-as-nodes`, from its Step nodes in order.
+pub fn steps(server: &Server) -> String {
+    format!(
+        "coalesce(r.steps, {})",
+        server.ordered_list("(r)-[h:HAS_STEP]->(s:Step)", "s.text", "h.index")
+    )
+}
+
+/// Writes recipes and their ingredients to Neo4j, checking every value
+/// against the types already stored in the graph.
+///
+/// Recipes are queued with `add_recipe` and written `batch_size` at a time,
+/// one UNWIND query per batch for the recipes and one each for their
+/// ingredients, tags, steps and their relationships, all in one
+/// transaction.
+pub struct GraphLoader {
+    graph: Graph,
+    audit: TypeAudit,
+    settings: Settings,
+    batch_size: usize,
+    steps_as_nodes: bool,
+    single_statement: bool,
+    recipes: Frame,
+    ingredients: Frame,
+    ingredient_keys: Frame,
+    tags: Frame,
+    steps: Frame,
+    step_counts: Frame,
+    ids: Vec<i32>,
+}
+
+impl GraphLoader {
+    /// A loader writing recipes parsed and keyed with `settings`.
+    pub fn new(graph: Graph, audit: TypeAudit, settings: Settings) -> GraphLoader {
+        GraphLoader {
+            graph,
+            audit,
+            settings,
+            batch_size: 500,
+            steps_as_nodes: false,
+            single_statement: false,
+            recipes: Frame::default(),
+            ingredients: Frame::default(),
+            ingredient_keys: Frame::default(),
+            tags: Frame::default(),
+            steps: Frame::default(),
+            step_counts: Frame::default(),
+            ids: Vec::new(),
+        }
+    }
+
+    /// Samples the graph's existing Recipe property types to check writes
+    /// against, handling mismatches according to `policy`.
+    pub async fn sample(
+        graph: Graph,
+        policy: TypePolicy,
+        settings: Settings,
+    ) -> Result<GraphLoader, Box<dyn Error>> {
+        create_indexes(&graph, &settings).await?;
+        let audit = TypeAudit::sample(&graph, "Recipe", policy).await?;
+        Ok(GraphLoader::new(graph, audit, settings))
+    }
+
+    /// Recipes written per transaction. Defaults to 500.
+    pub fn with_batch_size(mut self, batch_size: usize) -> GraphLoader {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Writes each step as a `(:Step {index, text})` node, linked from the
+    /// recipe by `HAS_STEP {index}` and to the next step by `NEXT`, instead
+    /// of the `steps` list property. Off by default.
+    pub fn with_steps_as_nodes(mut self, steps_as_nodes: bool) -> GraphLoader {
+        self.steps_as_nodes = steps_as_nodes;
+        self
+    }
+
+    /// Writes each batch as one Cypher statement, with a subquery per
+    /// table, instead of a transaction of one statement per table: one
+    /// round trip per batch rather than eight or so, which counts most
+    /// with small batches. Off by default.
+    pub fn with_single_statement(mut self, single_statement: bool) -> GraphLoader {
+        self.single_statement = single_statement;
+        self
+    }
+
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// `row` of `table` as Bolt values, checked against the types the
+    /// graph already stores: every property of a recipe row, and the
+    /// recipe id of the others.
+    fn bolt_row(&self, table: Table, row: rows::Row) -> Result<Row, Box<dyn Error>> {
+        row.into_iter()
+            .map(|(name, value)| {
+                let value = BoltType::from(value);
+                let value = match (table, name) {
+                    (Table::Recipes, _) => self.audit.check(name, value)?,
+                    (_, "recipe_id") => self.audit.check("id", value)?,
+                    _ => value,
+                };
+                Ok((name, value))
+            })
+            .collect()
+    }
+
+    fn bolt_rows(&self, table: Table, rows: &Rows) -> Result<Vec<Row>, Box<dyn Error>> {
+        rows.table(table)
+            .iter()
+            .map(|row| self.bolt_row(table, row.clone()))
+            .collect()
+    }
+
+    /// Queues the recipe and its ingredients, writing the batch once it is
+    /// full. Returns the ids of the recipes written, if any.
+    pub async fn add_recipe(&mut self, recipe: &Recipe) -> Result<Vec<i32>, Box<dyn Error>> {
+        self.queue(recipe)?;
+        if self.is_full() {
+            return self.flush().await;
+        }
+        Ok(Vec::new())
+    }
+
+    /// Builds and queues the recipe's rows without writing anything.
+    pub fn queue(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let rows = Rows::new(recipe, &self.settings, self.steps_as_nodes);
+        let recipes = self.bolt_rows(Table::Recipes, &rows)?;
+        let ingredients = self.bolt_rows(Table::Ingredients, &rows)?;
+        let ingredient_keys = self.bolt_rows(Table::IngredientKeys, &rows)?;
+        let tags = self.bolt_rows(Table::Tags, &rows)?;
+        let steps = self.bolt_rows(Table::Steps, &rows)?;
+        let step_counts = self.bolt_rows(Table::StepCounts, &rows)?;
+        self.recipes.extend(recipes);
+        self.ingredients.extend(ingredients);
+        self.ingredient_keys.extend(ingredient_keys);
+        self.tags.extend(tags);
+        self.steps.extend(steps);
+        self.step_counts.extend(step_counts);
+        self.ids.push(recipe.id);
+        Ok(())
+    }
+
+    /// Whether a batch's worth of recipes is queued.
+    pub fn is_full(&self) -> bool {
+        self.ids.len() >= self.batch_size
+    }
+
+    /// Empties the queue into a `Batch` for `write_batch` or `Writers`.
+    pub fn take_batch(&mut self) -> Batch {
+        Batch {
+            recipes: std::mem::take(&mut self.recipes),
+            ingredients: std::mem::take(&mut self.ingredients),
+            ingredient_keys: std::mem::take(&mut self.ingredient_keys),
+            tags: std::mem::take(&mut self.tags),
+            steps: std::mem::take(&mut self.steps),
+            step_counts: std::mem::take(&mut self.step_counts),
+            ids: std::mem::take(&mut self.ids),
+            single_statement: self.single_statement,
+            namespace: self.settings.namespace.clone(),
+        }
+    }
+
+    /// Writes the queued recipes, linking each to its tags and course, and
+    /// returns their ids. Call once more after the last `add_recipe`. The
+    /// queue is emptied up front, so a failed batch is dropped rather than
+    /// retried with the next one.
+    pub async fn flush(&mut self) -> Result<Vec<i32>, Box<dyn Error>> {
+        let batch = self.take_batch();
+        write_batch(&self.graph, &batch).await
+    }
+}
+
+/// The rows of queued recipes, written in one transaction.
+#[derive(Debug, Default)]
+pub struct Batch {
+    recipes: Frame,
+    ingredients: Frame,
+    ingredient_keys: Frame,
+    tags: Frame,
+    steps: Frame,
+    step_counts: Frame,
+    ids: Vec<i32>,
+    single_statement: bool,
+    namespace: String,
+}
+
+impl Batch {
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// The statements that write `batch`, in order, each run over the rows
+/// of its frame as bound by `Frame::unwind`. Tables the batch has no rows
+/// in are left out.
+fn statements(batch: &Batch) -> Result<Vec<(&Frame, String)>, Box<dyn Error>> {
+    let properties: HashSet<&str> = batch
+        .recipes
+        .columns
+        .iter()
+        .map(|(name, _)| *name)
+        .collect();
+    Ok(rows::statements(&properties)?
+        .into_iter()
+        .map(|(table, body)| {
+            let frame = match table {
+                Table::Recipes => &batch.recipes,
+                Table::Ingredients => &batch.ingredients,
+                Table::IngredientKeys => &batch.ingredient_keys,
+                Table::Tags => &batch.tags,
+                Table::Steps => &batch.steps,
+                Table::StepCounts => &batch.step_counts,
+            };
+            (frame, body)
+        })
+        .filter(|(frame, _)| !frame.is_empty())
+        .collect())
+}
+
+/// Writes `batch` in one transaction and returns its recipe ids. The batch
+/// is only read, so a failed one can be sent again.
+pub async fn write_batch(graph: &Graph, batch: &Batch) -> Result<Vec<i32>, Box<dyn Error>> {
+    if batch.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids = batch.recipes.column("id").unwrap_or_default().to_vec();
+    let statements = statements(batch)?;
+
+    if batch.single_statement {
+        // Each table in a unit subquery, so an empty one skips only itself
+        let mut text = String::new();
+        for (index, (frame, body)) in statements.iter().enumerate() {
+            text.push_str(&format!(
+                "CALL {{ {} {} }} ",
+                frame.unwind_prefixed(&format!("s{}_", index)),
+                body
+            ));
+        }
+        text.push_str(&format!("CALL {{ {} }}", derived::LINK_COURSE));
+        let query = statements.iter().enumerate().fold(
+            Query::new(text)
+                .param("ids", ids)
+                .param("keys", keys::course_keys(&batch.namespace)),
+            |query, (index, (frame, _))| frame.bind_prefixed(&format!("s{}_", index), query),
+        );
+        graph.run(query).await?;
+        return Ok(batch.ids.clone());
+    }
+
+    let mut tx = graph.start_txn().await?;
+    for (frame, body) in &statements {
+        tx.run(frame.bind(Query::new(format!("{} {}", frame.unwind(), body))))
+            .await?;
+    }
+    tx.run(derived::link_course(ids, &batch.namespace)).await?;
+    tx.commit().await?;
+    Ok(batch.ids.clone())
+}
+
+/// Attempts at a batch that fails with a transient error, such as a
+/// deadlock between writers merging the same ingredient.
+const ATTEMPTS: usize = 3;
+
+/// A submitted batch's tag and the ids it wrote, or why it failed.
+pub type Finished<T> = (T, Result<Vec<i32>, String>);
+
+/// Writes batches on up to `workers` pooled connections at once, so
+/// reading and cleaning go on while earlier batches are in flight.
+/// `submit` waits for a free writer, which keeps the reader from getting
+/// more than `workers` batches ahead.
+///
+/// Each batch carries a `T` for the caller to tell the results apart;
+/// results come back in completion order, not submission order.
+pub struct Writers<T> {
+    graph: Graph,
+    permits: Arc<Semaphore>,
+    sender: UnboundedSender<Finished<T>>,
+    results: UnboundedReceiver<Finished<T>>,
+    in_flight: usize,
+    tasks: Vec<JoinHandle<()>>,
+    stall_timeout: Option<Duration>,
+    stalled: Arc<AtomicBool>,
+}
+
+impl<T: Send + 'static> Writers<T> {
+    pub fn new(graph: Graph, workers: usize) -> Writers<T> {
+        let (sender, results) = mpsc::unbounded_channel();
+        Writers {
+            graph,
+            permits: Arc::new(Semaphore::new(workers.max(1))),
+            sender,
+            results,
+            in_flight: 0,
+            tasks: Vec::new(),
+            stall_timeout: None,
+            stalled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Gives up on a batch that has not committed after `timeout`, on the
+    /// grounds that its connection is stuck. Every later call then fails
+    /// with `Stalled`, and the caller should reconnect.
+    pub fn with_stall_timeout(mut self, timeout: Option<Duration>) -> Writers<T> {
+        self.stall_timeout = timeout;
+        self
+    }
+
+    /// Starts writing `batch` once a writer is free and returns the
+    /// results of any batches finished meanwhile.
+    pub async fn submit(&mut self, batch: Batch, tag: T) -> Result<Vec<Finished<T>>, Stalled> {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("writer semaphore is never closed");
+        self.check()?;
+        let graph = self.graph.clone();
+        let sender = self.sender.clone();
+        let (stall_timeout, stalled) = (self.stall_timeout, self.stalled.clone());
+        self.in_flight += 1;
+        self.tasks.retain(|task| !task.is_finished());
+        self.tasks.push(tokio::spawn(async move {
+            let mut result = Err(String::new());
+            for _ in 0..ATTEMPTS {
+                let attempt = write_batch(&graph, &batch);
+                result = match stall_timeout {
+                    Some(limit) => match tokio::time::timeout(limit, attempt).await {
+                        Ok(result) => result.map_err(|err| err.to_string()),
+                        Err(_) => {
+                            stalled.store(true, Ordering::SeqCst);
+                            Err(Stalled { after: limit }.to_string())
+                        }
+                    },
+                    None => attempt.await.map_err(|err| err.to_string()),
+                };
+                match &result {
+                    Err(err) if err.contains("TransientError") => continue,
+                    _ => break,
+                }
+            }
+            drop(permit);
+            let _ = sender.send((tag, result));
+        }));
+
+        let mut finished = Vec::new();
+        while let Ok(result) = self.results.try_recv() {
+            self.in_flight -= 1;
+            finished.push(result);
+        }
+        self.check()?;
+        Ok(finished)
+    }
+
+    /// Waits for every batch still in flight.
+    pub async fn finish(&mut self) -> Result<Vec<Finished<T>>, Stalled> {
+        let mut finished = Vec::new();
+        while self.in_flight > 0 {
+            let Some(result) = self.results.recv().await else {
+                break;
+            };
+            self.in_flight -= 1;
+            finished.push(result);
+            self.check()?;
+        }
+        Ok(finished)
+    }
+
+    fn check(&self) -> Result<(), Stalled> {
+        match self.stall_timeout {
+            Some(after) if self.stalled.load(Ordering::SeqCst) => Err(Stalled { after }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<T> Drop for Writers<T> {
+    /// Abandons batches still in flight, so a stalled run leaves nothing
+    /// writing on the old connections.
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_fills_columns_a_row_lacks_with_nulls() {
+        let mut frame = Frame::default();
+        frame.push(vec![("id", 1.into()), ("unit", "cup".into())]);
+        frame.push(vec![("id", 2.into()), ("brand", "Acme".into())]);
+
+        let null: BoltType = None::<i64>.into();
+        assert_eq!(frame.len, 2);
+        assert_eq!(frame.column("id").unwrap(), &[1.into(), 2.into()]);
+        assert_eq!(frame.column("unit").unwrap(), &["cup".into(), null.clone()]);
+        assert_eq!(frame.column("brand").unwrap(), &[null, "Acme".into()]);
+    }
+
+    #[test]
+    fn frame_unwinds_columns_into_row_maps() {
+        let mut frame = Frame::default();
+        frame.push(vec![("recipe_id", 7.into()), ("key", "k".into())]);
+
+        assert_eq!(
+            frame.unwind(),
+            "UNWIND range(0, $rows - 1) AS n WITH {recipe_id: $recipe_id[n], key: $key[n]} AS row"
+        );
+    }
+
+    #[test]
+    fn prefixed_frames_keep_row_fields_unprefixed() {
+        let mut frame = Frame::default();
+        frame.push(vec![("key", "k".into())]);
+
+        assert_eq!(
+            frame.unwind_prefixed("s2_"),
+            "UNWIND range(0, $s2_rows - 1) AS n WITH {key: $s2_key[n]} AS row"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use crate::precision::Precision;
+use crate::Recipe;
+use serde::Serialize;
+
+/// The fields that make up a recipe's content, with whitespace collapsed
+/// and empty list items dropped so cosmetic differences in the source do
+/// not change the hash. Field order here is part of the hash.
+#[derive(Serialize)]
+struct Canonical<'a> {
+    id: i32,
+    name: String,
+    description: String,
+    minutes: i32,
+    ingredients: Vec<String>,
+    steps: Vec<String>,
+    nutrition: Vec<Option<f64>>,
+    tags: Vec<String>,
+    /// Skipped when absent or inferred, so hashes from before servings
+    /// existed still match and a better guess is not a content change
+    #[serde(skip_serializing_if = "Option::is_none")]
+    servings: Option<i64>,
+    source_url: Option<&'a str>,
+    author: Option<&'a str>,
+    license: Option<&'a str>,
+}
+
+fn normalize(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn normalize_all(items: &[String]) -> Vec<String> {
+    items
+        .iter()
+        .map(|item| normalize(item))
+        .filter(|item| !item.is_empty())
+        .collect()
+}
+
+/// 64-bit FNV-1a. Hand-rolled rather than `DefaultHasher`, whose output
+/// may change between Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// A stable hash of the recipe's source content, as 16 hex digits. Equal
+/// hashes mean nothing a consumer can see has changed. Nutrition is hashed
+/// as parsed, not at `--precision`, and inferred servings are left out, so
+/// the same source hashes the same whatever the load settings.
+pub fn content_hash(recipe: &Recipe) -> String {
+    let as_parsed = Precision::default();
+    let canonical = Canonical {
+        id: recipe.id,
+        name: normalize(&recipe.name),
+        description: normalize(&recipe.description),
+        minutes: recipe.minutes,
+        ingredients: normalize_all(&recipe.ingredients),
+        steps: normalize_all(&recipe.steps),
+        nutrition: recipe
+            .nutrition
+            .iter()
+            .map(|value| Some(as_parsed.float(*value)).filter(|value| !value.is_nan()))
+            .collect(),
+        tags: normalize_all(&recipe.tags),
+        servings: recipe.servings.filter(|_| !recipe.servings_inferred),
+        source_url: recipe.source_url.as_deref(),
+        author: recipe.author.as_deref(),
+        license: recipe.license.as_deref(),
+    };
+    let json = serde_json::to_vec(&canonical).unwrap_or_default();
+    format!("{:016x}", fnv1a(&json))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{servings, testkit};
+
+    #[test]
+    fn inferred_servings_do_not_change_the_hash() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        let before = content_hash(&recipe);
+        recipe.description.push_str(" serves 6");
+        let described = content_hash(&recipe);
+        servings::infer(&mut recipe);
+
+        assert_eq!(recipe.servings, Some(6));
+        assert_ne!(described, before);
+        assert_eq!(content_hash(&recipe), described);
+    }
+
+    #[test]
+    fn stated_servings_and_nutrition_do() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        let before = content_hash(&recipe);
+        recipe.servings = Some(4);
+        let with_servings = content_hash(&recipe);
+        recipe.nutrition[0] += 0.01;
+
+        assert_ne!(with_servings, before);
+        assert_ne!(content_hash(&recipe), with_servings);
+    }
+
+    #[test]
+    fn whitespace_and_empty_items_are_cosmetic() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        let before = content_hash(&recipe);
+        recipe.name = format!("  {}  ", recipe.name.replace(' ', "\t "));
+        recipe.steps.push(" ".to_string());
+
+        assert_eq!(content_hash(&recipe), before);
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt;
+use std::str::FromStr;
+
+const MINUTES_PER_DAY: u64 = 24 * 60;
+
+/// A daily window of UTC wall-clock time, such as `01:00-05:00`, that
+/// `load --active-hours` writes in. A window that ends before it starts
+/// runs past midnight, as `22:00-04:00` does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActiveHours {
+    /// Minutes after midnight
+    start: u64,
+    end: u64,
+}
+
+fn minutes(time: &str) -> Option<u64> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u64, u64) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+impl FromStr for ActiveHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("active hours must look like 01:00-05:00, not {}", s);
+        let (start, end) = s.split_once('-').ok_or_else(invalid)?;
+        let (start, end) = (
+            minutes(start).ok_or_else(invalid)?,
+            minutes(end).ok_or_else(invalid)?,
+        );
+        if start == end {
+            return Err(format!("active hours {} are an empty window", s));
+        }
+        Ok(ActiveHours { start, end })
+    }
+}
+
+impl fmt::Display for ActiveHours {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:02}:{:02}-{:02}:{:02}",
+            self.start / 60,
+            self.start % 60,
+            self.end / 60,
+            self.end % 60
+        )
+    }
+}
+
+impl ActiveHours {
+    /// Seconds from `now`, in seconds since the Unix epoch, until the
+    /// window next opens: 0 inside it.
+    pub fn wait(&self, now: u64) -> u64 {
+        let second = now % (MINUTES_PER_DAY * 60);
+        let minute = second / 60;
+        let inside = if self.start < self.end {
+            (self.start..self.end).contains(&minute)
+        } else {
+            minute >= self.start || minute < self.end
+        };
+        if inside {
+            return 0;
+        }
+        let until = (self.start * 60 + MINUTES_PER_DAY * 60 - second) % (MINUTES_PER_DAY * 60);
+        until.max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Seconds since the epoch at `hours:minutes:seconds` UTC on some later day.
+    fn at(hours: u64, minutes: u64, seconds: u64) -> u64 {
+        20_000 * MINUTES_PER_DAY * 60 + hours * 3600 + minutes * 60 + seconds
+    }
+
+    fn hours(s: &str) -> ActiveHours {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn windows_are_parsed_and_printed() {
+        assert_eq!(hours(" 1:05-04:00").to_string(), "01:05-04:00");
+        assert_eq!(hours("22:00-04:00").to_string(), "22:00-04:00");
+        for invalid in ["01:00", "1-5", "24:00-01:00", "01:60-02:00", "01:00-x"] {
+            assert!(invalid.parse::<ActiveHours>().is_err(), "{}", invalid);
+        }
+        assert!("03:00-03:00"
+            .parse::<ActiveHours>()
+            .unwrap_err()
+            .contains("empty"));
+    }
+
+    #[test]
+    fn waits_until_a_window_in_the_same_day_opens() {
+        let window = hours("01:00-05:00");
+        assert_eq!(window.wait(at(1, 0, 0)), 0);
+        assert_eq!(window.wait(at(4, 59, 59)), 0);
+        assert_eq!(window.wait(at(0, 30, 0)), 30 * 60);
+        assert_eq!(window.wait(at(0, 59, 30)), 30);
+        assert_eq!(window.wait(at(5, 0, 0)), 20 * 3600);
+    }
+
+    #[test]
+    fn windows_can_wrap_past_midnight() {
+        let window = hours("22:00-04:00");
+        assert_eq!(window.wait(at(23, 0, 0)), 0);
+        assert_eq!(window.wait(at(0, 0, 0)), 0);
+        assert_eq!(window.wait(at(3, 59, 59)), 0);
+        assert_eq!(window.wait(at(4, 0, 0)), 18 * 3600);
+        assert_eq!(window.wait(at(21, 0, 0)), 3600);
+    }
+}
@@ -0,0 +1,149 @@
+use serde::Serialize;
+
+/// How a UI can draw an ingredient: an emoji, and an identifier for icon
+/// sets, the emoji's Unicode short name in kebab case.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Icon {
+    pub emoji: &'static str,
+    pub icon: &'static str,
+}
+
+/// Canonical ingredient names and words with an emoji. Names of several
+/// words are listed where their last word alone would pick the wrong one,
+/// as "coconut milk" would be drawn as milk.
+const ICONS: &[(&str, &str, &str)] = &[
+    ("coconut milk", "🥥", "coconut"),
+    ("peanut butter", "🥜", "peanuts"),
+    ("sweet potato", "🍠", "roasted-sweet-potato"),
+    ("bell pepper", "🫑", "bell-pepper"),
+    ("green bean", "🫛", "pea-pod"),
+    ("olive oil", "🫒", "olive"),
+    ("hot sauce", "🌶️", "hot-pepper"),
+    ("ice cream", "🍨", "ice-cream"),
+    ("apple", "🍎", "red-apple"),
+    ("avocado", "🥑", "avocado"),
+    ("bacon", "🥓", "bacon"),
+    ("banana", "🍌", "banana"),
+    ("basil", "🌿", "herb"),
+    ("beef", "🥩", "cut-of-meat"),
+    ("blueberry", "🫐", "blueberries"),
+    ("bread", "🍞", "bread"),
+    ("broccoli", "🥦", "broccoli"),
+    ("butter", "🧈", "butter"),
+    ("carrot", "🥕", "carrot"),
+    ("cheese", "🧀", "cheese-wedge"),
+    ("cherry", "🍒", "cherries"),
+    ("chicken", "🍗", "poultry-leg"),
+    ("chili", "🌶️", "hot-pepper"),
+    ("chocolate", "🍫", "chocolate-bar"),
+    ("coconut", "🥥", "coconut"),
+    ("coffee", "☕", "hot-beverage"),
+    ("corn", "🌽", "ear-of-corn"),
+    ("crab", "🦀", "crab"),
+    ("cucumber", "🥒", "cucumber"),
+    ("egg", "🥚", "egg"),
+    ("eggplant", "🍆", "eggplant"),
+    ("fish", "🐟", "fish"),
+    ("garlic", "🧄", "garlic"),
+    ("ginger", "🫚", "ginger-root"),
+    ("grape", "🍇", "grapes"),
+    ("honey", "🍯", "honey-pot"),
+    ("jalapeno", "🌶️", "hot-pepper"),
+    ("kiwi", "🥝", "kiwi-fruit"),
+    ("lemon", "🍋", "lemon"),
+    ("lettuce", "🥬", "leafy-green"),
+    ("lime", "🍋‍🟩", "lime"),
+    ("mango", "🥭", "mango"),
+    ("melon", "🍈", "melon"),
+    ("milk", "🥛", "glass-of-milk"),
+    ("mushroom", "🍄", "mushroom"),
+    ("noodle", "🍜", "steaming-bowl"),
+    ("olive", "🫒", "olive"),
+    ("onion", "🧅", "onion"),
+    ("orange", "🍊", "tangerine"),
+    ("pasta", "🍝", "spaghetti"),
+    ("pea", "🫛", "pea-pod"),
+    ("peach", "🍑", "peach"),
+    ("peanut", "🥜", "peanuts"),
+    ("pear", "🍐", "pear"),
+    ("pineapple", "🍍", "pineapple"),
+    ("pork", "🥩", "cut-of-meat"),
+    ("potato", "🥔", "potato"),
+    ("rice", "🍚", "cooked-rice"),
+    ("salt", "🧂", "salt"),
+    ("sausage", "🌭", "hot-dog"),
+    ("shrimp", "🦐", "shrimp"),
+    ("spaghetti", "🍝", "spaghetti"),
+    ("spinach", "🥬", "leafy-green"),
+    ("strawberry", "🍓", "strawberry"),
+    ("tea", "🍵", "teacup-without-handle"),
+    ("tomato", "🍅", "tomato"),
+    ("tortilla", "🫓", "flatbread"),
+    ("watermelon", "🍉", "watermelon"),
+    ("wine", "🍷", "wine-glass"),
+];
+
+fn find(name: &str) -> Option<Icon> {
+    ICONS
+        .iter()
+        .find(|(known, _, _)| *known == name)
+        .map(|(_, emoji, icon)| Icon { emoji, icon })
+}
+
+/// The icon for the canonical ingredient name `canonical`: by the whole
+/// name or its longest ending that has one ("red bell pepper", "cherry
+/// tomato"), else by its first word ("chicken breast"). `None` for most
+/// seasonings and pantry staples.
+pub fn lookup(canonical: &str) -> Option<Icon> {
+    let words: Vec<&str> = canonical.split_whitespace().collect();
+    (0..words.len())
+        .find_map(|start| find(&words[start..].join(" ")))
+        .or_else(|| words.first().and_then(|word| find(word)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn icon(canonical: &str) -> Option<&'static str> {
+        lookup(canonical).map(|icon| icon.icon)
+    }
+
+    #[test]
+    fn longest_ending_wins() {
+        assert_eq!(icon("red bell pepper"), Some("bell-pepper"));
+        assert_eq!(icon("cherry tomato"), Some("tomato"));
+        assert_eq!(icon("light coconut milk"), Some("coconut"));
+        assert_eq!(icon("milk"), Some("glass-of-milk"));
+    }
+
+    #[test]
+    fn first_word_is_the_fallback() {
+        assert_eq!(icon("chicken breast"), Some("poultry-leg"));
+        assert_eq!(icon("  garlic   powder "), Some("garlic"));
+    }
+
+    #[test]
+    fn unknown_names_have_no_icon() {
+        assert_eq!(icon("cumin"), None);
+        assert_eq!(icon("all purpose flour"), None);
+        assert_eq!(icon(""), None);
+    }
+
+    #[test]
+    fn table_entries_are_unique_and_named() {
+        for (index, (name, emoji, icon)) in ICONS.iter().enumerate() {
+            assert!(
+                ICONS[index + 1..].iter().all(|(other, _, _)| other != name),
+                "{} is listed twice",
+                name
+            );
+            assert!(!emoji.is_empty(), "{} has no emoji", name);
+            assert!(
+                icon.chars().all(|c| c.is_ascii_lowercase() || c == '-'),
+                "{} is not kebab case",
+                icon
+            );
+        }
+    }
+}
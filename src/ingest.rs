@@ -0,0 +1,878 @@
+//! The `load` and `validate` pipeline: reading the input CSV, cleaning
+//! each recipe and handing it to a sink, with checkpoints, the run log and
+//! the error report. The binary only turns its options into `LoadOptions`.
+
+use crate::errors::{ErrorReport, LineShift, LoadError, Row, RowError, RowsFailed};
+use crate::graph::{Finished, GraphLoader, Writers};
+use crate::settings::Settings;
+use crate::sink::{CompareSink, FileSink, GraphSink, Output, Sink};
+use crate::timings::{self, Stage};
+use crate::warnings::{WarningKind, Warnings};
+use crate::{
+    analyze, audit, autocomplete, cancel, checkpoint, conflicts, hashing, hours, mapping, names,
+    output, pricing, runs, scrub, server, servings, spelling, truncation, views, Recipe,
+};
+use csv::{Reader, StringRecord};
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Everything `load` and `validate` need beyond the run's `Settings`. The
+/// defaults are the command line's: the Kaggle dump, in batches of 500,
+/// through one writer into the profile's database.
+pub struct LoadOptions {
+    pub input: String,
+    pub skip: usize,
+    pub limit: usize,
+    pub mapping: Option<mapping::ColumnMapping>,
+    pub truncation: truncation::TruncationConfig,
+    pub prices: Option<pricing::PriceList>,
+    pub warnings_out: Option<String>,
+    pub type_policy: audit::TypePolicy,
+    pub runs_dir: PathBuf,
+    pub spelling: Option<SpellingOptions>,
+    pub scrub: Option<scrub::Scrubber>,
+    pub repair_steps: bool,
+    pub conflicts: conflicts::ConflictPolicy,
+    pub names: names::NameCleanup,
+    pub materialize_views: bool,
+    pub batch_size: usize,
+    pub steps_as_nodes: bool,
+    pub single_statement: bool,
+    pub workers: usize,
+    pub stall_timeout: Option<Duration>,
+    pub profile_pipeline: bool,
+    pub strict: bool,
+    pub error_report: Option<String>,
+    pub checkpoint: Option<PathBuf>,
+    pub active_hours: Option<hours::ActiveHours>,
+    pub compare: bool,
+    pub output: Output,
+    pub out: Option<String>,
+    pub autocomplete_index: Option<String>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        LoadOptions {
+            input: "data/RAW_recipes.csv".to_string(),
+            skip: 0,
+            limit: usize::MAX,
+            mapping: None,
+            truncation: truncation::TruncationConfig::default(),
+            prices: None,
+            warnings_out: None,
+            type_policy: audit::TypePolicy::default(),
+            runs_dir: PathBuf::from("runs"),
+            spelling: None,
+            scrub: None,
+            repair_steps: true,
+            conflicts: conflicts::ConflictPolicy::default(),
+            names: names::NameCleanup::default(),
+            materialize_views: false,
+            batch_size: 500,
+            steps_as_nodes: false,
+            single_statement: false,
+            workers: 1,
+            stall_timeout: None,
+            profile_pipeline: false,
+            strict: false,
+            error_report: None,
+            checkpoint: None,
+            active_hours: None,
+            compare: false,
+            output: Output::default(),
+            out: None,
+            autocomplete_index: None,
+        }
+    }
+}
+
+pub type Records = csv::StringRecordsIntoIter<Box<dyn Read>>;
+
+/// Opens `input` as CSV records, with its headers renamed through
+/// `mapping` when one is given, and the shift that puts the records on
+/// their source lines.
+pub fn read_records(
+    input: &str,
+    mapping: Option<&mapping::ColumnMapping>,
+) -> Result<(StringRecord, Records, LineShift), Box<dyn Error>> {
+    let mut rdr = Reader::from_reader(output::open_source(Path::new(input))?);
+    let headers = rdr.headers()?.clone();
+    let shift = LineShift::after_header(&rdr, &headers);
+    let headers = match mapping {
+        Some(mapping) => mapping.map_headers(&headers),
+        None => headers,
+    };
+    Ok((headers, rdr.into_records(), shift))
+}
+
+/// One input record as a recipe parsed with `settings`, its columns
+/// renamed and normalized through `mapping` when one is given.
+pub fn parse_record(
+    headers: &StringRecord,
+    mapping: Option<&mapping::ColumnMapping>,
+    record: StringRecord,
+    settings: &Settings,
+) -> Result<Recipe, RowError> {
+    let record = match mapping {
+        Some(mapping) => mapping.normalize_record(headers, &record),
+        None => record,
+    };
+    settings
+        .scope(|| record.deserialize(Some(headers)))
+        .map_err(|err| RowError::parse(err, headers, &record))
+}
+
+/// Reads recipes from `input` with `settings`, renaming and normalizing
+/// columns through `mapping` when one is given.
+pub fn read_recipes<'a>(
+    input: &str,
+    mapping: Option<&'a mapping::ColumnMapping>,
+    settings: &'a Settings,
+) -> Result<impl Iterator<Item = Result<Recipe, Box<dyn Error>>> + 'a, Box<dyn Error>> {
+    let (headers, records, _) = read_records(input, mapping)?;
+    Ok(records.map(move |result| Ok(parse_record(&headers, mapping, result?, settings)?)))
+}
+
+pub struct SpellingOptions {
+    pub confidence: f64,
+    pub whitelist: Option<PathBuf>,
+}
+
+/// Counts ingredient words over the whole input so rare misspellings can be
+/// corrected towards common spellings on the loading pass.
+fn build_speller(
+    options: &LoadOptions,
+    settings: &Settings,
+    spelling: &SpellingOptions,
+) -> Result<spelling::SpellChecker, Box<dyn Error>> {
+    let mut speller = spelling::SpellChecker::new(spelling.confidence);
+    if let Some(path) = &spelling.whitelist {
+        speller.load_whitelist(path)?;
+    }
+    for result in read_recipes(&options.input, options.mapping.as_ref(), settings)? {
+        for ingredient in &result?.ingredients {
+            speller.add(ingredient);
+        }
+    }
+    speller.index();
+    Ok(speller)
+}
+
+/// Builds the spell checker when `--spell-correct` is on.
+fn speller(
+    options: &LoadOptions,
+    settings: &Settings,
+) -> Result<Option<spelling::SpellChecker>, Box<dyn Error>> {
+    match &options.spelling {
+        Some(_) if options.input == "-" => {
+            Err("--spell-correct reads the input twice and cannot read stdin".into())
+        }
+        Some(spelling) => Ok(Some(build_speller(options, settings, spelling)?)),
+        None => Ok(None),
+    }
+}
+
+/// Applies `--on-duplicate-id`, reading the input's ids first when the
+/// policy needs them. Unreadable rows are left for the main pass.
+fn resolver(
+    options: &LoadOptions,
+    settings: &Settings,
+) -> Result<conflicts::Resolver, Box<dyn Error>> {
+    let duplicates = if options.conflicts.needs_scan() {
+        if options.input == "-" {
+            return Err("--on-duplicate-id reads the input twice unless it is first-wins, and cannot read stdin".into());
+        }
+        let ids = read_recipes(&options.input, options.mapping.as_ref(), settings)?
+            .skip(options.skip)
+            .take(options.limit)
+            .filter_map(|result| result.ok())
+            .map(|recipe| recipe.id);
+        conflicts::duplicate_ids(ids)
+    } else {
+        HashMap::new()
+    };
+    conflicts::Resolver::new(options.conflicts, duplicates)
+}
+
+/// Runs the cleanup passes `load` and `validate` share. Returns `false`
+/// when the truncation policy rejects the recipe.
+fn clean(
+    options: &LoadOptions,
+    speller: Option<&spelling::SpellChecker>,
+    warnings: &mut Warnings,
+    recipe: &mut Recipe,
+) -> Result<bool, Box<dyn Error>> {
+    warnings.clean(recipe);
+    if let Some(speller) = speller {
+        for ingredient in recipe.ingredients.iter_mut() {
+            let (corrected, changes) = speller.correct(ingredient);
+            for change in changes {
+                warnings.push(recipe.id, WarningKind::SpellCorrected, change);
+            }
+            *ingredient = corrected;
+        }
+    }
+    if let Some(scrubber) = &options.scrub {
+        for (category, field) in scrubber.apply(recipe) {
+            warnings.redacted(recipe.id, category, field);
+        }
+    }
+    recipe.content_hash = Some(hashing::content_hash(recipe));
+    if options.repair_steps {
+        warnings.repair_steps(recipe);
+    }
+    if options.truncation.is_enabled() {
+        match options.truncation.apply(recipe)? {
+            truncation::TruncationOutcome::Unchanged => {}
+            truncation::TruncationOutcome::Truncated(fields) => {
+                for field in fields {
+                    warnings.push(recipe.id, WarningKind::Truncated, field);
+                }
+            }
+            truncation::TruncationOutcome::Rejected(fields) => {
+                eprintln!(
+                    "skipping recipe {}: {} over the length limit",
+                    recipe.id,
+                    fields.join(", ")
+                );
+                warnings.push(recipe.id, WarningKind::Rejected, fields.join(", "));
+                return Ok(false);
+            }
+        }
+    }
+    // From the truncated name, so --max-name limits what is displayed too
+    recipe.name_display = Some(options.names.display(&recipe.name));
+    servings::infer(recipe);
+    if let Some(prices) = &options.prices {
+        recipe.estimated_cost = prices.estimate(&recipe.ingredients);
+        recipe.estimated_cost_per_serving =
+            prices.estimate_per_serving(&recipe.ingredients, recipe.servings);
+    }
+    Ok(true)
+}
+
+fn report_warnings(warnings: &Warnings, out: Option<&str>) -> Result<(), Box<dyn Error>> {
+    if !warnings.is_empty() {
+        eprintln!("warnings:\n{}", warnings.summary());
+    }
+    if let Some(path) = out {
+        let mut out = output::open_sink(path, false)?;
+        for warning in warnings.entries() {
+            serde_json::to_writer(&mut out, warning)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+    }
+    Ok(())
+}
+
+/// Loads recipes, and with `--stall-timeout` reconnects and resumes from
+/// the checkpoint whenever the writes stall. Without `--checkpoint` a
+/// checkpoint for this run is kept in the runs directory and removed at
+/// the end. Gives up when a resumed load stalls again before committing
+/// anything.
+pub async fn load(
+    mut options: LoadOptions,
+    settings: &Settings,
+    cancel: &cancel::CancellationToken,
+) -> Result<(), LoadError> {
+    if options.compare {
+        if options.output != Output::Neo4j
+            || options.checkpoint.is_some()
+            || options.stall_timeout.is_some()
+            || options.active_hours.is_some()
+        {
+            return Err(LoadError::Options(
+                "--dry-run --compare writes nothing, so takes no --output file, \
+                 --checkpoint, --stall-timeout or --active-hours"
+                    .to_string(),
+            ));
+        }
+        let mut sink = CompareSink::new(
+            settings.profile.connect().await?,
+            options.batch_size,
+            settings.clone(),
+        );
+        load_once(&options, settings, &mut sink, cancel).await?;
+        println!("label\tcreate\tupdate\tunchanged");
+        for (label, changes) in sink.changes() {
+            println!(
+                "{}\t{}\t{}\t{}",
+                label, changes.create, changes.update, changes.unchanged
+            );
+        }
+        return Ok(());
+    }
+    if let Some(default_path) = options.output.default_path() {
+        if options.checkpoint.is_some()
+            || options.stall_timeout.is_some()
+            || options.active_hours.is_some()
+        {
+            return Err(LoadError::Options(
+                "--checkpoint, --stall-timeout and --active-hours need --output neo4j".to_string(),
+            ));
+        }
+        let path = options.out.as_deref().unwrap_or(default_path);
+        let mut sink = FileSink::create(
+            options.output,
+            path,
+            options.batch_size,
+            options.steps_as_nodes,
+            settings,
+        )?;
+        load_once(&options, settings, &mut sink, cancel).await?;
+        eprintln!("recipes written to {}", path);
+        return Ok(());
+    }
+    if options.stall_timeout.is_none() {
+        let mut sink = graph_sink(&options, settings).await?;
+        return load_once(&options, settings, &mut sink, cancel).await;
+    }
+    let scratch = options.checkpoint.is_none().then(|| {
+        options
+            .runs_dir
+            .join(format!("load-{}.checkpoint", std::process::id()))
+    });
+    if let Some(path) = &scratch {
+        std::fs::create_dir_all(&options.runs_dir)?;
+        options.checkpoint = Some(path.clone());
+    }
+    let path = options.checkpoint.clone().unwrap_or_default();
+
+    let mut resumed_at = None;
+    let result = loop {
+        let mut sink = graph_sink(&options, settings).await?;
+        match load_once(&options, settings, &mut sink, cancel).await {
+            Err(LoadError::Stalled(stalled)) => {
+                let committed = checkpoint::read(&path)?;
+                if resumed_at == Some(committed) {
+                    break Err(LoadError::StalledAgain(stalled));
+                }
+                eprintln!(
+                    "{}; reconnecting and resuming after row {}",
+                    stalled, committed
+                );
+                resumed_at = Some(committed);
+            }
+            result => break result,
+        }
+    };
+    if let Some(path) = scratch {
+        let _ = std::fs::remove_file(path);
+    }
+    result
+}
+
+/// A batch's sequence number and the (row, id) of its recipes.
+type Batched = (usize, Vec<(Row, i32)>);
+
+/// A fresh connection for `load` to write through.
+async fn graph_sink(
+    options: &LoadOptions,
+    settings: &Settings,
+) -> Result<GraphSink<Batched>, Box<dyn Error>> {
+    let graph = settings.profile.connect().await?;
+    let loader = GraphLoader::sample(graph, options.type_policy, settings.clone())
+        .await?
+        .with_batch_size(options.batch_size)
+        .with_steps_as_nodes(options.steps_as_nodes)
+        .with_single_statement(options.single_statement);
+    let writers = Writers::new(loader.graph().clone(), options.workers)
+        .with_stall_timeout(options.stall_timeout);
+    Ok(GraphSink::new(loader, writers))
+}
+
+/// Loads recipes into `sink` until the input ends, `cancel` fires or the
+/// sink fails. A stopped load still records its run log and warnings for
+/// the recipes it created.
+async fn load_once(
+    options: &LoadOptions,
+    settings: &Settings,
+    sink: &mut impl Sink<Batched>,
+    cancel: &cancel::CancellationToken,
+) -> Result<(), LoadError> {
+    let speller = speller(options, settings)?;
+    let mut resolver = resolver(options, settings)?;
+
+    let mut warnings = Warnings::default();
+    let mut run = runs::RunLog::start(&options.input);
+    run.dry_run = options.compare;
+    let mut stopped = None;
+    let mut display_names = Vec::new();
+    let mut timings = timings::Timings::default();
+    let mut failures = ErrorReport::default();
+    // (row, id) of each queued recipe, to report a failed batch by row
+    let mut batch = Vec::new();
+    let mut watermark = checkpoint::Watermark::default();
+    let checkpoint = options.checkpoint.as_deref();
+    let resume_from = match checkpoint {
+        Some(path) => checkpoint::read(path)?,
+        None => 0,
+    };
+    if resume_from > 0 {
+        eprintln!("resuming after row {}", resume_from);
+    }
+    let (headers, records, shift) = read_records(&options.input, options.mapping.as_ref())?;
+    let mut records = records.enumerate().skip(options.skip).take(options.limit);
+    loop {
+        if let Err(reason) = cancel.check() {
+            stopped = Some(reason.into());
+            break;
+        }
+        let started = Instant::now();
+        let Some((row, record)) = records.next() else {
+            break;
+        };
+        let row = Row::read(row + 1, &record, shift);
+        if row.number <= resume_from {
+            continue;
+        }
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                failures.record(row, None, err.into(), options.strict)?;
+                continue;
+            }
+        };
+        timings.add(Stage::Read, started.elapsed());
+
+        let started = Instant::now();
+        let parsed = parse_record(&headers, options.mapping.as_ref(), record, settings);
+        timings.add(Stage::Parse, started.elapsed());
+        let recipe = match parsed {
+            Ok(recipe) => recipe,
+            Err(err) => {
+                failures.record(row, None, err, options.strict)?;
+                continue;
+            }
+        };
+        let Some(mut recipe) = resolver.resolve(row.number, recipe)? else {
+            continue;
+        };
+
+        let started = Instant::now();
+        let cleaned = clean(options, speller.as_ref(), &mut warnings, &mut recipe);
+        timings.add(Stage::Normalize, started.elapsed());
+        match cleaned {
+            Ok(true) => {}
+            Ok(false) => {
+                run.skipped.push(recipe.id);
+                continue;
+            }
+            Err(err) => {
+                let err = RowError::Clean(err.to_string());
+                failures.record(row, Some(recipe.id), err, options.strict)?;
+                continue;
+            }
+        }
+        let started = Instant::now();
+        let queued = sink.queue(&recipe);
+        timings.add(Stage::Serialize, started.elapsed());
+        if let Err(err) = queued {
+            let err = RowError::Write(err.to_string());
+            failures.record(row, Some(recipe.id), err, options.strict)?;
+            continue;
+        }
+        batch.push((row, recipe.id));
+
+        if sink.is_full() {
+            let held = hold(options, sink, cancel, |finished| {
+                settle(
+                    finished,
+                    &mut run,
+                    &mut failures,
+                    &mut watermark,
+                    checkpoint,
+                    options.strict,
+                )
+            });
+            if let Some(reason) = held.await? {
+                stopped = Some(reason);
+                break;
+            }
+            let started = Instant::now();
+            let rows = std::mem::take(&mut batch);
+            let finished = match sink.submit((watermark.issue(), rows)).await {
+                Ok(finished) => finished,
+                Err(err) => {
+                    stopped = Some(err.into());
+                    break;
+                }
+            };
+            settle(
+                finished,
+                &mut run,
+                &mut failures,
+                &mut watermark,
+                checkpoint,
+                options.strict,
+            )?;
+            timings.add(Stage::Commit, started.elapsed());
+            timings.end_batch();
+            eprintln!("{} recipes loaded", run.created.len());
+        }
+        if options.autocomplete_index.is_some() {
+            display_names.push((recipe.id, recipe.name_display.unwrap_or(recipe.name)));
+        }
+    }
+    // Recipes already cleaned and queued are written even when cancelled,
+    // but not to a sink that has failed
+    let started = Instant::now();
+    let failed = |stopped: &Option<LoadError>| {
+        stopped
+            .as_ref()
+            .is_some_and(|reason| !matches!(reason, LoadError::Cancelled(_)))
+    };
+    if !batch.is_empty() && !failed(&stopped) {
+        let held = hold(options, sink, cancel, |finished| {
+            settle(
+                finished,
+                &mut run,
+                &mut failures,
+                &mut watermark,
+                checkpoint,
+                options.strict,
+            )
+        });
+        if let Some(reason) = held.await? {
+            stopped = Some(reason);
+        }
+    }
+    if !batch.is_empty() && stopped.is_none() {
+        match sink.submit((watermark.issue(), batch)).await {
+            Ok(finished) => settle(
+                finished,
+                &mut run,
+                &mut failures,
+                &mut watermark,
+                checkpoint,
+                options.strict,
+            )?,
+            Err(err) => stopped = Some(err.into()),
+        }
+    }
+    if !failed(&stopped) {
+        match sink.finish().await {
+            Ok(finished) => settle(
+                finished,
+                &mut run,
+                &mut failures,
+                &mut watermark,
+                checkpoint,
+                options.strict,
+            )?,
+            Err(err) => stopped = Some(err.into()),
+        }
+    }
+    timings.add(Stage::Commit, started.elapsed());
+    timings.end_batch();
+
+    eprintln!("loaded {} recipes", run.created.len());
+    if options.profile_pipeline {
+        eprintln!("{}", timings.report());
+    }
+    run.conflicts = resolver.conflicts();
+    run.redactions = warnings
+        .redactions()
+        .iter()
+        .map(|(category, count)| (category.name().to_string(), *count))
+        .collect();
+    if !run.conflicts.is_empty() {
+        eprintln!("{} recipe ids appeared more than once", run.conflicts.len());
+    }
+    let run_path = run.save(&options.runs_dir)?;
+    eprintln!("run {} recorded in {}", run.run_id, run_path.display());
+    report_warnings(&warnings, options.warnings_out.as_deref())?;
+    let report = match &options.error_report {
+        Some(path) => path.clone(),
+        None => options
+            .runs_dir
+            .join(format!("{}.errors.json", run.run_id))
+            .display()
+            .to_string(),
+    };
+    if !failures.is_empty() {
+        failures.save(Path::new(&report))?;
+    }
+    if let Some(reason) = stopped {
+        return Err(reason);
+    }
+    if let Some(path) = &options.autocomplete_index {
+        autocomplete::Index::build(display_names).save(path)?;
+        eprintln!("name index written to {}", path);
+    }
+
+    if let Some(graph) = sink.graph() {
+        analyze::ingredient_counts(graph, options.batch_size, cancel).await?;
+        if options.materialize_views {
+            let server = server::detect(graph).await?;
+            views::materialize(graph, &server, options.batch_size, cancel).await?;
+        }
+    }
+
+    if !failures.is_empty() {
+        return Err(RowsFailed {
+            failed: failures.len(),
+            report: Some(report),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+/// Outside `--active-hours`, waits for the batches in flight, settles them
+/// so the checkpoint is current, then sleeps until the window opens.
+/// Returns why the load must stop instead, if the sink fails or `cancel`
+/// fires; a rerun resumes from the checkpoint.
+async fn hold<S: Sink<Batched>>(
+    options: &LoadOptions,
+    sink: &mut S,
+    cancel: &cancel::CancellationToken,
+    mut settle: impl FnMut(Vec<Finished<Batched>>) -> Result<(), LoadError>,
+) -> Result<Option<LoadError>, LoadError> {
+    let Some(hours) = options.active_hours else {
+        return Ok(None);
+    };
+    let wait = hours.wait(runs::now());
+    if wait == 0 {
+        return Ok(None);
+    }
+    match sink.flush().await {
+        Ok(finished) => settle(finished)?,
+        Err(err) => return Ok(Some(err.into())),
+    }
+    eprintln!(
+        "outside active hours {} UTC; pausing {}h{:02}m",
+        hours,
+        wait / 3600,
+        wait / 60 % 60
+    );
+    let opens = Instant::now() + Duration::from_secs(wait);
+    while let Some(left) = opens.checked_duration_since(Instant::now()) {
+        if let Err(reason) = cancel.check() {
+            return Ok(Some(reason.into()));
+        }
+        if left.is_zero() {
+            break;
+        }
+        tokio::time::sleep(left.min(Duration::from_secs(1))).await;
+    }
+    eprintln!("active hours {} UTC open; resuming", hours);
+    Ok(None)
+}
+
+/// Records batches the writers finished and moves `checkpoint` past the
+/// ones every earlier batch has finished before. When a batch failed,
+/// every row in it is recorded in `failures`, or the error is returned
+/// with `strict`, and the checkpoint waits for the next batch to commit.
+fn settle(
+    finished: Vec<Finished<Batched>>,
+    run: &mut runs::RunLog,
+    failures: &mut ErrorReport,
+    watermark: &mut checkpoint::Watermark,
+    checkpoint: Option<&Path>,
+    strict: bool,
+) -> Result<(), LoadError> {
+    for ((seq, batch), result) in finished {
+        let last_row = match result {
+            Ok(written) => {
+                run.created.extend(written);
+                batch.last().map(|(row, _)| row.number)
+            }
+            Err(message) => {
+                for (row, id) in &batch {
+                    failures.record(*row, Some(*id), RowError::Write(message.clone()), strict)?;
+                }
+                None
+            }
+        };
+        if let (Some(path), Some(row)) = (checkpoint, watermark.finish(seq, last_row)) {
+            checkpoint::write(path, row)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads and cleans recipes exactly as `load` would, without a graph.
+/// Unreadable rows are reported and counted rather than ending the run,
+/// unless `--strict` is set.
+pub fn validate(
+    options: &LoadOptions,
+    settings: &Settings,
+    cancel: &cancel::CancellationToken,
+) -> Result<(), LoadError> {
+    let speller = speller(options, settings)?;
+    let mut resolver = resolver(options, settings)?;
+    let mut warnings = Warnings::default();
+    let mut failures = ErrorReport::default();
+    let (mut valid, mut rejected) = (0, 0);
+    let (headers, records, shift) = read_records(&options.input, options.mapping.as_ref())?;
+    let records = records.enumerate().skip(options.skip).take(options.limit);
+    for (row, record) in records {
+        cancel.check()?;
+        let row = Row::read(row + 1, &record, shift);
+        let parsed = record
+            .map_err(RowError::from)
+            .and_then(|record| parse_record(&headers, options.mapping.as_ref(), record, settings));
+        let recipe = match parsed {
+            Ok(recipe) => recipe,
+            Err(err) => {
+                failures.record(row, None, err, options.strict)?;
+                continue;
+            }
+        };
+        let Some(mut recipe) = resolver.resolve(row.number, recipe)? else {
+            continue;
+        };
+        match clean(options, speller.as_ref(), &mut warnings, &mut recipe) {
+            Ok(true) => valid += 1,
+            Ok(false) => rejected += 1,
+            Err(err) => {
+                let err = RowError::Clean(err.to_string());
+                failures.record(row, Some(recipe.id), err, options.strict)?;
+            }
+        }
+    }
+
+    eprintln!(
+        "{} recipes valid, {} rejected, {} unreadable",
+        valid,
+        rejected,
+        failures.len()
+    );
+    for conflict in resolver.conflicts() {
+        eprintln!(
+            "recipe id {} is on rows {:?}; kept {}",
+            conflict.id, conflict.rows, conflict.kept
+        );
+    }
+    report_warnings(&warnings, options.warnings_out.as_deref())?;
+    if let Some(path) = &options.error_report {
+        failures.save(Path::new(path))?;
+    }
+    if !failures.is_empty() {
+        return Err(RowsFailed {
+            failed: failures.len(),
+            report: options.error_report.clone(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn corpus_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ingest_{}_{}.csv", std::process::id(), name));
+        std::fs::write(&path, testkit::corpus_csv(name).unwrap()).unwrap();
+        path
+    }
+
+    #[test]
+    fn validate_reads_a_clean_corpus() {
+        let options = LoadOptions {
+            input: corpus_file("small_corpus").display().to_string(),
+            ..LoadOptions::default()
+        };
+
+        validate(
+            &options,
+            &Settings::default(),
+            &cancel::CancellationToken::new(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn display_name_is_made_from_the_truncated_name() {
+        let options = LoadOptions {
+            truncation: truncation::TruncationConfig {
+                max_name: Some(5),
+                ..truncation::TruncationConfig::default()
+            },
+            ..LoadOptions::default()
+        };
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.name = "chicken soup".to_string();
+
+        assert!(clean(&options, None, &mut Warnings::default(), &mut recipe).unwrap());
+        assert_eq!(recipe.name, "chick");
+        assert_eq!(recipe.name_display.as_deref(), Some("Chick"));
+    }
+
+    #[test]
+    fn validate_stops_when_cancelled() {
+        let options = LoadOptions {
+            input: corpus_file("small_corpus").display().to_string(),
+            ..LoadOptions::default()
+        };
+        let cancel = cancel::CancellationToken::new();
+        cancel.cancel();
+
+        let err = validate(&options, &Settings::default(), &cancel).unwrap_err();
+        assert!(matches!(err, LoadError::Cancelled(_)), "{}", err);
+    }
+
+    #[test]
+    fn strict_validate_keeps_the_row_error() {
+        let csv = String::from_utf8(testkit::corpus_csv("small_corpus").unwrap()).unwrap();
+        let mut rdr = Reader::from_reader(csv.as_bytes());
+        let mut out = csv::Writer::from_writer(Vec::new());
+        out.write_record(rdr.headers().unwrap()).unwrap();
+        for (index, record) in rdr.records().enumerate() {
+            let mut fields: Vec<String> = record.unwrap().iter().map(str::to_string).collect();
+            if index == 1 {
+                fields[2] = "soon".to_string();
+            }
+            out.write_record(&fields).unwrap();
+        }
+        let path = std::env::temp_dir().join(format!("ingest_{}_strict.csv", std::process::id()));
+        std::fs::write(&path, out.into_inner().unwrap()).unwrap();
+        let options = LoadOptions {
+            input: path.display().to_string(),
+            strict: true,
+            ..LoadOptions::default()
+        };
+
+        let err = validate(
+            &options,
+            &Settings::default(),
+            &cancel::CancellationToken::new(),
+        )
+        .unwrap_err();
+        let LoadError::Row {
+            row,
+            error: RowError::Parse { column, value, .. },
+            ..
+        } = err
+        else {
+            panic!("{}", err);
+        };
+        assert_eq!(row.number, 2);
+        assert_eq!(column.as_deref(), Some("minutes"));
+        assert_eq!(value.as_deref(), Some("soon"));
+
+        let lenient = LoadOptions {
+            strict: false,
+            ..options
+        };
+        let err = validate(
+            &lenient,
+            &Settings::default(),
+            &cancel::CancellationToken::new(),
+        )
+        .unwrap_err();
+        assert!(
+            matches!(err, LoadError::RowsFailed(RowsFailed { failed: 1, .. })),
+            "{}",
+            err
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::output;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+
+/// Words that end in "s" without being plurals, or whose plural is the
+/// usual name.
+const KEEP: &[&str] = &[
+    "asparagus",
+    "bitters",
+    "chips",
+    "couscous",
+    "greens",
+    "grits",
+    "hummus",
+    "molasses",
+    "oats",
+    "swiss",
+];
+
+/// Plurals the suffix rules get wrong.
+const SINGULAR: &[(&str, &str)] = &[
+    ("brownies", "brownie"),
+    ("cookies", "cookie"),
+    ("leaves", "leaf"),
+    ("loaves", "loaf"),
+    ("smoothies", "smoothie"),
+];
+
+/// Other names for the same ingredient, by canonical form.
+const ALIASES: &[(&str, &str)] = &[
+    ("ap flour", "all purpose flour"),
+    ("plain flour", "all purpose flour"),
+    ("confectioners sugar", "powdered sugar"),
+    ("icing sugar", "powdered sugar"),
+    ("scallion", "green onion"),
+    ("spring onion", "green onion"),
+    ("garbanzo bean", "chickpea"),
+];
+
+/// Ingredient name rules, read from TOML:
+///
+/// ```toml
+/// builtin = true            # keep the bundled entries (default)
+/// keep = ["capers"]         # words never singularized
+/// descriptors = ["fresh"]   # words dropped before the last
+/// [singular]
+/// "geese" = "goose"
+/// [aliases]
+/// "ap flour" = "all purpose flour"
+/// ```
+///
+/// Alias keys and targets go through the same normalization as names, so
+/// "Confectioners' sugar" matches "confectioners sugar". There are no
+/// bundled descriptors; `analyze descriptors` suggests a list to review.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct RulesFile {
+    pub builtin: Option<bool>,
+    pub keep: Vec<String>,
+    pub descriptors: Vec<String>,
+    pub singular: HashMap<String, String>,
+    pub aliases: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    keep: HashSet<String>,
+    descriptors: HashSet<String>,
+    singular: HashMap<String, String>,
+    aliases: HashMap<String, String>,
+}
+
+/// Lowercases `name`, reads "flour, all purpose" as "all purpose flour",
+/// and turns punctuation into spaces. Apostrophes are dropped and `%` is
+/// kept, for "2% milk".
+fn clean(name: &str) -> String {
+    let lower = name.trim().to_lowercase();
+    let ordered = match lower.split_once(',') {
+        Some((head, tail)) if !tail.contains(',') && tail.split_whitespace().count() <= 3 => {
+            format!("{} {}", tail, head)
+        }
+        _ => lower,
+    };
+    ordered
+        .chars()
+        .filter(|c| *c != '\'' && *c != '’')
+        .map(|c| {
+            if c.is_alphanumeric() || c == '%' {
+                c
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+impl Rules {
+    pub fn builtin() -> Rules {
+        Rules::from_file(RulesFile::default())
+    }
+
+    pub fn load(path: &Path) -> Result<Rules, Box<dyn Error>> {
+        let file: RulesFile = toml::from_str(&output::read_text(path)?)?;
+        Ok(Rules::from_file(file))
+    }
+
+    fn from_file(file: RulesFile) -> Rules {
+        let mut rules = Rules::default();
+        let owned = |pairs: &[(&str, &str)]| -> Vec<(String, String)> {
+            pairs
+                .iter()
+                .map(|(from, to)| (from.to_string(), to.to_string()))
+                .collect()
+        };
+        let mut aliases = Vec::new();
+        if file.builtin.unwrap_or(true) {
+            rules.keep.extend(KEEP.iter().map(|word| word.to_string()));
+            rules.singular.extend(owned(SINGULAR));
+            aliases.extend(owned(ALIASES));
+        }
+        rules.keep.extend(file.keep.iter().map(|word| clean(word)));
+        rules
+            .descriptors
+            .extend(file.descriptors.iter().map(|word| clean(word)));
+        rules.singular.extend(
+            file.singular
+                .iter()
+                .map(|(from, to)| (clean(from), clean(to))),
+        );
+        aliases.extend(file.aliases);
+
+        // Aliases are looked up by canonical name, so both sides are
+        // normalized with the final word rules
+        rules.aliases = aliases
+            .iter()
+            .map(|(from, to)| (rules.normalize(from), rules.normalize(to)))
+            .collect();
+        rules
+    }
+
+    /// The singular of `word`, by the exceptions first and then the usual
+    /// English suffixes.
+    fn singular(&self, word: &str) -> String {
+        if let Some(singular) = self.singular.get(word) {
+            return singular.clone();
+        }
+        if word.len() <= 3 || self.keep.contains(word) {
+            return word.to_string();
+        }
+        if let Some(stem) = word.strip_suffix("ies") {
+            return format!("{}y", stem);
+        }
+        for suffix in ["oes", "ches", "shes", "sses", "xes"] {
+            if let Some(stem) = word.strip_suffix(suffix) {
+                return format!("{}{}", stem, &suffix[..suffix.len() - 2]);
+            }
+        }
+        if word.ends_with("ss") || word.ends_with("us") || word.ends_with("is") {
+            return word.to_string();
+        }
+        word.strip_suffix('s').unwrap_or(word).to_string()
+    }
+
+    /// `clean`, then descriptors dropped and the last word made singular:
+    /// "Tomatoes" and "tomato" both become "tomato", and with "fresh" a
+    /// descriptor so does "fresh tomatoes". The last word is never dropped.
+    fn normalize(&self, name: &str) -> String {
+        let cleaned = clean(name);
+        let cleaned = match cleaned.rsplit_once(' ') {
+            Some((head, last)) if !self.descriptors.is_empty() => head
+                .split(' ')
+                .filter(|word| !self.descriptors.contains(*word))
+                .chain(std::iter::once(last))
+                .collect::<Vec<_>>()
+                .join(" "),
+            _ => cleaned,
+        };
+        match cleaned.rsplit_once(' ') {
+            Some((head, last)) => format!("{} {}", head, self.singular(last)),
+            None => self.singular(&cleaned),
+        }
+    }
+
+    /// The start of `word` its plurals share with it: "berr" for "berry",
+    /// "lea" for "leaf" by way of "leaves", and `word` itself for "tomato".
+    fn stem(&self, word: &str) -> String {
+        let mut stem = word.strip_suffix('y').unwrap_or(word).to_string();
+        for (plural, singular) in &self.singular {
+            if singular == word {
+                let shared = stem
+                    .chars()
+                    .zip(plural.chars())
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                stem = stem.chars().take(shared).collect();
+            }
+        }
+        stem
+    }
+
+    /// Text that any spelling of the ingredient `canonical` contains once
+    /// lowercased, whether or not it was normalized when stored: the stem
+    /// of the last word of `canonical` and of each name aliased to it.
+    /// Coarse on purpose; "oil" turns up every oil, and callers narrow the
+    /// names found with `canonical`.
+    pub fn stems(&self, canonical: &str) -> Vec<String> {
+        let mut stems: Vec<String> = std::iter::once(canonical)
+            .chain(
+                self.aliases
+                    .iter()
+                    .filter(|(_, to)| to.as_str() == canonical)
+                    .map(|(from, _)| from.as_str()),
+            )
+            .filter_map(|name| name.split_whitespace().last())
+            .map(|word| self.stem(word))
+            .filter(|stem| !stem.is_empty())
+            .collect();
+        stems.sort();
+        stems.dedup();
+        stems
+    }
+
+    /// The name an ingredient is stored under: normalized, then resolved
+    /// through the alias table. Falls back to the trimmed input when
+    /// normalization leaves nothing.
+    pub fn canonical(&self, name: &str) -> String {
+        let normalized = self.normalize(name);
+        if normalized.is_empty() {
+            return name.trim().to_string();
+        }
+        self.aliases.get(&normalized).cloned().unwrap_or(normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plurals_become_singular() {
+        let rules = Rules::builtin();
+        for (name, canonical) in [
+            ("Tomatoes", "tomato"),
+            ("berries", "berry"),
+            ("peaches", "peach"),
+            ("glasses", "glass"),
+            ("eggs", "egg"),
+            ("bay leaves", "bay leaf"),
+            ("cookies", "cookie"),
+            ("Asparagus", "asparagus"),
+            ("oats", "oats"),
+            ("peas", "pea"),
+        ] {
+            assert_eq!(rules.canonical(name), canonical, "{}", name);
+        }
+    }
+
+    #[test]
+    fn names_are_cleaned_and_aliased() {
+        let rules = Rules::builtin();
+        assert_eq!(rules.canonical("Flour, all-purpose"), "all purpose flour");
+        assert_eq!(rules.canonical("AP flour"), "all purpose flour");
+        assert_eq!(rules.canonical("Confectioners' Sugar"), "powdered sugar");
+        assert_eq!(rules.canonical("Scallions"), "green onion");
+        assert_eq!(rules.canonical(" 2% milk "), "2% milk");
+        assert_eq!(rules.canonical("salt, pepper, oil"), "salt pepper oil");
+        assert_eq!(rules.canonical(" !!! "), "!!!");
+    }
+
+    #[test]
+    fn rules_files_can_replace_the_builtin_entries() {
+        let path = std::env::temp_dir().join(format!("rules-{}.toml", std::process::id()));
+        std::fs::write(
+            &path,
+            "builtin = false\n\
+             keep = [\"Capers\"]\n\
+             descriptors = [\"fresh\", \"Chopped\"]\n\
+             [singular]\n\"geese\" = \"goose\"\n\
+             [aliases]\n\"Garbanzo Beans\" = \"chickpeas\"\n",
+        )
+        .unwrap();
+        let rules = Rules::load(&path).unwrap();
+
+        assert_eq!(rules.canonical("fresh chopped tomatoes"), "tomato");
+        assert_eq!(rules.canonical("fresh"), "fresh");
+        assert_eq!(rules.canonical("capers"), "capers");
+        assert_eq!(rules.canonical("geese"), "goose");
+        assert_eq!(rules.canonical("garbanzo bean"), "chickpea");
+        // Without the builtin exceptions the suffix rules apply
+        assert_eq!(rules.canonical("cookies"), "cooky");
+        assert_eq!(rules.canonical("AP flour"), "ap flour");
+    }
+
+    #[test]
+    fn stems_cover_plurals_and_aliases() {
+        let rules = Rules::builtin();
+        assert_eq!(rules.stems("berry"), ["berr"]);
+        assert_eq!(rules.stems("leaf"), ["lea"]);
+        assert_eq!(rules.stems("tomato"), ["tomato"]);
+        assert_eq!(rules.stems("green onion"), ["onion", "scallion"]);
+    }
+}
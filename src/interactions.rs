@@ -0,0 +1,130 @@
+use crate::cancel::CancellationToken;
+use crate::checkpoint;
+use crate::graph;
+use crate::output;
+use crate::settings::Settings;
+use csv::Reader;
+use neo4rs::{BoltType, Graph, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::time::Instant;
+
+/// One row of RAW_interactions.csv.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Interaction {
+    pub user_id: i64,
+    pub recipe_id: i32,
+    pub date: String,
+    pub rating: i32,
+    pub review: String,
+}
+
+impl Interaction {
+    fn to_row(&self) -> HashMap<String, BoltType> {
+        let mut row = HashMap::new();
+        row.insert("user_id".to_string(), self.user_id.into());
+        row.insert("recipe_id".to_string(), self.recipe_id.into());
+        row.insert("date".to_string(), self.date.clone().into());
+        row.insert("rating".to_string(), self.rating.into());
+        row.insert("review".to_string(), self.review.clone().into());
+        row
+    }
+}
+
+async fn write_batch(
+    graph: &Graph,
+    batch: &[Interaction],
+    placeholders: bool,
+) -> Result<(), Box<dyn Error>> {
+    let rows: Vec<HashMap<String, BoltType>> = batch.iter().map(Interaction::to_row).collect();
+
+    let mut tx = graph.start_txn().await?;
+    if placeholders {
+        tx.run(
+            Query::new(
+                "UNWIND $rows AS row MERGE (r:Recipe {id: row.recipe_id}) \
+                 ON CREATE SET r.placeholder = true"
+                    .to_string(),
+            )
+            .param("rows", rows.clone()),
+        )
+        .await?;
+    }
+    tx.run(
+        Query::new("UNWIND $rows AS row MERGE (:User {id: row.user_id})".to_string())
+            .param("rows", rows.clone()),
+    )
+    .await?;
+    // Without placeholders, rows whose recipe isn't in the graph fall out
+    // of the MATCH
+    tx.run(
+        Query::new(
+            "UNWIND $rows AS row \
+             MATCH (u:User {id: row.user_id}), (r:Recipe {id: row.recipe_id}) \
+             MERGE (u)-[x:RATED]->(r) \
+             SET x.rating = row.rating, x.date = row.date, x.review = row.review"
+                .to_string(),
+        )
+        .param("rows", rows),
+    )
+    .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Loads interactions in batches of `batch_size` rows using UNWIND, recording
+/// the number of committed rows in `checkpoint` so an interrupted load
+/// resumes where it stopped. Interactions with recipes missing from the
+/// graph are skipped, unless `placeholders` is set, in which case a Recipe
+/// node holding only the id is created for them. Returns the number of rows
+/// written this run.
+pub async fn load(
+    graph: &Graph,
+    settings: &Settings,
+    input: &Path,
+    checkpoint: &Path,
+    batch_size: usize,
+    placeholders: bool,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    graph::create_indexes(graph, settings).await?;
+
+    let resume_from = checkpoint::read(checkpoint)?;
+    if resume_from > 0 {
+        println!("resuming after {} rows", resume_from);
+    }
+
+    let mut rdr = Reader::from_reader(output::open_source(input)?);
+    let mut batch = Vec::with_capacity(batch_size);
+    let mut committed = resume_from;
+    let started = Instant::now();
+
+    for result in rdr.deserialize().skip(resume_from) {
+        let interaction: Interaction = result?;
+        batch.push(interaction);
+
+        if batch.len() == batch_size {
+            // Stopping here leaves the checkpoint at the last committed batch
+            cancel.check()?;
+            write_batch(graph, &batch, placeholders).await?;
+            committed += batch.len();
+            batch.clear();
+            checkpoint::write(checkpoint, committed)?;
+
+            let rate = (committed - resume_from) as f64 / started.elapsed().as_secs_f64();
+            println!("{} interactions loaded ({:.0} rows/s)", committed, rate);
+        }
+    }
+
+    if !batch.is_empty() {
+        cancel.check()?;
+        write_batch(graph, &batch, placeholders).await?;
+        committed += batch.len();
+        checkpoint::write(checkpoint, committed)?;
+    }
+
+    println!("{} interactions loaded", committed);
+    Ok(committed - resume_from)
+}
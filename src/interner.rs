@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// A handle to a string stored once in an `Interner`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Symbol(u32);
+
+/// Stores each distinct string once and hands out `Symbol`s for it, so the
+/// corpus-wide tables in analytics ("salt", "preheat oven to 350 degrees f")
+/// hold four-byte handles instead of repeated copies.
+#[derive(Debug, Default)]
+pub struct Interner {
+    symbols: HashMap<Rc<str>, Symbol>,
+    strings: Vec<Rc<str>>,
+}
+
+impl Interner {
+    pub fn intern(&mut self, text: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(text) {
+            return *symbol;
+        }
+        let symbol = Symbol(self.strings.len() as u32);
+        let text: Rc<str> = Rc::from(text);
+        self.strings.push(text.clone());
+        self.symbols.insert(text, symbol);
+        symbol
+    }
+
+    /// The symbol for `text` if it has been interned.
+    pub fn get(&self, text: &str) -> Option<Symbol> {
+        self.symbols.get(text).copied()
+    }
+
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.strings[symbol.0 as usize]
+    }
+}
@@ -0,0 +1,137 @@
+#[cfg(feature = "neo4j")]
+use crate::course;
+#[cfg(feature = "neo4j")]
+use neo4rs::{BoltType, Graph, Query};
+use ring::digest::{digest, SHA256};
+#[cfg(feature = "neo4j")]
+use std::collections::HashMap;
+#[cfg(feature = "neo4j")]
+use std::error::Error;
+
+/// The namespace keys are made in when neither `--id-namespace` nor the
+/// profile names one.
+pub const DEFAULT_NAMESPACE: &str = "food.com";
+
+/// Nodes the loader generates from text rather than taking an id from the
+/// source. Each carries a `key` made by `surrogate`.
+pub const GENERATED: [&str; 3] = ["Ingredient", "Tag", "Course"];
+
+/// `text` with whitespace collapsed. Names reach here already canonical
+/// for their label, such as `ingredient::canonical`, so nothing else is
+/// folded; two nodes that exist apart must not share a key.
+fn canonical(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The key of the `label` node named `text` in `namespace`: the first 128
+/// bits of SHA-256 over namespace, label and canonical text, as hex. The
+/// same name under another label or namespace gets another key.
+pub fn surrogate(namespace: &str, label: &str, text: &str) -> String {
+    let input = format!("{}\u{1f}{}\u{1f}{}", namespace, label, canonical(text));
+    digest(&SHA256, input.as_bytes()).as_ref()[..16]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Course keys in `namespace` by course name, for queries that link
+/// courses from a recipe property.
+#[cfg(feature = "neo4j")]
+pub fn course_keys(namespace: &str) -> HashMap<String, BoltType> {
+    course::COURSES
+        .iter()
+        .map(|course| {
+            (
+                course.name().to_string(),
+                surrogate(namespace, "Course", course.name()).into(),
+            )
+        })
+        .collect()
+}
+
+/// Gives generated nodes from before keys existed a key in `namespace`,
+/// `batch_size` names at a time, so the unique constraint can be created
+/// and later merges find them. Returns the number of nodes keyed.
+#[cfg(feature = "neo4j")]
+pub async fn assign_missing(
+    graph: &Graph,
+    namespace: &str,
+    batch_size: usize,
+) -> Result<usize, Box<dyn Error>> {
+    let mut assigned = 0;
+    for label in GENERATED {
+        loop {
+            let mut result = graph
+                .execute(
+                    Query::new(format!(
+                        "MATCH (n:{}) WHERE n.key IS NULL AND n.name IS NOT NULL \
+                         RETURN DISTINCT n.name AS name LIMIT $limit",
+                        label
+                    ))
+                    .param("limit", batch_size as i64),
+                )
+                .await?;
+            let mut rows: Vec<HashMap<String, BoltType>> = Vec::new();
+            while let Some(row) = result.next().await? {
+                let name: String = row.get("name")?;
+                let mut values = HashMap::new();
+                values.insert("key".to_string(), surrogate(namespace, label, &name).into());
+                values.insert("name".to_string(), name.into());
+                rows.push(values);
+            }
+            if rows.is_empty() {
+                break;
+            }
+            assigned += rows.len();
+            graph
+                .run(
+                    Query::new(format!(
+                        "UNWIND $rows AS row MATCH (n:{} {{name: row.name}}) \
+                         WHERE n.key IS NULL SET n.key = row.key",
+                        label
+                    ))
+                    .param("rows", rows),
+                )
+                .await?;
+        }
+    }
+    Ok(assigned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_are_stable() {
+        // Stored keys depend on this value; changing it orphans them
+        assert_eq!(
+            surrogate(DEFAULT_NAMESPACE, "Ingredient", "garlic"),
+            "8b1f9ddd3eef938dfda7f46c6d347559"
+        );
+        assert_eq!(
+            surrogate(DEFAULT_NAMESPACE, "Ingredient", "  olive \t oil "),
+            surrogate(DEFAULT_NAMESPACE, "Ingredient", "olive oil")
+        );
+    }
+
+    #[test]
+    fn label_namespace_and_case_give_other_keys() {
+        let key = surrogate(DEFAULT_NAMESPACE, "Tag", "dessert");
+        assert_ne!(key, surrogate(DEFAULT_NAMESPACE, "Course", "dessert"));
+        assert_ne!(key, surrogate("allrecipes.com", "Tag", "dessert"));
+        assert_ne!(key, surrogate(DEFAULT_NAMESPACE, "Tag", "Dessert"));
+        // The separator keeps fields from running together
+        assert_ne!(surrogate("ns", "Tag", "a b"), surrogate("ns", "Tag a", "b"));
+    }
+
+    #[cfg(feature = "neo4j")]
+    #[test]
+    fn course_keys_cover_every_course() {
+        let keys = course_keys(DEFAULT_NAMESPACE);
+        assert_eq!(keys.len(), course::COURSES.len());
+        for course in course::COURSES {
+            assert!(keys.contains_key(course.name()));
+        }
+    }
+}
@@ -0,0 +1,114 @@
+//! Parses the Food.com recipe dump and loads it into Neo4j.
+//!
+//! [`parser`] holds the [`Recipe`] model and the deserializers for the
+//! dump's Python-style list columns, so a CSV of the dataset can be read
+//! with nothing but `csv` and this crate:
+//!
+//! ```no_run
+//! let mut reader = csv::Reader::from_path("data/RAW_recipes.csv")?;
+//! for result in reader.deserialize() {
+//!     let recipe: recipe_app::Recipe = result?;
+//!     println!("{} ({} ingredients)", recipe.name, recipe.ingredients.len());
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! That reads with the default options. [`settings::Settings`] holds the
+//! others, such as decimal commas or the raw nutrition layout, along with
+//! the connection profile and key namespace, and is passed to whatever
+//! reads, writes or loads recipes.
+//!
+//! [`graph::GraphLoader`] writes recipes and their ingredients to a graph,
+//! and [`ingest`] runs the whole `load` pipeline around it, as [`export`]
+//! does for the file exports; both stop early when their cancellation
+//! token fires. The remaining modules are the loader's cleanup passes,
+//! analytics and exporters, used by the `recipe_app` binary.
+//!
+//! The default features are `neo4j`, for every module that talks to a
+//! graph along with the neo4rs and Tokio dependencies, and `cli`, for the
+//! binary's argument parser. With `default-features = false` the crate is
+//! the parser, the cleanup passes and the file exporters.
+
+#[cfg(feature = "neo4j")]
+pub mod analyze;
+pub mod archive;
+#[cfg(feature = "neo4j")]
+pub mod audit;
+pub mod autocomplete;
+pub mod brands;
+pub mod cancel;
+pub mod checkpoint;
+#[cfg(feature = "neo4j")]
+pub mod companion;
+pub mod components;
+pub mod config;
+pub mod conflicts;
+pub mod course;
+pub mod credentials;
+pub mod derived;
+pub mod descriptors;
+pub mod edgelist;
+pub mod errors;
+pub mod export;
+pub mod fixtures;
+#[cfg(feature = "neo4j")]
+pub mod graph;
+pub mod hashing;
+pub mod hours;
+pub mod icons;
+#[cfg(feature = "neo4j")]
+pub mod ingest;
+pub mod ingredient;
+#[cfg(feature = "neo4j")]
+pub mod interactions;
+pub mod interner;
+pub mod keys;
+pub mod mapping;
+#[cfg(feature = "neo4j")]
+pub mod migrate;
+#[cfg(feature = "neo4j")]
+pub mod ml;
+pub mod names;
+pub mod norms;
+pub mod nutrition;
+pub mod output;
+pub mod parser;
+pub mod pipeline;
+pub mod precision;
+pub mod pricing;
+pub mod quantity;
+pub mod query;
+#[cfg(feature = "neo4j")]
+pub mod quickstart;
+pub mod readability;
+#[cfg(feature = "neo4j")]
+pub mod repair;
+pub mod rows;
+pub mod runs;
+pub mod schema;
+pub mod scrub;
+pub mod searches;
+#[cfg(feature = "neo4j")]
+pub mod server;
+pub mod servings;
+pub mod settings;
+#[cfg(feature = "neo4j")]
+pub mod sink;
+pub mod spelling;
+pub mod steps;
+pub mod suggest;
+pub mod testkit;
+pub mod timings;
+#[cfg(feature = "neo4j")]
+pub mod translate;
+pub mod truncation;
+#[cfg(feature = "neo4j")]
+pub mod verify;
+#[cfg(feature = "neo4j")]
+pub mod views;
+pub mod voice;
+pub mod warnings;
+#[cfg(feature = "neo4j")]
+pub mod wikidata;
+
+pub use parser::Recipe;
@@ -1,152 +1,514 @@
-use csv::Reader;
-use neo4rs::*;
-use serde::de::{self, Deserializer, SeqAccess, Visitor};
-use serde::{Deserialize, Serialize};
-use serde_json;
-use std::error::Error;
-use std::{fmt, string};
+mod args;
 
-#[derive(Debug, Serialize, Deserialize)]
-struct Recipe {
-    id: i32,
-    name: String,
-    description: String,
-    #[serde(deserialize_with = "deserialize_string_array")]
-    ingredients: Vec<String>,
-    minutes: i32,
-    #[serde(deserialize_with = "deserialize_string_array")]
-    steps: Vec<String>,
-    #[serde(deserialize_with = "deserialize_float_array")]
-    nutrition: Vec<f32>,
-}
+use args::Args;
+use recipe_app::errors::LoadError;
+use recipe_app::ingest;
+use recipe_app::names::NameStyle;
+use recipe_app::settings::Settings;
+use recipe_app::{
+    analyze, archive, autocomplete, cancel, companion, components, credentials, derived,
+    descriptors, export, fixtures, interactions, mapping, migrate, ml, norms, output, pipeline,
+    query, quickstart, repair, runs, schema, searches, server, suggest, translate, verify, views,
+    wikidata,
+};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-fn deserialize_string_array<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
-where
-    D: serde::Deserializer<'de>,
-{
-    deserializer.deserialize_string(StringArrayVisitor)
+fn broken_pipe(err: &(dyn Error + 'static)) -> bool {
+    let io = match err.downcast_ref() {
+        Some(LoadError::Io(err)) => Some(err),
+        _ => err.downcast_ref::<std::io::Error>(),
+    };
+    io.is_some_and(|err| err.kind() == std::io::ErrorKind::BrokenPipe)
 }
 
-struct StringArrayVisitor;
-
-impl<'de> serde::de::Visitor<'de> for StringArrayVisitor {
-    type Value = Vec<String>;
-
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        formatter.write_str("a string")
-    }
-
-    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
-    where
-        E: serde::de::Error,
-    {
-        let parts = value.trim_matches(|c| c == '[' || c == ']').split(',');
-        let mut strings = Vec::new();
-        for part in parts {
-            // Remove single quotes and extra whitespace
-            let string = part.trim().trim_matches(|c| c == '\'' || c == '"');
-            strings.push(string.to_string());
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn Error>> {
+    match run(Args::from_env()).await {
+        // A pipeline reader such as `head` closing early is not a failure
+        Err(err) if broken_pipe(err.as_ref()) => Ok(()),
+        // Rows were skipped but the rest of the input went through
+        Err(err) if matches!(err.downcast_ref(), Some(LoadError::RowsFailed(_))) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(2);
         }
-
-        Ok(strings)
+        result => result,
     }
 }
 
-fn deserialize_float_array<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
-where
-    D: Deserializer<'de>,
-{
-    deserializer.deserialize_string(FloatArrayVisitor)
-}
-
-struct FloatArrayVisitor;
-
-impl<'de> Visitor<'de> for FloatArrayVisitor {
-    type Value = Vec<f32>;
+async fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    // A pipeline's [global] options count as given on the command line,
+    // which wins where both set one
+    let args = match args.positional().as_slice() {
+        ["pipeline", "run", path] => {
+            let mut line = vec![env!("CARGO_PKG_NAME").to_string()];
+            line.extend(pipeline::Pipeline::load(Path::new(path))?.global_flags()?);
+            line.extend(["pipeline", "run", path].map(str::to_string));
+            let global = Args::try_parse_from(line)?;
+            args.with_defaults(global)
+        }
+        _ => args,
+    };
+    let settings = args.settings()?;
 
-    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-        formatter.write_str("a string representing an array of floats")
+    // Ctrl-C and --timeout stop long-running commands at their next batch
+    let cancel = cancel::CancellationToken::new();
+    if let Some(seconds) = args.get("timeout") {
+        cancel.cancel_after(Duration::from_secs_f64(seconds));
     }
-
-    fn visit_str<E>(self, value: &str) -> Result<Vec<f32>, E>
-    where
-        E: de::Error,
-    {
-        // Trim the brackets and then split the string by comma
-        let parts = value.trim_matches(|c| c == '[' || c == ']').split(',');
-
-        let mut floats = Vec::new();
-        for part in parts {
-            match part.trim().parse() {
-                Ok(num) => floats.push(num),
-                Err(_) => return Err(E::custom("failed to parse float")),
+    let on_interrupt = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("stopping after the current batch; press Ctrl-C again to abort");
+            on_interrupt.cancel();
+            if tokio::signal::ctrl_c().await.is_ok() {
+                std::process::exit(130);
             }
         }
-        Ok(floats)
-    }
-}
-
-async fn add_ingredients_to_recipe(
-    graph: &Graph,
-    recipe_id: i32,
-    ingredients: Vec<String>,
-) -> Result<(), Box<dyn Error>> {
-    let mut tx = graph.start_txn().await?;
+    });
 
-    for ingredient in ingredients {
-        // Create ingredient node if it doesn't exist
-        let query = Query::new("MERGE (i:Ingredient {name: $name})".to_string())
-            .param("name", ingredient.clone());
-        tx.run(query).await?;
-
-        // Create relationship between recipe and ingredient
-        let rel_query = Query::new("MATCH (r:Recipe {id: $recipe_id}), (i:Ingredient {name: $ingredient_name}) MERGE (r)-[:CONTAINS]->(i)".to_string())
-            .param("recipe_id", recipe_id)
-            .param("ingredient_name", ingredient);
-        tx.run(rel_query).await?;
-    }
-
-    tx.commit().await?;
-    Ok(())
-}
-
-async fn add_recipe_to_neo4j(
-    graph: &Graph,
-    recipe: &Recipe,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let query = Query::new("CREATE (r:Recipe {id: $id, name: $name, description: $description, minutes: $minutes, nutrition: $nutrition, steps: $steps}) RETURN r".to_string())
-        .param("id", recipe.id)
-        .param("name", recipe.name.clone())
-        .param("description", recipe.description.clone())
-        .param("minutes", recipe.minutes)
-        .param("nutrition", recipe.nutrition.clone())
-        .param("steps", recipe.steps.clone());
-
-    let mut tx = graph.start_txn().await?;
-    let _results = tx.run(query).await?;
-    tx.commit().await?;
-
-    Ok(())
+    dispatch(&args, &settings, &cancel).await
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn Error>> {
-    let graph = Graph::new("bolt://10.144.2.189:7687", "neo4j", "HAHAHA").await?;
-
-    let mut rdr = Reader::from_path("data/RAW_recipes.csv")?;
-
-    for result in rdr.deserialize() {
-        let recipe: Recipe = result?;
-        // println!("{:?}", recipe);
-        // let json = serde_json::to_string_pretty(&recipe)?;
-        // println!("{}", json);
-        add_recipe_to_neo4j(&graph, &recipe).await?;
-
-        let recipe_id: i32 = recipe.id.clone();
-        let ingredients = recipe.ingredients.clone();
-
-        add_ingredients_to_recipe(&graph, recipe_id, ingredients).await?;
+/// Runs the command `args` names with the `settings` its global options
+/// give.
+async fn dispatch(
+    args: &Args,
+    settings: &Settings,
+    cancel: &cancel::CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let connect = || settings.profile.connect();
+    match args.positional().as_slice() {
+        ["load", input @ ..] => {
+            let options = args.load_options(input.first().copied(), settings)?;
+            Ok(ingest::load(options, settings, cancel).await?)
+        }
+        ["validate", input @ ..] => {
+            let options = args.load_options(input.first().copied(), settings)?;
+            Ok(ingest::validate(&options, settings, cancel)?)
+        }
+        ["init"] => mapping::init(
+            &args.get::<PathBuf>("from").ok_or("init requires --from")?,
+            Path::new(args.text("out").unwrap_or("mapping.toml")),
+            args.flag("yes"),
+        ),
+        ["auth", "login"] => {
+            credentials::store(settings.user())?;
+            println!("saved the password for {} in the keyring", settings.user());
+            Ok(())
+        }
+        ["auth", "logout"] => {
+            credentials::remove(settings.user())?;
+            println!(
+                "removed the password for {} from the keyring",
+                settings.user()
+            );
+            Ok(())
+        }
+        ["schema", "describe"] => {
+            print!(
+                "{}",
+                schema::model().describe(args.text("format").unwrap_or("markdown"))?
+            );
+            Ok(())
+        }
+        ["load-interactions"] => {
+            let input = args.input("data/RAW_interactions.csv");
+            let checkpoint = args
+                .get::<PathBuf>("checkpoint")
+                .unwrap_or_else(|| "interactions.checkpoint".into());
+            let batch_size = args.batch_size(settings, 5000);
+            interactions::load(
+                &connect().await?,
+                settings,
+                Path::new(input),
+                &checkpoint,
+                batch_size,
+                args.flag("create-missing-recipes"),
+                cancel,
+            )
+            .await?;
+            Ok(())
+        }
+        ["ml", "split"] => {
+            let options = ml::SplitOptions {
+                input: args.input("data/RAW_interactions.csv").into(),
+                by: args.get("by").unwrap_or(ml::SplitBy::User),
+                test_fraction: args.get("test-fraction").unwrap_or(0.2),
+                seed: args.get("seed").unwrap_or(7),
+                train: args
+                    .get("train")
+                    .unwrap_or_else(|| "interactions_train.csv".into()),
+                test: args
+                    .get("test")
+                    .unwrap_or_else(|| "interactions_test.csv".into()),
+            };
+            let split = ml::split_file(&options)?;
+            if args.flag("label-graph") {
+                let batch_size = args.batch_size(settings, 5000);
+                ml::label(&connect().await?, &split, batch_size, cancel).await?;
+            }
+            Ok(())
+        }
+        ["ml", "evaluate"] => ml::evaluate_files(&ml::EvaluateOptions {
+            train: args
+                .get("train")
+                .unwrap_or_else(|| "interactions_train.csv".into()),
+            test: args
+                .get("test")
+                .unwrap_or_else(|| "interactions_test.csv".into()),
+            k: args.get("k").unwrap_or(10),
+            min_rating: args.get("min-rating").unwrap_or(4),
+            seed: args.get("seed").unwrap_or(7),
+            algorithms: args
+                .get("algorithms")
+                .unwrap_or_else(|| ml::ALGORITHMS.to_vec()),
+        }),
+        ["run", "report", run_id] => {
+            let runs_dir = args
+                .get::<PathBuf>("runs-dir")
+                .unwrap_or_else(|| "runs".into());
+            runs::RunLog::load(&runs_dir, run_id)?.report(args.sink()?)
+        }
+        ["graph", "capabilities"] => {
+            println!("{}", server::detect(&connect().await?).await?.describe());
+            Ok(())
+        }
+        ["graph", "verify"] => {
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let fraction = args.get("sample").unwrap_or(0.01);
+            let batch_size = args.batch_size(settings, 500);
+            let delay = Duration::from_millis(args.get("delay-ms").unwrap_or(200));
+            let graph = connect().await?;
+            let report =
+                verify::verify(&graph, settings, input, fraction, batch_size, delay, cancel)
+                    .await?;
+            println!("{}", report.summary(input));
+            Ok(())
+        }
+        ["graph", "repair"] => {
+            if !args.flag("dedupe-relationships") {
+                return Err(
+                    "graph repair needs a repair to run, such as --dedupe-relationships".into(),
+                );
+            }
+            let dry_run = args.flag("dry-run");
+            let batch_size = args.batch_size(settings, 1000);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            let found =
+                repair::dedupe_relationships(&graph, &server, dry_run, batch_size, cancel).await?;
+            println!("{}", repair::summary(&found, dry_run));
+            Ok(())
+        }
+        ["analyze", "users"] => {
+            let batch_size = args.batch_size(settings, 1000);
+            analyze::users(&connect().await?, batch_size, cancel).await?;
+            Ok(())
+        }
+        ["analyze", "trending"] => {
+            let window = args.get("window").unwrap_or(90);
+            let as_of = args.get("as-of").unwrap_or_else(query::today);
+            let batch_size = args.batch_size(settings, 1000);
+            analyze::trending(&connect().await?, window, &as_of, batch_size, cancel).await?;
+            Ok(())
+        }
+        ["analyze", "all"] => {
+            let jobs = args.get("jobs").unwrap_or(4);
+            let batch_size = args.batch_size(settings, 1000);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            let passes = analyze::passes();
+            let reports =
+                analyze::all(&graph, &server, settings, &passes, jobs, batch_size, cancel).await;
+            analyze::print_reports(&reports)
+        }
+        ["analyze", "suggest-tags"] => {
+            let options = suggest::Options {
+                max_tags: args.get("max-tags").unwrap_or(2),
+                min_support: args.get("min-support").unwrap_or(5),
+                min_confidence: args.get("min-confidence").unwrap_or(0.3),
+                per_recipe: args.get("per-recipe").unwrap_or(5),
+            };
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let report = suggest::suggest_file(input, args.sink()?, settings, options, cancel)?;
+            if args.flag("graph") {
+                let batch_size = args.batch_size(settings, 1000);
+                let graph = connect().await?;
+                suggest::write(&graph, settings, &report.suggestions, batch_size, cancel).await?;
+            }
+            Ok(())
+        }
+        ["analyze", "components"] => {
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let report = components::detect_file(input, args.sink()?, settings, cancel)?;
+            if args.flag("graph") {
+                let batch_size = args.batch_size(settings, 1000);
+                let graph = connect().await?;
+                components::write(&graph, &report.components, batch_size, cancel).await?;
+            }
+            Ok(())
+        }
+        ["analyze", "descriptors"] => {
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let min_heads = args.get("min-heads").unwrap_or(20);
+            descriptors::detect_file(input, args.sink()?, settings, min_heads, cancel)?;
+            Ok(())
+        }
+        ["analyze", "quantity-norms"] => {
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let min_recipes = args.get("min-recipes").unwrap_or(20);
+            let tolerance = args.get("tolerance").unwrap_or(10.0);
+            let anomalies_out = match args.text("anomalies-out") {
+                Some(path) => Some(output::open_sink(path, false)?),
+                None => None,
+            };
+            norms::compute_file(
+                input,
+                args.sink()?,
+                anomalies_out,
+                settings,
+                min_recipes,
+                tolerance,
+                cancel,
+            )?;
+            Ok(())
+        }
+        ["materialize", "views"] => {
+            let batch_size = args.batch_size(settings, 1000);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            views::materialize(&graph, &server, batch_size, cancel).await?;
+            Ok(())
+        }
+        ["query", "random"] => {
+            let input = Path::new(args.input("data/RAW_recipes.csv"));
+            let filter = query::RecipeFilter {
+                tag: args.get("tag"),
+                max_minutes: args.get("max-minutes"),
+                max_cost: args.get("max-cost"),
+                prices: args.prices(settings)?,
+                beginner_friendly: args.flag("beginner-friendly"),
+            };
+            let count = args.get("count").unwrap_or(1);
+            let seed = args.get("seed");
+            let recipes = query::random(input, settings, &filter, count, seed)?;
+            query::print_recipes(&recipes, args.flag("json"))
+        }
+        ["query", "autocomplete", prefix] => {
+            let index = match args.get::<PathBuf>("index") {
+                Some(path) => autocomplete::Index::load(&path)?,
+                None => {
+                    let input = Path::new(args.input("data/RAW_recipes.csv"));
+                    autocomplete::Index::from_input(input, settings, &args.names())?
+                }
+            };
+            let count = args.get("count").unwrap_or(10);
+            autocomplete::print(&index.complete(prefix, count), args.flag("json"))
+        }
+        ["query", "by-ingredient", ingredients @ ..] => {
+            let ingredients: Vec<String> =
+                ingredients.iter().map(|name| name.to_string()).collect();
+            let max_minutes = args.get("max-minutes");
+            let count = args.get("count").unwrap_or(20);
+            let graph = connect().await?;
+            let matches =
+                query::by_ingredient(&graph, settings, &ingredients, max_minutes, count).await?;
+            query::print_matches(&matches, args.flag("json"))
+        }
+        ["query", "similar", id] => {
+            let id = id.parse()?;
+            let count = args.get("count").unwrap_or(20);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            let matches = query::similar(&graph, &server, settings, id, count).await?;
+            query::print_matches(&matches, args.flag("json"))
+        }
+        ["query", "rotd"] => {
+            let date = args.get("date").unwrap_or_else(query::today);
+            let max_minutes = args.get("max-minutes");
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            let tag = args.text("tag");
+            let pick =
+                query::of_the_day(&graph, &server, settings, &date, tag, max_minutes).await?;
+            query::print_matches(&[pick.ok_or("no recipe matches")?], args.flag("json"))
+        }
+        ["query", "save", name] => {
+            let filter: searches::Filter =
+                args.get("filter").ok_or("query save requires --filter")?;
+            searches::save(&connect().await?, name, &filter).await?;
+            println!("saved {}: {}", name, filter);
+            Ok(())
+        }
+        ["query", "run", name] => {
+            let count = args.get("count").unwrap_or(20);
+            let graph = connect().await?;
+            let search = searches::load(&graph, name)
+                .await?
+                .ok_or_else(|| format!("no saved search named {}", name))?;
+            let server = server::detect(&graph).await?;
+            let matches = query::search(&graph, &server, settings, &search.filter, count).await?;
+            query::print_matches(&matches, args.flag("json"))
+        }
+        ["query", "searches"] => {
+            for search in searches::list(&connect().await?, None).await? {
+                println!("{}\t{}", search.name, search.filter);
+            }
+            Ok(())
+        }
+        ["query", "trending"] => {
+            let count = args.get("count").unwrap_or(20);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            let matches =
+                query::trending(&graph, &server, settings, args.text("tag"), count).await?;
+            query::print_matches(&matches, args.flag("json"))
+        }
+        ["query", "nutrition"] => {
+            let max_calories = args
+                .get("max-calories")
+                .ok_or("query nutrition requires --max-calories")?;
+            let count = args.get("count").unwrap_or(20);
+            let graph = connect().await?;
+            let matches = query::nutrition(&graph, settings, max_calories, count).await?;
+            query::print_matches(&matches, args.flag("json"))
+        }
+        ["quickstart"] => {
+            let options = args.load_options(None, settings)?;
+            quickstart::run(options, settings, cancel).await
+        }
+        ["fixtures", "generate"] => fixtures::generate_to(
+            args.text("out").unwrap_or("-"),
+            args.get("count").unwrap_or(500),
+            args.get("seed").unwrap_or(42),
+        ),
+        ["export", input @ ..] => {
+            let options = args.export_options(input.first().copied())?;
+            let sink = output::open_sink(args.text("out").unwrap_or("-"), args.flag("compress"))?;
+            export::export(&options, settings, sink, cancel)
+        }
+        ["archive", "create"] => {
+            let options = archive::ArchiveOptions {
+                input: args.input("data/RAW_recipes.csv").to_string(),
+                out: args
+                    .text("out")
+                    .ok_or("archive create requires --out")?
+                    .into(),
+                mapping: args.get("mapping"),
+                names: args.names(),
+                name_style: args
+                    .get::<NameStyle>("name-style")
+                    .unwrap_or_default()
+                    .name()
+                    .to_string(),
+                name_fillers: args.get("name-fillers"),
+            };
+            print!(
+                "{}",
+                archive::create_from_input(&options, settings, cancel)?
+            );
+            println!("wrote {}", options.out.display());
+            Ok(())
+        }
+        ["enrich", "wikidata"] => {
+            let cache = args
+                .get::<PathBuf>("cache")
+                .unwrap_or_else(|| "wikidata_cache.json".into());
+            let rate = args.get("rate").unwrap_or(1.0);
+            if rate <= 0.0 {
+                return Err("--rate must be positive".into());
+            }
+            let batch_size = args.batch_size(settings, 100);
+            wikidata::enrich(
+                &connect().await?,
+                &mut wikidata::Client::new(rate),
+                &cache,
+                args.flag("refresh"),
+                batch_size,
+                cancel,
+            )
+            .await?;
+            Ok(())
+        }
+        ["translate"] => {
+            let languages = translate::Languages {
+                from: args.get("from").unwrap_or_else(|| "en".to_string()),
+                to: args.get("to").ok_or("translate requires --to")?,
+            };
+            let service = match args.get("command") {
+                Some(command) => translate::Service::Command(command),
+                None => translate::Service::LibreTranslate {
+                    url: args
+                        .get("url")
+                        .unwrap_or_else(|| "http://localhost:5000".to_string()),
+                    api_key: args.get("api-key"),
+                },
+            };
+            let cache = args
+                .get::<PathBuf>("cache")
+                .unwrap_or_else(|| "translation_cache.json".into());
+            let batch_size = args.batch_size(settings, 100);
+            translate::translate_with(
+                &connect().await?,
+                settings,
+                &service,
+                &languages,
+                &cache,
+                args.flag("refresh"),
+                batch_size,
+                cancel,
+            )
+            .await?;
+            Ok(())
+        }
+        ["enrich", "pp-recipes", input @ ..] => {
+            let input = input
+                .first()
+                .copied()
+                .unwrap_or(args.input("data/PP_recipes.csv"));
+            let batch_size = args.batch_size(settings, 1000);
+            let graph = connect().await?;
+            companion::import_preprocessed(&graph, Path::new(input), batch_size, cancel).await?;
+            Ok(())
+        }
+        ["migrate"] => {
+            let from = args
+                .get("from-model")
+                .ok_or("migrate requires --from-model")?;
+            let to = args.get("to-model").unwrap_or(migrate::CURRENT);
+            let batch_size = args.batch_size(settings, 1000);
+            migrate::migrate(&connect().await?, settings, from, to, batch_size, cancel).await
+        }
+        ["backfill"] => {
+            let property = args.get("property").ok_or("backfill requires --property")?;
+            let batch_size = args.batch_size(settings, 1000);
+            let graph = connect().await?;
+            let server = server::detect(&graph).await?;
+            derived::backfill(&graph, &server, settings, property, batch_size, cancel).await?;
+            Ok(())
+        }
+        ["pipeline", "run", path] => {
+            let commands = pipeline::Pipeline::load(Path::new(path))?.commands()?;
+            for (index, command) in commands.iter().enumerate() {
+                println!(
+                    "pipeline step {}/{}: {} {}",
+                    index + 1,
+                    commands.len(),
+                    env!("CARGO_PKG_NAME"),
+                    command.join(" ")
+                );
+                if args.flag("dry-run") {
+                    continue;
+                }
+                let line = std::iter::once(env!("CARGO_PKG_NAME").to_string())
+                    .chain(command.iter().cloned());
+                let step = Args::try_parse_from(line)?;
+                Box::pin(dispatch(&step, settings, cancel)).await?;
+            }
+            Ok(())
+        }
+        other => Err(format!("unknown command: {}", other.join(" ")).into()),
     }
-
-    Ok(())
 }
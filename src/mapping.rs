@@ -0,0 +1,271 @@
+use crate::output;
+use crate::parser;
+use csv::{Reader, StringRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// Fields of the `Recipe` model a source column can be mapped onto.
+pub const RECIPE_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "description",
+    "ingredients",
+    "minutes",
+    "steps",
+    "nutrition",
+    "tags",
+    "servings",
+    "source_url",
+    "author",
+    "license",
+];
+
+const LIST_FIELDS: &[&str] = &["ingredients", "steps", "nutrition", "tags"];
+
+/// Rows sniffed when guessing column types.
+const SAMPLE_ROWS: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColumnType {
+    Integer,
+    Float,
+    Text,
+    List,
+}
+
+/// How a list-valued column encodes its elements.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ListEncoding {
+    /// `['a', 'b']` as in the Food.com dump
+    PythonList,
+    /// `["a", "b"]`
+    Json,
+    /// `a|b` with the given separator
+    Delimited(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct ColumnGuess {
+    pub column: String,
+    pub kind: ColumnType,
+    pub encoding: Option<ListEncoding>,
+}
+
+/// Maps source CSV columns onto the `Recipe` model; written by `init` and
+/// read by `load --mapping`.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct ColumnMapping {
+    /// model field -> source column
+    pub columns: BTreeMap<String, String>,
+    /// model field -> list encoding of its source column
+    #[serde(default)]
+    pub encodings: BTreeMap<String, ListEncoding>,
+}
+
+fn guess_encoding(values: &[&str]) -> Option<ListEncoding> {
+    let bracketed = values
+        .iter()
+        .all(|value| value.starts_with('[') && value.ends_with(']'));
+    if bracketed {
+        let double_quoted = values.iter().any(|value| value.contains('"'));
+        return Some(if double_quoted {
+            ListEncoding::Json
+        } else {
+            ListEncoding::PythonList
+        });
+    }
+
+    ["|", ";"]
+        .iter()
+        .find(|separator| values.iter().all(|value| value.contains(*separator)))
+        .map(|separator| ListEncoding::Delimited(separator.to_string()))
+}
+
+fn guess_column(column: &str, values: &[&str]) -> ColumnGuess {
+    let present: Vec<&str> = values.iter().copied().filter(|v| !v.is_empty()).collect();
+
+    let (kind, encoding) = if present.is_empty() {
+        (ColumnType::Text, None)
+    } else if present.iter().all(|value| value.parse::<i64>().is_ok()) {
+        (ColumnType::Integer, None)
+    } else if present.iter().all(|value| value.parse::<f64>().is_ok()) {
+        (ColumnType::Float, None)
+    } else {
+        match guess_encoding(&present) {
+            Some(encoding) => (ColumnType::List, Some(encoding)),
+            None => (ColumnType::Text, None),
+        }
+    };
+
+    ColumnGuess {
+        column: column.to_string(),
+        kind,
+        encoding,
+    }
+}
+
+/// Reads the first rows of `path` and guesses each column's type.
+pub fn sniff(path: &Path) -> Result<Vec<ColumnGuess>, Box<dyn Error>> {
+    let mut rdr = Reader::from_reader(output::open_source(path)?);
+    let headers = rdr.headers()?.clone();
+    let rows: Vec<StringRecord> = rdr.records().take(SAMPLE_ROWS).collect::<Result<_, _>>()?;
+
+    Ok(headers
+        .iter()
+        .enumerate()
+        .map(|(index, column)| {
+            let values: Vec<&str> = rows.iter().filter_map(|row| row.get(index)).collect();
+            guess_column(column, &values)
+        })
+        .collect())
+}
+
+fn synonyms(field: &str) -> &'static [&'static str] {
+    match field {
+        "id" => &["id", "recipe_id", "recipeid"],
+        "name" => &["name", "title", "recipe_name"],
+        "description" => &["description", "summary", "desc"],
+        "ingredients" => &["ingredients", "ingredient_list", "ingredient"],
+        "minutes" => &["minutes", "total_time", "time", "cook_time"],
+        "steps" => &["steps", "instructions", "directions", "method"],
+        "nutrition" => &["nutrition", "nutrients"],
+        "tags" => &["tags", "keywords", "categories"],
+        "source_url" => &["source_url", "url", "source", "link"],
+        "author" => &["author", "contributor", "creator", "by"],
+        "license" => &["license", "licence", "rights"],
+        _ => &[],
+    }
+}
+
+/// Picks the most likely source column for each model field by name.
+pub fn propose(guesses: &[ColumnGuess]) -> ColumnMapping {
+    let mut mapping = ColumnMapping::default();
+
+    for field in RECIPE_FIELDS {
+        let found = guesses.iter().find(|guess| {
+            let column = guess.column.trim().to_lowercase().replace([' ', '-'], "_");
+            synonyms(field).contains(&column.as_str())
+        });
+
+        if let Some(guess) = found {
+            mapping
+                .columns
+                .insert(field.to_string(), guess.column.clone());
+            if let Some(encoding) = &guess.encoding {
+                mapping
+                    .encodings
+                    .insert(field.to_string(), encoding.clone());
+            }
+        }
+    }
+
+    mapping
+}
+
+/// Walks through each model field on the terminal, offering the proposed
+/// column as the default.
+pub fn confirm_interactively(
+    guesses: &[ColumnGuess],
+    mut mapping: ColumnMapping,
+) -> Result<ColumnMapping, Box<dyn Error>> {
+    println!("Columns found:");
+    for guess in guesses {
+        println!("  {} ({:?})", guess.column, guess.kind);
+    }
+
+    let stdin = io::stdin();
+    for field in RECIPE_FIELDS {
+        let proposed = mapping.columns.get(*field).cloned().unwrap_or_default();
+        print!("column for `{}` [{}]: ", field, proposed);
+        io::stdout().flush()?;
+
+        let mut answer = String::new();
+        stdin.lock().read_line(&mut answer)?;
+        let answer = answer.trim();
+        if answer.is_empty() {
+            continue;
+        }
+
+        let guess = guesses
+            .iter()
+            .find(|guess| guess.column == answer)
+            .ok_or_else(|| format!("no column named {}", answer))?;
+        mapping
+            .columns
+            .insert(field.to_string(), guess.column.clone());
+        match &guess.encoding {
+            Some(encoding) => mapping
+                .encodings
+                .insert(field.to_string(), encoding.clone()),
+            None => mapping.encodings.remove(*field),
+        };
+    }
+
+    Ok(mapping)
+}
+
+/// `init`: proposes a mapping for the columns of `source`, lets the user
+/// confirm each one unless `yes`, and saves it to `out`.
+pub fn init(source: &Path, out: &Path, yes: bool) -> Result<(), Box<dyn Error>> {
+    let guesses = sniff(source)?;
+    let mut mapping = propose(&guesses);
+    if !yes {
+        mapping = confirm_interactively(&guesses, mapping)?;
+    }
+    mapping.save(out)?;
+    println!(
+        "wrote {}; load with --input {} --mapping {}",
+        out.display(),
+        source.display(),
+        out.display()
+    );
+    Ok(())
+}
+
+impl ColumnMapping {
+    pub fn load(path: &Path) -> Result<ColumnMapping, Box<dyn Error>> {
+        Ok(toml::from_str(&output::read_text(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, toml::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Renames source headers to model field names; unmapped columns keep
+    /// their names and are ignored by deserialization.
+    pub fn map_headers(&self, headers: &StringRecord) -> StringRecord {
+        headers
+            .iter()
+            .map(|header| {
+                self.columns
+                    .iter()
+                    .find(|(_, column)| column.as_str() == header)
+                    .map(|(field, _)| field.as_str())
+                    .unwrap_or(header)
+            })
+            .collect()
+    }
+
+    /// Rewrites list columns into the Python-list form the deserializers
+    /// expect.
+    pub fn normalize_record(&self, headers: &StringRecord, record: &StringRecord) -> StringRecord {
+        record
+            .iter()
+            .zip(headers.iter())
+            .map(|(value, field)| match self.encodings.get(field) {
+                Some(ListEncoding::Delimited(separator)) if LIST_FIELDS.contains(&field) => {
+                    let items: Vec<&str> = value.split(separator.as_str()).map(str::trim).collect();
+                    parser::python_list(&items)
+                }
+                _ => value.to_string(),
+            })
+            .collect()
+    }
+}
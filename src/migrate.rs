@@ -0,0 +1,228 @@
+use crate::cancel::CancellationToken;
+use crate::graph;
+use crate::keys;
+use crate::nutrition;
+use crate::settings::Settings;
+use neo4rs::{Graph, Query};
+use std::error::Error;
+use std::str::FromStr;
+
+/// The versions of the graph model, oldest first. Each differs from the
+/// one before it in a way `migrate` can rewrite in place.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Model {
+    /// The nutrition array only, generated nodes merged by name
+    V1,
+    /// Named nutrition properties beside the array
+    V2,
+    /// Surrogate keys on Ingredient, Tag and Course
+    V3,
+}
+
+pub const MODELS: [Model; 3] = [Model::V1, Model::V2, Model::V3];
+
+/// The model this version of the crate loads.
+pub const CURRENT: Model = Model::V3;
+
+impl FromStr for Model {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        MODELS
+            .iter()
+            .find(|model| model.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown model: {} (expected v1 to v{})", s, MODELS.len()))
+    }
+}
+
+impl Model {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Model::V1 => "v1",
+            Model::V2 => "v2",
+            Model::V3 => "v3",
+        }
+    }
+
+    fn next(&self) -> Model {
+        MODELS[(*self as usize + 1).min(MODELS.len() - 1)]
+    }
+}
+
+/// Runs `update` over every recipe, `batch_size` at a time in id order,
+/// with the batch bound to `r`. Returns the number of recipes visited.
+async fn each_recipe(
+    graph: &Graph,
+    update: &str,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_id = i64::MIN;
+    let mut visited = 0;
+    loop {
+        cancel.check()?;
+        let query = Query::new(format!(
+            "MATCH (r:Recipe) WHERE r.id > $after \
+             WITH r ORDER BY r.id LIMIT $limit \
+             {} \
+             RETURN max(r.id) AS last, count(r) AS seen",
+            update
+        ))
+        .param("after", last_id)
+        .param("limit", batch_size as i64);
+        let mut result = graph.execute(query).await?;
+        let Some(row) = result.next().await? else {
+            break;
+        };
+        let seen: i64 = row.get("seen")?;
+        if seen == 0 {
+            break;
+        }
+        last_id = row.get("last")?;
+        visited += seen as usize;
+        println!("{} recipes migrated", visited);
+    }
+    Ok(visited)
+}
+
+/// Removes `key` from generated nodes, `batch_size` at a time.
+async fn remove_keys(
+    graph: &Graph,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut removed = 0;
+    for label in keys::GENERATED {
+        loop {
+            cancel.check()?;
+            let query = Query::new(format!(
+                "MATCH (n:{}) WHERE n.key IS NOT NULL \
+                 WITH n LIMIT $limit REMOVE n.key RETURN count(n) AS removed",
+                label
+            ))
+            .param("limit", batch_size as i64);
+            let mut result = graph.execute(query).await?;
+            let count = match result.next().await? {
+                Some(row) => row.get::<i64>("removed")? as usize,
+                None => 0,
+            };
+            if count == 0 {
+                break;
+            }
+            removed += count;
+            println!("{} keys removed", removed);
+        }
+    }
+    Ok(removed)
+}
+
+/// Moves the graph from `from` to the next model, or back from the next
+/// model to `from` when `up` is false. Every step can be run again over a
+/// graph it has already been applied to, partly or wholly.
+async fn step(
+    graph: &Graph,
+    settings: &Settings,
+    from: Model,
+    up: bool,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    match (from, up) {
+        (Model::V1, true) => {
+            let named: Vec<String> = nutrition::FIELDS
+                .iter()
+                .enumerate()
+                .map(|(index, field)| {
+                    format!(
+                        "r.{} = CASE WHEN size(coalesce(r.nutrition, [])) = {} \
+                         THEN r.nutrition[{}] END",
+                        field,
+                        nutrition::FIELDS.len(),
+                        index
+                    )
+                })
+                .collect();
+            each_recipe(
+                graph,
+                &format!("SET {}", named.join(", ")),
+                batch_size,
+                cancel,
+            )
+            .await
+        }
+        (Model::V1, false) => {
+            let named: Vec<String> = nutrition::FIELDS
+                .iter()
+                .map(|field| format!("r.{}", field))
+                .collect();
+            each_recipe(
+                graph,
+                &format!("REMOVE {}", named.join(", ")),
+                batch_size,
+                cancel,
+            )
+            .await
+        }
+        (Model::V2, true) => {
+            cancel.check()?;
+            let assigned = keys::assign_missing(graph, &settings.namespace, batch_size).await?;
+            println!("{} keys assigned", assigned);
+            Ok(assigned)
+        }
+        (Model::V2, false) => remove_keys(graph, batch_size, cancel).await,
+        (Model::V3, _) => Err("v3 is the newest model".into()),
+    }
+}
+
+/// Rewrites the graph from model `from` to model `to`, one version at a
+/// time, in batches of `batch_size`. If a step fails or is cancelled, the
+/// steps already taken are undone, newest first, so the graph is left at
+/// `from`, and the error is returned. The current indexes are created
+/// after migrating to `CURRENT`.
+pub async fn migrate(
+    graph: &Graph,
+    settings: &Settings,
+    from: Model,
+    to: Model,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let up = to > from;
+    // Each step is named by the older model of its pair
+    let steps: Vec<Model> = if up {
+        MODELS[from as usize..to as usize].to_vec()
+    } else {
+        MODELS[to as usize..from as usize]
+            .iter()
+            .rev()
+            .copied()
+            .collect()
+    };
+
+    let mut taken = Vec::new();
+    for lower in steps {
+        let (start, end) = if up {
+            (lower, lower.next())
+        } else {
+            (lower.next(), lower)
+        };
+        println!("migrating {} to {}", start.name(), end.name());
+        if let Err(err) = step(graph, settings, lower, up, batch_size, cancel).await {
+            eprintln!("{}; rolling back to {}", err, from.name());
+            // The rollback runs to the end even after a cancel
+            let rollback = CancellationToken::new();
+            for lower in std::iter::once(lower).chain(taken.into_iter().rev()) {
+                step(graph, settings, lower, !up, batch_size, &rollback).await?;
+            }
+            return Err(err);
+        }
+        taken.push(lower);
+    }
+
+    if to == CURRENT {
+        graph::create_indexes(graph, settings).await?;
+    }
+    println!("graph is at model {}", to.name());
+    Ok(())
+}
@@ -0,0 +1,449 @@
+use crate::cancel::CancellationToken;
+use crate::interactions::Interaction;
+use crate::output;
+use csv::{Reader, Writer};
+use neo4rs::{BoltType, Graph, Query};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// How interactions are divided between train and test.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitBy {
+    /// Hold out a fraction of each user's ratings, always leaving at least
+    /// one in train
+    User,
+    /// Hold out a fraction of all ratings, regardless of user
+    Random,
+}
+
+impl FromStr for SplitBy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "user" => Ok(SplitBy::User),
+            "random" => Ok(SplitBy::Random),
+            other => Err(format!("unknown split: {}", other)),
+        }
+    }
+}
+
+pub struct Split {
+    pub train: Vec<Interaction>,
+    pub test: Vec<Interaction>,
+}
+
+/// `ml split`'s options: the interactions to split and where each half
+/// is written.
+pub struct SplitOptions {
+    pub input: PathBuf,
+    pub by: SplitBy,
+    pub test_fraction: f64,
+    pub seed: u64,
+    pub train: PathBuf,
+    pub test: PathBuf,
+}
+
+/// `ml evaluate`'s options: the two halves `ml split` wrote and how the
+/// recommenders are scored.
+pub struct EvaluateOptions {
+    pub train: PathBuf,
+    pub test: PathBuf,
+    pub k: usize,
+    pub min_rating: i32,
+    pub seed: u64,
+    pub algorithms: Vec<Algorithm>,
+}
+
+fn held_out(len: usize, fraction: f64) -> usize {
+    (len as f64 * fraction).round() as usize
+}
+
+/// Splits `interactions` reproducibly: the same input, fraction and seed
+/// always hold out the same rows.
+pub fn split(interactions: Vec<Interaction>, by: SplitBy, fraction: f64, seed: u64) -> Split {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut groups: BTreeMap<i64, Vec<Interaction>> = BTreeMap::new();
+    match by {
+        SplitBy::User => {
+            for interaction in interactions {
+                groups
+                    .entry(interaction.user_id)
+                    .or_default()
+                    .push(interaction);
+            }
+        }
+        SplitBy::Random => {
+            groups.insert(0, interactions);
+        }
+    }
+
+    let mut result = Split {
+        train: Vec::new(),
+        test: Vec::new(),
+    };
+    for (_, mut group) in groups {
+        group.shuffle(&mut rng);
+        let mut test = held_out(group.len(), fraction);
+        if by == SplitBy::User {
+            test = test.min(group.len() - 1);
+        }
+        let train = group.split_off(test);
+        result.test.extend(group);
+        result.train.extend(train);
+    }
+    result
+}
+
+pub fn read(input: &Path) -> Result<Vec<Interaction>, Box<dyn Error>> {
+    let mut interactions = Vec::new();
+    for result in Reader::from_reader(output::open_source(input)?).deserialize() {
+        interactions.push(result?);
+    }
+    Ok(interactions)
+}
+
+/// Writes interactions in the RAW_interactions.csv layout, so either half
+/// can be passed back to `load-interactions`.
+pub fn write(path: &Path, interactions: &[Interaction]) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(path)?;
+    for interaction in interactions {
+        wtr.serialize(interaction)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Splits the interactions in `options.input` and writes both halves.
+pub fn split_file(options: &SplitOptions) -> Result<Split, Box<dyn Error>> {
+    if !(0.0..=1.0).contains(&options.test_fraction) {
+        return Err("--test-fraction must be between 0 and 1".into());
+    }
+    let split = split(
+        read(&options.input)?,
+        options.by,
+        options.test_fraction,
+        options.seed,
+    );
+    write(&options.train, &split.train)?;
+    write(&options.test, &split.test)?;
+    println!(
+        "{} train and {} test interactions written to {} and {}",
+        split.train.len(),
+        split.test.len(),
+        options.train.display(),
+        options.test.display()
+    );
+    Ok(split)
+}
+
+/// Scores each of `options.algorithms` on the split and prints a table.
+pub fn evaluate_files(options: &EvaluateOptions) -> Result<(), Box<dyn Error>> {
+    let train = read(&options.train)?;
+    let test = read(&options.test)?;
+    let k = options.k;
+    println!(
+        "{:<12} {:>12} {:>12} {:>12} {:>8}",
+        "algorithm",
+        format!("precision@{}", k),
+        format!("recall@{}", k),
+        format!("ndcg@{}", k),
+        "users"
+    );
+    for algorithm in &options.algorithms {
+        let scores = evaluate(
+            *algorithm,
+            &train,
+            &test,
+            k,
+            options.min_rating,
+            options.seed,
+        );
+        println!(
+            "{:<12} {:>12.4} {:>12.4} {:>12.4} {:>8}",
+            algorithm.name(),
+            scores.precision,
+            scores.recall,
+            scores.ndcg,
+            scores.users
+        );
+    }
+    Ok(())
+}
+
+/// Sets `split` to "train" or "test" on the RATED edges of an already
+/// loaded graph.
+pub async fn label(
+    graph: &Graph,
+    split: &Split,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    for (name, rows) in [("train", &split.train), ("test", &split.test)] {
+        for batch in rows.chunks(batch_size.max(1)) {
+            cancel.check()?;
+            let rows: Vec<HashMap<String, BoltType>> = batch
+                .iter()
+                .map(|interaction| {
+                    let mut row = HashMap::new();
+                    row.insert("user_id".to_string(), interaction.user_id.into());
+                    row.insert("recipe_id".to_string(), interaction.recipe_id.into());
+                    row
+                })
+                .collect();
+            let query = Query::new(
+                "UNWIND $rows AS row \
+                 MATCH (:User {id: row.user_id})-[x:RATED]->(:Recipe {id: row.recipe_id}) \
+                 SET x.split = $split"
+                    .to_string(),
+            )
+            .param("rows", rows)
+            .param("split", name);
+            graph.run(query).await?;
+        }
+        println!("labelled {} {} ratings", rows.len(), name);
+    }
+    Ok(())
+}
+
+/// Baseline recommenders the harness can score. Each ranks every recipe
+/// seen in the training data and recommends the best ones a user has not
+/// already rated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    /// Most-rated recipes first
+    Popularity,
+    /// Highest mean rating, shrunk towards the global mean for recipes with
+    /// few ratings
+    TopRated,
+    /// A seeded shuffle, as the floor any real recommender should beat
+    Random,
+}
+
+pub const ALGORITHMS: [Algorithm; 3] = [
+    Algorithm::Popularity,
+    Algorithm::TopRated,
+    Algorithm::Random,
+];
+
+/// Ratings a recipe needs before its own mean outweighs the global mean.
+const PRIOR_WEIGHT: f64 = 10.0;
+
+impl FromStr for Algorithm {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ALGORITHMS
+            .iter()
+            .find(|algorithm| algorithm.name() == s)
+            .copied()
+            .ok_or_else(|| format!("unknown algorithm: {}", s))
+    }
+}
+
+impl Algorithm {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Popularity => "popularity",
+            Algorithm::TopRated => "top-rated",
+            Algorithm::Random => "random",
+        }
+    }
+
+    fn ranking(&self, train: &[Interaction], seed: u64) -> Vec<i32> {
+        let mut stats: HashMap<i32, (usize, f64)> = HashMap::new();
+        for interaction in train {
+            let entry = stats.entry(interaction.recipe_id).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += f64::from(interaction.rating);
+        }
+        let global_mean =
+            train.iter().map(|i| f64::from(i.rating)).sum::<f64>() / train.len().max(1) as f64;
+
+        let mut scored: Vec<(f64, i32)> = stats
+            .into_iter()
+            .map(|(recipe, (count, total))| {
+                let score = match self {
+                    Algorithm::Popularity => count as f64,
+                    Algorithm::TopRated => {
+                        (total + PRIOR_WEIGHT * global_mean) / (count as f64 + PRIOR_WEIGHT)
+                    }
+                    Algorithm::Random => 0.0,
+                };
+                (score, recipe)
+            })
+            .collect();
+        // Ties break on id so rankings are reproducible
+        scored.sort_by(|(a, x), (b, y)| b.total_cmp(a).then(x.cmp(y)));
+        let mut ranking: Vec<i32> = scored.into_iter().map(|(_, recipe)| recipe).collect();
+        if *self == Algorithm::Random {
+            ranking.shuffle(&mut StdRng::seed_from_u64(seed));
+        }
+        ranking
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Scores {
+    pub precision: f64,
+    pub recall: f64,
+    pub ndcg: f64,
+    pub users: usize,
+}
+
+/// Scores `algorithm` at cutoff `k`: for every test user, the recipes they
+/// rated at least `min_rating` in the test half are the relevant set, and
+/// recipes they rated in train are never recommended.
+pub fn evaluate(
+    algorithm: Algorithm,
+    train: &[Interaction],
+    test: &[Interaction],
+    k: usize,
+    min_rating: i32,
+    seed: u64,
+) -> Scores {
+    let ranking = algorithm.ranking(train, seed);
+    let mut seen: HashMap<i64, Vec<i32>> = HashMap::new();
+    for interaction in train {
+        seen.entry(interaction.user_id)
+            .or_default()
+            .push(interaction.recipe_id);
+    }
+    let mut relevant: BTreeMap<i64, Vec<i32>> = BTreeMap::new();
+    for interaction in test.iter().filter(|i| i.rating >= min_rating) {
+        relevant
+            .entry(interaction.user_id)
+            .or_default()
+            .push(interaction.recipe_id);
+    }
+
+    let mut scores = Scores::default();
+    for (user, relevant) in &relevant {
+        let seen = seen.get(user).map(Vec::as_slice).unwrap_or_default();
+        let recommended = ranking
+            .iter()
+            .filter(|recipe| !seen.contains(recipe))
+            .take(k);
+
+        let mut hits = 0;
+        let mut dcg = 0.0;
+        for (position, recipe) in recommended.enumerate() {
+            if relevant.contains(recipe) {
+                hits += 1;
+                dcg += 1.0 / (position as f64 + 2.0).log2();
+            }
+        }
+        let ideal: f64 = (0..relevant.len().min(k))
+            .map(|position| 1.0 / (position as f64 + 2.0).log2())
+            .sum();
+
+        scores.precision += hits as f64 / k as f64;
+        scores.recall += hits as f64 / relevant.len() as f64;
+        scores.ndcg += dcg / ideal;
+        scores.users += 1;
+    }
+
+    if scores.users > 0 {
+        let users = scores.users as f64;
+        scores.precision /= users;
+        scores.recall /= users;
+        scores.ndcg /= users;
+    }
+    scores
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rated(ratings: &[(i64, i32, i32)]) -> Vec<Interaction> {
+        ratings
+            .iter()
+            .map(|&(user_id, recipe_id, rating)| Interaction {
+                user_id,
+                recipe_id,
+                date: String::new(),
+                rating,
+                review: String::new(),
+            })
+            .collect()
+    }
+
+    fn close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn rankings_order_by_count_or_shrunk_mean() {
+        let train = rated(&[(1, 1, 1), (2, 1, 1), (3, 2, 5)]);
+        assert_eq!(Algorithm::Popularity.ranking(&train, 0), [1, 2]);
+        // Global mean 7/3: recipe 1 scores (2 + 70/3) / 12, recipe 2 (5 + 70/3) / 11
+        assert_eq!(Algorithm::TopRated.ranking(&train, 0), [2, 1]);
+    }
+
+    #[test]
+    fn scores_match_the_hand_computed_values() {
+        // Popularity ranks 1, 2, 3, 4; 3 and 4 tie and break on id
+        let train = rated(&[
+            (10, 1, 5),
+            (11, 1, 5),
+            (12, 1, 5),
+            (11, 2, 4),
+            (12, 2, 4),
+            (12, 3, 3),
+            (12, 4, 2),
+        ]);
+        let test = rated(&[
+            (10, 2, 2),
+            (10, 3, 5),
+            (10, 4, 4),
+            (11, 3, 4),
+            // Nothing relevant, so not scored
+            (13, 1, 2),
+        ]);
+        let scores = evaluate(Algorithm::Popularity, &train, &test, 2, 4, 0);
+
+        // User 10 is shown [2, 3] and wants {3, 4}: one hit at rank 2.
+        // User 11 is shown [3, 4] and wants {3}: one hit at rank 1.
+        let user_10_ndcg = (1.0 / 3f64.log2()) / (1.0 + 1.0 / 3f64.log2());
+        assert_eq!(scores.users, 2);
+        close(scores.precision, (0.5 + 0.5) / 2.0);
+        close(scores.recall, (0.5 + 1.0) / 2.0);
+        close(scores.ndcg, (user_10_ndcg + 1.0) / 2.0);
+    }
+
+    #[test]
+    fn no_relevant_ratings_score_zero() {
+        let train = rated(&[(1, 1, 5)]);
+        let scores = evaluate(Algorithm::Popularity, &train, &rated(&[(2, 1, 3)]), 5, 4, 0);
+        assert_eq!(scores.users, 0);
+        assert_eq!(scores.precision, 0.0);
+    }
+
+    #[test]
+    fn user_splits_keep_a_rating_of_each_user_in_train() {
+        let interactions = rated(&[(1, 1, 5), (1, 2, 5), (1, 3, 5), (1, 4, 5), (2, 1, 5)]);
+        let first = split(interactions.clone(), SplitBy::User, 1.0, 7);
+        assert_eq!(first.test.len(), 3);
+        assert_eq!(
+            first.train.iter().map(|i| i.user_id).collect::<Vec<_>>(),
+            [1, 2]
+        );
+
+        let again = split(interactions, SplitBy::User, 1.0, 7);
+        assert_eq!(
+            again.train.iter().map(|i| i.recipe_id).collect::<Vec<_>>(),
+            first.train.iter().map(|i| i.recipe_id).collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,205 @@
+use std::str::FromStr;
+
+/// Leading words in Food.com titles that label a contest or source rather
+/// than describe the dish ("aww marinated olives").
+const FILLERS: &[&str] = &["aww", "zwt", "rsc", "pac", "ww", "weight watchers"];
+
+/// Words kept lowercase inside a title-cased name.
+const MINOR_WORDS: &[&str] = &[
+    "a", "an", "and", "as", "at", "but", "by", "for", "from", "in", "n", "of", "on", "or", "the",
+    "to", "with",
+];
+
+/// How `name_display` is cased.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NameStyle {
+    /// "Marinated Olives with Feta"
+    #[default]
+    Title,
+    /// "Marinated olives with feta"
+    Sentence,
+    /// Cleaned but left in the source's case
+    Raw,
+}
+
+impl NameStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            NameStyle::Title => "title",
+            NameStyle::Sentence => "sentence",
+            NameStyle::Raw => "raw",
+        }
+    }
+}
+
+impl FromStr for NameStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "title" => Ok(NameStyle::Title),
+            "sentence" => Ok(NameStyle::Sentence),
+            "raw" => Ok(NameStyle::Raw),
+            other => Err(format!("unknown name style: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NameCleanup {
+    pub style: NameStyle,
+    /// Leading words to drop, longest first
+    fillers: Vec<String>,
+}
+
+impl Default for NameCleanup {
+    fn default() -> Self {
+        NameCleanup::new(NameStyle::default(), &[])
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Capitalizes each hyphenated part, so "no-bake" becomes "No-Bake".
+fn capitalize_parts(word: &str) -> String {
+    word.split('-')
+        .map(capitalize)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+impl NameCleanup {
+    /// The built-in fillers plus `extra_fillers`.
+    pub fn new(style: NameStyle, extra_fillers: &[String]) -> NameCleanup {
+        let mut fillers: Vec<String> = FILLERS
+            .iter()
+            .map(|filler| filler.to_string())
+            .chain(extra_fillers.iter().map(|filler| {
+                filler
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase()
+            }))
+            .filter(|filler| !filler.is_empty())
+            .collect();
+        fillers.sort_by(|a, b| b.len().cmp(&a.len()).then(a.cmp(b)));
+        fillers.dedup();
+        NameCleanup { style, fillers }
+    }
+
+    /// `name` without its leading filler, if it has one and more follows.
+    fn strip_filler(&self, name: &str) -> Option<String> {
+        let lower = name.to_lowercase();
+        self.fillers.iter().find_map(|filler| {
+            // Contest tags carry an edition number ("zwt8")
+            let rest = lower
+                .strip_prefix(filler.as_str())?
+                .trim_start_matches(|c: char| c.is_ascii_digit());
+            rest.starts_with(' ').then(|| {
+                name.split(' ')
+                    .skip(filler.split(' ').count())
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+        })
+    }
+
+    /// Drops filler prefixes, trailing punctuation and emoji, collapses
+    /// whitespace and applies the style.
+    pub fn display(&self, name: &str) -> String {
+        let mut name = name.split_whitespace().collect::<Vec<_>>().join(" ");
+        while let Some(rest) = self.strip_filler(&name) {
+            name = rest;
+        }
+        let name = name.trim_end_matches(|c: char| !c.is_alphanumeric() && c != ')');
+
+        let words: Vec<&str> = name.split(' ').collect();
+        let last = words.len().saturating_sub(1);
+        words
+            .iter()
+            .enumerate()
+            .map(|(index, word)| match self.style {
+                NameStyle::Raw => word.to_string(),
+                NameStyle::Sentence if index == 0 => capitalize(word),
+                NameStyle::Sentence => word.to_string(),
+                NameStyle::Title => {
+                    let lower = word.to_lowercase();
+                    if index != 0 && index != last && MINOR_WORDS.contains(&lower.as_str()) {
+                        lower
+                    } else {
+                        capitalize_parts(word)
+                    }
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(style: NameStyle, name: &str) -> String {
+        NameCleanup::new(style, &[]).display(name)
+    }
+
+    #[test]
+    fn titles_keep_minor_words_lowercase_inside_the_name() {
+        assert_eq!(
+            display(NameStyle::Title, "marinated  olives WITH feta"),
+            "Marinated Olives with Feta"
+        );
+        assert_eq!(display(NameStyle::Title, "the best pie"), "The Best Pie");
+        assert_eq!(
+            display(NameStyle::Title, "something to dip in"),
+            "Something to Dip In"
+        );
+        assert_eq!(
+            display(NameStyle::Title, "no-bake cookies"),
+            "No-Bake Cookies"
+        );
+    }
+
+    #[test]
+    fn styles_case_the_name_differently() {
+        assert_eq!(
+            display(NameStyle::Sentence, "no-bake Cookies"),
+            "No-bake Cookies"
+        );
+        assert_eq!(display(NameStyle::Raw, "mom's CHILI"), "mom's CHILI");
+        assert_eq!("sentence".parse(), Ok(NameStyle::Sentence));
+        assert!("shouting".parse::<NameStyle>().is_err());
+    }
+
+    #[test]
+    fn fillers_and_trailing_symbols_are_dropped() {
+        assert_eq!(
+            display(NameStyle::Title, "aww  pickled beets!!"),
+            "Pickled Beets"
+        );
+        assert_eq!(display(NameStyle::Title, "ZWT8 aww pie 😀"), "Pie");
+        assert_eq!(display(NameStyle::Raw, "weight watchers soup"), "soup");
+        assert_eq!(display(NameStyle::Title, "chili (mild)"), "Chili (mild)");
+    }
+
+    #[test]
+    fn fillers_must_be_whole_words_with_something_after_them() {
+        assert_eq!(display(NameStyle::Title, "awwesome pie"), "Awwesome Pie");
+        assert_eq!(display(NameStyle::Title, "aww"), "Aww");
+    }
+
+    #[test]
+    fn extra_fillers_are_normalised() {
+        let cleanup = NameCleanup::new(NameStyle::Title, &["  Food  Network ".to_string()]);
+        assert_eq!(cleanup.display("food network lasagna"), "Lasagna");
+        assert_eq!(cleanup.display("network lasagna"), "Network Lasagna");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::cancel::CancellationToken;
+use crate::interner::{Interner, Symbol};
+use crate::output;
+use crate::quantity;
+use crate::servings;
+use crate::settings::Settings;
+use crate::Recipe;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+/// The typical amount of one ingredient per serving across the corpus, in
+/// the unit it is most often measured in.
+#[derive(Debug, Serialize)]
+pub struct Norm {
+    pub ingredient: String,
+    pub recipes: usize,
+    /// `None` for counted items ("2 eggs")
+    pub unit: Option<String>,
+    pub p10: f64,
+    pub median: f64,
+    pub p90: f64,
+}
+
+/// An ingredient amount far outside its norm, most likely a data error
+/// such as "1 cup salt" or a quantity typed in the wrong unit.
+#[derive(Debug, Serialize)]
+pub struct Anomaly {
+    pub recipe_id: i32,
+    pub ingredient: String,
+    pub line: String,
+    pub per_serving: f64,
+    pub median: f64,
+    pub unit: Option<String>,
+    /// `per_serving / median`
+    pub ratio: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub norms: Vec<Norm>,
+    pub anomalies: Vec<Anomaly>,
+    /// Recipes left out for having no stated servings
+    pub skipped: usize,
+}
+
+/// One weighed ingredient line, per serving.
+struct Sample {
+    recipe_id: i32,
+    line: Symbol,
+    unit: Option<Symbol>,
+    grams: f64,
+}
+
+/// Nearest-rank percentile of sorted `values`.
+fn percentile(values: &[f64], fraction: f64) -> f64 {
+    let rank = (fraction * (values.len() - 1) as f64).round() as usize;
+    values[rank.min(values.len() - 1)]
+}
+
+/// Grams in one `unit`, weighing counted items as `approximate_grams` does.
+fn unit_grams(unit: Option<&str>) -> f64 {
+    match unit {
+        Some(unit) => quantity::grams_per_unit(unit).unwrap_or(1.0),
+        None => quantity::GRAMS_PER_ITEM,
+    }
+}
+
+/// `analyze quantity-norms`: computes the norms of `input`'s ingredients,
+/// writing them to `out` as CSV and the anomalies to `anomalies_out`, if
+/// given, as JSON lines.
+pub fn compute_file(
+    input: &Path,
+    out: Box<dyn Write>,
+    anomalies_out: Option<Box<dyn Write>>,
+    settings: &Settings,
+    min_recipes: usize,
+    tolerance: f64,
+    cancel: &CancellationToken,
+) -> Result<Report, Box<dyn Error>> {
+    if tolerance <= 1.0 {
+        return Err("--tolerance must be greater than 1".into());
+    }
+    let recipes = settings.recipes_in(input, cancel)?;
+    let report = compute(recipes, settings, min_recipes, tolerance)?;
+    output::write_csv(out, &report.norms)?;
+    if let Some(mut out) = anomalies_out {
+        for anomaly in &report.anomalies {
+            serde_json::to_writer(&mut out, anomaly)?;
+            out.write_all(b"\n")?;
+        }
+        out.flush()?;
+    }
+    eprintln!(
+        "{} ingredient norms, {} anomalous lines; {} recipes without stated servings skipped",
+        report.norms.len(),
+        report.anomalies.len(),
+        report.skipped
+    );
+    Ok(report)
+}
+
+/// Computes per-serving norms for every ingredient weighed in at least
+/// `min_recipes` recipes, then flags lines more than `tolerance` times above
+/// or below their ingredient's median. Only recipes whose servings are
+/// stated take part, since a weight-based guess would hide the anomaly.
+pub fn compute(
+    recipes: impl IntoIterator<Item = Result<Recipe, Box<dyn Error>>>,
+    settings: &Settings,
+    min_recipes: usize,
+    tolerance: f64,
+) -> Result<Report, Box<dyn Error>> {
+    let mut report = Report::default();
+    // Lines, names and units repeat across hundreds of thousands of recipes
+    let mut strings = Interner::default();
+    let mut samples: HashMap<Symbol, Vec<Sample>> = HashMap::new();
+
+    for recipe in recipes {
+        let recipe = recipe?;
+        let Some(servings) = servings::stated(&recipe) else {
+            report.skipped += 1;
+            continue;
+        };
+        for line in &recipe.ingredients {
+            let parsed = settings.parse_ingredient(line);
+            let Some(grams) = parsed.approximate_grams().filter(|grams| *grams > 0.0) else {
+                continue;
+            };
+            let ingredient = strings.intern(&parsed.name);
+            samples.entry(ingredient).or_default().push(Sample {
+                recipe_id: recipe.id,
+                line: strings.intern(line),
+                unit: parsed.unit.map(|unit| strings.intern(&unit)),
+                grams: grams / servings as f64,
+            });
+        }
+    }
+
+    for (ingredient, samples) in samples {
+        if samples.len() < min_recipes {
+            continue;
+        }
+        let mut grams: Vec<f64> = samples.iter().map(|sample| sample.grams).collect();
+        grams.sort_by(f64::total_cmp);
+        let median = percentile(&grams, 0.5);
+
+        let mut units: HashMap<Option<&str>, usize> = HashMap::new();
+        for sample in &samples {
+            let unit = sample.unit.map(|unit| strings.resolve(unit));
+            *units.entry(unit).or_insert(0) += 1;
+        }
+        let unit = units
+            .into_iter()
+            .max_by(|(a, x), (b, y)| x.cmp(y).then(b.cmp(a)))
+            .and_then(|(unit, _)| unit);
+        let ingredient = strings.resolve(ingredient);
+        let scale = unit_grams(unit);
+
+        for sample in &samples {
+            let ratio = sample.grams / median;
+            if ratio > tolerance || ratio < 1.0 / tolerance {
+                report.anomalies.push(Anomaly {
+                    recipe_id: sample.recipe_id,
+                    ingredient: ingredient.to_string(),
+                    line: strings.resolve(sample.line).to_string(),
+                    per_serving: sample.grams / scale,
+                    median: median / scale,
+                    unit: unit.map(str::to_string),
+                    ratio,
+                });
+            }
+        }
+        report.norms.push(Norm {
+            recipes: samples.len(),
+            unit: unit.map(str::to_string),
+            p10: percentile(&grams, 0.1) / scale,
+            median: median / scale,
+            p90: percentile(&grams, 0.9) / scale,
+            ingredient: ingredient.to_string(),
+        });
+    }
+
+    report.norms.sort_by(|a, b| {
+        b.recipes
+            .cmp(&a.recipes)
+            .then(a.ingredient.cmp(&b.ingredient))
+    });
+    report.anomalies.sort_by(|a, b| {
+        b.ratio
+            .total_cmp(&a.ratio)
+            .then(a.recipe_id.cmp(&b.recipe_id))
+    });
+    Ok(report)
+}
@@ -0,0 +1,332 @@
+use serde::Serialize;
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// Names of the nutrition vector's elements, in source order, as written to
+/// the graph.
+pub const FIELDS: [&str; 7] = [
+    "calories",
+    "total_fat_pdv",
+    "sugar_pdv",
+    "sodium_pdv",
+    "protein_pdv",
+    "saturated_fat_pdv",
+    "carbohydrates_pdv",
+];
+
+/// What a nutrition value is measured in. Food.com mixes an absolute
+/// amount with percentages, so the two must not be compared or summed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Unit {
+    /// Kilocalories per serving
+    #[serde(rename = "kcal")]
+    Kcal,
+    /// Percent of the daily value per serving; see `daily_value`
+    #[serde(rename = "pdv")]
+    Pdv,
+}
+
+impl Unit {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Unit::Kcal => "kcal",
+            Unit::Pdv => "pdv",
+        }
+    }
+}
+
+/// The unit of each element of `FIELDS`.
+pub const UNITS: [Unit; 7] = [
+    Unit::Kcal,
+    Unit::Pdv,
+    Unit::Pdv,
+    Unit::Pdv,
+    Unit::Pdv,
+    Unit::Pdv,
+    Unit::Pdv,
+];
+
+/// The daily values Food.com's percentages are taken against, for
+/// `FIELDS[1..]` in order, with the unit of each amount.
+const DAILY_VALUES: [(f32, &str); 6] = [
+    (65.0, "g"),
+    (50.0, "g"),
+    (2400.0, "mg"),
+    (50.0, "g"),
+    (20.0, "g"),
+    (300.0, "g"),
+];
+
+/// A row of the facts panel: label, daily value index, and whether it is
+/// indented under the row above.
+const LABEL_ROWS: [(&str, usize, bool); 6] = [
+    ("Total Fat", 0, false),
+    ("Saturated Fat", 4, true),
+    ("Sodium", 2, false),
+    ("Total Carbohydrate", 5, false),
+    ("Total Sugars", 1, true),
+    ("Protein", 3, false),
+];
+
+const LABEL_FOOTNOTE: &str = "* Percent Daily Values are based on a 2,000 calorie diet.";
+
+fn svg_text(x: u32, y: u32, attributes: &str, content: &str) -> String {
+    format!(
+        "  <text x=\"{}\" y=\"{}\"{}>{}</text>\n",
+        x,
+        y,
+        if attributes.is_empty() {
+            String::new()
+        } else {
+            format!(" {}", attributes)
+        },
+        content
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+    )
+}
+
+fn svg_rule(y: u32, width: u32, thickness: u32) -> String {
+    format!(
+        "  <rect x=\"8\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+        y,
+        width - 16,
+        thickness
+    )
+}
+
+/// How the nutrition column is read and stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Layout {
+    /// Exactly seven values, stored as named properties alongside the array
+    #[default]
+    Named,
+    /// Any number of values, stored only as the array, for dumps whose
+    /// vector differs from the Food.com one
+    Raw,
+}
+
+impl Layout {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Layout::Named => "named",
+            Layout::Raw => "raw",
+        }
+    }
+}
+
+impl FromStr for Layout {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "named" => Ok(Layout::Named),
+            "raw" => Ok(Layout::Raw),
+            other => Err(format!("unknown nutrition layout: {}", other)),
+        }
+    }
+}
+
+/// The unit of the field named `field`, one of `FIELDS`.
+pub fn unit(field: &str) -> Option<Unit> {
+    let index = FIELDS.iter().position(|name| *name == field)?;
+    Some(UNITS[index])
+}
+
+/// The unit symbol of each of `values`, or `None` when they are not the
+/// Food.com vector: under `Layout::Raw`, or without its seven values.
+pub fn unit_symbols(values: &[f32], layout: Layout) -> Option<Vec<&'static str>> {
+    (layout == Layout::Named && values.len() == FIELDS.len())
+        .then(|| UNITS.iter().map(Unit::symbol).collect())
+}
+
+/// The daily value a `pdv` field is a percentage of, as an amount and its
+/// unit (`g` or `mg`). `None` for calories and unknown fields.
+pub fn daily_value(field: &str) -> Option<(f32, &'static str)> {
+    let index = FIELDS.iter().position(|name| *name == field)?;
+    DAILY_VALUES.get(index.checked_sub(1)?).copied()
+}
+
+/// The amount `pdv` percent of `field`'s daily value comes to, in the unit
+/// `daily_value` gives.
+pub fn pdv_to_amount(field: &str, pdv: f32) -> Option<f32> {
+    let (daily, _) = daily_value(field)?;
+    Some(pdv / 100.0 * daily)
+}
+
+/// The percent of `field`'s daily value that `amount` is, `amount` being in
+/// the unit `daily_value` gives.
+pub fn amount_to_pdv(field: &str, amount: f32) -> Option<f32> {
+    let (daily, _) = daily_value(field)?;
+    Some(amount / daily * 100.0)
+}
+
+/// The Food.com nutrition vector by name. Everything but calories is a
+/// percentage of daily value; missing values are NaN.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Nutrition {
+    pub calories: f32,
+    pub total_fat_pdv: f32,
+    pub sugar_pdv: f32,
+    pub sodium_pdv: f32,
+    pub protein_pdv: f32,
+    pub saturated_fat_pdv: f32,
+    pub carbohydrates_pdv: f32,
+}
+
+impl Nutrition {
+    /// `None` unless `values` has exactly the seven elements of `FIELDS`.
+    pub fn from_values(values: &[f32]) -> Option<Nutrition> {
+        let [calories, total_fat_pdv, sugar_pdv, sodium_pdv, protein_pdv, saturated_fat_pdv, carbohydrates_pdv] =
+            *values
+        else {
+            return None;
+        };
+        Some(Nutrition {
+            calories,
+            total_fat_pdv,
+            sugar_pdv,
+            sodium_pdv,
+            protein_pdv,
+            saturated_fat_pdv,
+            carbohydrates_pdv,
+        })
+    }
+
+    /// Percent daily values in the order of `DAILY_VALUES`.
+    fn percentages(&self) -> [f32; 6] {
+        [
+            self.total_fat_pdv,
+            self.sugar_pdv,
+            self.sodium_pdv,
+            self.protein_pdv,
+            self.saturated_fat_pdv,
+            self.carbohydrates_pdv,
+        ]
+    }
+
+    /// (label, amount, % daily value, indented) for each panel row, amounts
+    /// worked back from the percentages.
+    fn label_rows(&self) -> Vec<(&'static str, String, String, bool)> {
+        let percentages = self.percentages();
+        LABEL_ROWS
+            .iter()
+            .map(|(label, index, indented)| {
+                let percent = percentages[*index];
+                let (daily, unit) = DAILY_VALUES[*index];
+                let amount = percent / 100.0 * daily;
+                (
+                    *label,
+                    format!("{:.0}{}", amount, unit),
+                    format!("{:.0}%", percent),
+                    *indented,
+                )
+            })
+            .collect()
+    }
+
+    /// A US-style "Nutrition Facts" panel as plain text, per serving.
+    pub fn label_text(&self, title: &str, servings: Option<i64>) -> String {
+        const WIDTH: usize = 40;
+        let rule = "─".repeat(WIDTH);
+        let mut out = format!("Nutrition Facts\n{}\n", title);
+        if let Some(servings) = servings {
+            let _ = writeln!(out, "{} servings per recipe", servings);
+        }
+        let _ = writeln!(out, "{}", "━".repeat(WIDTH));
+        let _ = writeln!(out, "Amount per serving");
+        let _ = writeln!(
+            out,
+            "Calories{:>width$.0}",
+            self.calories,
+            width = WIDTH - 8
+        );
+        let _ = writeln!(out, "{}", rule);
+        let _ = writeln!(out, "{:>width$}", "% Daily Value*", width = WIDTH);
+        for (label, amount, percent, indented) in self.label_rows() {
+            let name = format!("{}{} {}", if indented { "  " } else { "" }, label, amount);
+            let _ = writeln!(out, "{:<width$}{:>5}", name, percent, width = WIDTH - 5);
+        }
+        let _ = writeln!(out, "{}", rule);
+        out.push_str(LABEL_FOOTNOTE);
+        out.push('\n');
+        out
+    }
+
+    /// The panel of `label_text` as a standalone SVG image.
+    pub fn label_svg(&self, title: &str, servings: Option<i64>) -> String {
+        const WIDTH: u32 = 280;
+        const LINE: u32 = 20;
+        const RIGHT: &str = r#"text-anchor="end" font-weight="bold""#;
+        let mut body = String::new();
+        let mut y = 30;
+
+        body += &svg_text(
+            8,
+            y,
+            r#"font-size="24" font-weight="bold""#,
+            "Nutrition Facts",
+        );
+        y += LINE;
+        body += &svg_text(8, y, "", title);
+        if let Some(servings) = servings {
+            y += LINE;
+            body += &svg_text(8, y, "", &format!("{} servings per recipe", servings));
+        }
+        y += 8;
+        body += &svg_rule(y, WIDTH, 8);
+        y += 8 + LINE;
+        body += &svg_text(8, y, r#"font-weight="bold""#, "Amount per serving");
+        y += LINE + 4;
+        body += &svg_text(8, y, r#"font-size="20" font-weight="bold""#, "Calories");
+        body += &svg_text(
+            WIDTH - 8,
+            y,
+            r#"font-size="20" text-anchor="end" font-weight="bold""#,
+            &format!("{:.0}", self.calories),
+        );
+        y += 6;
+        body += &svg_rule(y, WIDTH, 4);
+        y += 4 + LINE;
+        body += &svg_text(WIDTH - 8, y, RIGHT, "% Daily Value*");
+        for (label, amount, percent, indented) in self.label_rows() {
+            y += 4;
+            body += &svg_rule(y, WIDTH, 1);
+            y += LINE - 4;
+            let line = format!("{} {}", label, amount);
+            body += &match indented {
+                true => svg_text(20, y, "", &line),
+                false => svg_text(8, y, r#"font-weight="bold""#, &line),
+            };
+            body += &svg_text(WIDTH - 8, y, RIGHT, &percent);
+        }
+        y += 6;
+        body += &svg_rule(y, WIDTH, 8);
+        y += 8 + LINE;
+        body += &svg_text(8, y, r#"font-size="9""#, LABEL_FOOTNOTE);
+        y += 10;
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+             font-family=\"Helvetica, Arial, sans-serif\" font-size=\"12\">\n\
+             \x20 <rect width=\"{w}\" height=\"{h}\" fill=\"white\" stroke=\"black\"/>\n{body}</svg>\n",
+            w = WIDTH,
+            h = y,
+            body = body
+        )
+    }
+
+    /// The values in `FIELDS` order.
+    pub fn values(&self) -> [f32; 7] {
+        [
+            self.calories,
+            self.total_fat_pdv,
+            self.sugar_pdv,
+            self.sodium_pdv,
+            self.protein_pdv,
+            self.saturated_fat_pdv,
+            self.carbohydrates_pdv,
+        ]
+    }
+}
@@ -0,0 +1,1039 @@
+use crate::edgelist::{self, Projection};
+use crate::icons;
+use crate::nutrition;
+use crate::parser::{python_list, python_repr};
+use crate::quantity::IngredientLine;
+use crate::rows::{self, Rows, Value};
+use crate::settings::Settings;
+use crate::voice::VoiceRecipe;
+use crate::Recipe;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+/// A streaming exporter. Recipes are written one at a time so exports never
+/// hold the whole corpus in memory; `finish` writes any trailer and flushes.
+pub trait OutputWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>>;
+
+    fn finish(self: Box<Self>) -> Result<(), Box<dyn Error>>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Jsonl,
+    Csv,
+    Markdown,
+    Cypher,
+    CypherStream,
+    Graphml,
+    Voice,
+    Csr,
+    Edgelist,
+    NutritionLabel,
+    NutritionLabelSvg,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jsonl" => Ok(Format::Jsonl),
+            "csv" => Ok(Format::Csv),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "cypher" => Ok(Format::Cypher),
+            "cypher-stream" => Ok(Format::CypherStream),
+            "graphml" => Ok(Format::Graphml),
+            "voice" => Ok(Format::Voice),
+            "csr" => Ok(Format::Csr),
+            "edgelist" => Ok(Format::Edgelist),
+            "nutrition-label" => Ok(Format::NutritionLabel),
+            "nutrition-label-svg" => Ok(Format::NutritionLabelSvg),
+            other => Err(format!("unknown output format: {}", other)),
+        }
+    }
+}
+
+/// Opens `path` for writing, where `-` means stdout. Output is gzipped when
+/// `compress` is set or the path ends in `.gz`.
+pub fn open_sink(path: &str, compress: bool) -> Result<Box<dyn Write>, Box<dyn Error>> {
+    let sink: Box<dyn Write> = match path {
+        "-" => Box::new(io::stdout().lock()),
+        path => Box::new(BufWriter::new(File::create(path)?)),
+    };
+
+    if compress || path.ends_with(".gz") {
+        Ok(Box::new(GzEncoder::new(sink, Compression::default())))
+    } else {
+        Ok(sink)
+    }
+}
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Why `path` could not be opened or read, naming it. Windows paths are
+/// the usual cause off Windows, so a path that looks like one says so.
+fn unreadable(path: &Path, err: io::Error) -> String {
+    let text = path.to_string_lossy();
+    let windows = text.contains('\\')
+        || text.as_bytes().get(1) == Some(&b':') && text.as_bytes()[0].is_ascii_alphabetic();
+    if windows && !cfg!(windows) {
+        format!(
+            "cannot read {}: {} (this looks like a Windows path; use / between directories)",
+            text, err
+        )
+    } else {
+        format!("cannot read {}: {}", text, err)
+    }
+}
+
+/// Skips a UTF-8 byte order mark, as Excel and Notepad write, at the start
+/// of `source`, and rejects UTF-16, which would otherwise fail as invalid
+/// UTF-8 somewhere in the first row.
+fn skip_bom(path: &Path, mut source: impl BufRead) -> Result<impl BufRead, Box<dyn Error>> {
+    let head = source.fill_buf().map_err(|err| unreadable(path, err))?;
+    if head.starts_with(UTF8_BOM) {
+        source.consume(UTF8_BOM.len());
+    } else if head.starts_with(b"\xFF\xFE") || head.starts_with(b"\xFE\xFF") {
+        return Err(format!(
+            "{} is UTF-16 text; save it as UTF-8 (\"CSV UTF-8\" in Excel) and try again",
+            path.display()
+        )
+        .into());
+    }
+    Ok(source)
+}
+
+/// Opens `path` for reading, where `-` means stdin. Input ending in `.gz`,
+/// in any case, is decompressed. A leading byte order mark is skipped.
+pub fn open_source(path: &Path) -> Result<Box<dyn Read>, Box<dyn Error>> {
+    let source: Box<dyn Read> = if path == Path::new("-") {
+        Box::new(io::stdin().lock())
+    } else {
+        Box::new(File::open(path).map_err(|err| unreadable(path, err))?)
+    };
+
+    if path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("gz"))
+    {
+        let source = BufReader::new(GzDecoder::new(source));
+        Ok(Box::new(skip_bom(path, source)?))
+    } else {
+        Ok(Box::new(skip_bom(path, BufReader::new(source))?))
+    }
+}
+
+/// The whole of the text file at `path`, for the TOML, JSON and word list
+/// files options name. A leading byte order mark is dropped, and text
+/// that is not UTF-8 is an error naming the file.
+pub fn read_text(path: &Path) -> Result<String, Box<dyn Error>> {
+    let mut bytes = Vec::new();
+    skip_bom(
+        path,
+        BufReader::new(File::open(path).map_err(|err| unreadable(path, err))?),
+    )?
+    .read_to_end(&mut bytes)
+    .map_err(|err| unreadable(path, err))?;
+    String::from_utf8(bytes).map_err(|err| {
+        format!(
+            "{} is not UTF-8 text (invalid byte at {}); save it as UTF-8",
+            path.display(),
+            err.utf8_error().valid_up_to()
+        )
+        .into()
+    })
+}
+
+/// Writes `rows` to `sink` as CSV, one record per row with a header.
+pub fn write_csv<T: Serialize>(sink: Box<dyn Write>, rows: &[T]) -> Result<(), Box<dyn Error>> {
+    let mut out = csv::Writer::from_writer(sink);
+    for row in rows {
+        out.serialize(row)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Statements per `:begin`/`:commit` block in `cypher-stream` output unless
+/// `cypher_stream` is given another size.
+pub const CYPHER_STREAM_TRANSACTION: usize = 1000;
+
+/// The writer for `format`, which parses and formats recipes with `settings`.
+pub fn writer(format: Format, sink: Box<dyn Write>, settings: &Settings) -> Box<dyn OutputWriter> {
+    let settings = settings.clone();
+    match format {
+        Format::Jsonl => Box::new(JsonlWriter { sink, settings }),
+        Format::Csv => Box::new(CsvWriter {
+            csv: csv::Writer::from_writer(sink),
+            wrote_header: false,
+            settings,
+        }),
+        Format::Markdown => Box::new(MarkdownWriter {
+            sink,
+            settings: settings.clone(),
+        }),
+        Format::Cypher => cypher(sink, false, &settings),
+        Format::CypherStream => cypher_stream(sink, CYPHER_STREAM_TRANSACTION, &settings),
+        Format::Graphml => Box::new(GraphmlWriter {
+            sink,
+            ingredients: HashSet::new(),
+            started: false,
+            settings,
+        }),
+        Format::Voice => Box::new(VoiceWriter { sink }),
+        Format::Csr => Box::new(CsrWriter {
+            sink,
+            recipe_ids: Vec::new(),
+            columns: HashMap::new(),
+            ingredients: Vec::new(),
+            indptr: vec![0],
+            indices: Vec::new(),
+            settings,
+        }),
+        Format::Edgelist => edgelist::writer(sink, Projection::RecipeIngredient, false, &settings)
+            .expect("recipe-ingredient edges are read from recipes"),
+        Format::NutritionLabel => Box::new(NutritionLabelWriter { sink, svg: false }),
+        Format::NutritionLabelSvg => Box::new(NutritionLabelWriter { sink, svg: true }),
+    }
+}
+
+/// The `cypher` writer, writing steps as Step nodes with `steps_as_nodes`
+/// as `GraphLoader::with_steps_as_nodes` does.
+pub fn cypher(
+    sink: Box<dyn Write>,
+    steps_as_nodes: bool,
+    settings: &Settings,
+) -> Box<dyn OutputWriter> {
+    Box::new(CypherWriter {
+        sink,
+        settings: settings.clone(),
+        steps_as_nodes,
+    })
+}
+
+/// The `cypher-stream` writer with `transaction` statements per block.
+pub fn cypher_stream(
+    sink: Box<dyn Write>,
+    transaction: usize,
+    settings: &Settings,
+) -> Box<dyn OutputWriter> {
+    Box::new(CypherStreamWriter {
+        sink,
+        transaction: transaction.max(1),
+        open: 0,
+        settings: settings.clone(),
+    })
+}
+
+/// Quotes a string as a Cypher literal. Line breaks are escaped so every
+/// statement stays on one line.
+pub fn cypher_string(value: &str) -> String {
+    format!(
+        "'{}'",
+        value
+            .replace('\\', "\\\\")
+            .replace('\'', "\\'")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+    )
+}
+
+/// Optional string properties carried by the Cypher and GraphML writers.
+fn optional_fields(recipe: &Recipe) -> [(&'static str, &Option<String>); 5] {
+    [
+        ("name_display", &recipe.name_display),
+        ("source_url", &recipe.source_url),
+        ("author", &recipe.author),
+        ("license", &recipe.license),
+        ("content_hash", &recipe.content_hash),
+    ]
+}
+
+/// "Source: url · by author · license", or `None` when nothing is credited.
+fn attribution(recipe: &Recipe) -> Option<String> {
+    let parts: Vec<String> = [
+        recipe
+            .source_url
+            .as_ref()
+            .map(|url| format!("Source: {}", url)),
+        recipe
+            .author
+            .as_ref()
+            .map(|author| format!("by {}", author)),
+        recipe.license.clone(),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// The parsed quantity of one ingredient line, the values the loader sets
+/// on its CONTAINS relationship.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct LineQuantity {
+    quantity_min: Option<f64>,
+    quantity_max: Option<f64>,
+    unit: Option<String>,
+    to_taste: bool,
+}
+
+impl LineQuantity {
+    fn new(line: &IngredientLine) -> LineQuantity {
+        let quantity = line.quantity.as_ref();
+        LineQuantity {
+            quantity_min: quantity.and_then(|q| q.min()),
+            quantity_max: quantity.and_then(|q| q.max()),
+            unit: line.unit.clone(),
+            to_taste: quantity.is_some_and(|q| q.is_to_taste()),
+        }
+    }
+
+    /// One per line of `recipe`, in order.
+    fn of(recipe: &Recipe, settings: &Settings) -> Vec<LineQuantity> {
+        recipe
+            .ingredients
+            .iter()
+            .map(|line| LineQuantity::new(&settings.parse_ingredient(line)))
+            .collect()
+    }
+
+    /// `key: value` pairs for what was parsed, empty for a bare name.
+    fn pairs(&self) -> Vec<(&'static str, String)> {
+        [
+            ("quantity_min", self.quantity_min.map(|min| min.to_string())),
+            ("quantity_max", self.quantity_max.map(|max| max.to_string())),
+            ("unit", self.unit.clone()),
+            ("to_taste", self.to_taste.then(|| "true".to_string())),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+/// A Python list literal of `items`, `None` for missing values.
+fn python_values<T>(items: &[T], value: impl Fn(&T) -> Option<String>) -> String {
+    let values: Vec<String> = items
+        .iter()
+        .map(|item| value(item).unwrap_or_else(|| "None".to_string()))
+        .collect();
+    format!("[{}]", values.join(", "))
+}
+
+/// A recipe as exported, with the unit of each nutrition value beside the
+/// values and the icon and parsed quantity of each ingredient line beside
+/// the lines.
+#[derive(Serialize)]
+struct Exported<'a> {
+    #[serde(flatten)]
+    recipe: &'a Recipe,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    nutrition_units: Option<Vec<&'static str>>,
+    /// One per ingredient line, `null` where there is none
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ingredient_icons: Option<Vec<Option<icons::Icon>>>,
+    /// One per ingredient line
+    ingredient_quantities: Vec<LineQuantity>,
+}
+
+impl<'a> Exported<'a> {
+    fn new(recipe: &'a Recipe, settings: &Settings) -> Exported<'a> {
+        let lines: Vec<IngredientLine> = recipe
+            .ingredients
+            .iter()
+            .map(|line| settings.parse_ingredient(line))
+            .collect();
+        let ingredient_icons: Vec<Option<icons::Icon>> =
+            lines.iter().map(|line| icons::lookup(&line.name)).collect();
+        Exported {
+            recipe,
+            nutrition_units: nutrition::unit_symbols(&recipe.nutrition, settings.layout),
+            ingredient_icons: ingredient_icons
+                .iter()
+                .any(Option::is_some)
+                .then_some(ingredient_icons),
+            ingredient_quantities: lines.iter().map(LineQuantity::new).collect(),
+        }
+    }
+}
+
+struct JsonlWriter {
+    sink: Box<dyn Write>,
+    settings: Settings,
+}
+
+impl OutputWriter for JsonlWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let exported = Exported::new(recipe, &self.settings);
+        self.settings
+            .scope(|| serde_json::to_writer(&mut self.sink, &exported))?;
+        self.sink.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// JSON lines of short, SSML-ready utterances for voice-assistant skills.
+struct VoiceWriter {
+    sink: Box<dyn Write>,
+}
+
+impl OutputWriter for VoiceWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer(&mut self.sink, &VoiceRecipe::from_recipe(recipe))?;
+        self.sink.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// Writes lists back in the Python-literal form the loader reads, so CSV
+/// exports can be re-ingested.
+struct CsvWriter {
+    csv: csv::Writer<Box<dyn Write>>,
+    wrote_header: bool,
+    settings: Settings,
+}
+
+impl OutputWriter for CsvWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        if !self.wrote_header {
+            self.csv.write_record([
+                "id",
+                "name",
+                "description",
+                "minutes",
+                "ingredients",
+                "steps",
+                "nutrition",
+                "nutrition_units",
+                "tags",
+                "source_url",
+                "author",
+                "license",
+                "content_hash",
+                "quantity_min",
+                "quantity_max",
+                "unit",
+                "to_taste",
+            ])?;
+            self.wrote_header = true;
+        }
+
+        // Always floats, so the export reads back in as the same values
+        let precision = self.settings.precision;
+        let nutrition: Vec<String> = recipe
+            .nutrition
+            .iter()
+            .map(|value| precision.float(*value).to_string())
+            .collect();
+        let quantities = LineQuantity::of(recipe, &self.settings);
+        self.csv.write_record([
+            recipe.id.to_string(),
+            recipe.name.clone(),
+            recipe.description.clone(),
+            recipe.minutes.to_string(),
+            python_list(&recipe.ingredients),
+            python_list(&recipe.steps),
+            format!("[{}]", nutrition.join(", ")),
+            nutrition::unit_symbols(&recipe.nutrition, self.settings.layout)
+                .map(|units| python_list(&units))
+                .unwrap_or_default(),
+            python_list(&recipe.tags),
+            recipe.source_url.clone().unwrap_or_default(),
+            recipe.author.clone().unwrap_or_default(),
+            recipe.license.clone().unwrap_or_default(),
+            recipe.content_hash.clone().unwrap_or_default(),
+            python_values(&quantities, |q| {
+                q.quantity_min.map(|min| format!("{:?}", min))
+            }),
+            python_values(&quantities, |q| {
+                q.quantity_max.map(|max| format!("{:?}", max))
+            }),
+            python_values(&quantities, |q| q.unit.as_deref().map(python_repr)),
+            python_values(&quantities, |q| {
+                Some(if q.to_taste { "True" } else { "False" }.to_string())
+            }),
+        ])?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.csv.flush()?;
+        Ok(())
+    }
+}
+
+struct MarkdownWriter {
+    sink: Box<dyn Write>,
+    settings: Settings,
+}
+
+impl OutputWriter for MarkdownWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let out = &mut self.sink;
+        let title = recipe.name_display.as_deref().unwrap_or(&recipe.name);
+        writeln!(out, "# {}\n", title)?;
+        if !recipe.description.is_empty() {
+            writeln!(out, "{}\n", recipe.description)?;
+        }
+        writeln!(out, "_{} minutes_\n", recipe.minutes)?;
+        writeln!(out, "## Ingredients\n")?;
+        for (ingredient, quantity) in recipe
+            .ingredients
+            .iter()
+            .zip(LineQuantity::of(recipe, &self.settings))
+        {
+            let pairs: Vec<String> = quantity
+                .pairs()
+                .iter()
+                .map(|(key, value)| format!("{}: {}", key, value))
+                .collect();
+            if pairs.is_empty() {
+                writeln!(out, "- {}", ingredient)?;
+            } else {
+                writeln!(out, "- {} <!-- {} -->", ingredient, pairs.join(", "))?;
+            }
+        }
+        writeln!(out, "\n## Steps\n")?;
+        for (index, step) in recipe.steps.iter().enumerate() {
+            writeln!(out, "{}. {}", index + 1, step)?;
+        }
+        if let Some(attribution) = attribution(recipe) {
+            writeln!(out, "\n_{}_", attribution)?;
+        }
+        if let Some(hash) = &recipe.content_hash {
+            writeln!(out, "\n<!-- content_hash: {} -->", hash)?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// The statements that load one recipe, one per row: the live loader's
+/// statements and rows, each row bound with `WITH … AS row` instead of
+/// unwound from a batch, then the link to its course.
+fn cypher_statements(
+    recipe: &Recipe,
+    settings: &Settings,
+    steps_as_nodes: bool,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let rows = Rows::new(recipe, settings, steps_as_nodes);
+    let columns: HashSet<&str> = rows.recipes[0].iter().map(|(name, _)| *name).collect();
+    let mut statements = Vec::new();
+    for (table, body) in rows::statements(&columns)? {
+        for row in rows.table(table) {
+            statements.push(format!("WITH {} AS row {};", rows::cypher_map(row), body));
+        }
+    }
+    let course = rows.recipes[0]
+        .iter()
+        .find_map(|(name, value)| match value {
+            Value::Text(course) if *name == "course" => Some(course),
+            _ => None,
+        });
+    if let Some(course) = course {
+        statements.push(format!(
+            "{};",
+            rows::link_course(recipe.id, course, &settings.namespace)
+        ));
+    }
+    Ok(statements)
+}
+
+/// One MERGE script per recipe.
+struct CypherWriter {
+    sink: Box<dyn Write>,
+    settings: Settings,
+    steps_as_nodes: bool,
+}
+
+impl OutputWriter for CypherWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        for statement in cypher_statements(recipe, &self.settings, self.steps_as_nodes)? {
+            writeln!(self.sink, "{}", statement)?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// The MERGE statements of `CypherWriter` in `:begin`/`:commit` blocks of
+/// `transaction` statements, for piping into `cypher-shell` where the Bolt
+/// driver can't be used.
+struct CypherStreamWriter {
+    sink: Box<dyn Write>,
+    transaction: usize,
+    /// Statements written in the open block
+    open: usize,
+    settings: Settings,
+}
+
+impl OutputWriter for CypherStreamWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        for statement in cypher_statements(recipe, &self.settings, false)? {
+            if self.open == 0 {
+                writeln!(self.sink, ":begin")?;
+            }
+            writeln!(self.sink, "{}", statement)?;
+            self.open += 1;
+            if self.open == self.transaction {
+                writeln!(self.sink, ":commit")?;
+                self.open = 0;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        if self.open > 0 {
+            writeln!(self.sink, ":commit")?;
+        }
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// Recipe and Ingredient nodes with CONTAINS edges, for Gephi/yEd/networkx.
+struct GraphmlWriter {
+    sink: Box<dyn Write>,
+    ingredients: HashSet<String>,
+    started: bool,
+    settings: Settings,
+}
+
+impl GraphmlWriter {
+    fn start(&mut self) -> io::Result<()> {
+        let out = &mut self.sink;
+        writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            out,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#
+        )?;
+        writeln!(
+            out,
+            r#"  <key id="name" for="node" attr.name="name" attr.type="string"/>"#
+        )?;
+        for key in ["source_url", "author", "license", "content_hash"] {
+            writeln!(
+                out,
+                r#"  <key id="{0}" for="node" attr.name="{0}" attr.type="string"/>"#,
+                key
+            )?;
+        }
+        for (key, kind) in [
+            ("quantity_min", "double"),
+            ("quantity_max", "double"),
+            ("unit", "string"),
+            ("to_taste", "boolean"),
+        ] {
+            writeln!(
+                out,
+                r#"  <key id="{0}" for="edge" attr.name="{0}" attr.type="{1}"/>"#,
+                key, kind
+            )?;
+        }
+        writeln!(out, r#"  <graph id="recipes" edgedefault="directed">"#)?;
+        self.started = true;
+        Ok(())
+    }
+}
+
+impl OutputWriter for GraphmlWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        if !self.started {
+            self.start()?;
+        }
+
+        let out = &mut self.sink;
+        let attribution: String = optional_fields(recipe)
+            .iter()
+            .filter_map(|(key, value)| {
+                let value = value.as_ref()?;
+                Some(format!(
+                    r#"<data key="{}">{}</data>"#,
+                    key,
+                    xml_escape(value)
+                ))
+            })
+            .collect();
+        writeln!(
+            out,
+            r#"    <node id="recipe-{}"><data key="label">Recipe</data><data key="name">{}</data>{}</node>"#,
+            recipe.id,
+            xml_escape(&recipe.name),
+            attribution
+        )?;
+        for (ingredient, quantity) in recipe
+            .ingredients
+            .iter()
+            .zip(LineQuantity::of(recipe, &self.settings))
+        {
+            let id = format!("ingredient-{}", xml_escape(ingredient));
+            if self.ingredients.insert(ingredient.clone()) {
+                writeln!(
+                    out,
+                    r#"    <node id="{}"><data key="label">Ingredient</data><data key="name">{}</data></node>"#,
+                    id,
+                    xml_escape(ingredient)
+                )?;
+            }
+            let data: String = quantity
+                .pairs()
+                .iter()
+                .map(|(key, value)| format!(r#"<data key="{}">{}</data>"#, key, xml_escape(value)))
+                .collect();
+            writeln!(
+                out,
+                r#"    <edge source="recipe-{}" target="{}">{}</edge>"#,
+                recipe.id, id, data
+            )?;
+        }
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        if !self.started {
+            self.start()?;
+        }
+        writeln!(self.sink, "  </graph>\n</graphml>")?;
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// The recipe x ingredient incidence matrix in CSR form, as one JSON object
+/// that loads with `scipy.sparse.csr_matrix((data, indices, indptr), shape)`.
+/// Row `i` is `recipe_ids[i]`; column `j` is `ingredients[j]`, using the
+/// parsed names the graph's Ingredient nodes carry.
+struct CsrWriter {
+    sink: Box<dyn Write>,
+    recipe_ids: Vec<i32>,
+    columns: HashMap<String, usize>,
+    ingredients: Vec<String>,
+    indptr: Vec<usize>,
+    indices: Vec<usize>,
+    settings: Settings,
+}
+
+impl OutputWriter for CsrWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let mut row: Vec<usize> = recipe
+            .ingredients
+            .iter()
+            .map(|line| {
+                let name = self.settings.parse_ingredient(line).name;
+                let next = self.ingredients.len();
+                *self.columns.entry(name.clone()).or_insert_with(|| {
+                    self.ingredients.push(name);
+                    next
+                })
+            })
+            .collect();
+        row.sort_unstable();
+        row.dedup();
+
+        self.recipe_ids.push(recipe.id);
+        self.indices.extend(row);
+        self.indptr.push(self.indices.len());
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        let matrix = serde_json::json!({
+            "shape": [self.recipe_ids.len(), self.ingredients.len()],
+            "indptr": self.indptr,
+            "indices": self.indices,
+            "data": vec![1u8; self.indices.len()],
+            "recipe_ids": self.recipe_ids,
+            "ingredients": self.ingredients,
+        });
+        serde_json::to_writer(&mut self.sink, &matrix)?;
+        self.sink.write_all(b"\n")?;
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+/// A "Nutrition Facts" panel per recipe, as text or SVG, for recipe cards.
+/// Recipes without the seven named nutrition values are skipped.
+struct NutritionLabelWriter {
+    sink: Box<dyn Write>,
+    svg: bool,
+}
+
+impl OutputWriter for NutritionLabelWriter {
+    fn write_recipe(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let Some(facts) = recipe.nutrition_facts() else {
+            eprintln!("recipe {} has no nutrition facts", recipe.id);
+            return Ok(());
+        };
+        let title = recipe.name_display.as_deref().unwrap_or(&recipe.name);
+        let label = if self.svg {
+            facts.label_svg(title, recipe.servings)
+        } else {
+            facts.label_text(title, recipe.servings)
+        };
+        writeln!(self.sink, "{}", label)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> Result<(), Box<dyn Error>> {
+        self.sink.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    /// A file in the temp directory holding `bytes`, named for the test.
+    fn fixture(name: &str, bytes: &[u8]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("recipe_app_{}_{}", std::process::id(), name));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn read_source(path: &Path) -> Result<String, Box<dyn Error>> {
+        let mut text = String::new();
+        open_source(path)?.read_to_string(&mut text)?;
+        Ok(text)
+    }
+
+    #[test]
+    fn byte_order_marks_are_skipped() {
+        let csv = fixture("bom.csv", b"\xEF\xBB\xBFid,name\r\n1,pie\r\n");
+        let toml = fixture("bom.toml", b"\xEF\xBB\xBFkeep = [\"capers\"]\n");
+
+        assert_eq!(read_source(&csv).unwrap(), "id,name\r\n1,pie\r\n");
+        assert_eq!(read_text(&toml).unwrap(), "keep = [\"capers\"]\n");
+    }
+
+    #[test]
+    fn compressed_input_is_read_whatever_the_extension_case() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"\xEF\xBB\xBFid\n1\n").unwrap();
+        let path = fixture("upper.CSV.GZ", &encoder.finish().unwrap());
+
+        assert_eq!(read_source(&path).unwrap(), "id\n1\n");
+    }
+
+    #[test]
+    fn utf16_and_other_encodings_are_named_errors() {
+        let utf16 = fixture("utf16.csv", b"\xFF\xFEi\0d\0\n\0");
+        let latin1 = fixture("latin1.txt", b"cr\xE8me\n");
+
+        let err = read_source(&utf16).unwrap_err().to_string();
+        assert!(err.contains("UTF-16"), "{}", err);
+        let err = read_text(&latin1).unwrap_err().to_string();
+        assert!(err.contains("not UTF-8"), "{}", err);
+    }
+
+    #[test]
+    fn csv_list_columns_read_back_with_quotes_and_backslashes() {
+        let mut recipe = crate::testkit::recipes("pathological_encoding")
+            .unwrap()
+            .into_iter()
+            .find(|recipe| recipe.id == 900005)
+            .unwrap();
+        recipe.ingredients.push("mom's pie".to_string());
+        recipe
+            .ingredients
+            .push("both ' and \" with a \\ and \\'".to_string());
+        recipe
+            .steps
+            .push("line one\nline two\tand a tab".to_string());
+        let path = fixture("quoted_export.csv", b"");
+
+        let mut out = writer(
+            Format::Csv,
+            open_sink(path.to_str().unwrap(), false).unwrap(),
+            &Settings::default(),
+        );
+        out.write_recipe(&recipe).unwrap();
+        out.finish().unwrap();
+
+        let mut rdr = csv::Reader::from_path(&path).unwrap();
+        let headers = rdr.headers().unwrap().clone();
+        let record = rdr.records().next().unwrap().unwrap();
+        let column = |name: &str| {
+            let index = headers.iter().position(|header| header == name).unwrap();
+            crate::parser::parse_python_list(&record[index]).unwrap()
+        };
+        assert_eq!(column("ingredients"), recipe.ingredients);
+        assert_eq!(column("steps"), recipe.steps);
+        assert_eq!(column("tags"), recipe.tags);
+    }
+
+    #[test]
+    fn cypher_export_writes_the_loaders_rows() {
+        let mut recipe = crate::testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.ingredients = ["2-3 cloves garlic", "1 egg", "2 eggs", "salt, to taste"]
+            .map(str::to_string)
+            .to_vec();
+        recipe.steps = vec!["mix".to_string(), "bake".to_string()];
+        recipe.name_display = Some("Garlic Bake".to_string());
+        recipe.servings = Some(4);
+        let settings = Settings::default();
+
+        for steps_as_nodes in [false, true] {
+            let rows = Rows::new(&recipe, &settings, steps_as_nodes);
+            let statements = cypher_statements(&recipe, &settings, steps_as_nodes).unwrap();
+            let mut expected = 0;
+            for (table, body) in rows::statements(&HashSet::new()).unwrap() {
+                for row in rows.table(table) {
+                    let bound = format!("WITH {} AS row ", rows::cypher_map(row));
+                    assert!(
+                        statements
+                            .iter()
+                            .any(|statement| statement.starts_with(&bound)
+                                && (table == rows::Table::Recipes || statement.contains(&body))),
+                        "no statement for {:?} row {}",
+                        table,
+                        bound
+                    );
+                    expected += 1;
+                }
+            }
+            let course = statements
+                .iter()
+                .filter(|statement| statement.contains("IN_COURSE"))
+                .count();
+            assert_eq!(statements.len(), expected + course);
+        }
+
+        let script = cypher_statements(&recipe, &settings, false)
+            .unwrap()
+            .join("\n");
+        for property in [
+            "name_raw: ",
+            "calories: ",
+            "servings: 4",
+            "difficulty: ",
+            "emoji: ",
+        ] {
+            assert!(script.contains(property), "{} missing", property);
+        }
+        assert!(script.contains("name: 'garlic'"), "{}", script);
+        assert!(!script.contains("name: '2-3 cloves garlic'"), "{}", script);
+        // One Ingredient row and one CONTAINS row for both egg lines
+        assert_eq!(script.matches("name: 'egg'").count(), 2, "{}", script);
+    }
+
+    fn export(format: Format, recipe: &Recipe) -> String {
+        let path = fixture(&format!("quantities.{:?}", format), b"");
+        let mut out = writer(
+            format,
+            open_sink(path.to_str().unwrap(), false).unwrap(),
+            &Settings::default(),
+        );
+        out.write_recipe(recipe).unwrap();
+        out.finish().unwrap();
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn every_export_writes_the_parsed_quantities() {
+        let mut recipe = crate::testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.ingredients = ["2-3 cloves garlic", "Salt To Taste", "parsley"]
+            .map(str::to_string)
+            .to_vec();
+
+        let jsonl: serde_json::Value =
+            serde_json::from_str(&export(Format::Jsonl, &recipe)).unwrap();
+        let quantities = &jsonl["ingredient_quantities"];
+        assert_eq!(quantities[0]["quantity_min"], 2.0);
+        assert_eq!(quantities[0]["quantity_max"], 3.0);
+        assert_eq!(quantities[0]["unit"], "cloves");
+        assert_eq!(quantities[1]["to_taste"], true);
+        assert!(quantities[2]["quantity_min"].is_null());
+
+        let csv = export(Format::Csv, &recipe);
+        let mut rdr = csv::Reader::from_reader(csv.as_bytes());
+        let headers = rdr.headers().unwrap().clone();
+        let record = rdr.records().next().unwrap().unwrap();
+        let column = |name: &str| {
+            let index = headers.iter().position(|header| header == name).unwrap();
+            record[index].to_string()
+        };
+        assert_eq!(column("quantity_min"), "[2.0, None, None]");
+        assert_eq!(column("quantity_max"), "[3.0, None, None]");
+        assert_eq!(column("unit"), "['cloves', None, None]");
+        assert_eq!(column("to_taste"), "[False, True, False]");
+
+        let markdown = export(Format::Markdown, &recipe);
+        assert!(markdown.contains(
+            "- 2-3 cloves garlic <!-- quantity_min: 2, quantity_max: 3, unit: cloves -->"
+        ));
+        assert!(markdown.contains("- Salt To Taste <!-- to_taste: true -->"));
+        assert!(markdown.contains("- parsley\n"));
+
+        let graphml = export(Format::Graphml, &recipe);
+        assert!(graphml.contains(r#"<key id="to_taste" for="edge""#));
+        assert!(graphml.contains(
+            r#"<data key="quantity_min">2</data><data key="quantity_max">3</data><data key="unit">cloves</data></edge>"#
+        ));
+        assert!(graphml.contains(r#"<data key="to_taste">true</data></edge>"#));
+
+        let cypher = export(Format::Cypher, &recipe);
+        assert!(
+            cypher.contains("quantity_min: 2.0, quantity_max: 3.0"),
+            "{}",
+            cypher
+        );
+        assert!(cypher.contains("to_taste: true"), "{}", cypher);
+    }
+
+    #[test]
+    fn missing_files_name_the_path() {
+        let err = read_text(Path::new("C:\\data\\missing.toml"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("C:\\data\\missing.toml"), "{}", err);
+        if !cfg!(windows) {
+            assert!(err.contains("Windows path"), "{}", err);
+        }
+    }
+}
@@ -0,0 +1,694 @@
+use crate::nutrition::{self, Nutrition};
+use crate::settings;
+use serde::de::{self, Deserializer, Visitor};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// One row of the Food.com `RAW_recipes.csv` dump. The list columns are
+/// Python literals in the source (`['a', 'b']`) and are read with the
+/// deserializers below. Fields after `license` are filled in by the loader
+/// and are never read from the CSV.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recipe {
+    pub id: i32,
+    pub name: String,
+    pub description: String,
+    /// Ingredient lines in the author's order
+    #[serde(deserialize_with = "deserialize_string_array")]
+    pub ingredients: Vec<String>,
+    pub minutes: i32,
+    #[serde(deserialize_with = "deserialize_string_array")]
+    pub steps: Vec<String>,
+    /// Calories, then total fat, sugar, sodium, protein, saturated fat and
+    /// carbohydrates as % daily value. Missing values are NaN. See
+    /// `Recipe::nutrition_facts` for the values by name.
+    #[serde(
+        deserialize_with = "deserialize_nutrition",
+        serialize_with = "crate::precision::serialize_nutrition"
+    )]
+    pub nutrition: Vec<f32>,
+    #[serde(default, deserialize_with = "deserialize_string_array")]
+    pub tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub servings: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub license: Option<String>,
+    /// `name` cleaned and cased for display
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub name_display: Option<String>,
+    /// The source steps, when fragments were merged into `steps`
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub original_steps: Vec<String>,
+    /// Fields shortened by the loader's length limits
+    #[serde(skip_deserializing, skip_serializing_if = "Vec::is_empty")]
+    pub truncated_fields: Vec<String>,
+    /// `servings` was inferred rather than read
+    #[serde(skip_deserializing, skip_serializing_if = "std::ops::Not::not")]
+    pub servings_inferred: bool,
+    /// Whole-recipe cost from the price list
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost: Option<f64>,
+    /// `estimated_cost` divided by `servings`, when that is known
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub estimated_cost_per_serving: Option<f64>,
+    /// See `hashing::content_hash`
+    #[serde(skip_deserializing, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+impl Recipe {
+    /// `nutrition` by name, or `None` when it does not have seven values
+    /// (possible only with `nutrition::Layout::Raw`).
+    pub fn nutrition_facts(&self) -> Option<Nutrition> {
+        Nutrition::from_values(&self.nutrition)
+    }
+}
+
+/// Reads a Python-style list of strings such as `['a', 'b']`.
+pub fn deserialize_string_array<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    deserializer.deserialize_string(StringArrayVisitor)
+}
+
+/// Why a list literal could not be parsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    /// Byte offset in the input
+    pub position: usize,
+    pub message: &'static str,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+struct ListParser<'a> {
+    input: &'a str,
+    position: usize,
+}
+
+impl ListParser<'_> {
+    fn error(&self, message: &'static str) -> ParseError {
+        ParseError {
+            position: self.position,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    /// A quoted string after its opening `quote`, with Python's escapes
+    /// resolved. Unknown escapes keep their backslash, as in Python.
+    fn quoted(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut text = String::new();
+        loop {
+            match self.bump() {
+                None => return Err(self.error("unterminated string")),
+                Some(c) if c == quote => return Ok(text),
+                Some('\\') => match self.bump() {
+                    None => return Err(self.error("unterminated string")),
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some(c @ ('\\' | '\'' | '"')) => text.push(c),
+                    Some(c) => {
+                        text.push('\\');
+                        text.push(c);
+                    }
+                },
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    /// A nested list after its `[`, kept as its source text.
+    fn nested(&mut self, start: usize) -> Result<String, ParseError> {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.bump() {
+                None => return Err(self.error("unclosed nested list")),
+                Some('[') => depth += 1,
+                Some(']') => depth -= 1,
+                Some(quote @ ('\'' | '"')) => {
+                    self.quoted(quote)?;
+                }
+                Some(_) => {}
+            }
+        }
+        Ok(self.input[start..self.position].to_string())
+    }
+
+    /// An unquoted element, up to the next `,` or `]`.
+    fn bare(&mut self) -> Result<String, ParseError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c != ',' && c != ']') {
+            self.bump();
+        }
+        let text = self.input[start..self.position].trim();
+        if text.is_empty() {
+            return Err(self.error("empty element"));
+        }
+        Ok(text.to_string())
+    }
+
+    fn list(&mut self) -> Result<Vec<String>, ParseError> {
+        self.skip_whitespace();
+        if self.bump() != Some('[') {
+            return Err(self.error("expected ["));
+        }
+        let mut items = Vec::new();
+        loop {
+            self.skip_whitespace();
+            let start = self.position;
+            let item = match self.peek() {
+                None => return Err(self.error("unclosed list")),
+                // Closes an empty list or follows a trailing comma
+                Some(']') => {
+                    self.bump();
+                    break;
+                }
+                Some(quote @ ('\'' | '"')) => {
+                    self.bump();
+                    self.quoted(quote)?
+                }
+                Some('[') => {
+                    self.bump();
+                    self.nested(start)?
+                }
+                Some(_) => self.bare()?,
+            };
+            items.push(item);
+
+            self.skip_whitespace();
+            match self.bump() {
+                Some(',') => {}
+                Some(']') => break,
+                None => return Err(self.error("unclosed list")),
+                Some(_) => return Err(self.error("expected , or ]")),
+            }
+        }
+        self.skip_whitespace();
+        if self.position < self.input.len() {
+            return Err(self.error("text after the list"));
+        }
+        Ok(items)
+    }
+}
+
+/// A Python string literal for `item`, quoted and escaped the way `repr`
+/// does it, so `parse_python_list` reads it back unchanged.
+pub fn python_repr(item: &str) -> String {
+    let escaped = item
+        .replace('\\', "\\\\")
+        .replace('\n', "\\n")
+        .replace('\t', "\\t");
+    if escaped.contains('\'') && !escaped.contains('"') {
+        format!("\"{}\"", escaped)
+    } else {
+        format!("'{}'", escaped.replace('\'', "\\'"))
+    }
+}
+
+/// `items` as a Python list literal of strings, as the dump writes its
+/// list columns.
+pub fn python_list<T: AsRef<str>>(items: &[T]) -> String {
+    let quoted: Vec<String> = items
+        .iter()
+        .map(|item| python_repr(item.as_ref()))
+        .collect();
+    format!("[{}]", quoted.join(", "))
+}
+
+/// Parses a Python list literal of strings, as written by `repr()`:
+/// `['preheat oven', "grandma's mix, sifted", 'it\'s done']`. Commas and
+/// brackets inside quotes belong to the element, backslash escapes are
+/// resolved, unquoted elements are trimmed, and a nested list is kept as
+/// its source text.
+pub fn parse_python_list(input: &str) -> Result<Vec<String>, ParseError> {
+    ListParser { input, position: 0 }.list()
+}
+
+/// The old comma split, for cells `parse_python_list` rejects (truncated
+/// rows, stray brackets) so a malformed cell never fails the whole row.
+fn split_list(value: &str) -> Vec<String> {
+    let inner = value.trim_matches(|c| c == '[' || c == ']');
+    if inner.trim().is_empty() {
+        return Vec::new();
+    }
+    inner
+        .split(',')
+        .map(|part| {
+            part.trim()
+                .trim_matches(|c| c == '\'' || c == '"')
+                .to_string()
+        })
+        .collect()
+}
+
+/// The visitor behind `deserialize_string_array`. Reads the cell with
+/// `parse_python_list`, falling back to splitting on commas when it is
+/// not a well-formed literal; `[]` is an empty list.
+pub struct StringArrayVisitor;
+
+impl<'de> serde::de::Visitor<'de> for StringArrayVisitor {
+    type Value = Vec<String>;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("a string")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Self::Value, E>
+    where
+        E: serde::de::Error,
+    {
+        Ok(parse_python_list(value).unwrap_or_else(|_| split_list(value)))
+    }
+}
+
+/// What to do with `nan`, `inf` and empty elements in a float list.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NanPolicy {
+    /// Keep the row and store the element as missing (NaN, written as
+    /// `null` in JSON)
+    #[default]
+    Missing,
+    /// Fail the row
+    Reject,
+}
+
+impl std::str::FromStr for NanPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "missing" => Ok(NanPolicy::Missing),
+            "reject" => Ok(NanPolicy::Reject),
+            other => Err(format!("unknown nan policy: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FloatOptions {
+    pub nan: NanPolicy,
+    /// Accept `1,5` as 1.5, and `1.234,5` as 1234.5. Elements must then be
+    /// separated by `;` or by a comma followed by whitespace.
+    pub decimal_comma: bool,
+}
+
+/// Reads a Python-style list of floats such as `[51.5, 0.0, nan]`, using
+/// the float options of the enclosing `Settings::scope`.
+pub fn deserialize_float_array<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let options = settings::serde_settings().floats;
+    deserializer.deserialize_string(FloatArrayVisitor { options })
+}
+
+/// Reads the nutrition column as a float list. Under the default
+/// `nutrition::Layout::Named` it must hold exactly the seven values of
+/// `nutrition::FIELDS`.
+pub fn deserialize_nutrition<'de, D>(deserializer: D) -> Result<Vec<f32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let values = deserialize_float_array(deserializer)?;
+    let layout = settings::serde_settings().layout;
+    if layout == nutrition::Layout::Named && values.len() != nutrition::FIELDS.len() {
+        return Err(de::Error::custom(format!(
+            "expected {} nutrition values, found {}",
+            nutrition::FIELDS.len(),
+            values.len()
+        )));
+    }
+    Ok(values)
+}
+
+/// The visitor behind `deserialize_float_array`.
+#[derive(Default)]
+pub struct FloatArrayVisitor {
+    pub options: FloatOptions,
+}
+
+fn split_floats(inner: &str, decimal_comma: bool) -> Vec<&str> {
+    if !decimal_comma {
+        return inner.split(',').collect();
+    }
+    if inner.contains(';') {
+        return inner.split(';').collect();
+    }
+
+    // "1,5, 2,25" -> ["1,5", "2,25"]: only a comma followed by whitespace
+    // separates elements
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for (index, _) in inner.match_indices(',') {
+        if inner[index + 1..].starts_with(char::is_whitespace) {
+            parts.push(&inner[start..index]);
+            start = index + 1;
+        }
+    }
+    parts.push(&inner[start..]);
+    parts
+}
+
+/// `part` as Rust parses floats: digit group separators dropped, a
+/// decimal comma made a point, and a Unicode minus made ASCII. With
+/// `decimal_comma`, points are group separators only where a comma is
+/// the decimal mark, so `1.5` still reads as 1.5.
+fn delocalize(part: &str, decimal_comma: bool) -> String {
+    let grouping_points = decimal_comma && part.contains(',');
+    part.chars()
+        .filter(|c| !matches!(c, '\u{a0}' | '\u{202f}' | '\'' | '_'))
+        .filter(|c| !(grouping_points && *c == '.'))
+        .map(|c| match c {
+            ',' => '.',
+            '\u{2212}' => '-',
+            c => c,
+        })
+        .collect()
+}
+
+/// Whether `inner` reads like a list written with decimal commas, such as
+/// `1,5, 2,25`: elements separated by ", " with commas between digits
+/// too. Python's own lists never put a comma between two digits.
+fn looks_decimal_comma(inner: &str) -> bool {
+    let bytes = inner.as_bytes();
+    inner.contains(", ")
+        && bytes
+            .windows(3)
+            .any(|w| w[0].is_ascii_digit() && w[1] == b',' && w[2].is_ascii_digit())
+}
+
+impl<'de> Visitor<'de> for FloatArrayVisitor {
+    type Value = Vec<f32>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a string representing an array of floats")
+    }
+
+    fn visit_str<E>(self, value: &str) -> Result<Vec<f32>, E>
+    where
+        E: de::Error,
+    {
+        // Trim the brackets and then split the string by comma
+        let inner = value.trim_matches(|c| c == '[' || c == ']');
+        if inner.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        if !self.options.decimal_comma && looks_decimal_comma(inner) {
+            return Err(E::custom(format!(
+                "{} looks like numbers with decimal commas; read it with --decimal-comma",
+                value
+            )));
+        }
+        let parts = split_floats(inner, self.options.decimal_comma);
+
+        let mut floats = Vec::new();
+        for part in parts {
+            let part = part.trim();
+            let parsed = delocalize(part, self.options.decimal_comma).parse::<f32>();
+
+            match parsed {
+                Ok(num) if num.is_finite() => floats.push(num),
+                Ok(_) if self.options.nan == NanPolicy::Missing => floats.push(f32::NAN),
+                Err(_) if part.is_empty() && self.options.nan == NanPolicy::Missing => {
+                    floats.push(f32::NAN)
+                }
+                Ok(_) => return Err(E::custom(format!("non-finite float: {}", part))),
+                Err(_) => return Err(E::custom(format!("failed to parse float {:?}", part))),
+            }
+        }
+        Ok(floats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use csv::Reader;
+    use proptest::prelude::*;
+    use serde::de::value::Error as ValueError;
+
+    fn parse_strings(input: &str) -> Result<Vec<String>, ValueError> {
+        StringArrayVisitor.visit_str(input)
+    }
+
+    fn parse_floats(input: &str) -> Result<Vec<f32>, ValueError> {
+        FloatArrayVisitor::default().visit_str(input)
+    }
+
+    fn parse_floats_with(input: &str, options: FloatOptions) -> Result<Vec<f32>, ValueError> {
+        FloatArrayVisitor { options }.visit_str(input)
+    }
+
+    fn python_list<T: fmt::Display>(items: &[T], quote: &str) -> String {
+        let items: Vec<String> = items
+            .iter()
+            .map(|item| format!("{0}{1}{0}", quote, item))
+            .collect();
+        format!("[{}]", items.join(", "))
+    }
+
+    /// Elements with quotes, backslash escapes and non-ASCII text, but no
+    /// separators, so they can be wrapped into a list literal.
+    fn messy_element() -> impl Strategy<Value = String> {
+        prop::collection::vec(
+            prop_oneof![
+                Just("\\'".to_string()),
+                Just("\\\"".to_string()),
+                Just("\"".to_string()),
+                "[a-z ]{1,4}",
+                "\\p{L}{1,3}",
+            ],
+            0..5,
+        )
+        .prop_map(|parts| parts.concat())
+    }
+
+    proptest! {
+        #[test]
+        fn string_parser_accepts_any_input(input in "\\PC*") {
+            prop_assert!(parse_strings(&input).is_ok());
+        }
+
+        #[test]
+        fn float_parser_never_panics(input in "\\PC*") {
+            let _ = parse_floats(&input);
+        }
+
+        #[test]
+        fn messy_list_literals_parse(items in prop::collection::vec(messy_element(), 0..6)) {
+            prop_assert!(parse_strings(&python_list(&items, "'")).is_ok());
+        }
+
+        #[test]
+        fn plain_elements_round_trip(
+            items in prop::collection::vec("[a-z0-9\\p{L}][a-z0-9\\p{L} ()&/-]{0,20}[a-z0-9\\p{L}]", 0..8),
+            quote in prop_oneof![Just("'"), Just("\"")],
+        ) {
+            prop_assert_eq!(parse_strings(&python_list(&items, quote)).unwrap(), items);
+        }
+
+        #[test]
+        fn repr_quoted_elements_round_trip(items in prop::collection::vec("\\PC*", 0..6)) {
+            let literal = super::python_list(&items);
+            prop_assert_eq!(parse_python_list(&literal).unwrap(), items);
+        }
+
+        #[test]
+        fn float_lists_round_trip(values in prop::collection::vec(-1.0e6f32..1.0e6f32, 0..10)) {
+            let literal = format!("{:?}", values);
+            prop_assert_eq!(parse_floats(&literal).unwrap(), values);
+        }
+    }
+
+    #[test]
+    fn commas_inside_quotes_stay_in_the_element() {
+        assert_eq!(
+            parse_python_list("['preheat oven', 'mix flour, sugar, and salt']").unwrap(),
+            vec!["preheat oven", "mix flour, sugar, and salt"]
+        );
+    }
+
+    #[test]
+    fn escaped_and_mixed_quotes() {
+        assert_eq!(
+            parse_python_list(r#"["grandma's pie", 'it\'s "done"', 'a \\ b']"#).unwrap(),
+            vec!["grandma's pie", "it's \"done\"", "a \\ b"]
+        );
+    }
+
+    #[test]
+    fn brackets_inside_quotes_and_nested_lists() {
+        assert_eq!(
+            parse_python_list("['add salt [optional]', ['a', 'b]'], 'serve']").unwrap(),
+            vec!["add salt [optional]", "['a', 'b]']", "serve"]
+        );
+    }
+
+    #[test]
+    fn whitespace_bare_words_and_trailing_commas() {
+        assert_eq!(
+            parse_python_list("  [ 'a' ,b ,  c d, ]  ").unwrap(),
+            vec!["a", "b", "c d"]
+        );
+        assert_eq!(parse_python_list("[ ]").unwrap(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn malformed_literals_are_errors() {
+        for input in ["", "'a'", "['a', 'b", "['a' 'b']", "['a'] x", "[a,,b]"] {
+            assert!(parse_python_list(input).is_err(), "{}", input);
+        }
+        assert_eq!(
+            parse_python_list("['unclosed").unwrap_err(),
+            ParseError {
+                position: 10,
+                message: "unterminated string"
+            }
+        );
+    }
+
+    #[test]
+    fn visitor_falls_back_on_malformed_cells() {
+        assert_eq!(
+            parse_strings("['truncated, row").unwrap(),
+            vec!["truncated", "row"]
+        );
+    }
+
+    #[test]
+    fn empty_lists_have_no_elements() {
+        assert_eq!(parse_strings("[]").unwrap(), Vec::<String>::new());
+        assert_eq!(parse_floats("[]").unwrap(), Vec::<f32>::new());
+        assert_eq!(parse_floats("[ ]").unwrap(), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn malformed_float_is_an_error() {
+        assert!(parse_floats("[1.0, abc]").is_err());
+    }
+
+    #[test]
+    fn nan_and_infinity_become_missing_by_default() {
+        let parsed = parse_floats("[51.5, nan, inf, , 1e2]").unwrap();
+
+        assert_eq!(parsed.len(), 5);
+        assert_eq!(parsed[0], 51.5);
+        assert!(parsed[1].is_nan() && parsed[2].is_nan() && parsed[3].is_nan());
+        assert_eq!(parsed[4], 100.0);
+    }
+
+    #[test]
+    fn nan_can_be_rejected() {
+        let options = FloatOptions {
+            nan: NanPolicy::Reject,
+            ..FloatOptions::default()
+        };
+
+        assert!(parse_floats_with("[1.0, NaN]", options).is_err());
+        assert!(parse_floats_with("[1.0, ]", options).is_err());
+    }
+
+    #[test]
+    fn decimal_commas_parse_with_locale_flag() {
+        let options = FloatOptions {
+            decimal_comma: true,
+            ..FloatOptions::default()
+        };
+
+        assert_eq!(
+            parse_floats_with("[1,5, 2,25, 3]", options).unwrap(),
+            vec![1.5, 2.25, 3.0]
+        );
+        assert_eq!(
+            parse_floats_with("[1,5;2,25]", options).unwrap(),
+            vec![1.5, 2.25]
+        );
+    }
+
+    #[test]
+    fn grouped_and_locale_numbers_parse() {
+        let options = FloatOptions {
+            decimal_comma: true,
+            ..FloatOptions::default()
+        };
+
+        assert_eq!(
+            parse_floats_with("[1.234,5, 1.5, \u{2212}2,5]", options).unwrap(),
+            vec![1234.5, 1.5, -2.5]
+        );
+        assert_eq!(
+            parse_floats("[1\u{a0}234.5, 1'000, \u{2212}0.5]").unwrap(),
+            vec![1234.5, 1000.0, -0.5]
+        );
+    }
+
+    #[test]
+    fn decimal_commas_without_the_flag_are_an_error() {
+        let err = parse_floats("[1,5, 2,25, 3]").unwrap_err().to_string();
+
+        assert!(err.contains("--decimal-comma"), "{}", err);
+        assert_eq!(parse_floats("[1,2,3]").unwrap(), vec![1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn bom_prefixed_csv_reads_first_column() {
+        let csv = "\u{feff}id,name,description,ingredients,minutes,steps,nutrition\r\n\
+                   1,pie,tasty,[],30,[],\"[51.5, 0.0, 13.0, 0.0, 2.0, 0.0, 4.0]\"\r\n";
+        let mut rdr = Reader::from_reader(csv.as_bytes());
+        let recipe: Recipe = rdr.deserialize().next().unwrap().unwrap();
+
+        assert_eq!(recipe.id, 1);
+        assert_eq!(recipe.name, "pie");
+    }
+
+    #[test]
+    fn deserialized_ingredients_keep_csv_order() {
+        let csv = "id,name,description,ingredients,minutes,steps,nutrition\n\
+                   1,pie,tasty,\"['zucchini', 'apple', 'butter']\",30,\"['bake']\",\"[1.0, 0, 0, 0, 0, 0, 0]\"\n";
+        let mut rdr = Reader::from_reader(csv.as_bytes());
+        let recipe: Recipe = rdr.deserialize().next().unwrap().unwrap();
+
+        assert_eq!(recipe.ingredients, vec!["zucchini", "apple", "butter"]);
+    }
+
+    #[test]
+    fn nutrition_needs_seven_values() {
+        let csv = "id,name,description,ingredients,minutes,steps,nutrition\n\
+                   1,pie,tasty,[],30,[],\"[51.5, 0.0, 13.0, 0.0, 2.0, 0.0, 4.0]\"\n\
+                   2,tart,tasty,[],30,[],\"[51.5, 0.0, 13.0]\"\n";
+        let mut rdr = Reader::from_reader(csv.as_bytes());
+        let mut recipes = rdr.deserialize::<Recipe>();
+
+        let facts = recipes.next().unwrap().unwrap().nutrition_facts().unwrap();
+        assert_eq!(facts.calories, 51.5);
+        assert_eq!(facts.carbohydrates_pdv, 4.0);
+        assert!(recipes.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::output;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+use toml::Value;
+
+/// Command-line options by name, without the leading dashes.
+pub type Options = BTreeMap<String, Value>;
+
+/// A command run after the loads, such as `load-interactions` or
+/// `enrich wikidata`, with its options.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Step {
+    pub command: String,
+    #[serde(flatten)]
+    pub options: Options,
+}
+
+/// A multi-step load declared in TOML, run by `pipeline run`:
+///
+/// ```toml
+/// [global]                  # options given before the command
+/// profile = "prod"
+/// brands = "brands.toml"
+///
+/// [source]
+/// input = "data/RAW_recipes.csv"
+/// mapping = "mapping.toml"
+///
+/// [filters]
+/// limit = 10000
+/// on-duplicate-id = "last-wins"
+///
+/// [normalizers]
+/// spell-correct = true
+/// scrub = ["email", "url"]
+///
+/// [[sinks]]                 # one load per sink, in order
+/// output = "neo4j"
+///
+/// [[sinks]]
+/// output = "jsonl"
+/// out = "recipes.jsonl"
+///
+/// [[enrichers]]             # run after every sink, in order
+/// command = "load-interactions"
+/// input = "data/RAW_interactions.csv"
+///
+/// [[enrichers]]
+/// command = "enrich wikidata"
+/// rate = 2
+/// ```
+///
+/// Options are the command line's, by the same names. `true` gives a
+/// switch, `false` leaves it off, and a list is joined with commas.
+/// Source, filter and normalizer options all go to `load`; a sink's own
+/// options win over them. With no sinks the recipes go to the graph.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Pipeline {
+    pub global: Options,
+    pub source: Options,
+    pub filters: Options,
+    pub normalizers: Options,
+    pub enrichers: Vec<Step>,
+    pub sinks: Vec<Options>,
+}
+
+/// `options` as command-line arguments.
+fn flags(options: &Options) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut flags = Vec::new();
+    for (name, value) in options {
+        let text = match value {
+            Value::Boolean(true) => {
+                flags.push(format!("--{}", name));
+                continue;
+            }
+            Value::Boolean(false) => continue,
+            Value::String(text) => text.clone(),
+            Value::Integer(number) => number.to_string(),
+            Value::Float(number) => number.to_string(),
+            Value::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Value::String(text) => Ok(text.clone()),
+                    Value::Integer(number) => Ok(number.to_string()),
+                    Value::Float(number) => Ok(number.to_string()),
+                    other => Err(format!(
+                        "{}: lists hold strings or numbers, not {}",
+                        name, other
+                    )),
+                })
+                .collect::<Result<Vec<_>, _>>()?
+                .join(","),
+            other => return Err(format!("{}: unsupported option value {}", name, other).into()),
+        };
+        flags.push(format!("--{}", name));
+        flags.push(text);
+    }
+    Ok(flags)
+}
+
+impl Pipeline {
+    pub fn load(path: &Path) -> Result<Pipeline, Box<dyn Error>> {
+        let text = output::read_text(path)?;
+        Ok(toml::from_str(&text)?)
+    }
+
+    /// The `[global]` options as command-line arguments.
+    pub fn global_flags(&self) -> Result<Vec<String>, Box<dyn Error>> {
+        flags(&self.global)
+    }
+
+    /// The commands the pipeline runs, in order, each as its subcommand
+    /// words and options: a `load` per sink, then the enrichers.
+    pub fn commands(&self) -> Result<Vec<Vec<String>>, Box<dyn Error>> {
+        let mut shared = Options::new();
+        for section in [&self.source, &self.filters, &self.normalizers] {
+            shared.extend(section.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+        let sinks = if self.sinks.is_empty() {
+            vec![Options::new()]
+        } else {
+            self.sinks.clone()
+        };
+        let stdin = shared.get("input").and_then(Value::as_str) == Some("-");
+        if stdin && sinks.len() > 1 {
+            return Err("a pipeline with more than one sink reads its input once per sink and cannot read stdin".into());
+        }
+
+        let mut commands = Vec::new();
+        for sink in &sinks {
+            let mut options = shared.clone();
+            options.extend(sink.iter().map(|(k, v)| (k.clone(), v.clone())));
+            let mut command = vec!["load".to_string()];
+            command.extend(flags(&options)?);
+            commands.push(command);
+        }
+        for step in &self.enrichers {
+            let words: Vec<String> = step
+                .command
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            match words.first().map(String::as_str) {
+                None => return Err("an enricher has an empty command".into()),
+                Some("pipeline") => return Err("a pipeline cannot run another pipeline".into()),
+                Some(_) => {}
+            }
+            let mut command = words;
+            command.extend(flags(&step.options)?);
+            commands.push(command);
+        }
+        Ok(commands)
+    }
+}
@@ -0,0 +1,135 @@
+use crate::rows::Value;
+use crate::settings;
+#[cfg(feature = "neo4j")]
+use neo4rs::BoltType;
+use serde::ser::{SerializeSeq, Serializer};
+use std::str::FromStr;
+
+/// How nutrition values are stored. `Float` keeps them as floats, optionally
+/// rounded; `Fixed` stores integers scaled by `10^digits` (tenths by default)
+/// so values compare exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Storage {
+    #[default]
+    Float,
+    Fixed,
+}
+
+impl Storage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Storage::Float => "float",
+            Storage::Fixed => "fixed",
+        }
+    }
+}
+
+impl FromStr for Storage {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "float" => Ok(Storage::Float),
+            "fixed" => Ok(Storage::Fixed),
+            other => Err(format!("unknown nutrition storage: {}", other)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Precision {
+    pub storage: Storage,
+    /// Decimal places kept. `None` keeps every digit the source had, or one
+    /// digit for fixed-point storage.
+    pub digits: Option<u32>,
+}
+
+impl Precision {
+    fn scale(&self) -> f64 {
+        10f64.powi(self.digits.unwrap_or(1) as i32)
+    }
+
+    /// Widens through the shortest decimal form, so 51.1f32 becomes 51.1
+    /// rather than 51.099998474121094, then rounds to `digits`.
+    pub fn float(&self, value: f32) -> f64 {
+        let wide: f64 = value.to_string().parse().unwrap_or(f64::NAN);
+        match self.digits {
+            Some(digits) => {
+                let scale = 10f64.powi(digits as i32);
+                (wide * scale).round() / scale
+            }
+            None => wide,
+        }
+    }
+
+    /// The fixed-point integer for `value`; `None` for a missing value.
+    pub fn fixed(&self, value: f32) -> Option<i64> {
+        let wide = self.float(value);
+        (!wide.is_nan()).then(|| (wide * self.scale()).round() as i64)
+    }
+
+    /// A nutrition list as a graph property. Missing values are NaN as
+    /// floats and null as fixed-point integers.
+    pub fn value_list(&self, values: &[f32]) -> Value {
+        match self.storage {
+            Storage::Float => values
+                .iter()
+                .map(|value| self.float(*value))
+                .collect::<Vec<f64>>()
+                .into(),
+            Storage::Fixed => values
+                .iter()
+                .map(|value| self.fixed(*value))
+                .collect::<Vec<Option<i64>>>()
+                .into(),
+        }
+    }
+
+    /// A single value as a graph property, null when missing.
+    pub fn value(&self, value: f32) -> Value {
+        match self.storage {
+            _ if value.is_nan() => Value::Null,
+            Storage::Float => self.float(value).into(),
+            Storage::Fixed => self.fixed(value).into(),
+        }
+    }
+
+    #[cfg(feature = "neo4j")]
+    pub fn bolt(&self, value: f32) -> BoltType {
+        self.value(value).into()
+    }
+
+    /// A value read back from the graph, in the source's units.
+    pub fn unscale(&self, stored: f64) -> f64 {
+        match self.storage {
+            Storage::Float => stored,
+            Storage::Fixed => stored / self.scale(),
+        }
+    }
+
+    /// A single value as text, `null` when missing.
+    pub fn format(&self, value: f32) -> String {
+        match self.storage {
+            _ if value.is_nan() => "null".to_string(),
+            Storage::Float => self.float(value).to_string(),
+            Storage::Fixed => self.fixed(value).unwrap_or_default().to_string(),
+        }
+    }
+}
+
+/// `serialize_with` helper writing nutrition the same way the graph stores
+/// it, at the precision of the enclosing `Settings::scope`.
+pub fn serialize_nutrition<S>(values: &[f32], serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let precision = settings::serde_settings().precision;
+    let mut seq = serializer.serialize_seq(Some(values.len()))?;
+    for value in values {
+        match precision.storage {
+            Storage::Float => seq.serialize_element(&precision.float(*value))?,
+            Storage::Fixed => seq.serialize_element(&precision.fixed(*value))?,
+        }
+    }
+    seq.end()
+}
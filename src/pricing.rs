@@ -0,0 +1,178 @@
+use crate::brands::Lexicon;
+use crate::ingredient::Rules;
+use crate::output;
+use crate::quantity::{self, IngredientLine};
+use crate::settings::Settings;
+use csv::Reader;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+/// One row of a user-supplied price list: `ingredient,unit,price`. An empty
+/// unit prices the ingredient per item.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Price {
+    pub ingredient: String,
+    #[serde(default)]
+    pub unit: String,
+    pub price: f64,
+}
+
+/// Prices by canonical ingredient name, with the lexicon and rules they
+/// were named with so recipe lines are parsed to match.
+#[derive(Debug, Clone, Default)]
+pub struct PriceList {
+    prices: HashMap<String, Price>,
+    lexicon: Lexicon,
+    rules: Rules,
+}
+
+/// Canonical unit name and its size in the base unit of its dimension
+/// (teaspoons for volume, grams for weight).
+fn unit_size(unit: &str) -> Option<(&'static str, f64)> {
+    match unit.trim_end_matches('.') {
+        "tsp" | "teaspoon" | "teaspoons" => Some(("volume", 1.0)),
+        "tbsp" | "tbs" | "tablespoon" | "tablespoons" => Some(("volume", 3.0)),
+        "cup" | "cups" | "c" => Some(("volume", 48.0)),
+        "ml" => Some(("volume", 0.203)),
+        "l" | "liter" | "liters" => Some(("volume", 202.9)),
+        "pint" | "pints" => Some(("volume", 96.0)),
+        "quart" | "quarts" => Some(("volume", 192.0)),
+        "g" | "gram" | "grams" => Some(("weight", 1.0)),
+        "kg" => Some(("weight", 1000.0)),
+        "oz" | "ounce" | "ounces" => Some(("weight", 28.35)),
+        "lb" | "lbs" | "pound" | "pounds" => Some(("weight", 453.6)),
+        _ => None,
+    }
+}
+
+/// How many price units `amount` of `unit` is, if the two are comparable.
+fn convert(amount: f64, unit: &str, price_unit: &str) -> Option<f64> {
+    if unit == price_unit {
+        return Some(amount);
+    }
+    let (dimension, size) = unit_size(unit)?;
+    let (price_dimension, price_size) = unit_size(price_unit)?;
+    (dimension == price_dimension).then(|| amount * size / price_size)
+}
+
+impl PriceList {
+    pub fn load(path: &Path, settings: &Settings) -> Result<PriceList, Box<dyn Error>> {
+        let mut prices = HashMap::new();
+        for result in Reader::from_reader(output::open_source(path)?).deserialize() {
+            let price: Price = result?;
+            // Keyed the way ingredient lines are named
+            prices.insert(settings.rules.canonical(&price.ingredient), price);
+        }
+        Ok(PriceList {
+            prices,
+            lexicon: settings.lexicon.clone(),
+            rules: settings.rules.clone(),
+        })
+    }
+
+    /// Cost of one ingredient line. Lines without an amount (most of the
+    /// Food.com data) are priced as one price unit.
+    pub fn line_cost(&self, line: &IngredientLine) -> Option<f64> {
+        let price = self.prices.get(&line.name.to_lowercase())?;
+        let amount = line
+            .quantity
+            .as_ref()
+            .and_then(|quantity| quantity.max())
+            .unwrap_or(1.0);
+
+        let units = match &line.unit {
+            Some(unit) => convert(amount, unit, &price.unit)?,
+            None => amount,
+        };
+        Some(units * price.price)
+    }
+
+    /// Sum of the priced ingredients; `None` when none of them are listed.
+    pub fn estimate(&self, ingredients: &[String]) -> Option<f64> {
+        let costs: Vec<f64> = ingredients
+            .iter()
+            .filter_map(|line| {
+                self.line_cost(&quantity::parse_ingredient(
+                    line,
+                    &self.lexicon,
+                    &self.rules,
+                ))
+            })
+            .collect();
+        (!costs.is_empty()).then(|| cents(costs.iter().sum()))
+    }
+
+    /// `estimate` shared among `servings`; `None` also when the recipe does
+    /// not say how many it serves.
+    pub fn estimate_per_serving(
+        &self,
+        ingredients: &[String],
+        servings: Option<i64>,
+    ) -> Option<f64> {
+        let servings = servings.filter(|servings| *servings > 0)?;
+        Some(cents(self.estimate(ingredients)? / servings as f64))
+    }
+}
+
+fn cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prices(rows: &[(&str, &str, f64)]) -> PriceList {
+        PriceList {
+            prices: rows
+                .iter()
+                .map(|(ingredient, unit, price)| {
+                    let price = Price {
+                        ingredient: ingredient.to_string(),
+                        unit: unit.to_string(),
+                        price: *price,
+                    };
+                    (ingredient.to_string(), price)
+                })
+                .collect(),
+            lexicon: Lexicon::builtin(),
+            rules: Rules::builtin(),
+        }
+    }
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn amounts_convert_to_the_price_unit() {
+        let list = prices(&[
+            ("flour", "cup", 0.5),
+            ("butter", "lb", 4.0),
+            ("egg", "", 0.25),
+        ]);
+
+        let line = |text| list.line_cost(&Settings::default().parse_ingredient(text));
+        assert_eq!(line("2 cups flour"), Some(1.0));
+        assert_eq!(line("4 tablespoons flour"), Some(0.125));
+        assert_eq!(line("8 oz butter").map(cents), Some(2.0));
+        assert_eq!(line("3 egg"), Some(0.75));
+        // Volume cannot be priced by weight
+        assert_eq!(line("1 cup butter"), None);
+        assert_eq!(line("salt"), None);
+    }
+
+    #[test]
+    fn costs_are_shared_among_servings() {
+        let list = prices(&[("flour", "cup", 0.5), ("egg", "", 0.25)]);
+        let ingredients = lines(&["2 cups flour", "2 egg", "salt"]);
+
+        assert_eq!(list.estimate(&ingredients), Some(1.5));
+        assert_eq!(list.estimate_per_serving(&ingredients, Some(4)), Some(0.38));
+        assert_eq!(list.estimate_per_serving(&ingredients, None), None);
+        assert_eq!(list.estimate_per_serving(&ingredients, Some(0)), None);
+        assert_eq!(list.estimate(&lines(&["salt"])), None);
+    }
+}
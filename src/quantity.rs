@@ -0,0 +1,442 @@
+use crate::brands::Lexicon;
+use crate::ingredient::Rules;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How much of an ingredient a recipe calls for. Ranges such as
+/// "2-3 cloves" keep both bounds instead of collapsing to one number.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Quantity {
+    Amount { value: f64 },
+    Range { min: f64, max: f64 },
+    ToTaste,
+}
+
+impl Quantity {
+    pub fn min(&self) -> Option<f64> {
+        match self {
+            Quantity::Amount { value } => Some(*value),
+            Quantity::Range { min, .. } => Some(*min),
+            Quantity::ToTaste => None,
+        }
+    }
+
+    pub fn max(&self) -> Option<f64> {
+        match self {
+            Quantity::Amount { value } => Some(*value),
+            Quantity::Range { max, .. } => Some(*max),
+            Quantity::ToTaste => None,
+        }
+    }
+
+    pub fn is_to_taste(&self) -> bool {
+        matches!(self, Quantity::ToTaste)
+    }
+}
+
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Quantity::Amount { value } => write!(f, "{}", value),
+            Quantity::Range { min, max } => write!(f, "{}-{}", min, max),
+            Quantity::ToTaste => f.write_str("to taste"),
+        }
+    }
+}
+
+/// An ingredient line split into its quantity, unit and remaining name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngredientLine {
+    pub name: String,
+    pub quantity: Option<Quantity>,
+    pub unit: Option<String>,
+    pub brand: Option<String>,
+    /// The name as written, when canonicalization changed it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub variant: Option<String>,
+    /// Marked "(optional)"; a cook can skip it
+    pub optional: bool,
+    /// Only used to garnish or serve, not in the dish itself
+    pub garnish: bool,
+}
+
+const UNITS: &[&str] = &[
+    "cup",
+    "cups",
+    "c",
+    "tablespoon",
+    "tablespoons",
+    "tbsp",
+    "tbs",
+    "teaspoon",
+    "teaspoons",
+    "tsp",
+    "ounce",
+    "ounces",
+    "oz",
+    "pound",
+    "pounds",
+    "lb",
+    "lbs",
+    "gram",
+    "grams",
+    "g",
+    "kg",
+    "ml",
+    "l",
+    "liter",
+    "liters",
+    "pinch",
+    "dash",
+    "clove",
+    "cloves",
+    "can",
+    "cans",
+    "slice",
+    "slices",
+    "stick",
+    "sticks",
+    "package",
+    "packages",
+    "quart",
+    "quarts",
+    "pint",
+    "pints",
+];
+
+const TO_TASTE: &[&str] = &["to taste", "as needed"];
+const OPTIONAL: &[&str] = &["optional"];
+const GARNISH: &[&str] = &[
+    "for garnish",
+    "to garnish",
+    "for garnishing",
+    "for serving",
+    "to serve",
+    "for decoration",
+];
+
+/// Where the ASCII `marker` first occurs in `text`, ignoring ASCII case.
+/// Offsets are into `text` itself: lowercasing can change its length.
+fn find_marker(text: &str, marker: &str) -> Option<usize> {
+    text.as_bytes()
+        .windows(marker.len())
+        .position(|window| window.eq_ignore_ascii_case(marker.as_bytes()))
+}
+
+/// Whether `text` ends with the ASCII `marker`, ignoring ASCII case.
+fn ends_with_marker(text: &str, marker: &str) -> bool {
+    text.len() >= marker.len()
+        && text.as_bytes()[text.len() - marker.len()..].eq_ignore_ascii_case(marker.as_bytes())
+}
+
+/// Removes `markers` from `text`, either in parentheses anywhere in the
+/// line or as a trailing clause, and reports whether any were found.
+fn strip_markers(text: &mut String, markers: &[&str]) -> bool {
+    let mut found = false;
+    for marker in markers {
+        let parenthesized = format!("({})", marker);
+        if let Some(start) = find_marker(text, &parenthesized) {
+            text.replace_range(start..start + parenthesized.len(), "");
+            found = true;
+        } else if ends_with_marker(text, marker)
+            && text[..text.len() - marker.len()].ends_with([' ', ','])
+        {
+            text.truncate(text.len() - marker.len());
+            found = true;
+        }
+        if found {
+            *text = text
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .trim_end_matches([' ', ','])
+                .to_string();
+        }
+    }
+    found
+}
+
+fn parse_number(token: &str) -> Option<f64> {
+    let unicode = match token {
+        "½" => Some(0.5),
+        "⅓" => Some(1.0 / 3.0),
+        "⅔" => Some(2.0 / 3.0),
+        "¼" => Some(0.25),
+        "¾" => Some(0.75),
+        "⅛" => Some(0.125),
+        _ => None,
+    };
+    if unicode.is_some() {
+        return unicode;
+    }
+
+    match token.split_once('/') {
+        Some((numerator, denominator)) => {
+            let numerator: f64 = numerator.parse().ok()?;
+            let denominator: f64 = denominator.parse().ok()?;
+            (denominator != 0.0).then(|| numerator / denominator)
+        }
+        None => token.parse().ok(),
+    }
+}
+
+/// Parses a leading amount such as `2`, `1 1/2` or `2-3` off the token list,
+/// returning the quantity and how many tokens it consumed.
+fn parse_leading_quantity(tokens: &[&str]) -> Option<(Quantity, usize)> {
+    let first = tokens.first()?;
+
+    if let Some((low, high)) = first.split_once('-') {
+        let (min, max) = (parse_number(low)?, parse_number(high)?);
+        return Some((Quantity::Range { min, max }, 1));
+    }
+
+    let mut value = parse_number(first)?;
+    let mut used = 1;
+    // Mixed numbers: "1 1/2"
+    if let Some(fraction) = tokens.get(1).filter(|token| token.contains('/')) {
+        if let Some(fraction) = parse_number(fraction) {
+            value += fraction;
+            used += 1;
+        }
+    }
+
+    // Spelled-out ranges: "2 to 3"
+    if tokens.get(used).is_some_and(|token| *token == "to") {
+        if let Some(max) = tokens.get(used + 1).and_then(|token| parse_number(token)) {
+            return Some((Quantity::Range { min: value, max }, used + 2));
+        }
+    }
+
+    Some((Quantity::Amount { value }, used))
+}
+
+/// Splits "2-3 cloves garlic" into quantity, unit and name. Lines without a
+/// recognisable amount (most of the Food.com dump) come back as just a name.
+/// Brands in `lexicon` are split off and the name is made canonical with
+/// `rules`; `Settings::parse_ingredient` passes a run's own.
+pub fn parse_ingredient(line: &str, lexicon: &Lexicon, rules: &Rules) -> IngredientLine {
+    let mut text = line.trim().to_string();
+    let mut quantity = None;
+    // Markers can come in either order: "parsley, for garnish (optional)"
+    let mut optional = strip_markers(&mut text, OPTIONAL);
+    let garnish = strip_markers(&mut text, GARNISH);
+    optional |= strip_markers(&mut text, OPTIONAL);
+
+    for marker in TO_TASTE {
+        if ends_with_marker(&text, marker) {
+            let stripped = text[..text.len() - marker.len()].trim_end_matches([' ', ',']);
+            text = stripped.to_string();
+            quantity = Some(Quantity::ToTaste);
+        }
+    }
+
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let mut rest = &tokens[..];
+
+    if quantity.is_none() {
+        if let Some((parsed, used)) = parse_leading_quantity(rest) {
+            quantity = Some(parsed);
+            rest = &rest[used..];
+        }
+    }
+
+    let mut unit = None;
+    if quantity.is_some() {
+        if let Some(first) = rest.first() {
+            let candidate = first.trim_end_matches('.').to_lowercase();
+            if UNITS.contains(&candidate.as_str()) && rest.len() > 1 {
+                unit = Some(candidate);
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    if rest.is_empty() {
+        let whole = if text.is_empty() { line.trim() } else { &text };
+        let (name, brand) = lexicon.extract(whole);
+        let (name, variant) = canonical(name, rules);
+        return IngredientLine {
+            name,
+            quantity: None,
+            unit: None,
+            brand,
+            variant,
+            optional,
+            garnish,
+        };
+    }
+
+    let (name, brand) = lexicon.extract(&rest.join(" "));
+    let (name, variant) = canonical(name, rules);
+    IngredientLine {
+        name,
+        quantity,
+        unit,
+        brand,
+        variant,
+        optional,
+        garnish,
+    }
+}
+
+/// The canonical form of `name`, and `name` itself if that differs.
+fn canonical(name: String, rules: &Rules) -> (String, Option<String>) {
+    let canonical = rules.canonical(&name);
+    let variant = (canonical != name).then_some(name);
+    (canonical, variant)
+}
+
+/// Rough weight of one unit in grams, treating volumes as water. Good
+/// enough to size a dish, not to scale a recipe.
+pub fn grams_per_unit(unit: &str) -> Option<f64> {
+    let grams = match unit {
+        "cup" | "cups" | "c" => 240.0,
+        "tablespoon" | "tablespoons" | "tbsp" | "tbs" => 15.0,
+        "teaspoon" | "teaspoons" | "tsp" => 5.0,
+        "ounce" | "ounces" | "oz" => 28.35,
+        "pound" | "pounds" | "lb" | "lbs" => 453.6,
+        "gram" | "grams" | "g" | "ml" => 1.0,
+        "kg" | "l" | "liter" | "liters" => 1000.0,
+        "pinch" | "dash" => 0.5,
+        "clove" | "cloves" => 5.0,
+        "can" | "cans" => 400.0,
+        "slice" | "slices" => 30.0,
+        "stick" | "sticks" => 113.0,
+        "package" | "packages" => 250.0,
+        "quart" | "quarts" => 946.0,
+        "pint" | "pints" => 473.0,
+        _ => return None,
+    };
+    Some(grams)
+}
+
+/// Grams assumed for one unitless item, such as "2 eggs" or "1 onion".
+pub const GRAMS_PER_ITEM: f64 = 100.0;
+
+impl IngredientLine {
+    /// Approximate weight of the line in grams, from the middle of a range.
+    /// `None` without a numeric quantity or with an unknown unit.
+    pub fn approximate_grams(&self) -> Option<f64> {
+        let quantity = self.quantity.as_ref()?;
+        let amount = (quantity.min()? + quantity.max()?) / 2.0;
+        let per_unit = match &self.unit {
+            Some(unit) => grams_per_unit(unit)?,
+            None => GRAMS_PER_ITEM,
+        };
+        Some(amount * per_unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> IngredientLine {
+        parse_ingredient(line, &Lexicon::default(), &Rules::default())
+    }
+
+    #[test]
+    fn amounts_fractions_and_ranges() {
+        assert_eq!(
+            parse("2 eggs").quantity,
+            Some(Quantity::Amount { value: 2.0 })
+        );
+        assert_eq!(
+            parse("1 1/2 cups flour").quantity,
+            Some(Quantity::Amount { value: 1.5 })
+        );
+        assert_eq!(
+            parse("½ onion").quantity,
+            Some(Quantity::Amount { value: 0.5 })
+        );
+        assert_eq!(
+            parse("2-3 cloves garlic").quantity,
+            Some(Quantity::Range { min: 2.0, max: 3.0 })
+        );
+        assert_eq!(
+            parse("2 to 3 tbsp. butter").quantity,
+            Some(Quantity::Range { min: 2.0, max: 3.0 })
+        );
+    }
+
+    #[test]
+    fn units_split_from_the_name() {
+        let line = parse("2 Tbsp. olive oil");
+        assert_eq!(line.unit.as_deref(), Some("tbsp"));
+        assert_eq!(line.name, "olive oil");
+
+        // A unit word with nothing after it is the name, made singular
+        let line = parse("3 cloves");
+        assert_eq!(line.unit, None);
+        assert_eq!(line.name, "clove");
+        assert_eq!(line.variant.as_deref(), Some("cloves"));
+    }
+
+    #[test]
+    fn lines_without_amounts_are_names() {
+        let line = parse("garlic");
+        assert_eq!(line.quantity, None);
+        assert_eq!(line.name, "garlic");
+
+        // A zero denominator is not a number
+        assert_eq!(parse("1/0 cup sugar").quantity, None);
+    }
+
+    #[test]
+    fn to_taste_in_any_case() {
+        for line in ["Salt To Taste", "salt, TO TASTE", "Salt, As Needed"] {
+            let line = parse(line);
+            assert_eq!(line.quantity, Some(Quantity::ToTaste), "{:?}", line);
+            assert_eq!(line.name, "salt");
+        }
+    }
+
+    #[test]
+    fn markers_in_either_order() {
+        let line = parse("salt and pepper, to taste");
+        assert!(line.quantity.as_ref().is_some_and(Quantity::is_to_taste));
+        assert_eq!(line.name, "salt and pepper");
+
+        let line = parse("parsley, for garnish (optional)");
+        assert!(line.optional && line.garnish);
+        assert_eq!(line.name, "parsley");
+
+        let line = parse("1 lemon (Optional), for serving");
+        assert!(line.optional && line.garnish);
+        assert_eq!(line.name, "lemon");
+
+        // Part of a word is not a marker
+        assert!(!parse("unoptional nuts").optional);
+    }
+
+    #[test]
+    fn markers_after_text_that_lowercases_longer() {
+        // "İ" lowercases to two chars, three bytes for two
+        let line = parse("İİ (optional)");
+        assert!(line.optional);
+        assert_eq!(line.variant.as_deref(), Some("İİ"));
+
+        let line = parse("ÇAPA, FOR GARNISH");
+        assert!(line.garnish);
+        assert_eq!(line.name, "çapa");
+    }
+
+    #[test]
+    fn approximate_grams_use_the_middle_of_a_range() {
+        assert_eq!(parse("2-4 tbsp sugar").approximate_grams(), Some(45.0));
+        assert_eq!(
+            parse("2 eggs").approximate_grams(),
+            Some(2.0 * GRAMS_PER_ITEM)
+        );
+        assert_eq!(parse("salt, to taste").approximate_grams(), None);
+        assert_eq!(parse("garlic").approximate_grams(), None);
+    }
+
+    #[test]
+    fn quantities_display_as_written() {
+        assert_eq!(Quantity::Amount { value: 1.5 }.to_string(), "1.5");
+        assert_eq!(Quantity::Range { min: 2.0, max: 3.0 }.to_string(), "2-3");
+        assert_eq!(Quantity::ToTaste.to_string(), "to taste");
+    }
+}
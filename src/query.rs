@@ -0,0 +1,484 @@
+use crate::output;
+#[cfg(feature = "neo4j")]
+use crate::precision::Precision;
+use crate::pricing::PriceList;
+use crate::readability;
+#[cfg(feature = "neo4j")]
+use crate::searches;
+#[cfg(feature = "neo4j")]
+use crate::server::Server;
+use crate::settings::Settings;
+use crate::Recipe;
+use csv::Reader;
+#[cfg(feature = "neo4j")]
+use neo4rs::{Graph, Query};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "neo4j")]
+use ring::digest::{digest, SHA256};
+#[cfg(feature = "neo4j")]
+use serde::Serialize;
+use std::error::Error;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Conditions a recipe must meet to be returned by a query.
+#[derive(Debug, Default, Clone)]
+pub struct RecipeFilter {
+    pub tag: Option<String>,
+    pub max_minutes: Option<i32>,
+    pub max_cost: Option<f64>,
+    pub prices: Option<PriceList>,
+    pub beginner_friendly: bool,
+}
+
+impl RecipeFilter {
+    pub fn matches(&self, recipe: &Recipe) -> bool {
+        let tag_ok = self
+            .tag
+            .as_ref()
+            .is_none_or(|tag| recipe.tags.iter().any(|t| t == tag));
+        let minutes_ok = self.max_minutes.is_none_or(|max| recipe.minutes <= max);
+        // Per serving where the recipe says how many it serves
+        let cost_ok = match (self.max_cost, &self.prices) {
+            (Some(max), Some(prices)) => prices
+                .estimate_per_serving(&recipe.ingredients, recipe.servings)
+                .or_else(|| prices.estimate(&recipe.ingredients))
+                .is_some_and(|cost| cost <= max),
+            _ => true,
+        };
+        let beginner_ok = !self.beginner_friendly || readability::beginner_friendly(&recipe.steps);
+        tag_ok && minutes_ok && cost_ok && beginner_ok
+    }
+}
+
+/// Picks `count` random recipes matching `filter` in one pass over the CSV
+/// (reservoir sampling), parsed with `settings`. The same `seed` always
+/// yields the same picks.
+pub fn random(
+    input: &Path,
+    settings: &Settings,
+    filter: &RecipeFilter,
+    count: usize,
+    seed: Option<u64>,
+) -> Result<Vec<Recipe>, Box<dyn Error>> {
+    if filter.max_cost.is_some() && filter.prices.is_none() {
+        return Err("--max-cost requires --prices".into());
+    }
+    let mut rng = match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+
+    let rdr = Reader::from_reader(output::open_source(input)?);
+    let mut picked: Vec<Recipe> = Vec::with_capacity(count);
+    let mut seen = 0;
+
+    for result in settings.recipes(rdr) {
+        let recipe: Recipe = result?;
+        if !filter.matches(&recipe) {
+            continue;
+        }
+
+        seen += 1;
+        if picked.len() < count {
+            picked.push(recipe);
+        } else {
+            let slot = rng.gen_range(0..seen);
+            if slot < count {
+                picked[slot] = recipe;
+            }
+        }
+    }
+
+    Ok(picked)
+}
+
+/// Prints `recipes` one per line, as JSON or as id, minutes and name.
+pub fn print_recipes(recipes: &[Recipe], json: bool) -> Result<(), Box<dyn Error>> {
+    for recipe in recipes {
+        if json {
+            println!("{}", serde_json::to_string(recipe)?);
+        } else {
+            println!("{}\t{} min\t{}", recipe.id, recipe.minutes, recipe.name);
+        }
+    }
+    Ok(())
+}
+
+/// Calories per serving as stored, whichever nutrition layout loaded it.
+#[cfg(feature = "neo4j")]
+const CALORIES: &str = "coalesce(r.calories, r.nutrition[0])";
+
+/// One recipe found by a graph query.
+#[cfg(feature = "neo4j")]
+#[derive(Debug, Serialize)]
+pub struct RecipeMatch {
+    pub id: i64,
+    pub name: String,
+    pub minutes: Option<i64>,
+    pub calories: Option<f64>,
+    /// Ingredients in common with the recipe `similar` was asked about
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared: Option<i64>,
+    /// Trending score, for `trending`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+}
+
+/// Prints graph query results one per line, as JSON or as a tab-separated
+/// table in the style of `print_recipes`.
+#[cfg(feature = "neo4j")]
+pub fn print_matches(matches: &[RecipeMatch], json: bool) -> Result<(), Box<dyn Error>> {
+    for found in matches {
+        if json {
+            println!("{}", serde_json::to_string(found)?);
+            continue;
+        }
+        let mut columns = vec![found.id.to_string()];
+        if let Some(shared) = found.shared {
+            columns.push(format!("{} shared", shared));
+        }
+        if let Some(score) = found.score {
+            columns.push(format!("{:.2} trending", score));
+        }
+        columns.push(match found.minutes {
+            Some(minutes) => format!("{} min", minutes),
+            None => "- min".to_string(),
+        });
+        columns.push(match found.calories {
+            Some(calories) => format!("{:.0} kcal", calories),
+            None => "- kcal".to_string(),
+        });
+        columns.push(found.name.clone());
+        println!("{}", columns.join("\t"));
+    }
+    Ok(())
+}
+
+/// Runs `query`, which binds a recipe to `r` and may return `shared` and
+/// `score`,
+/// adding the columns every `RecipeMatch` carries. Calories are stored
+/// with `precision`.
+#[cfg(feature = "neo4j")]
+async fn recipe_matches(
+    graph: &Graph,
+    precision: Precision,
+    query: Query,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let mut result = graph.execute(query).await?;
+    let mut matches = Vec::new();
+    while let Some(row) = result.next().await? {
+        matches.push(RecipeMatch {
+            id: row.get("id")?,
+            name: row.get::<Option<String>>("name")?.unwrap_or_default(),
+            minutes: row.get("minutes")?,
+            calories: row
+                .get::<Option<f64>>("calories")?
+                .map(|calories| precision.unscale(calories)),
+            shared: row.get("shared")?,
+            score: row.get("score")?,
+        });
+    }
+    Ok(matches)
+}
+
+/// Recipes that contain every one of `ingredients`, quickest first. Names
+/// are cleaned the way the loader cleans ingredient lines, so "Flour" and
+/// "flour" find the same node, but "flour" does not find "bread flour".
+/// Stored names are normalized here as well rather than trusted to be, so
+/// "tomato" also finds a "Tomatoes" node from a graph loaded before
+/// ingredient names were made singular.
+#[cfg(feature = "neo4j")]
+pub async fn by_ingredient(
+    graph: &Graph,
+    settings: &Settings,
+    ingredients: &[String],
+    max_minutes: Option<i64>,
+    count: usize,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let mut wanted: Vec<String> = ingredients
+        .iter()
+        .map(|line| settings.parse_ingredient(line).name)
+        .collect();
+    wanted.sort();
+    wanted.dedup();
+
+    // The stored names of each ingredient wanted, in order
+    let mut names: Vec<Vec<String>> = Vec::new();
+    for canonical in &wanted {
+        let stems = settings.rules.stems(canonical);
+        let mut result = graph
+            .execute(
+                Query::new(
+                    "MATCH (i:Ingredient) \
+                     WHERE any(stem IN $stems WHERE toLower(i.name) CONTAINS stem) \
+                     RETURN DISTINCT i.name AS name"
+                        .to_string(),
+                )
+                .param("stems", stems),
+            )
+            .await?;
+        let mut found = Vec::new();
+        while let Some(row) = result.next().await? {
+            let name: String = row.get("name")?;
+            if name == *canonical || settings.parse_ingredient(&name).name == *canonical {
+                found.push(name);
+            }
+        }
+        if found.is_empty() {
+            return Ok(Vec::new());
+        }
+        names.push(found);
+    }
+
+    let query = Query::new(format!(
+        "UNWIND range(0, size($names) - 1) AS t \
+         MATCH (r:Recipe)-[:CONTAINS]->(i:Ingredient) WHERE i.name IN $names[t] \
+         WITH r, count(DISTINCT t) AS found WHERE found = size($names) \
+         AND ($max_minutes IS NULL OR r.minutes <= $max_minutes) \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat({}) AS calories, NULL AS shared, NULL AS score \
+         ORDER BY r.minutes, r.id LIMIT $limit",
+        CALORIES
+    ))
+    .param("names", names)
+    .param("max_minutes", max_minutes)
+    .param("limit", count as i64);
+    recipe_matches(graph, settings.precision, query).await
+}
+
+/// The recipes sharing the most ingredients with recipe `id`. Ties go to
+/// the recipe with fewer ingredients of its own, as the closer overlap.
+#[cfg(feature = "neo4j")]
+pub async fn similar(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    id: i32,
+    count: usize,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let query = Query::new(format!(
+        "MATCH (:Recipe {{id: $id}})-[:CONTAINS]->(i:Ingredient)<-[:CONTAINS]-(r:Recipe) \
+         WHERE r.id <> $id \
+         WITH r, count(DISTINCT i) AS shared \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat({}) AS calories, shared, NULL AS score \
+         ORDER BY shared DESC, {}, r.id LIMIT $limit",
+        CALORIES,
+        server.count("(r)-[:CONTAINS]->()")
+    ))
+    .param("id", id)
+    .param("limit", count as i64);
+    recipe_matches(graph, settings.precision, query).await
+}
+
+/// Recipes with at most `max_calories` per serving, lightest first.
+/// Recipes without nutrition are left out.
+#[cfg(feature = "neo4j")]
+pub async fn nutrition(
+    graph: &Graph,
+    settings: &Settings,
+    max_calories: f32,
+    count: usize,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let query = Query::new(format!(
+        "MATCH (r:Recipe) WITH r, {} AS calories \
+         WHERE calories IS NOT NULL AND calories <= $max_calories \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat(calories) AS calories, NULL AS shared, NULL AS score \
+         ORDER BY calories, r.id LIMIT $limit",
+        CALORIES
+    ))
+    .param("max_calories", settings.precision.bolt(max_calories))
+    .param("limit", count as i64);
+    recipe_matches(graph, settings.precision, query).await
+}
+
+/// The recipes with the highest `trending_score` from `analyze trending`,
+/// hottest first, optionally only those with `tag`. Recipes with no
+/// ratings in the window are left out.
+#[cfg(feature = "neo4j")]
+pub async fn trending(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    tag: Option<&str>,
+    count: usize,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let query = Query::new(format!(
+        "MATCH (r:Recipe) WHERE r.trending_score > 0 \
+         AND ($tag IS NULL OR {}) \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat({}) AS calories, NULL AS shared, r.trending_score AS score \
+         ORDER BY score DESC, r.id LIMIT $limit",
+        server.exists("(r)-[:TAGGED]->(:Tag {key: $tag})"),
+        CALORIES
+    ))
+    .param("tag", tag.map(|tag| settings.key("Tag", tag)))
+    .param("limit", count as i64);
+    recipe_matches(graph, settings.precision, query).await
+}
+
+/// Recipes matching every term of `filter`, quickest first. Its
+/// ingredients are made canonical with `settings`' rules.
+#[cfg(feature = "neo4j")]
+pub async fn search(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    filter: &searches::Filter,
+    count: usize,
+) -> Result<Vec<RecipeMatch>, Box<dyn Error>> {
+    let tags: Vec<String> = filter
+        .tags
+        .iter()
+        .map(|tag| settings.key("Tag", tag))
+        .collect();
+    let ingredients: Vec<String> = filter
+        .ingredients
+        .iter()
+        .map(|name| settings.rules.canonical(name))
+        .collect();
+    let query = Query::new(format!(
+        "MATCH (r:Recipe) \
+         WHERE all(tag IN $tags WHERE {}) \
+         AND all(name IN $ingredients WHERE {}) \
+         AND ($max_minutes IS NULL OR r.minutes <= $max_minutes) \
+         WITH r, {} AS calories, [(:User)-[x:RATED]->(r) | x.rating] AS ratings \
+         WHERE ($max_calories IS NULL OR calories <= $max_calories) \
+         AND ($min_rating IS NULL OR size(ratings) > 0 \
+              AND reduce(total = 0.0, rating IN ratings | total + rating) / size(ratings) \
+                  >= $min_rating) \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat(calories) AS calories, NULL AS shared, NULL AS score \
+         ORDER BY r.minutes, r.id LIMIT $limit",
+        server.exists("(r)-[:TAGGED]->(:Tag {key: tag})"),
+        server.exists("(r)-[:CONTAINS]->(:Ingredient {name: name})"),
+        CALORIES
+    ))
+    .param("tags", tags)
+    .param("ingredients", ingredients)
+    .param("max_minutes", filter.max_minutes)
+    .param(
+        "max_calories",
+        filter.max_calories.map(|max| settings.precision.bolt(max)),
+    )
+    .param("min_rating", filter.min_rating)
+    .param("limit", count as i64);
+    recipe_matches(graph, settings.precision, query).await
+}
+
+/// Today's date in UTC, as YYYY-MM-DD.
+pub fn today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() / 86_400)
+        .unwrap_or(0) as i64;
+    // Days since 1970-01-01 to a civil date (Hinnant's algorithm), with
+    // years starting in March so the leap day comes last
+    let era_day = days + 719_468;
+    let era = era_day.div_euclid(146_097);
+    let day_of_era = era_day.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Checks that `date` is a real day written as YYYY-MM-DD, so that a typo
+/// is not quietly hashed into a pick of its own.
+pub fn check_date(date: &str) -> Result<(), String> {
+    let invalid = || format!("invalid date: {} (expected YYYY-MM-DD)", date);
+    let parts: Vec<&str> = date.split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(invalid());
+    };
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return Err(invalid());
+    }
+    let year: u32 = year.parse().map_err(|_| invalid())?;
+    let month: u32 = month.parse().map_err(|_| invalid())?;
+    let day: u32 = day.parse().map_err(|_| invalid())?;
+    let leap = year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400));
+    let days_in_month = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return Err(invalid()),
+    };
+    if !(1..=days_in_month).contains(&day) {
+        return Err(invalid());
+    }
+    Ok(())
+}
+
+/// The recipe of the day for `date`: one recipe with `tag` and at most
+/// `max_minutes`, picked by a hash of the date and filters, so every run
+/// against the same graph agrees without storing anything. Loading more
+/// recipes can change the pick. `None` when nothing matches.
+#[cfg(feature = "neo4j")]
+pub async fn of_the_day(
+    graph: &Graph,
+    server: &Server,
+    settings: &Settings,
+    date: &str,
+    tag: Option<&str>,
+    max_minutes: Option<i64>,
+) -> Result<Option<RecipeMatch>, Box<dyn Error>> {
+    check_date(date)?;
+    let filter = format!(
+        "MATCH (r:Recipe) WHERE ($tag IS NULL OR {}) \
+         AND ($max_minutes IS NULL OR r.minutes <= $max_minutes)",
+        server.exists("(r)-[:TAGGED]->(:Tag {key: $tag})")
+    );
+    let tag_key = tag.map(|tag| settings.key("Tag", tag));
+
+    let mut result = graph
+        .execute(
+            Query::new(format!("{} RETURN count(r) AS matching", filter))
+                .param("tag", tag_key.clone())
+                .param("max_minutes", max_minutes),
+        )
+        .await?;
+    let matching = match result.next().await? {
+        Some(row) => row.get::<i64>("matching")?,
+        None => 0,
+    };
+    if matching == 0 {
+        return Ok(None);
+    }
+
+    let seed = format!(
+        "{}\u{1f}{}\u{1f}{}",
+        date,
+        tag.unwrap_or_default(),
+        max_minutes.map(|max| max.to_string()).unwrap_or_default()
+    );
+    let hash = digest(&SHA256, seed.as_bytes());
+    let mut head = [0; 8];
+    head.copy_from_slice(&hash.as_ref()[..8]);
+    let skip = u64::from_be_bytes(head) % matching as u64;
+
+    let query = Query::new(format!(
+        "{} WITH r ORDER BY r.id SKIP $skip LIMIT 1 \
+         RETURN r.id AS id, r.name AS name, r.minutes AS minutes, \
+                toFloat({}) AS calories, NULL AS shared, NULL AS score",
+        filter, CALORIES
+    ))
+    .param("tag", tag_key)
+    .param("max_minutes", max_minutes)
+    .param("skip", skip as i64);
+    Ok(recipe_matches(graph, settings.precision, query)
+        .await?
+        .pop())
+}
@@ -0,0 +1,52 @@
+//! `quickstart`: loads a small synthetic corpus, so the tool can be tried
+//! without downloading the Kaggle dump, and suggests queries to run on it.
+
+use crate::cancel::CancellationToken;
+use crate::fixtures;
+use crate::ingest::{self, LoadOptions};
+use crate::settings::Settings;
+use std::error::Error;
+
+/// Fixture recipes loaded, the same ones on every run.
+pub const RECIPES: usize = 200;
+pub const SEED: u64 = 42;
+
+const QUERIES: &[(&str, &str)] = &[
+    (
+        "the most used ingredients",
+        "MATCH (i:Ingredient) RETURN i.name, i.recipe_count ORDER BY i.recipe_count DESC LIMIT 10",
+    ),
+    (
+        "recipes with garlic, quickest first",
+        "MATCH (r:Recipe)-[:CONTAINS]->(:Ingredient {name: 'garlic'}) \
+         RETURN r.name_display, r.minutes ORDER BY r.minutes LIMIT 10",
+    ),
+    (
+        "recipes per course",
+        "MATCH (r:Recipe)-[:IN_COURSE]->(c:Course) RETURN c.name, count(r) ORDER BY count(r) DESC",
+    ),
+];
+
+/// Generates the fixture corpus, loads it with `options` in place of their
+/// input, and prints the queries to try.
+pub async fn run(
+    mut options: LoadOptions,
+    settings: &Settings,
+    cancel: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let sample = std::env::temp_dir().join("recipe_app_quickstart.csv");
+    fixtures::generate(std::fs::File::create(&sample)?, RECIPES, SEED)?;
+    options.input = sample.to_string_lossy().into_owned();
+    ingest::load(options, settings, cancel).await?;
+
+    println!("\ntry these in the Neo4j browser:");
+    for (about, query) in QUERIES {
+        println!("\n// {}\n{}", about, query);
+    }
+    println!(
+        "\nor from here:\n  {} query autocomplete chick --input {}",
+        env!("CARGO_PKG_NAME"),
+        sample.display()
+    );
+    Ok(())
+}
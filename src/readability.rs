@@ -0,0 +1,150 @@
+/// Verbs that open an instruction ("stir the sauce"). A step that starts
+/// with one reads as a direct command.
+const IMPERATIVES: &[&str] = &[
+    "add",
+    "arrange",
+    "bake",
+    "beat",
+    "blend",
+    "boil",
+    "bring",
+    "brown",
+    "brush",
+    "chill",
+    "chop",
+    "combine",
+    "cook",
+    "cool",
+    "cover",
+    "cream",
+    "cut",
+    "dice",
+    "divide",
+    "drain",
+    "drizzle",
+    "fold",
+    "fry",
+    "garnish",
+    "grate",
+    "grease",
+    "grill",
+    "heat",
+    "knead",
+    "let",
+    "line",
+    "melt",
+    "microwave",
+    "mince",
+    "mix",
+    "peel",
+    "place",
+    "pour",
+    "preheat",
+    "put",
+    "reduce",
+    "refrigerate",
+    "remove",
+    "repeat",
+    "return",
+    "roast",
+    "roll",
+    "saute",
+    "season",
+    "serve",
+    "set",
+    "shape",
+    "simmer",
+    "slice",
+    "spoon",
+    "spray",
+    "spread",
+    "sprinkle",
+    "stir",
+    "strain",
+    "top",
+    "toss",
+    "transfer",
+    "turn",
+    "whisk",
+];
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+}
+
+fn sentences(steps: &[String]) -> Vec<&str> {
+    steps
+        .iter()
+        .flat_map(|step| step.split(['.', '!', '?', ';']))
+        .map(str::trim)
+        .filter(|sentence| words(sentence).next().is_some())
+        .collect()
+}
+
+/// Vowel groups, less a silent final "e"; never below one.
+fn syllables(word: &str) -> usize {
+    let word = word.to_lowercase();
+    let mut count = 0;
+    let mut previous_vowel = false;
+    for c in word.chars() {
+        let vowel = "aeiouy".contains(c);
+        if vowel && !previous_vowel {
+            count += 1;
+        }
+        previous_vowel = vowel;
+    }
+    if word.ends_with('e') && !word.ends_with("le") && count > 1 {
+        count -= 1;
+    }
+    count.max(1)
+}
+
+pub fn avg_words_per_step(steps: &[String]) -> f64 {
+    if steps.is_empty() {
+        return 0.0;
+    }
+    let total: usize = steps.iter().map(|step| words(step).count()).sum();
+    total as f64 / steps.len() as f64
+}
+
+/// Flesch-Kincaid grade level of the instructions, treating each step
+/// clause as a sentence.
+pub fn reading_level(steps: &[String]) -> f64 {
+    let sentences = sentences(steps);
+    let words: Vec<&str> = sentences.iter().flat_map(|s| words(s)).collect();
+    if words.is_empty() {
+        return 0.0;
+    }
+    let syllables: usize = words.iter().map(|word| syllables(word)).sum();
+    let grade = 0.39 * (words.len() as f64 / sentences.len() as f64)
+        + 11.8 * (syllables as f64 / words.len() as f64)
+        - 15.59;
+    (grade.max(0.0) * 10.0).round() / 10.0
+}
+
+/// Share of sentences that open with an imperative verb.
+pub fn imperative_density(steps: &[String]) -> f64 {
+    let sentences = sentences(steps);
+    if sentences.is_empty() {
+        return 0.0;
+    }
+    let imperative = sentences
+        .iter()
+        .filter(|sentence| {
+            words(sentence)
+                .next()
+                .is_some_and(|first| IMPERATIVES.contains(&first.to_lowercase().as_str()))
+        })
+        .count();
+    imperative as f64 / sentences.len() as f64
+}
+
+/// Short, plainly worded steps that mostly read as direct commands.
+pub fn beginner_friendly(steps: &[String]) -> bool {
+    !steps.is_empty()
+        && avg_words_per_step(steps) <= 20.0
+        && reading_level(steps) <= 8.0
+        && imperative_density(steps) >= 0.5
+}
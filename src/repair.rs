@@ -0,0 +1,117 @@
+use crate::cancel::CancellationToken;
+use crate::schema;
+use crate::server::Server;
+use neo4rs::{BoltType, Graph, Query};
+use std::error::Error;
+
+/// Parallel relationships found between node pairs for one relationship
+/// of the schema.
+#[derive(Debug, Clone)]
+pub struct Duplicates {
+    pub rel_type: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+    /// Node pairs joined by more than one relationship of the type
+    pub pairs: usize,
+    /// Relationships beyond the first of each pair, deleted unless a dry
+    /// run
+    pub extra: usize,
+}
+
+/// What `graph repair --dedupe-relationships` reports for `found`.
+pub fn summary(found: &[Duplicates], dry_run: bool) -> String {
+    let extra: usize = found.iter().map(|duplicates| duplicates.extra).sum();
+    if dry_run {
+        format!("{} duplicate relationships found; none removed", extra)
+    } else {
+        format!("{} duplicate relationships removed", extra)
+    }
+}
+
+/// Collapses each group of `rel_type` relationships between the same two
+/// nodes into the one with the most properties, the oldest on a tie. The
+/// one kept takes the properties it lacks from the others, richest first;
+/// its own values are never overwritten.
+const COLLAPSE: &str = "WITH keep, extra, properties(keep) AS own \
+     FOREACH (x IN reverse(extra) | SET keep += properties(x)) \
+     SET keep += own \
+     FOREACH (x IN extra | DELETE x)";
+
+/// Finds the node pairs joined more than once by each relationship in the
+/// schema, `batch_size` start nodes per transaction in key order, and
+/// collapses them unless `dry_run`. Every write the loader makes is a
+/// MERGE, so these come from older runs that created relationships.
+pub async fn dedupe_relationships(
+    graph: &Graph,
+    server: &Server,
+    dry_run: bool,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<Vec<Duplicates>, Box<dyn Error>> {
+    let model = schema::model();
+    let mut found = Vec::new();
+    for relationship in &model.relationships {
+        let key = model
+            .node(relationship.from)
+            .map(|node| node.key)
+            .ok_or_else(|| format!("no node {} in the schema", relationship.from))?;
+        let mut duplicates = Duplicates {
+            rel_type: relationship.rel_type,
+            from: relationship.from,
+            to: relationship.to,
+            pairs: 0,
+            extra: 0,
+        };
+        let mut after: Option<BoltType> = None;
+        loop {
+            cancel.check()?;
+            let query = Query::new(format!(
+                "MATCH (a:{from}) {after} \
+                 WITH a ORDER BY a.{key} LIMIT $limit \
+                 WITH collect(a) AS batch, max(a.{key}) AS last \
+                 CALL {{ \
+                     WITH batch UNWIND batch AS a \
+                     MATCH (a)-[x:{rel_type}]->(b:{to}) \
+                     WITH a, b, x ORDER BY size(keys(x)) DESC, {element_id} \
+                     WITH a, b, collect(x) AS rels WHERE size(rels) > 1 \
+                     WITH rels[0] AS keep, rels[1..] AS extra \
+                     {collapse} \
+                     RETURN count(*) AS pairs, sum(size(extra)) AS extra \
+                 }} \
+                 RETURN last, size(batch) AS seen, pairs, extra",
+                from = relationship.from,
+                to = relationship.to,
+                rel_type = relationship.rel_type,
+                key = key,
+                after = if after.is_some() {
+                    format!("WHERE a.{} > $after", key)
+                } else {
+                    String::new()
+                },
+                collapse = if dry_run { "" } else { COLLAPSE },
+                element_id = server.element_id("x"),
+            ))
+            .param("limit", batch_size as i64);
+            let query = match &after {
+                Some(last) => query.param("after", last.clone()),
+                None => query,
+            };
+            let mut result = graph.execute(query).await?;
+            let Some(row) = result.next().await? else {
+                break;
+            };
+            if row.get::<i64>("seen")? == 0 {
+                break;
+            }
+            duplicates.pairs += row.get::<i64>("pairs")? as usize;
+            duplicates.extra += row.get::<i64>("extra")? as usize;
+            after = Some(row.get("last")?);
+        }
+        println!(
+            "{} ({})-[:{}]->({}) pairs joined more than once, {} extra relationships",
+            duplicates.pairs, duplicates.from, duplicates.rel_type, duplicates.to, duplicates.extra
+        );
+        found.push(duplicates);
+    }
+    Ok(found)
+}
@@ -0,0 +1,541 @@
+//! The rows a load writes for each recipe, and the Cypher that writes them.
+//! `graph::GraphLoader` sends them in batches over Bolt; the Cypher export
+//! writes the same statements one row at a time, so a script and a live
+//! load build the same graph.
+
+use crate::derived;
+use crate::icons;
+use crate::keys;
+use crate::nutrition;
+use crate::output::cypher_string;
+use crate::schema;
+use crate::settings::Settings;
+use crate::Recipe;
+#[cfg(feature = "neo4j")]
+use neo4rs::BoltType;
+use std::collections::HashSet;
+
+/// A property value as the loader writes it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Text(String),
+    List(Vec<Value>),
+}
+
+impl Value {
+    /// The value as a Cypher literal. Floats keep a decimal point, so they
+    /// are stored as floats, and NaN is written as the division that
+    /// yields it.
+    pub fn cypher(&self) -> String {
+        match self {
+            Value::Null => "null".to_string(),
+            Value::Bool(value) => value.to_string(),
+            Value::Int(value) => value.to_string(),
+            Value::Float(value) if value.is_nan() => "0.0/0.0".to_string(),
+            Value::Float(value) if value.is_infinite() => {
+                format!("{}1.0/0.0", if *value < 0.0 { "-" } else { "" })
+            }
+            Value::Float(value) => format!("{:?}", value),
+            Value::Text(value) => cypher_string(value),
+            Value::List(values) => {
+                let values: Vec<String> = values.iter().map(Value::cypher).collect();
+                format!("[{}]", values.join(", "))
+            }
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Value {
+        Value::Bool(value)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(value: i32) -> Value {
+        Value::Int(value.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Value {
+        Value::Int(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Float(value)
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::Text(value.to_string())
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Value {
+        Value::List(values.into_iter().map(Into::into).collect())
+    }
+}
+
+#[cfg(feature = "neo4j")]
+impl From<Value> for BoltType {
+    fn from(value: Value) -> BoltType {
+        match value {
+            Value::Null => None::<i64>.into(),
+            Value::Bool(value) => value.into(),
+            Value::Int(value) => value.into(),
+            Value::Float(value) => value.into(),
+            Value::Text(value) => value.into(),
+            Value::List(values) => values
+                .into_iter()
+                .map(BoltType::from)
+                .collect::<Vec<_>>()
+                .into(),
+        }
+    }
+}
+
+/// One row: each value beside its property name.
+pub type Row = Vec<(&'static str, Value)>;
+
+/// The tables a batch is written from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Table {
+    Recipes,
+    Ingredients,
+    /// One row per recipe: its id and the keys of its ingredients
+    IngredientKeys,
+    Tags,
+    Steps,
+    /// One row per recipe written with step nodes: its id and step count
+    StepCounts,
+}
+
+/// One recipe's rows in each table.
+#[derive(Debug, Default)]
+pub struct Rows {
+    pub recipes: Vec<Row>,
+    pub ingredients: Vec<Row>,
+    pub ingredient_keys: Vec<Row>,
+    pub tags: Vec<Row>,
+    pub steps: Vec<Row>,
+    pub step_counts: Vec<Row>,
+}
+
+impl Rows {
+    /// The rows for `recipe`, parsed and keyed with `settings`. With
+    /// `steps_as_nodes` the steps are Step rows and the `steps` property is
+    /// cleared, so a recipe is never stored both ways.
+    pub fn new(recipe: &Recipe, settings: &Settings, steps_as_nodes: bool) -> Rows {
+        let mut recipe_row = recipe_row(recipe, settings);
+        let (mut steps, mut step_counts) = (Vec::new(), Vec::new());
+        if steps_as_nodes {
+            for (name, value) in recipe_row.iter_mut() {
+                if *name == "steps" {
+                    *value = Value::Null;
+                }
+            }
+            steps = step_rows(recipe.id, &recipe.steps, settings);
+            step_counts.push(vec![
+                ("recipe_id", recipe.id.into()),
+                ("count", (recipe.steps.len() as i64).into()),
+            ]);
+        }
+        let ingredients = ingredient_rows(recipe.id, &recipe.ingredients, settings);
+        let keys: Vec<Value> = ingredients
+            .iter()
+            .filter_map(|row| row.iter().find(|(name, _)| *name == "key"))
+            .map(|(_, key)| key.clone())
+            .collect();
+        Rows {
+            recipes: vec![recipe_row],
+            ingredients,
+            ingredient_keys: vec![vec![
+                ("recipe_id", recipe.id.into()),
+                ("keys", Value::List(keys)),
+            ]],
+            tags: tag_rows(recipe.id, &recipe.tags, settings),
+            steps,
+            step_counts,
+        }
+    }
+
+    pub fn table(&self, table: Table) -> &[Row] {
+        match table {
+            Table::Recipes => &self.recipes,
+            Table::Ingredients => &self.ingredients,
+            Table::IngredientKeys => &self.ingredient_keys,
+            Table::Tags => &self.tags,
+            Table::Steps => &self.steps,
+            Table::StepCounts => &self.step_counts,
+        }
+    }
+}
+
+/// The Recipe node's properties, derived ones included. With the named
+/// nutrition layout every field is present, null when the recipe has no
+/// complete nutrition list, so a rerun clears values it no longer has.
+pub fn recipe_row(recipe: &Recipe, settings: &Settings) -> Row {
+    let precision = settings.precision;
+    let mut properties: Row = vec![
+        ("id", recipe.id.into()),
+        ("name", recipe.name.clone().into()),
+        ("name_raw", recipe.name.clone().into()),
+        ("name_display", recipe.name_display.clone().into()),
+        ("description", recipe.description.clone().into()),
+        ("minutes", recipe.minutes.into()),
+        ("nutrition", precision.value_list(&recipe.nutrition)),
+        (
+            "nutrition_units",
+            nutrition::unit_symbols(&recipe.nutrition, settings.layout)
+                .map(|units| units.into_iter().map(str::to_string).collect::<Vec<_>>())
+                .into(),
+        ),
+        ("steps", recipe.steps.clone().into()),
+        ("original_steps", recipe.original_steps.clone().into()),
+        ("source_url", recipe.source_url.clone().into()),
+        ("author", recipe.author.clone().into()),
+        ("license", recipe.license.clone().into()),
+        ("truncated_fields", recipe.truncated_fields.clone().into()),
+        ("servings", recipe.servings.into()),
+        ("servings_inferred", recipe.servings_inferred.into()),
+        ("estimated_cost", recipe.estimated_cost.into()),
+        (
+            "estimated_cost_per_serving",
+            recipe.estimated_cost_per_serving.into(),
+        ),
+        ("content_hash", recipe.content_hash.clone().into()),
+    ];
+
+    if settings.layout == nutrition::Layout::Named {
+        let values = recipe.nutrition_facts().map(|facts| facts.values());
+        for (index, name) in nutrition::FIELDS.iter().enumerate() {
+            let value = values.map_or(Value::Null, |values| precision.value(values[index]));
+            properties.push((name, value));
+        }
+    }
+
+    let input = derived::DerivedInput::from_recipe(recipe, settings);
+    properties.extend(
+        derived::ALL
+            .iter()
+            .map(|property| (property.name(), property.compute(&input))),
+    );
+    properties
+}
+
+/// One CONTAINS row per distinct parsed ingredient, carrying its quantity,
+/// unit and markers. Lines naming the same ingredient, such as "1 egg" and
+/// "2 eggs", share a node and keep the first line's position and amount,
+/// so the `order` never depends on how names are merged.
+pub fn ingredient_rows(recipe_id: i32, ingredients: &[String], settings: &Settings) -> Vec<Row> {
+    let mut seen = HashSet::new();
+    let mut rows = Vec::new();
+    for (order, ingredient) in ingredients.iter().enumerate() {
+        let line = settings.parse_ingredient(ingredient);
+        if !seen.insert(line.name.clone()) {
+            continue;
+        }
+        let quantity = line.quantity.as_ref();
+        let icon = icons::lookup(&line.name);
+        rows.push(vec![
+            ("recipe_id", recipe_id.into()),
+            ("key", settings.key("Ingredient", &line.name).into()),
+            ("name", line.name.into()),
+            ("order", (order as i64).into()),
+            ("quantity_min", quantity.and_then(|q| q.min()).into()),
+            ("quantity_max", quantity.and_then(|q| q.max()).into()),
+            ("unit", line.unit.into()),
+            ("to_taste", quantity.is_some_and(|q| q.is_to_taste()).into()),
+            ("brand", line.brand.into()),
+            ("variant", line.variant.into()),
+            ("optional", line.optional.into()),
+            ("garnish", line.garnish.into()),
+            ("emoji", icon.map(|icon| icon.emoji).into()),
+            ("icon", icon.map(|icon| icon.icon).into()),
+        ]);
+    }
+    rows
+}
+
+/// One row per step, keyed by recipe and position, since the same text in
+/// two recipes is two steps. Each row names the key of the step after it,
+/// if any.
+pub fn step_rows(recipe_id: i32, steps: &[String], settings: &Settings) -> Vec<Row> {
+    let key = |index: usize| settings.key("Step", &format!("{}/{}", recipe_id, index));
+    steps
+        .iter()
+        .enumerate()
+        .map(|(index, text)| {
+            let next = (index + 1 < steps.len()).then(|| key(index + 1));
+            vec![
+                ("recipe_id", recipe_id.into()),
+                ("key", key(index).into()),
+                ("index", (index as i64).into()),
+                ("text", text.clone().into()),
+                ("next", next.into()),
+            ]
+        })
+        .collect()
+}
+
+/// One TAGGED row per distinct, non-empty tag.
+pub fn tag_rows(recipe_id: i32, tags: &[String], settings: &Settings) -> Vec<Row> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .map(|tag| tag.trim())
+        .filter(|tag| !tag.is_empty() && seen.insert(*tag))
+        .map(|tag| {
+            vec![
+                ("recipe_id", recipe_id.into()),
+                ("key", settings.key("Tag", tag).into()),
+                ("name", tag.into()),
+            ]
+        })
+        .collect()
+}
+
+/// The statements that write a batch, in order, each run once per row of
+/// its table with the row bound to `row`. Only the recipe properties in
+/// `recipe_columns` are set, so a rerun keeps what the enrichment and
+/// analytics passes stored.
+pub fn statements(recipe_columns: &HashSet<&str>) -> Result<Vec<(Table, String)>, String> {
+    let model = schema::model();
+    let recipe_node = model.node("Recipe").ok_or("schema has no Recipe node")?;
+    Ok(vec![
+        (
+            Table::Recipes,
+            format!(
+                "MERGE (r:Recipe {{id: row.id}}) SET r += {}",
+                recipe_node.property_map("row", |name| recipe_columns.contains(name))
+            ),
+        ),
+        (
+            Table::Ingredients,
+            "MERGE (i:Ingredient {key: row.key}) \
+             ON CREATE SET i.name = row.name \
+             SET i.emoji = row.emoji, i.icon = row.icon \
+             WITH i, row WHERE row.variant IS NOT NULL \
+             AND NOT row.variant IN coalesce(i.aliases, []) \
+             SET i.aliases = coalesce(i.aliases, []) + row.variant"
+                .to_string(),
+        ),
+        // Ingredients dropped from the recipe since the last load
+        (
+            Table::IngredientKeys,
+            "MATCH (:Recipe {id: row.recipe_id})-[c:CONTAINS]->(i:Ingredient) \
+             WHERE NOT i.key IN row.keys DELETE c"
+                .to_string(),
+        ),
+        (
+            Table::Ingredients,
+            "MATCH (r:Recipe {id: row.recipe_id}), (i:Ingredient {key: row.key}) \
+             MERGE (r)-[c:CONTAINS]->(i) \
+             SET c.order = row.order, c.quantity_min = row.quantity_min, \
+             c.quantity_max = row.quantity_max, c.unit = row.unit, \
+             c.to_taste = row.to_taste, c.brand = row.brand, \
+             c.optional = row.optional, c.garnish = row.garnish"
+                .to_string(),
+        ),
+        (
+            Table::Tags,
+            "MERGE (t:Tag {key: row.key}) ON CREATE SET t.name = row.name".to_string(),
+        ),
+        (
+            Table::Tags,
+            "MATCH (r:Recipe {id: row.recipe_id}), (t:Tag {key: row.key}) \
+             MERGE (r)-[:TAGGED]->(t)"
+                .to_string(),
+        ),
+        // Steps past the new end are left over from a longer version
+        (
+            Table::StepCounts,
+            "MATCH (:Recipe {id: row.recipe_id})-[h:HAS_STEP]->(s:Step) \
+             WHERE h.index >= row.count DETACH DELETE s"
+                .to_string(),
+        ),
+        (
+            Table::Steps,
+            "MATCH (r:Recipe {id: row.recipe_id}) \
+             MERGE (s:Step {key: row.key}) SET s.index = row.index, s.text = row.text \
+             MERGE (r)-[h:HAS_STEP]->(s) SET h.index = row.index"
+                .to_string(),
+        ),
+        (
+            Table::Steps,
+            "WITH row WHERE row.next IS NOT NULL \
+             MATCH (s:Step {key: row.key}), (next:Step {key: row.next}) \
+             MERGE (s)-[:NEXT]->(next)"
+                .to_string(),
+        ),
+    ])
+}
+
+/// `row` as a Cypher map literal, to bind with `WITH … AS row`.
+pub fn cypher_map(row: &Row) -> String {
+    let fields: Vec<String> = row
+        .iter()
+        .map(|(name, value)| format!("{}: {}", name, value.cypher()))
+        .collect();
+    format!("{{{}}}", fields.join(", "))
+}
+
+/// The statement linking the recipe `id` to its course, as
+/// `derived::LINK_COURSE` does for a batch, keyed in `namespace`.
+pub fn link_course(id: i32, course: &str, namespace: &str) -> String {
+    format!(
+        "WITH {{id: {}, key: {}}} AS row {}",
+        id,
+        cypher_string(&keys::surrogate(namespace, "Course", course)),
+        derived::LINK_COURSE_ROW
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn lines(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    fn column<'a>(row: &'a Row, name: &str) -> &'a Value {
+        &row.iter().find(|(column, _)| *column == name).unwrap().1
+    }
+
+    #[test]
+    fn ingredients_are_named_and_ordered_as_parsed() {
+        let settings = Settings::default();
+        let rows = ingredient_rows(
+            1,
+            &lines(&["2-3 cloves garlic", "1 cup flour", "salt, to taste"]),
+            &settings,
+        );
+
+        let names: Vec<&Value> = rows.iter().map(|row| column(row, "name")).collect();
+        assert_eq!(names, [&"garlic".into(), &"flour".into(), &"salt".into()]);
+        assert_eq!(column(&rows[0], "quantity_min"), &Value::Float(2.0));
+        assert_eq!(column(&rows[0], "quantity_max"), &Value::Float(3.0));
+        assert_eq!(column(&rows[0], "unit"), &"cloves".into());
+        assert_eq!(column(&rows[2], "to_taste"), &Value::Bool(true));
+        assert_eq!(column(&rows[2], "order"), &Value::Int(2));
+        assert_eq!(
+            column(&rows[0], "key"),
+            &settings.key("Ingredient", "garlic").into()
+        );
+    }
+
+    #[test]
+    fn repeated_ingredient_keeps_its_first_line() {
+        let rows = ingredient_rows(
+            1,
+            &lines(&["1 egg", "2 cups sugar", "2 eggs", "salt"]),
+            &Settings::default(),
+        );
+
+        let kept: Vec<(&Value, &Value)> = rows
+            .iter()
+            .map(|row| (column(row, "name"), column(row, "order")))
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                (&"egg".into(), &Value::Int(0)),
+                (&"sugar".into(), &Value::Int(1)),
+                (&"salt".into(), &Value::Int(3)),
+            ]
+        );
+        assert_eq!(column(&rows[0], "quantity_min"), &Value::Float(1.0));
+    }
+
+    #[test]
+    fn every_recipe_lists_its_ingredient_keys() {
+        let settings = Settings::default();
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.ingredients = lines(&["1 egg", "2 eggs", "salt"]);
+
+        let rows = Rows::new(&recipe, &settings, false);
+        assert_eq!(
+            column(&rows.ingredient_keys[0], "keys"),
+            &vec![
+                settings.key("Ingredient", "egg"),
+                settings.key("Ingredient", "salt")
+            ]
+            .into()
+        );
+
+        // Even with none left, so the cleanup still runs
+        recipe.ingredients.clear();
+        let rows = Rows::new(&recipe, &settings, false);
+        assert_eq!(
+            column(&rows.ingredient_keys[0], "keys"),
+            &Value::List(Vec::new())
+        );
+    }
+
+    #[test]
+    fn step_nodes_replace_the_steps_property() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.steps = lines(&["mix", "bake"]);
+
+        let rows = Rows::new(&recipe, &Settings::default(), true);
+        assert_eq!(column(&rows.recipes[0], "steps"), &Value::Null);
+        assert_eq!(rows.steps.len(), 2);
+        assert_eq!(
+            column(&rows.steps[0], "next"),
+            column(&rows.steps[1], "key")
+        );
+        assert_eq!(column(&rows.step_counts[0], "count"), &Value::Int(2));
+
+        let rows = Rows::new(&recipe, &Settings::default(), false);
+        assert_eq!(
+            column(&rows.recipes[0], "steps"),
+            &lines(&["mix", "bake"]).into()
+        );
+        assert!(rows.steps.is_empty() && rows.step_counts.is_empty());
+    }
+
+    #[test]
+    fn named_nutrition_is_always_present() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.nutrition = vec![1.0, 2.0];
+
+        let row = recipe_row(&recipe, &Settings::default());
+        for name in nutrition::FIELDS {
+            assert_eq!(column(&row, name), &Value::Null, "{}", name);
+        }
+    }
+
+    #[test]
+    fn cypher_literals_keep_their_types() {
+        assert_eq!(Value::Float(2.0).cypher(), "2.0");
+        assert_eq!(Value::Float(f64::NAN).cypher(), "0.0/0.0");
+        assert_eq!(Value::Int(2).cypher(), "2");
+        assert_eq!(Value::from(Some("it's")).cypher(), "'it\\'s'");
+        assert_eq!(
+            cypher_map(&vec![("a", Value::Null), ("b", vec![1.5, 2.0].into())]),
+            "{a: null, b: [1.5, 2.0]}"
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use crate::conflicts::Conflict;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The recipe ids one ingestion run touched, saved as
+/// `<runs-dir>/<run_id>.json` so downstream caches can invalidate exactly
+/// what changed. Recipes are merged on id, so ones a rerun rewrites are
+/// listed under `created` too; `updated` and `deleted` stay empty until
+/// the loader tells the two apart.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RunLog {
+    pub run_id: String,
+    pub input: String,
+    pub started_at: u64,
+    pub finished_at: Option<u64>,
+    pub created: Vec<i32>,
+    pub updated: Vec<i32>,
+    pub deleted: Vec<i32>,
+    pub skipped: Vec<i32>,
+    /// Ids that appeared more than once in the input
+    #[serde(default)]
+    pub conflicts: Vec<Conflict>,
+    /// Redactions made by `--scrub`, by category
+    #[serde(default)]
+    pub redactions: BTreeMap<String, usize>,
+    /// Nothing was written; `created` lists the recipes compared with the
+    /// graph by `load --dry-run --compare`
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// Seconds since the Unix epoch.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Runs started by this process so far, so loads a pipeline runs within
+/// the same second still get their own ids.
+static STARTED: AtomicU32 = AtomicU32::new(0);
+
+fn path(dir: &Path, run_id: &str) -> PathBuf {
+    dir.join(format!("{}.json", run_id))
+}
+
+impl RunLog {
+    pub fn start(input: &str) -> RunLog {
+        let started_at = now();
+        RunLog {
+            run_id: format!(
+                "load-{}-{}-{}",
+                started_at,
+                process::id(),
+                STARTED.fetch_add(1, Ordering::Relaxed)
+            ),
+            input: input.to_string(),
+            started_at,
+            finished_at: None,
+            created: Vec::new(),
+            updated: Vec::new(),
+            deleted: Vec::new(),
+            skipped: Vec::new(),
+            conflicts: Vec::new(),
+            redactions: BTreeMap::new(),
+            dry_run: false,
+        }
+    }
+
+    pub fn save(&mut self, dir: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        self.finished_at = Some(now());
+        fs::create_dir_all(dir)?;
+        let path = path(dir, &self.run_id);
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+
+    pub fn load(dir: &Path, run_id: &str) -> Result<RunLog, Box<dyn Error>> {
+        let path = path(dir, run_id);
+        let text = fs::read_to_string(&path)
+            .map_err(|err| format!("no run {} in {}: {}", run_id, dir.display(), err))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Writes the log to `out` as pretty-printed JSON, for `run report`.
+    pub fn report(&self, mut out: impl Write) -> Result<(), Box<dyn Error>> {
+        serde_json::to_writer_pretty(&mut out, self)?;
+        out.write_all(b"\n")?;
+        out.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn runs_started_together_get_their_own_ids() {
+        let first = RunLog::start("a.csv");
+        let second = RunLog::start("a.csv");
+
+        assert_ne!(first.run_id, second.run_id);
+        assert!(first
+            .run_id
+            .starts_with(&format!("load-{}-{}-", first.started_at, process::id())));
+    }
+}
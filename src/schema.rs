@@ -0,0 +1,708 @@
+use std::fmt::{self, Write};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PropertyType {
+    Integer,
+    Float,
+    String,
+    FloatList,
+    StringList,
+    Boolean,
+}
+
+impl PropertyType {
+    fn mermaid_name(&self) -> &'static str {
+        match self {
+            PropertyType::Integer => "INTEGER",
+            PropertyType::Float => "FLOAT",
+            PropertyType::String => "STRING",
+            PropertyType::FloatList => "LIST~FLOAT~",
+            PropertyType::StringList => "LIST~STRING~",
+            PropertyType::Boolean => "BOOLEAN",
+        }
+    }
+}
+
+impl fmt::Display for PropertyType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PropertyType::Integer => f.write_str("INTEGER"),
+            PropertyType::Float => f.write_str("FLOAT"),
+            PropertyType::String => f.write_str("STRING"),
+            PropertyType::FloatList => f.write_str("LIST<FLOAT>"),
+            PropertyType::StringList => f.write_str("LIST<STRING>"),
+            PropertyType::Boolean => f.write_str("BOOLEAN"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Property {
+    pub name: &'static str,
+    pub kind: PropertyType,
+    pub description: &'static str,
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeSchema {
+    pub label: &'static str,
+    pub key: &'static str,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelationshipSchema {
+    pub rel_type: &'static str,
+    pub from: &'static str,
+    pub to: &'static str,
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone)]
+pub struct IndexSchema {
+    pub label: &'static str,
+    pub property: &'static str,
+    pub unique: bool,
+}
+
+/// The graph model the loader writes. Queries that create nodes build their
+/// property maps from here, so this is the single source of truth.
+#[derive(Debug, Clone)]
+pub struct GraphSchema {
+    pub nodes: Vec<NodeSchema>,
+    pub relationships: Vec<RelationshipSchema>,
+    pub indexes: Vec<IndexSchema>,
+}
+
+fn prop(name: &'static str, kind: PropertyType, description: &'static str) -> Property {
+    Property {
+        name,
+        kind,
+        description,
+    }
+}
+
+pub fn model() -> GraphSchema {
+    GraphSchema {
+        nodes: vec![
+            NodeSchema {
+                label: "Recipe",
+                key: "id",
+                properties: vec![
+                    prop("id", PropertyType::Integer, "Food.com recipe id"),
+                    prop("name", PropertyType::String, "Recipe title"),
+                    prop(
+                        "name_raw",
+                        PropertyType::String,
+                        "Title as read from the source",
+                    ),
+                    prop(
+                        "name_display",
+                        PropertyType::String,
+                        "Cleaned, cased title for display",
+                    ),
+                    prop("description", PropertyType::String, "Free-text description"),
+                    prop("minutes", PropertyType::Integer, "Total preparation time"),
+                    prop(
+                        "nutrition",
+                        PropertyType::FloatList,
+                        "Nutrition vector; scaled integers with --nutrition-storage fixed",
+                    ),
+                    prop(
+                        "nutrition_units",
+                        PropertyType::StringList,
+                        "Unit of each nutrition value: kcal, or pdv for % daily value",
+                    ),
+                    prop("calories", PropertyType::Float, "nutrition[0], kcal"),
+                    prop(
+                        "total_fat_pdv",
+                        PropertyType::Float,
+                        "nutrition[1], % daily value",
+                    ),
+                    prop(
+                        "sugar_pdv",
+                        PropertyType::Float,
+                        "nutrition[2], % daily value",
+                    ),
+                    prop(
+                        "sodium_pdv",
+                        PropertyType::Float,
+                        "nutrition[3], % daily value",
+                    ),
+                    prop(
+                        "protein_pdv",
+                        PropertyType::Float,
+                        "nutrition[4], % daily value",
+                    ),
+                    prop(
+                        "saturated_fat_pdv",
+                        PropertyType::Float,
+                        "nutrition[5], % daily value",
+                    ),
+                    prop(
+                        "carbohydrates_pdv",
+                        PropertyType::Float,
+                        "nutrition[6], % daily value",
+                    ),
+                    prop(
+                        "steps",
+                        PropertyType::StringList,
+                        "Instructions in order; absent with --steps-as-nodes",
+                    ),
+                    prop(
+                        "original_steps",
+                        PropertyType::StringList,
+                        "Steps as read, when fragments were merged",
+                    ),
+                    prop(
+                        "difficulty",
+                        PropertyType::String,
+                        "Derived: easy/medium/hard",
+                    ),
+                    prop(
+                        "time_bucket",
+                        PropertyType::String,
+                        "Derived: coarse duration band",
+                    ),
+                    prop(
+                        "completeness",
+                        PropertyType::Float,
+                        "Derived: share of populated fields",
+                    ),
+                    prop(
+                        "main_ingredient",
+                        PropertyType::String,
+                        "Derived: ingredient the dish is built around",
+                    ),
+                    prop(
+                        "course",
+                        PropertyType::String,
+                        "Derived: breakfast/lunch/dinner/dessert/snack",
+                    ),
+                    prop(
+                        "avg_words_per_step",
+                        PropertyType::Float,
+                        "Derived: mean words per instruction",
+                    ),
+                    prop(
+                        "reading_level",
+                        PropertyType::Float,
+                        "Derived: Flesch-Kincaid grade of the steps",
+                    ),
+                    prop(
+                        "imperative_density",
+                        PropertyType::Float,
+                        "Derived: share of steps opening with a verb",
+                    ),
+                    prop(
+                        "source_url",
+                        PropertyType::String,
+                        "Where the recipe was published",
+                    ),
+                    prop("author", PropertyType::String, "Credited author"),
+                    prop("license", PropertyType::String, "Redistribution license"),
+                    prop(
+                        "content_hash",
+                        PropertyType::String,
+                        "Hash of the source content, for change detection",
+                    ),
+                    prop(
+                        "truncated_fields",
+                        PropertyType::StringList,
+                        "Fields shortened by the overflow policy",
+                    ),
+                    prop(
+                        "servings",
+                        PropertyType::Integer,
+                        "Servings from the source, else inferred",
+                    ),
+                    prop(
+                        "servings_inferred",
+                        PropertyType::Boolean,
+                        "Servings came from a yield phrase or ingredient weight",
+                    ),
+                    prop(
+                        "estimated_cost",
+                        PropertyType::Float,
+                        "Cost from the --prices list, if given",
+                    ),
+                    prop(
+                        "estimated_cost_per_serving",
+                        PropertyType::Float,
+                        "estimated_cost divided by servings, when servings are known",
+                    ),
+                    prop(
+                        "techniques",
+                        PropertyType::StringList,
+                        "Cooking techniques flagged in PP_recipes.csv",
+                    ),
+                    prop(
+                        "calorie_level",
+                        PropertyType::String,
+                        "low, medium or high, from PP_recipes.csv",
+                    ),
+                    prop(
+                        "placeholder",
+                        PropertyType::Boolean,
+                        "Only an id, created for an interaction by --create-missing-recipes",
+                    ),
+                    prop(
+                        "trending_score",
+                        PropertyType::Float,
+                        "Derived: recent ratings weighted by age, by analyze trending",
+                    ),
+                    prop(
+                        "trending_as_of",
+                        PropertyType::String,
+                        "Day trending_score was computed for, as YYYY-MM-DD",
+                    ),
+                ],
+            },
+            NodeSchema {
+                label: "Ingredient",
+                key: "key",
+                properties: vec![
+                    prop(
+                        "key",
+                        PropertyType::String,
+                        "Surrogate key: hash of the id namespace, label and name",
+                    ),
+                    prop("name", PropertyType::String, "Canonical ingredient name"),
+                    prop(
+                        "aliases",
+                        PropertyType::StringList,
+                        "Names as written that were canonicalized to this one",
+                    ),
+                    prop(
+                        "recipe_count",
+                        PropertyType::Integer,
+                        "Recipes containing it, recounted after each load",
+                    ),
+                    prop(
+                        "emoji",
+                        PropertyType::String,
+                        "Emoji for UIs, from the bundled icon table",
+                    ),
+                    prop(
+                        "icon",
+                        PropertyType::String,
+                        "Icon identifier: the emoji's short name, kebab case",
+                    ),
+                    prop(
+                        "wikidata_qid",
+                        PropertyType::String,
+                        "Wikidata item, set by enrich wikidata",
+                    ),
+                    prop(
+                        "image_url",
+                        PropertyType::String,
+                        "Wikimedia Commons image of the Wikidata item",
+                    ),
+                    prop(
+                        "wikidata_instance_of",
+                        PropertyType::StringList,
+                        "QIDs the item is an instance of",
+                    ),
+                    prop(
+                        "wikidata_subclass_of",
+                        PropertyType::StringList,
+                        "QIDs of its parent classes and parent taxa",
+                    ),
+                ],
+            },
+            NodeSchema {
+                label: "Tag",
+                key: "key",
+                properties: vec![
+                    prop(
+                        "key",
+                        PropertyType::String,
+                        "Surrogate key: hash of the id namespace, label and name",
+                    ),
+                    prop("name", PropertyType::String, "Food.com tag"),
+                ],
+            },
+            NodeSchema {
+                label: "Course",
+                key: "key",
+                properties: vec![
+                    prop(
+                        "key",
+                        PropertyType::String,
+                        "Surrogate key: hash of the id namespace, label and name",
+                    ),
+                    prop("name", PropertyType::String, "Course name"),
+                ],
+            },
+            NodeSchema {
+                label: "Step",
+                key: "key",
+                properties: vec![
+                    prop(
+                        "key",
+                        PropertyType::String,
+                        "Surrogate key: hash of the id namespace, recipe id and index",
+                    ),
+                    prop(
+                        "index",
+                        PropertyType::Integer,
+                        "Position in the recipe, from 0",
+                    ),
+                    prop("text", PropertyType::String, "The instruction"),
+                ],
+            },
+            NodeSchema {
+                label: "RecipeTranslation",
+                key: "key",
+                properties: vec![
+                    prop(
+                        "key",
+                        PropertyType::String,
+                        "Surrogate key: hash of the id namespace, recipe id and language",
+                    ),
+                    prop("lang", PropertyType::String, "Language translated into"),
+                    prop("name", PropertyType::String, "Translated title"),
+                    prop(
+                        "description",
+                        PropertyType::String,
+                        "Translated description",
+                    ),
+                    prop("steps", PropertyType::StringList, "Translated steps"),
+                ],
+            },
+            NodeSchema {
+                label: "SavedSearch",
+                key: "name",
+                properties: vec![
+                    prop("name", PropertyType::String, "Name given by query save"),
+                    prop(
+                        "filter",
+                        PropertyType::String,
+                        "Search terms, such as tag:healthy max-minutes:30",
+                    ),
+                    prop(
+                        "saved_at",
+                        PropertyType::String,
+                        "Day last saved, as YYYY-MM-DD",
+                    ),
+                ],
+            },
+            NodeSchema {
+                label: "User",
+                key: "id",
+                properties: vec![
+                    prop("id", PropertyType::Integer, "Food.com user id"),
+                    prop(
+                        "ratings_count",
+                        PropertyType::Integer,
+                        "Analyzed: recipes rated",
+                    ),
+                    prop(
+                        "avg_rating",
+                        PropertyType::Float,
+                        "Analyzed: mean rating given",
+                    ),
+                    prop(
+                        "favorite_tags",
+                        PropertyType::StringList,
+                        "Analyzed: most rated tags",
+                    ),
+                ],
+            },
+        ],
+        relationships: vec![
+            RelationshipSchema {
+                rel_type: "CONTAINS",
+                from: "Recipe",
+                to: "Ingredient",
+                properties: vec![
+                    prop(
+                        "order",
+                        PropertyType::Integer,
+                        "Position in the author's ingredient list",
+                    ),
+                    prop(
+                        "quantity_min",
+                        PropertyType::Float,
+                        "Lower bound of the amount",
+                    ),
+                    prop(
+                        "quantity_max",
+                        PropertyType::Float,
+                        "Upper bound (equals min if exact)",
+                    ),
+                    prop("unit", PropertyType::String, "Unit of the amount, if any"),
+                    prop("to_taste", PropertyType::Boolean, "Amount left to the cook"),
+                    prop(
+                        "brand",
+                        PropertyType::String,
+                        "Brand named in the line, if any",
+                    ),
+                    prop(
+                        "optional",
+                        PropertyType::Boolean,
+                        "Marked optional in the line",
+                    ),
+                    prop(
+                        "garnish",
+                        PropertyType::Boolean,
+                        "Only for garnish or serving",
+                    ),
+                ],
+            },
+            RelationshipSchema {
+                rel_type: "TAGGED",
+                from: "Recipe",
+                to: "Tag",
+                properties: Vec::new(),
+            },
+            RelationshipSchema {
+                rel_type: "HAS_STEP",
+                from: "Recipe",
+                to: "Step",
+                properties: vec![prop(
+                    "index",
+                    PropertyType::Integer,
+                    "Position of the step, from 0",
+                )],
+            },
+            RelationshipSchema {
+                rel_type: "NEXT",
+                from: "Step",
+                to: "Step",
+                properties: Vec::new(),
+            },
+            RelationshipSchema {
+                rel_type: "HAS_TRANSLATION",
+                from: "Recipe",
+                to: "RecipeTranslation",
+                properties: Vec::new(),
+            },
+            RelationshipSchema {
+                rel_type: "SUGGESTED_TAG",
+                from: "Recipe",
+                to: "Tag",
+                properties: vec![prop(
+                    "confidence",
+                    PropertyType::Float,
+                    "From analyze suggest-tags, 0 to 1",
+                )],
+            },
+            RelationshipSchema {
+                rel_type: "USES_COMPONENT",
+                from: "Recipe",
+                to: "Recipe",
+                properties: vec![prop(
+                    "ingredient",
+                    PropertyType::String,
+                    "Ingredient line naming the component, from analyze components",
+                )],
+            },
+            RelationshipSchema {
+                rel_type: "RATED",
+                from: "User",
+                to: "Recipe",
+                properties: vec![
+                    prop("rating", PropertyType::Integer, "Rating from 0 to 5"),
+                    prop("date", PropertyType::String, "Date of the review"),
+                    prop("review", PropertyType::String, "Review text"),
+                    prop("split", PropertyType::String, "train/test, set by ml split"),
+                ],
+            },
+        ],
+        indexes: vec![
+            IndexSchema {
+                label: "Recipe",
+                property: "id",
+                unique: true,
+            },
+            IndexSchema {
+                label: "User",
+                property: "id",
+                unique: false,
+            },
+            IndexSchema {
+                label: "Ingredient",
+                property: "key",
+                unique: true,
+            },
+            IndexSchema {
+                label: "Ingredient",
+                property: "name",
+                unique: false,
+            },
+            IndexSchema {
+                label: "Tag",
+                property: "key",
+                unique: true,
+            },
+            IndexSchema {
+                label: "Tag",
+                property: "name",
+                unique: false,
+            },
+            IndexSchema {
+                label: "Course",
+                property: "key",
+                unique: true,
+            },
+            IndexSchema {
+                label: "Step",
+                property: "key",
+                unique: true,
+            },
+            IndexSchema {
+                label: "RecipeTranslation",
+                property: "key",
+                unique: true,
+            },
+        ],
+    }
+}
+
+impl GraphSchema {
+    /// The schema as `markdown` or a `mermaid` diagram.
+    pub fn describe(&self, format: &str) -> Result<String, String> {
+        match format {
+            "markdown" => Ok(self.to_markdown()),
+            "mermaid" => Ok(self.to_mermaid()),
+            other => Err(format!("unknown schema format: {}", other)),
+        }
+    }
+
+    pub fn node(&self, label: &str) -> Option<&NodeSchema> {
+        self.nodes.iter().find(|node| node.label == label)
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Graph schema\n");
+
+        for node in &self.nodes {
+            let _ = write!(out, "\n## (:{})\n\n", node.label);
+            write_property_table(&mut out, &node.properties, node.key);
+        }
+
+        for rel in &self.relationships {
+            let _ = write!(
+                out,
+                "\n## (:{})-[:{}]->(:{})\n\n",
+                rel.from, rel.rel_type, rel.to
+            );
+            write_property_table(&mut out, &rel.properties, "");
+        }
+
+        out.push_str("\n## Indexes\n\n");
+        if self.indexes.is_empty() {
+            out.push_str("_none_\n");
+        }
+        for index in &self.indexes {
+            let kind = if index.unique { "unique" } else { "range" };
+            let _ = writeln!(out, "- `:{}({})` ({})", index.label, index.property, kind);
+        }
+
+        let _ = write!(
+            out,
+            "\n## Diagram\n\n```mermaid\n{}```\n",
+            self.to_mermaid()
+        );
+        out
+    }
+
+    pub fn to_mermaid(&self) -> String {
+        let mut out = String::from("erDiagram\n");
+
+        for node in &self.nodes {
+            let _ = writeln!(out, "    {} {{", node.label);
+            for property in &node.properties {
+                let key = if property.name == node.key { " PK" } else { "" };
+                let _ = writeln!(
+                    out,
+                    "        {} {}{}",
+                    property.kind.mermaid_name(),
+                    property.name,
+                    key
+                );
+            }
+            out.push_str("    }\n");
+        }
+
+        for rel in &self.relationships {
+            let _ = writeln!(
+                out,
+                "    {} }}o--o{{ {} : {}",
+                rel.from, rel.to, rel.rel_type
+            );
+        }
+
+        out
+    }
+}
+
+impl IndexSchema {
+    fn name(&self) -> String {
+        format!("{}_{}", self.label.to_lowercase(), self.property)
+    }
+
+    /// Drops what earlier versions created for the property when it has
+    /// since changed kind: the plain index of a property now unique, or the
+    /// constraint of one no longer unique. Neo4j refuses the new one while
+    /// the old exists.
+    pub fn drop_replaced_statement(&self) -> String {
+        if self.unique {
+            format!("DROP INDEX {} IF EXISTS", self.name())
+        } else {
+            format!("DROP CONSTRAINT {}_unique IF EXISTS", self.name())
+        }
+    }
+
+    pub fn create_statement(&self) -> String {
+        let name = self.name();
+        if self.unique {
+            format!(
+                "CREATE CONSTRAINT {}_unique IF NOT EXISTS FOR (n:{}) REQUIRE n.{} IS UNIQUE",
+                name, self.label, self.property
+            )
+        } else {
+            format!(
+                "CREATE INDEX {} IF NOT EXISTS FOR (n:{}) ON (n.{})",
+                name, self.label, self.property
+            )
+        }
+    }
+}
+
+impl NodeSchema {
+    /// Renders `{id: row.id, name: row.name, ...}` for CREATE/MERGE queries
+    /// over `UNWIND $rows AS row`, with `row` as the variable name. Only
+    /// properties `written` accepts are included.
+    pub fn property_map(&self, row: &str, written: impl Fn(&str) -> bool) -> String {
+        let fields: Vec<String> = self
+            .properties
+            .iter()
+            .filter(|property| written(property.name))
+            .map(|property| format!("{0}: {1}.{0}", property.name, row))
+            .collect();
+        format!("{{{}}}", fields.join(", "))
+    }
+}
+
+fn write_property_table(out: &mut String, properties: &[Property], key: &str) {
+    if properties.is_empty() {
+        out.push_str("_no properties_\n");
+        return;
+    }
+
+    out.push_str("| Property | Type | Description |\n|---|---|---|\n");
+    for property in properties {
+        let name = if property.name == key {
+            format!("`{}` (key)", property.name)
+        } else {
+            format!("`{}`", property.name)
+        };
+        let _ = writeln!(
+            out,
+            "| {} | {} | {} |",
+            name, property.kind, property.description
+        );
+    }
+}
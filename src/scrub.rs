@@ -0,0 +1,346 @@
+use crate::output;
+use crate::Recipe;
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// A kind of text the scrubbing pass can redact. Each match is replaced by
+/// the category's name in brackets, such as `[email]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Category {
+    Email,
+    Url,
+    Phone,
+    /// A capitalized name after a family or friend word: "my husband Tom",
+    /// "Aunt Rose". Dumps stored in lowercase need patterns of their own.
+    Name,
+    Profanity,
+}
+
+pub const CATEGORIES: [Category; 5] = [
+    Category::Email,
+    Category::Url,
+    Category::Phone,
+    Category::Name,
+    Category::Profanity,
+];
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Email => "email",
+            Category::Url => "url",
+            Category::Phone => "phone",
+            Category::Name => "name",
+            Category::Profanity => "profanity",
+        }
+    }
+
+    /// The bundled patterns, in the order they run. Where a pattern has a
+    /// group, only the group is redacted.
+    fn builtin(&self) -> &'static [&'static str] {
+        match self {
+            Category::Email => &[r"(?i)\b[a-z0-9._%+-]+@[a-z0-9-]+(?:\.[a-z0-9-]+)*\.[a-z]{2,}\b"],
+            Category::Url => &[
+                r#"(?i)\b(?:https?://|www\.)[^\s<>"]*[^\s<>".,;:!?)\]]"#,
+                r#"(?i)\b[a-z0-9-]+(?:\.[a-z0-9-]+)*\.(?:com|net|org|edu|gov|co\.uk)\b(?:/[^\s<>"]*[^\s<>".,;:!?)\]])?"#,
+            ],
+            Category::Phone => &[r"(?:\+?1[-. ]?)?(?:\(\d{3}\) ?|\b\d{3}[-. ])\d{3}[-. ]\d{4}\b"],
+            Category::Name => &[
+                r"\b(?i:my|our)\s+(?i:husband|wife|partner|friend|son|daughter|mom|mother|dad|father|sister|brother|aunt|uncle|cousin|grandma|grandmother|grandpa|grandfather|neighbor|neighbour|boss|boyfriend|girlfriend|fiance|fiancee|kids?)\s+([A-Z][a-z]+)",
+                r"\b(?:Aunt|Uncle|Grandma|Grandpa|Granny|Nana|Mama|Papa|Cousin)\s+([A-Z][a-z]+)",
+            ],
+            Category::Profanity => &[
+                r"(?i)\b(?:damn(?:ed|it)?|dammit|crap(?:py)?|shit(?:ty)?|bullshit|fuck(?:ing|ed)?|bitch(?:y)?|bastard|ass(?:hole)?|piss(?:ed)?)\b",
+            ],
+        }
+    }
+}
+
+impl FromStr for Category {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CATEGORIES
+            .iter()
+            .find(|category| category.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let names: Vec<&str> = CATEGORIES.iter().map(Category::name).collect();
+                format!(
+                    "unknown scrub category: {} (expected {} or all)",
+                    s,
+                    names.join(", ")
+                )
+            })
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The categories in a comma-separated `list`, where `all` means every one.
+pub fn categories(list: &str) -> Result<Vec<Category>, String> {
+    let mut found = Vec::new();
+    for name in list
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+    {
+        if name == "all" {
+            found.extend(CATEGORIES);
+        } else {
+            found.push(name.parse()?);
+        }
+    }
+    found.sort();
+    found.dedup();
+    Ok(found)
+}
+
+/// Extra scrubbing patterns for a deployment, read from TOML:
+///
+/// ```toml
+/// builtin = true            # keep the bundled patterns (default)
+/// [patterns]
+/// name = ["(?i)\\bchef (\\w+)"]
+/// profanity = ["(?i)\\bfrick\\b"]
+/// ```
+///
+/// Patterns are regular expressions in the `regex` crate's syntax. Only
+/// the categories a run enables are applied.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PatternsFile {
+    pub builtin: Option<bool>,
+    pub patterns: HashMap<String, Vec<String>>,
+}
+
+/// Redacts text in the enabled categories from descriptions and steps, for
+/// deployments that republish the data.
+#[derive(Debug, Clone)]
+pub struct Scrubber {
+    rules: Vec<(Category, Regex)>,
+}
+
+/// `text` with every match of `regex` replaced by `mark`, or only its
+/// first group where it has one, and the number of replacements.
+fn redact(regex: &Regex, text: &str, mark: &str) -> (String, usize) {
+    let grouped = regex.captures_len() > 1;
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut count = 0;
+    for captures in regex.captures_iter(text) {
+        let found = if grouped {
+            captures.get(1)
+        } else {
+            captures.get(0)
+        };
+        let Some(found) = found else {
+            continue;
+        };
+        out.push_str(&text[last..found.start()]);
+        out.push_str(mark);
+        last = found.end();
+        count += 1;
+    }
+    if count == 0 {
+        return (text.to_string(), 0);
+    }
+    out.push_str(&text[last..]);
+    (out, count)
+}
+
+impl Scrubber {
+    /// The bundled patterns for `categories`.
+    pub fn new(categories: &[Category]) -> Scrubber {
+        Scrubber::from_file(categories, PatternsFile::default())
+            .expect("bundled scrub patterns compile")
+    }
+
+    /// The patterns for `categories` from the file at `path`, after the
+    /// bundled ones unless the file turns them off.
+    pub fn load(categories: &[Category], path: &Path) -> Result<Scrubber, Box<dyn Error>> {
+        let file: PatternsFile = toml::from_str(&output::read_text(path)?)?;
+        Scrubber::from_file(categories, file)
+    }
+
+    fn from_file(categories: &[Category], file: PatternsFile) -> Result<Scrubber, Box<dyn Error>> {
+        for name in file.patterns.keys() {
+            name.parse::<Category>()?;
+        }
+        let mut rules = Vec::new();
+        for category in categories {
+            let builtin = if file.builtin.unwrap_or(true) {
+                category.builtin()
+            } else {
+                &[]
+            };
+            let extra = file
+                .patterns
+                .get(category.name())
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            for pattern in builtin
+                .iter()
+                .copied()
+                .chain(extra.iter().map(String::as_str))
+            {
+                let regex = Regex::new(pattern)
+                    .map_err(|err| format!("bad {} pattern {}: {}", category, pattern, err))?;
+                rules.push((*category, regex));
+            }
+        }
+        Ok(Scrubber { rules })
+    }
+
+    /// `text` with every enabled category redacted, and the category of
+    /// each redaction made.
+    pub fn scrub(&self, text: &str) -> (String, Vec<Category>) {
+        let mut text = text.to_string();
+        let mut redacted = Vec::new();
+        for (category, regex) in &self.rules {
+            let (scrubbed, count) = redact(regex, &text, &format!("[{}]", category));
+            if count > 0 {
+                text = scrubbed;
+                redacted.extend(std::iter::repeat_n(*category, count));
+            }
+        }
+        (text, redacted)
+    }
+
+    /// Scrubs the description and steps of `recipe` in place, including
+    /// steps kept from before step repair, and returns each redaction
+    /// with the field it was made in.
+    pub fn apply(&self, recipe: &mut Recipe) -> Vec<(Category, &'static str)> {
+        let mut redactions = Vec::new();
+        let mut scrub = |field: &'static str, text: &mut String| {
+            let (scrubbed, found) = self.scrub(text);
+            if !found.is_empty() {
+                *text = scrubbed;
+                redactions.extend(found.into_iter().map(|category| (category, field)));
+            }
+        };
+        scrub("description", &mut recipe.description);
+        for step in recipe.steps.iter_mut() {
+            scrub("steps", step);
+        }
+        for step in recipe.original_steps.iter_mut() {
+            scrub("original_steps", step);
+        }
+        redactions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn scrub(categories: &[Category], text: &str) -> (String, Vec<Category>) {
+        Scrubber::new(categories).scrub(text)
+    }
+
+    #[test]
+    fn contact_details_are_redacted() {
+        assert_eq!(
+            scrub(
+                &CATEGORIES,
+                "mail jo.smith+food@example.co.uk or call (555) 123-4567"
+            ),
+            (
+                "mail [email] or call [phone]".to_string(),
+                vec![Category::Email, Category::Phone]
+            )
+        );
+        assert_eq!(
+            scrub(&[Category::Url], "from https://food.com/recipe/12. Enjoy!").0,
+            "from [url]. Enjoy!"
+        );
+        assert_eq!(
+            scrub(&[Category::Url], "see allrecipes.com for more").0,
+            "see [url] for more"
+        );
+    }
+
+    #[test]
+    fn only_the_name_after_a_relation_is_redacted() {
+        assert_eq!(
+            scrub(&[Category::Name], "My husband Tom and Aunt Rose love it").0,
+            "My husband [name] and Aunt [name] love it"
+        );
+        // No capitalized name to redact
+        assert_eq!(
+            scrub(&[Category::Name], "my husband loves it").1,
+            Vec::new()
+        );
+    }
+
+    #[test]
+    fn disabled_categories_are_left_alone() {
+        let text = "damn good, says bob@example.com";
+        assert_eq!(
+            scrub(&[Category::Profanity], text),
+            (
+                "[profanity] good, says bob@example.com".to_string(),
+                vec![Category::Profanity]
+            )
+        );
+        // Whole words only
+        assert!(scrub(&[Category::Profanity], "a glass of water")
+            .1
+            .is_empty());
+    }
+
+    #[test]
+    fn category_lists() {
+        assert_eq!(
+            categories("url, email,url").unwrap(),
+            vec![Category::Email, Category::Url]
+        );
+        assert_eq!(categories("all,phone").unwrap(), CATEGORIES.to_vec());
+        assert!(categories("emails").is_err());
+    }
+
+    #[test]
+    fn pattern_files_replace_or_extend_the_builtins() {
+        let file: PatternsFile =
+            toml::from_str("builtin = false\n[patterns]\nname = ['(?i)\\bchef (\\w+)']").unwrap();
+        let scrubber = Scrubber::from_file(&[Category::Name], file).unwrap();
+        assert_eq!(
+            scrubber.scrub("Chef Ana and Aunt Rose").0,
+            "Chef [name] and Aunt Rose"
+        );
+
+        let unknown: PatternsFile = toml::from_str("[patterns]\nnames = ['x']").unwrap();
+        assert!(Scrubber::from_file(&[Category::Name], unknown).is_err());
+    }
+
+    #[test]
+    fn apply_reports_the_field_of_each_redaction() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.description = "from grandma@example.com".to_string();
+        recipe.steps = vec!["stir".to_string(), "call 555-123-4567".to_string()];
+        recipe.original_steps = vec!["stir, damn it".to_string()];
+
+        let redactions = Scrubber::new(&CATEGORIES).apply(&mut recipe);
+
+        assert_eq!(
+            redactions,
+            vec![
+                (Category::Email, "description"),
+                (Category::Phone, "steps"),
+                (Category::Profanity, "original_steps"),
+            ]
+        );
+        assert_eq!(recipe.description, "from [email]");
+        assert_eq!(recipe.steps[1], "call [phone]");
+    }
+}
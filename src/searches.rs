@@ -0,0 +1,171 @@
+#[cfg(feature = "neo4j")]
+use neo4rs::{Graph, Query};
+#[cfg(feature = "neo4j")]
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A recipe search, written as space-separated `term:value` pairs:
+///
+/// ```text
+/// tag:healthy max-minutes:30 max-calories:400 ingredient:"olive oil"
+/// ```
+///
+/// `tag` and `ingredient` may repeat, and a recipe must have every one.
+/// Ingredients are kept as written and matched by canonical name under the
+/// run's rules, so "Tomatoes" finds "tomato". `min-rating` is the lowest average rating, counting only
+/// recipes rated at least once.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    pub tags: Vec<String>,
+    pub ingredients: Vec<String>,
+    pub max_minutes: Option<i64>,
+    pub max_calories: Option<f32>,
+    pub min_rating: Option<f64>,
+}
+
+/// Splits `text` at whitespace outside double quotes, dropping the quotes.
+fn terms(text: &str) -> Result<Vec<String>, String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in text.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if quoted {
+        return Err(format!("unclosed quote in filter: {}", text));
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+    Ok(terms)
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = Filter::default();
+        for term in terms(s)? {
+            let (name, value) = term
+                .split_once(':')
+                .filter(|(_, value)| !value.is_empty())
+                .ok_or_else(|| format!("filter terms look like tag:healthy, not {}", term))?;
+            let invalid = || format!("invalid {} in filter: {}", name, value);
+            match name {
+                "tag" => filter.tags.push(value.to_string()),
+                "ingredient" => filter.ingredients.push(value.to_string()),
+                "max-minutes" => filter.max_minutes = Some(value.parse().map_err(|_| invalid())?),
+                "max-calories" => filter.max_calories = Some(value.parse().map_err(|_| invalid())?),
+                "min-rating" => filter.min_rating = Some(value.parse().map_err(|_| invalid())?),
+                other => {
+                    return Err(format!(
+                        "unknown filter term: {} (expected tag, ingredient, max-minutes, \
+                         max-calories or min-rating)",
+                        other
+                    ))
+                }
+            }
+        }
+        if filter == Filter::default() {
+            return Err("a filter needs at least one term".to_string());
+        }
+        Ok(filter)
+    }
+}
+
+/// `value`, quoted if it holds whitespace.
+fn quote(value: &str) -> String {
+    if value.contains(char::is_whitespace) {
+        format!("\"{}\"", value)
+    } else {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for Filter {
+    /// The filter in the form it parses from, terms in a fixed order.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut terms: Vec<String> = Vec::new();
+        terms.extend(self.tags.iter().map(|tag| format!("tag:{}", quote(tag))));
+        terms.extend(
+            self.ingredients
+                .iter()
+                .map(|name| format!("ingredient:{}", quote(name))),
+        );
+        if let Some(max) = self.max_minutes {
+            terms.push(format!("max-minutes:{}", max));
+        }
+        if let Some(max) = self.max_calories {
+            terms.push(format!("max-calories:{}", max));
+        }
+        if let Some(min) = self.min_rating {
+            terms.push(format!("min-rating:{}", min));
+        }
+        f.write_str(&terms.join(" "))
+    }
+}
+
+/// A named filter kept in the graph as a `(:SavedSearch)` node, so every
+/// client of the graph shares it.
+#[derive(Debug, Clone)]
+pub struct SavedSearch {
+    pub name: String,
+    pub filter: Filter,
+}
+
+/// Saves `filter` as `name`, replacing any search saved under it.
+#[cfg(feature = "neo4j")]
+pub async fn save(graph: &Graph, name: &str, filter: &Filter) -> Result<(), Box<dyn Error>> {
+    graph
+        .run(
+            Query::new(
+                "MERGE (s:SavedSearch {name: $name}) SET s.filter = $filter, s.saved_at = $today"
+                    .to_string(),
+            )
+            .param("name", name)
+            .param("filter", filter.to_string())
+            .param("today", crate::query::today()),
+        )
+        .await?;
+    Ok(())
+}
+
+/// The search saved as `name`, if there is one.
+#[cfg(feature = "neo4j")]
+pub async fn load(graph: &Graph, name: &str) -> Result<Option<SavedSearch>, Box<dyn Error>> {
+    Ok(list(graph, Some(name)).await?.pop())
+}
+
+/// Every saved search by name, or only the one named `name`.
+#[cfg(feature = "neo4j")]
+pub async fn list(graph: &Graph, name: Option<&str>) -> Result<Vec<SavedSearch>, Box<dyn Error>> {
+    let mut result = graph
+        .execute(
+            Query::new(
+                "MATCH (s:SavedSearch) WHERE $name IS NULL OR s.name = $name \
+                 RETURN s.name AS name, s.filter AS filter ORDER BY s.name"
+                    .to_string(),
+            )
+            .param("name", name.map(str::to_string)),
+        )
+        .await?;
+    let mut searches = Vec::new();
+    while let Some(row) = result.next().await? {
+        let name: String = row.get("name")?;
+        let filter: String = row.get("filter")?;
+        let filter = filter
+            .parse()
+            .map_err(|err| format!("saved search {}: {}", name, err))?;
+        searches.push(SavedSearch { name, filter });
+    }
+    Ok(searches)
+}
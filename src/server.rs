@@ -0,0 +1,333 @@
+use neo4rs::{Graph, Query};
+use std::error::Error;
+use std::fmt;
+
+/// The oldest Neo4j release the generated Cypher runs on: `CREATE
+/// CONSTRAINT ... REQUIRE` and `SHOW PROCEDURES ... YIELD` arrived in 4.4.
+pub const MINIMUM: Version = Version(4, 4, 0);
+
+/// A Neo4j release as `dbms.components()` reports it. Calendar versions
+/// such as 2025.01 compare above every 5.x.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version(pub u32, pub u32, pub u32);
+
+impl Version {
+    /// Reads `5.26.0`, `5.27-aura` or `2025.01.0`, ignoring anything after
+    /// the digits of each part.
+    pub fn parse(text: &str) -> Option<Version> {
+        let mut parts = text.split(['.', '-']).map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse::<u32>().ok()
+        });
+        let major = parts.next().flatten()?;
+        let minor = parts.next().flatten().unwrap_or(0);
+        let patch = parts.next().flatten().unwrap_or(0);
+        Some(Version(major, minor, patch))
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.0, self.1, self.2)
+    }
+}
+
+/// Something the generated Cypher may use that not every server has.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Feature {
+    /// `EXISTS { ... }` anywhere an expression goes, from 5.0
+    ExistsSubquery,
+    /// `COUNT { ... }`, from 5.3
+    CountSubquery,
+    /// `COLLECT { ... }`, from 5.6
+    CollectSubquery,
+    /// `elementId()`, from 5.0
+    ElementId,
+    /// `CREATE VECTOR INDEX`, from 5.13
+    VectorIndex,
+    /// The APOC procedure library
+    Apoc,
+    /// The Graph Data Science library
+    Gds,
+}
+
+impl Feature {
+    pub const ALL: &'static [Feature] = &[
+        Feature::ExistsSubquery,
+        Feature::CountSubquery,
+        Feature::CollectSubquery,
+        Feature::ElementId,
+        Feature::VectorIndex,
+        Feature::Apoc,
+        Feature::Gds,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::ExistsSubquery => "EXISTS subqueries",
+            Feature::CountSubquery => "COUNT subqueries",
+            Feature::CollectSubquery => "COLLECT subqueries",
+            Feature::ElementId => "elementId()",
+            Feature::VectorIndex => "vector indexes",
+            Feature::Apoc => "APOC",
+            Feature::Gds => "Graph Data Science",
+        }
+    }
+}
+
+/// A feature the connected server lacks, for commands that cannot do
+/// without it.
+#[derive(Debug, thiserror::Error)]
+#[error("{what} needs {}, which Neo4j {version} ({edition}) does not have", .feature.name())]
+pub struct Unsupported {
+    pub what: String,
+    pub feature: Feature,
+    pub version: Version,
+    pub edition: String,
+}
+
+/// What the connected server runs and offers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Server {
+    pub version: Version,
+    /// `community` or `enterprise`
+    pub edition: String,
+    pub apoc: bool,
+    pub gds: bool,
+}
+
+impl Default for Server {
+    /// A current server without plugins, for Cypher written without one
+    /// detected.
+    fn default() -> Self {
+        Server {
+            version: Version(5, 26, 0),
+            edition: "community".to_string(),
+            apoc: false,
+            gds: false,
+        }
+    }
+}
+
+impl Server {
+    /// The version and edition, then whether each feature is available,
+    /// as `graph capabilities` prints them.
+    pub fn describe(&self) -> String {
+        let mut lines = vec![format!("Neo4j {} ({})", self.version, self.edition)];
+        for feature in Feature::ALL {
+            let available = if self.supports(*feature) { "yes" } else { "no" };
+            lines.push(format!("  {:<20} {}", feature.name(), available));
+        }
+        lines.join("\n")
+    }
+
+    pub fn supports(&self, feature: Feature) -> bool {
+        match feature {
+            Feature::ExistsSubquery | Feature::ElementId => self.version >= Version(5, 0, 0),
+            Feature::CountSubquery => self.version >= Version(5, 3, 0),
+            Feature::CollectSubquery => self.version >= Version(5, 6, 0),
+            Feature::VectorIndex => self.version >= Version(5, 13, 0),
+            Feature::Apoc => self.apoc,
+            Feature::Gds => self.gds,
+        }
+    }
+
+    /// Fails with an [`Unsupported`] naming `what` when the server lacks
+    /// `feature`.
+    pub fn require(&self, feature: Feature, what: &str) -> Result<(), Unsupported> {
+        if self.supports(feature) {
+            return Ok(());
+        }
+        Err(Unsupported {
+            what: what.to_string(),
+            feature,
+            version: self.version,
+            edition: self.edition.clone(),
+        })
+    }
+
+    /// A predicate that `pattern` matches: an `EXISTS` subquery, or on 4.4
+    /// the `exists()` function 5.0 removed.
+    pub fn exists(&self, pattern: &str) -> String {
+        if self.supports(Feature::ExistsSubquery) {
+            format!("EXISTS {{ {} }}", pattern)
+        } else {
+            format!("exists({})", pattern)
+        }
+    }
+
+    /// The number of matches of `pattern`: a `COUNT` subquery, or before 5.3
+    /// the size of a pattern comprehension.
+    pub fn count(&self, pattern: &str) -> String {
+        if self.supports(Feature::CountSubquery) {
+            format!("COUNT {{ {} }}", pattern)
+        } else {
+            format!("size([{} | 1])", pattern)
+        }
+    }
+
+    /// The list of `value` over the matches of `pattern`, in the order of
+    /// `index`: a `COLLECT` subquery, or before 5.6 a pattern comprehension
+    /// per position, which needs `index` to run 0, 1, 2... without gaps.
+    pub fn ordered_list(&self, pattern: &str, value: &str, index: &str) -> String {
+        if self.supports(Feature::CollectSubquery) {
+            format!(
+                "COLLECT {{ MATCH {} RETURN {} ORDER BY {} }}",
+                pattern, value, index
+            )
+        } else {
+            format!(
+                "[position IN range(0, {} - 1) | head([{} WHERE {} = position | {}])]",
+                self.count(pattern),
+                pattern,
+                index,
+                value
+            )
+        }
+    }
+
+    /// A stable id for the node or relationship `variable`, for ordering:
+    /// `elementId()`, or before 5.0 the `id()` 5.0 deprecated.
+    pub fn element_id(&self, variable: &str) -> String {
+        if self.supports(Feature::ElementId) {
+            format!("elementId({})", variable)
+        } else {
+            format!("id({})", variable)
+        }
+    }
+}
+
+/// Asks `graph` for its version, edition and plugins. Servers older than
+/// [`MINIMUM`] are refused. A server that will not list its procedures,
+/// as a user without the privilege may find, is taken to have no plugins.
+pub async fn detect(graph: &Graph) -> Result<Server, Box<dyn Error>> {
+    let mut result = graph
+        .execute(Query::new(
+            "CALL dbms.components() YIELD name, versions, edition \
+             WHERE name = 'Neo4j Kernel' \
+             RETURN versions[0] AS version, edition"
+                .to_string(),
+        ))
+        .await?;
+    let Some(row) = result.next().await? else {
+        return Err("the server did not report a Neo4j Kernel component".into());
+    };
+    let text: String = row.get("version")?;
+    let version =
+        Version::parse(&text).ok_or_else(|| format!("unreadable Neo4j version: {}", text))?;
+    let edition: String = row.get("edition")?;
+    if version < MINIMUM {
+        return Err(format!(
+            "Neo4j {} is not supported; {} needs {} or later",
+            version,
+            env!("CARGO_PKG_NAME"),
+            MINIMUM
+        )
+        .into());
+    }
+
+    let (apoc, gds) = plugins(graph).await.unwrap_or((false, false));
+    Ok(Server {
+        version,
+        edition,
+        apoc,
+        gds,
+    })
+}
+
+/// Whether APOC and GDS procedures are installed.
+async fn plugins(graph: &Graph) -> Result<(bool, bool), Box<dyn Error>> {
+    let mut result = graph
+        .execute(Query::new(
+            "SHOW PROCEDURES YIELD name \
+             WITH collect(name) AS names \
+             RETURN any(name IN names WHERE name STARTS WITH 'apoc.') AS apoc, \
+                    any(name IN names WHERE name STARTS WITH 'gds.') AS gds"
+                .to_string(),
+        ))
+        .await?;
+    let Some(row) = result.next().await? else {
+        return Ok((false, false));
+    };
+    Ok((row.get("apoc")?, row.get("gds")?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server(major: u32, minor: u32) -> Server {
+        Server {
+            version: Version(major, minor, 0),
+            ..Server::default()
+        }
+    }
+
+    #[test]
+    fn versions_parse_with_suffixes_and_calendar_numbers() {
+        assert_eq!(Version::parse("5.26.0"), Some(Version(5, 26, 0)));
+        assert_eq!(Version::parse("5.27-aura"), Some(Version(5, 27, 0)));
+        assert_eq!(Version::parse("4.4.12"), Some(Version(4, 4, 12)));
+        assert_eq!(Version::parse("5"), Some(Version(5, 0, 0)));
+        assert_eq!(Version::parse("unknown"), None);
+        let calendar = Version::parse("2025.01.0").unwrap();
+        assert_eq!(calendar, Version(2025, 1, 0));
+        assert!(calendar > Version(5, 26, 0));
+        assert!(Version(4, 3, 9) < MINIMUM && MINIMUM <= Version(4, 4, 12));
+    }
+
+    #[test]
+    fn subqueries_fall_back_to_pattern_expressions_on_older_servers() {
+        let (old, new) = (server(4, 4), server(5, 26));
+
+        assert_eq!(old.exists("(r)-[:TAGGED]->()"), "exists((r)-[:TAGGED]->())");
+        assert_eq!(
+            new.exists("(r)-[:TAGGED]->()"),
+            "EXISTS { (r)-[:TAGGED]->() }"
+        );
+        assert_eq!(
+            old.count("(r)-[:CONTAINS]->()"),
+            "size([(r)-[:CONTAINS]->() | 1])"
+        );
+        assert_eq!(
+            new.count("(r)-[:CONTAINS]->()"),
+            "COUNT { (r)-[:CONTAINS]->() }"
+        );
+        assert_eq!(old.element_id("x"), "id(x)");
+        assert_eq!(new.element_id("x"), "elementId(x)");
+    }
+
+    #[test]
+    fn ordered_lists_need_collect_from_five_six() {
+        let pattern = "(r)-[h:HAS_STEP]->(s:Step)";
+
+        assert_eq!(
+            server(5, 6).ordered_list(pattern, "s.text", "h.index"),
+            "COLLECT { MATCH (r)-[h:HAS_STEP]->(s:Step) RETURN s.text ORDER BY h.index }"
+        );
+        assert_eq!(
+            server(5, 5).ordered_list(pattern, "s.text", "h.index"),
+            "[position IN range(0, COUNT { (r)-[h:HAS_STEP]->(s:Step) } - 1) | \
+             head([(r)-[h:HAS_STEP]->(s:Step) WHERE h.index = position | s.text])]"
+        );
+        assert!(server(4, 4)
+            .ordered_list(pattern, "s.text", "h.index")
+            .starts_with("[position IN range(0, size([(r)-[h:HAS_STEP]->(s:Step) | 1]) - 1)"));
+    }
+
+    #[test]
+    fn missing_features_name_the_server() {
+        let err = server(4, 4)
+            .require(Feature::VectorIndex, "vector search")
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "vector search needs vector indexes, which Neo4j 4.4.0 (community) does not have"
+        );
+        assert!(server(5, 13)
+            .require(Feature::VectorIndex, "vector search")
+            .is_ok());
+        assert!(server(5, 26).require(Feature::Apoc, "export").is_err());
+    }
+}
@@ -0,0 +1,159 @@
+use crate::brands::Lexicon;
+use crate::ingredient::Rules;
+use crate::quantity;
+use crate::Recipe;
+
+/// Phrases whose following number is the yield: "serves 4", "makes 12
+/// muffins", "yield: 6".
+const BEFORE: &[&str] = &["serves", "serve", "makes", "yields", "yield", "feeds"];
+/// Words whose preceding number is the yield: "4 servings".
+const AFTER: &[&str] = &["servings", "serving", "portions", "people"];
+/// Filler allowed between a phrase and its number: "makes about 12".
+const SKIP: &[&str] = &["about", "approximately", "around", "roughly", "up", "to"];
+
+/// Grams of finished dish taken as one serving when sizing by weight.
+const GRAMS_PER_SERVING: f64 = 300.0;
+/// Inferred yields above this are treated as misreads.
+const MAX_SERVINGS: i64 = 48;
+
+fn number(word: &str) -> Option<i64> {
+    let word = word.trim_matches(|c: char| !c.is_alphanumeric() && c != '-');
+    // "4-6" serves at least four
+    let word = word.split(['-', '/']).next().unwrap_or(word);
+    let spelled = [
+        "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten", "eleven",
+        "twelve",
+    ];
+    match spelled.iter().position(|spelled| *spelled == word) {
+        Some(index) => Some(index as i64 + 1),
+        None => word.parse().ok(),
+    }
+    .filter(|servings| (1..=MAX_SERVINGS).contains(servings))
+}
+
+fn clean(word: &str) -> String {
+    word.trim_matches(|c: char| !c.is_alphanumeric())
+        .to_lowercase()
+}
+
+/// The yield stated in `text`, if any.
+pub fn from_text(text: &str) -> Option<i64> {
+    let words: Vec<String> = text
+        .split_whitespace()
+        .map(|word| word.to_lowercase())
+        .collect();
+    for (index, word) in words.iter().enumerate() {
+        if BEFORE.contains(&clean(word).as_str()) {
+            let found = words[index + 1..]
+                .iter()
+                .find(|next| !SKIP.contains(&clean(next).as_str()))
+                .and_then(|next| number(next));
+            if found.is_some() {
+                return found;
+            }
+        }
+        if index > 0 && AFTER.contains(&clean(word).as_str()) {
+            if let Some(servings) = number(&words[index - 1]) {
+                return Some(servings);
+            }
+        }
+    }
+    None
+}
+
+/// Sizes a recipe from the approximate weight of its ingredients, counting
+/// only lines whose amount can be weighed. Only amounts and units count, so
+/// names are left as written rather than run through a lexicon.
+pub fn from_mass(ingredients: &[String]) -> Option<i64> {
+    let (lexicon, rules) = (Lexicon::default(), Rules::default());
+    let grams: f64 = ingredients
+        .iter()
+        .filter_map(|line| quantity::parse_ingredient(line, &lexicon, &rules).approximate_grams())
+        .sum();
+    (grams > 0.0).then(|| ((grams / GRAMS_PER_SERVING).round() as i64).clamp(1, MAX_SERVINGS))
+}
+
+/// Servings from the source or a yield phrase in the description or steps,
+/// leaving out the weight-based guess.
+pub fn stated(recipe: &Recipe) -> Option<i64> {
+    recipe.servings.or_else(|| {
+        std::iter::once(&recipe.description)
+            .chain(&recipe.steps)
+            .find_map(|text| from_text(text))
+    })
+}
+
+/// Fills in `servings` when the source has none, from a yield phrase in the
+/// description or steps, else from ingredient weight, and marks it as
+/// inferred.
+pub fn infer(recipe: &mut Recipe) {
+    if recipe.servings.is_some() {
+        return;
+    }
+    recipe.servings = stated(recipe).or_else(|| from_mass(&recipe.ingredients));
+    recipe.servings_inferred = recipe.servings.is_some();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn lines(lines: &[&str]) -> Vec<String> {
+        lines.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn yield_phrases() {
+        assert_eq!(from_text("Serves 4."), Some(4));
+        assert_eq!(from_text("makes about 12 muffins"), Some(12));
+        assert_eq!(from_text("Yield: six"), Some(6));
+        assert_eq!(from_text("enough for 4-6 servings"), Some(4));
+        assert_eq!(from_text("feeds up to 8 people"), Some(8));
+    }
+
+    #[test]
+    fn numbers_that_are_not_yields() {
+        assert_eq!(from_text("bake 25 minutes"), None);
+        assert_eq!(from_text("makes a great lunch"), None);
+        assert_eq!(from_text("serves 500"), None);
+        assert_eq!(from_text("servings"), None);
+    }
+
+    #[test]
+    fn weight_sizes_the_dish() {
+        // 2 cups of rice and 2 eggs weigh 680 g
+        assert_eq!(
+            from_mass(&lines(&["2 cups rice", "2 eggs", "salt"])),
+            Some(2)
+        );
+        // A pinch is still one serving
+        assert_eq!(from_mass(&lines(&["1 pinch salt"])), Some(1));
+        assert_eq!(from_mass(&lines(&["salt", "pepper, to taste"])), None);
+        assert_eq!(from_mass(&lines(&["100 lbs flour"])), Some(MAX_SERVINGS));
+    }
+
+    #[test]
+    fn infer_prefers_stated_yields_and_marks_its_guess() {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.servings = Some(3);
+        recipe.description = "serves 8".to_string();
+        infer(&mut recipe);
+        assert_eq!(
+            (recipe.servings, recipe.servings_inferred),
+            (Some(3), false)
+        );
+
+        recipe.servings = None;
+        infer(&mut recipe);
+        assert_eq!((recipe.servings, recipe.servings_inferred), (Some(8), true));
+
+        recipe.servings = None;
+        recipe.description.clear();
+        recipe.steps = lines(&["mix"]);
+        recipe.ingredients = lines(&["3 cups flour"]);
+        assert_eq!(stated(&recipe), None);
+        infer(&mut recipe);
+        assert_eq!((recipe.servings, recipe.servings_inferred), (Some(2), true));
+    }
+}
@@ -0,0 +1,180 @@
+//! The options a run sets once and every stage reads: the connection
+//! profile, how list columns and nutrition are parsed and stored, the key
+//! namespace and the ingredient lexicons. The binary builds one `Settings`
+//! from its global options and passes it down; library callers build their
+//! own, starting from `Settings::default()`.
+
+use crate::brands::Lexicon;
+use crate::cancel::CancellationToken;
+use crate::config::Profile;
+use crate::ingredient::Rules;
+use crate::nutrition::Layout;
+use crate::parser::FloatOptions;
+use crate::precision::Precision;
+use crate::quantity::{self, IngredientLine};
+use crate::{keys, output, Recipe};
+use std::cell::Cell;
+use std::error::Error;
+use std::io::Read;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub profile: Profile,
+    /// How float lists such as nutrition are read
+    pub floats: FloatOptions,
+    pub layout: Layout,
+    pub precision: Precision,
+    /// Namespace for generated node keys; see `keys::surrogate`
+    pub namespace: String,
+    /// Brand names stripped from ingredient lines
+    pub lexicon: Lexicon,
+    /// Plurals, aliases and descriptors for ingredient names
+    pub rules: Rules,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            profile: Profile::default(),
+            floats: FloatOptions::default(),
+            layout: Layout::default(),
+            precision: Precision::default(),
+            namespace: keys::DEFAULT_NAMESPACE.to_string(),
+            lexicon: Lexicon::builtin(),
+            rules: Rules::builtin(),
+        }
+    }
+}
+
+/// The part of `Settings` that `Recipe`'s serde attributes see. Serde
+/// gives them no way to take an argument, so `Settings::scope` lends them
+/// these for the length of a call; outside one they get the defaults.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct SerdeSettings {
+    pub floats: FloatOptions,
+    pub layout: Layout,
+    pub precision: Precision,
+}
+
+thread_local! {
+    static SERDE: Cell<Option<SerdeSettings>> = const { Cell::new(None) };
+}
+
+/// The settings lent by the innermost `Settings::scope` on this thread.
+pub(crate) fn serde_settings() -> SerdeSettings {
+    SERDE.with(Cell::get).unwrap_or_default()
+}
+
+/// Puts back the settings of the enclosing scope, even when `f` panics.
+struct Restore(Option<SerdeSettings>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        SERDE.with(|cell| cell.set(self.0));
+    }
+}
+
+impl Settings {
+    /// Runs `f`, which reads or writes recipes with serde, with these
+    /// settings' float options, layout and precision.
+    pub fn scope<T>(&self, f: impl FnOnce() -> T) -> T {
+        let lent = SerdeSettings {
+            floats: self.floats,
+            layout: self.layout,
+            precision: self.precision,
+        };
+        let _restore = Restore(SERDE.with(|cell| cell.replace(Some(lent))));
+        f()
+    }
+
+    /// The recipes in `reader`, parsed with these settings.
+    pub fn recipes<'a, R: Read + 'a>(
+        &'a self,
+        reader: csv::Reader<R>,
+    ) -> impl Iterator<Item = csv::Result<Recipe>> + 'a {
+        let mut records = reader.into_deserialize();
+        std::iter::from_fn(move || self.scope(|| records.next()))
+    }
+
+    /// The recipes in the CSV at `input` (`-` for stdin), failing with
+    /// `Cancelled` once `cancel` fires.
+    pub fn recipes_in<'a>(
+        &'a self,
+        input: &Path,
+        cancel: &'a CancellationToken,
+    ) -> Result<impl Iterator<Item = Result<Recipe, Box<dyn Error>>> + 'a, Box<dyn Error>> {
+        let reader = csv::Reader::from_reader(output::open_source(input)?);
+        Ok(self.recipes(reader).map(|result| {
+            cancel.check()?;
+            Ok(result?)
+        }))
+    }
+
+    /// `line` split into quantity, unit and canonical name with this run's
+    /// lexicon and rules.
+    pub fn parse_ingredient(&self, line: &str) -> IngredientLine {
+        quantity::parse_ingredient(line, &self.lexicon, &self.rules)
+    }
+
+    /// The key of the `label` node named `text` in this run's namespace.
+    pub fn key(&self, label: &str, text: &str) -> String {
+        keys::surrogate(&self.namespace, label, text)
+    }
+
+    /// The Neo4j user, `neo4j` unless the profile names another.
+    pub fn user(&self) -> &str {
+        self.profile.user()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::NanPolicy;
+
+    #[test]
+    fn scopes_nest_and_unwind() {
+        let strict = Settings {
+            floats: FloatOptions {
+                nan: NanPolicy::Reject,
+                decimal_comma: true,
+            },
+            ..Settings::default()
+        };
+        let raw = Settings {
+            layout: Layout::Raw,
+            ..Settings::default()
+        };
+
+        strict.scope(|| {
+            raw.scope(|| assert_eq!(serde_settings().layout, Layout::Raw));
+            assert!(serde_settings().floats.decimal_comma);
+        });
+        assert!(!serde_settings().floats.decimal_comma);
+    }
+
+    #[test]
+    fn recipes_are_read_with_the_settings() {
+        let csv = "name,id,minutes,contributor_id,submitted,tags,nutrition,n_steps,steps,\
+                   description,ingredients,n_ingredients\n\
+                   pie,1,10,1,2001-01-01,[],\"[1,5; 2,0]\",1,['bake'],d,['flour'],1\n";
+        let settings = Settings {
+            floats: FloatOptions {
+                decimal_comma: true,
+                ..FloatOptions::default()
+            },
+            layout: Layout::Raw,
+            ..Settings::default()
+        };
+
+        let recipes: Vec<Recipe> = settings
+            .recipes(csv::Reader::from_reader(csv.as_bytes()))
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(recipes[0].nutrition, vec![1.5, 2.0]);
+        assert!(Settings::default()
+            .recipes(csv::Reader::from_reader(csv.as_bytes()))
+            .all(|recipe| recipe.is_err()));
+    }
+}
@@ -0,0 +1,361 @@
+use crate::graph::{Finished, GraphLoader, Writers};
+use crate::output::{self, OutputWriter};
+use crate::settings::Settings;
+use crate::Recipe;
+use neo4rs::{BoltType, Graph, Query};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::future::Future;
+use std::str::FromStr;
+
+/// Where `load` sends the recipes it has cleaned.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Output {
+    /// Written to the graph as they are read
+    #[default]
+    Neo4j,
+    /// One parsed `Recipe` per line, as `export --format jsonl` writes
+    Jsonl,
+    /// A MERGE script to apply later, as `export --format cypher` writes
+    Cypher,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "neo4j" => Ok(Output::Neo4j),
+            "jsonl" => Ok(Output::Jsonl),
+            "cypher" => Ok(Output::Cypher),
+            other => Err(format!("unknown output backend: {}", other)),
+        }
+    }
+}
+
+impl Output {
+    /// Where a file backend writes when no path is given.
+    pub fn default_path(&self) -> Option<&'static str> {
+        match self {
+            Output::Neo4j => None,
+            Output::Jsonl => Some("recipes.jsonl"),
+            Output::Cypher => Some("recipes.cypher"),
+        }
+    }
+}
+
+/// A backend for `load`. Recipes are queued one at a time and sent a batch
+/// at a time; each batch carries a `T` the caller gets back with its
+/// result, which may arrive later and out of order.
+pub trait Sink<T> {
+    /// Queues `recipe` for the next batch. An error rejects only this
+    /// recipe.
+    fn queue(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>>;
+
+    /// Whether a batch's worth of recipes is queued.
+    fn is_full(&self) -> bool;
+
+    /// Sends the queued recipes as one batch and returns the batches
+    /// finished since the last call. An error means the backend can take
+    /// nothing more.
+    fn submit(&mut self, tag: T) -> impl Future<Output = Result<Vec<Finished<T>>, Box<dyn Error>>>;
+
+    /// Waits for every batch sent, leaving the output open for more.
+    fn flush(&mut self) -> impl Future<Output = Result<Vec<Finished<T>>, Box<dyn Error>>>;
+
+    /// Waits for every batch sent and closes the output.
+    fn finish(&mut self) -> impl Future<Output = Result<Vec<Finished<T>>, Box<dyn Error>>>;
+
+    /// The graph written to, for the passes that run after a load.
+    fn graph(&self) -> Option<&Graph>;
+}
+
+/// The live backend: a `GraphLoader` builds the rows and `Writers` sends
+/// them.
+pub struct GraphSink<T> {
+    loader: GraphLoader,
+    writers: Writers<T>,
+}
+
+impl<T: Send + 'static> GraphSink<T> {
+    pub fn new(loader: GraphLoader, writers: Writers<T>) -> GraphSink<T> {
+        GraphSink { loader, writers }
+    }
+}
+
+impl<T: Send + 'static> Sink<T> for GraphSink<T> {
+    fn queue(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        self.loader.queue(recipe)
+    }
+
+    fn is_full(&self) -> bool {
+        self.loader.is_full()
+    }
+
+    async fn submit(&mut self, tag: T) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        let batch = self.loader.take_batch();
+        Ok(self.writers.submit(batch, tag).await?)
+    }
+
+    async fn flush(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(self.writers.finish().await?)
+    }
+
+    async fn finish(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(self.writers.finish().await?)
+    }
+
+    fn graph(&self) -> Option<&Graph> {
+        Some(self.loader.graph())
+    }
+}
+
+/// An offline backend writing each recipe through an export writer as it
+/// is queued, so `submit` returns its own batch as finished.
+pub struct FileSink {
+    writer: Option<Box<dyn OutputWriter>>,
+    batch_size: usize,
+    queued: Vec<i32>,
+}
+
+impl FileSink {
+    /// Writes `output` to `path`, where `-` means stdout. Cypher scripts
+    /// write steps as Step nodes with `steps_as_nodes`, as the live loader
+    /// would.
+    pub fn create(
+        output: Output,
+        path: &str,
+        batch_size: usize,
+        steps_as_nodes: bool,
+        settings: &Settings,
+    ) -> Result<FileSink, Box<dyn Error>> {
+        let sink = output::open_sink(path, false)?;
+        let writer = match output {
+            Output::Neo4j => return Err("neo4j is not a file backend".into()),
+            Output::Jsonl => output::writer(output::Format::Jsonl, sink, settings),
+            Output::Cypher => output::cypher(sink, steps_as_nodes, settings),
+        };
+        Ok(FileSink {
+            writer: Some(writer),
+            batch_size: batch_size.max(1),
+            queued: Vec::new(),
+        })
+    }
+}
+
+impl<T> Sink<T> for FileSink {
+    fn queue(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        let writer = self.writer.as_mut().ok_or("output already finished")?;
+        writer.write_recipe(recipe)?;
+        self.queued.push(recipe.id);
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.queued.len() >= self.batch_size
+    }
+
+    async fn submit(&mut self, tag: T) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(vec![(tag, Ok(std::mem::take(&mut self.queued)))])
+    }
+
+    async fn flush(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+
+    async fn finish(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        if let Some(writer) = self.writer.take() {
+            writer.finish()?;
+        }
+        Ok(Vec::new())
+    }
+
+    fn graph(&self) -> Option<&Graph> {
+        None
+    }
+}
+
+/// What a load would do to the nodes of one label.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Changes {
+    pub create: usize,
+    pub update: usize,
+    pub unchanged: usize,
+}
+
+/// The backend for `load --dry-run --compare`: writes nothing, and counts
+/// by label what the load would create, update or leave as it is in the
+/// live graph. A recipe is updated when its content hash differs from the
+/// stored one, and an ingredient when it would gain an alias; tags are
+/// only ever created. Ingredients and tags are counted where they first
+/// appear in the input.
+pub struct CompareSink {
+    graph: Graph,
+    batch_size: usize,
+    /// (id, content hash) of each queued recipe
+    recipes: Vec<(i32, Option<String>)>,
+    /// Spellings each queued ingredient was canonicalized from, by key
+    ingredients: HashMap<String, HashSet<String>>,
+    tags: HashSet<String>,
+    /// Ingredient and tag keys already counted
+    counted: HashSet<String>,
+    changes: BTreeMap<&'static str, Changes>,
+    settings: Settings,
+}
+
+impl CompareSink {
+    /// Compares against `graph` the recipes as `settings` would load them.
+    pub fn new(graph: Graph, batch_size: usize, settings: Settings) -> CompareSink {
+        CompareSink {
+            graph,
+            batch_size: batch_size.max(1),
+            recipes: Vec::new(),
+            ingredients: HashMap::new(),
+            tags: HashSet::new(),
+            counted: HashSet::new(),
+            changes: BTreeMap::new(),
+            settings,
+        }
+    }
+
+    /// The changes counted so far, by label.
+    pub fn changes(&self) -> &BTreeMap<&'static str, Changes> {
+        &self.changes
+    }
+
+    async fn compare_recipes(&mut self) -> Result<(), Box<dyn Error>> {
+        let rows: Vec<HashMap<String, BoltType>> = self
+            .recipes
+            .iter()
+            .map(|(id, hash)| {
+                let mut row = HashMap::new();
+                row.insert("id".to_string(), (*id).into());
+                row.insert("hash".to_string(), hash.clone().into());
+                row
+            })
+            .collect();
+        let mut result = self
+            .graph
+            .execute(
+                Query::new(
+                    "UNWIND $rows AS row OPTIONAL MATCH (r:Recipe {id: row.id}) \
+                     RETURN r IS NOT NULL AS found, \
+                            r.content_hash IS NOT NULL AND r.content_hash = row.hash AS same"
+                        .to_string(),
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        let changes = self.changes.entry("Recipe").or_default();
+        while let Some(row) = result.next().await? {
+            match (row.get::<bool>("found")?, row.get::<bool>("same")?) {
+                (false, _) => changes.create += 1,
+                (true, false) => changes.update += 1,
+                (true, true) => changes.unchanged += 1,
+            }
+        }
+        Ok(())
+    }
+
+    async fn compare_ingredients(&mut self) -> Result<(), Box<dyn Error>> {
+        let keys: Vec<String> = self.ingredients.keys().cloned().collect();
+        let mut result = self
+            .graph
+            .execute(
+                Query::new(
+                    "UNWIND $keys AS key OPTIONAL MATCH (i:Ingredient {key: key}) \
+                     RETURN key, i IS NOT NULL AS found, coalesce(i.aliases, []) AS aliases"
+                        .to_string(),
+                )
+                .param("keys", keys),
+            )
+            .await?;
+        let changes = self.changes.entry("Ingredient").or_default();
+        while let Some(row) = result.next().await? {
+            let key: String = row.get("key")?;
+            let aliases: Vec<String> = row.get("aliases")?;
+            let variants = self
+                .ingredients
+                .get(&key)
+                .ok_or("unexpected ingredient key")?;
+            if !row.get::<bool>("found")? {
+                changes.create += 1;
+            } else if variants.iter().any(|variant| !aliases.contains(variant)) {
+                changes.update += 1;
+            } else {
+                changes.unchanged += 1;
+            }
+        }
+        Ok(())
+    }
+
+    async fn compare_tags(&mut self) -> Result<(), Box<dyn Error>> {
+        let keys: Vec<String> = self.tags.iter().cloned().collect();
+        let mut result = self
+            .graph
+            .execute(
+                Query::new(
+                    "UNWIND $keys AS key OPTIONAL MATCH (t:Tag {key: key}) \
+                     RETURN count(key) - count(t) AS missing, count(t) AS found"
+                        .to_string(),
+                )
+                .param("keys", keys),
+            )
+            .await?;
+        let changes = self.changes.entry("Tag").or_default();
+        if let Some(row) = result.next().await? {
+            changes.create += row.get::<i64>("missing")? as usize;
+            changes.unchanged += row.get::<i64>("found")? as usize;
+        }
+        Ok(())
+    }
+}
+
+impl<T> Sink<T> for CompareSink {
+    fn queue(&mut self, recipe: &Recipe) -> Result<(), Box<dyn Error>> {
+        self.recipes.push((recipe.id, recipe.content_hash.clone()));
+        for line in &recipe.ingredients {
+            let line = self.settings.parse_ingredient(line);
+            let key = self.settings.key("Ingredient", &line.name);
+            if self.counted.contains(&key) {
+                continue;
+            }
+            let variants = self.ingredients.entry(key).or_default();
+            variants.extend(line.variant);
+        }
+        for tag in recipe.tags.iter().map(|tag| tag.trim()) {
+            let key = self.settings.key("Tag", tag);
+            if !tag.is_empty() && !self.counted.contains(&key) {
+                self.tags.insert(key);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.recipes.len() >= self.batch_size
+    }
+
+    async fn submit(&mut self, tag: T) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        self.compare_recipes().await?;
+        self.compare_ingredients().await?;
+        self.compare_tags().await?;
+        self.counted
+            .extend(self.ingredients.drain().map(|(key, _)| key));
+        self.counted.extend(self.tags.drain());
+        let ids = self.recipes.drain(..).map(|(id, _)| id).collect();
+        Ok(vec![(tag, Ok(ids))])
+    }
+
+    async fn flush(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+
+    async fn finish(&mut self) -> Result<Vec<Finished<T>>, Box<dyn Error>> {
+        Ok(Vec::new())
+    }
+
+    fn graph(&self) -> Option<&Graph> {
+        None
+    }
+}
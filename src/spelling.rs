@@ -0,0 +1,275 @@
+use crate::interner::{Interner, Symbol};
+use crate::output;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::path::Path;
+
+/// Largest edit distance a correction may span.
+const MAX_DISTANCE: usize = 2;
+/// Words shorter than this are too ambiguous to correct.
+const MIN_LENGTH: usize = 4;
+/// A replacement must be seen at least this often in the corpus.
+const MIN_COUNT: usize = 5;
+
+/// Every string reachable from `word` by deleting up to `distance`
+/// characters, including `word` itself.
+fn deletes(word: &str, distance: usize) -> HashSet<String> {
+    let mut found = HashSet::from([word.to_string()]);
+    let mut frontier = vec![word.to_string()];
+    for _ in 0..distance {
+        let mut next = Vec::new();
+        for current in &frontier {
+            let chars: Vec<char> = current.chars().collect();
+            for skip in 0..chars.len() {
+                let shorter: String = chars
+                    .iter()
+                    .enumerate()
+                    .filter(|(index, _)| *index != skip)
+                    .map(|(_, c)| *c)
+                    .collect();
+                if found.insert(shorter.clone()) {
+                    next.push(shorter);
+                }
+            }
+        }
+        frontier = next;
+    }
+    found
+}
+
+/// "tomato"/"tomatoes" differ by a plural, not a typo.
+fn is_plural_of(a: &str, b: &str) -> bool {
+    let (short, long) = if a.len() < b.len() { (a, b) } else { (b, a) };
+    long.strip_prefix(short)
+        .is_some_and(|suffix| suffix == "s" || suffix == "es")
+}
+
+/// Optimal string alignment distance: edits plus adjacent transpositions.
+fn distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+fn words(line: &str) -> impl Iterator<Item = String> + '_ {
+    line.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+}
+
+/// A SymSpell-style corrector for ingredient words, trained on the corpus
+/// being loaded: a rare word close to a much more common one is taken to be
+/// a misspelling of it ("tumeric" -> "turmeric").
+#[derive(Debug, Default)]
+pub struct SpellChecker {
+    words: Interner,
+    counts: HashMap<Symbol, usize>,
+    /// Each delete variant and the corpus words it was derived from
+    index: HashMap<String, Vec<Symbol>>,
+    whitelist: HashSet<String>,
+    /// Minimum `candidate / (candidate + word)` frequency share to correct
+    confidence: f64,
+}
+
+impl SpellChecker {
+    pub fn new(confidence: f64) -> SpellChecker {
+        SpellChecker {
+            confidence,
+            ..SpellChecker::default()
+        }
+    }
+
+    /// Reads words that must never be corrected, one per line.
+    pub fn load_whitelist(&mut self, path: &Path) -> Result<(), Box<dyn Error>> {
+        for line in output::read_text(path)?.lines() {
+            let word = line.trim().to_lowercase();
+            if !word.is_empty() && !word.starts_with('#') {
+                self.whitelist.insert(word);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn add(&mut self, line: &str) {
+        for word in words(line) {
+            let word = self.words.intern(&word);
+            *self.counts.entry(word).or_insert(0) += 1;
+        }
+    }
+
+    /// Builds the delete index once every line has been added.
+    pub fn index(&mut self) {
+        self.index.clear();
+        for (symbol, count) in &self.counts {
+            let word = self.words.resolve(*symbol);
+            if *count < MIN_COUNT || word.chars().count() < MIN_LENGTH {
+                continue;
+            }
+            for delete in deletes(word, MAX_DISTANCE) {
+                self.index.entry(delete).or_default().push(*symbol);
+            }
+        }
+    }
+
+    /// The correction for `word` and its confidence, if one clears the
+    /// threshold.
+    pub fn suggest(&self, word: &str) -> Option<(&str, f64)> {
+        if word.chars().count() < MIN_LENGTH || self.whitelist.contains(word) {
+            return None;
+        }
+        let count = self
+            .words
+            .get(word)
+            .and_then(|symbol| self.counts.get(&symbol))
+            .copied()
+            .unwrap_or(0);
+        // Common words are the corpus's own vocabulary, not typos
+        if count >= MIN_COUNT {
+            return None;
+        }
+
+        let mut candidates: HashSet<Symbol> = HashSet::new();
+        for delete in deletes(word, MAX_DISTANCE) {
+            for symbol in self.index.get(&delete).into_iter().flatten() {
+                let candidate = self.words.resolve(*symbol);
+                if candidate != word
+                    && !is_plural_of(word, candidate)
+                    && distance(word, candidate) <= MAX_DISTANCE
+                {
+                    candidates.insert(*symbol);
+                }
+            }
+        }
+
+        let (best, best_count) = candidates
+            .into_iter()
+            .map(|symbol| (self.words.resolve(symbol), self.counts[&symbol]))
+            .max_by(|(a, x), (b, y)| x.cmp(y).then(b.cmp(a)))?;
+        let confidence = best_count as f64 / (best_count + count) as f64;
+        (confidence >= self.confidence).then_some((best, confidence))
+    }
+
+    /// Rewrites misspelled words in an ingredient line, returning the new
+    /// line and a "from -> to (confidence)" note per change.
+    pub fn correct(&self, line: &str) -> (String, Vec<String>) {
+        let mut corrected = String::with_capacity(line.len());
+        let mut changes = Vec::new();
+        let mut word = String::new();
+
+        let mut flush = |word: &mut String, out: &mut String| {
+            if word.is_empty() {
+                return;
+            }
+            let lower = word.to_lowercase();
+            match self.suggest(&lower) {
+                Some((replacement, confidence)) => {
+                    changes.push(format!("{} -> {} ({:.2})", word, replacement, confidence));
+                    out.push_str(replacement);
+                }
+                None => out.push_str(word),
+            }
+            word.clear();
+        };
+
+        for c in line.chars() {
+            if c.is_alphabetic() {
+                word.push(c);
+            } else {
+                flush(&mut word, &mut corrected);
+                corrected.push(c);
+            }
+        }
+        flush(&mut word, &mut corrected);
+        (corrected, changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A checker trained on `common` ten times and `rare` once.
+    fn trained(common: &str, rare: &str, confidence: f64) -> SpellChecker {
+        let mut checker = SpellChecker::new(confidence);
+        for _ in 0..10 {
+            checker.add(common);
+        }
+        checker.add(rare);
+        checker.index();
+        checker
+    }
+
+    #[test]
+    fn distance_counts_transpositions_as_one_edit() {
+        assert_eq!(distance("tumeric", "turmeric"), 1);
+        assert_eq!(distance("teh", "the"), 1);
+        assert_eq!(distance("kitten", "sitting"), 3);
+        assert_eq!(distance("", "abc"), 3);
+        assert!(
+            deletes("abc", 1).is_superset(&HashSet::from(["ab".to_string(), "abc".to_string()]))
+        );
+    }
+
+    #[test]
+    fn rare_word_near_a_common_one_is_corrected() {
+        let checker = trained("1 tsp turmeric", "1 tsp tumeric", 0.9);
+
+        let (replacement, confidence) = checker.suggest("tumeric").unwrap();
+        assert_eq!(replacement, "turmeric");
+        assert!((confidence - 10.0 / 11.0).abs() < 1e-9);
+        // The corpus's own vocabulary is never corrected
+        assert_eq!(checker.suggest("turmeric"), None);
+    }
+
+    #[test]
+    fn below_the_confidence_threshold_is_left_alone() {
+        let checker = trained("1 tsp turmeric", "1 tsp tumeric", 0.95);
+        assert_eq!(checker.suggest("tumeric"), None);
+    }
+
+    #[test]
+    fn plurals_short_words_and_whitelisted_words_are_left_alone() {
+        let checker = trained("2 tomato", "3 tomatoes", 0.5);
+        assert_eq!(checker.suggest("tomatoes"), None);
+
+        let checker = trained("olive oil", "olive oli", 0.5);
+        assert_eq!(checker.suggest("oli"), None);
+
+        let path = std::env::temp_dir().join(format!("whitelist-{}.txt", std::process::id()));
+        std::fs::write(&path, "# house spellings\nTumeric\n").unwrap();
+        let mut checker = trained("1 tsp turmeric", "1 tsp tumeric", 0.5);
+        checker.load_whitelist(&path).unwrap();
+        assert_eq!(checker.suggest("tumeric"), None);
+    }
+
+    #[test]
+    fn correct_rewrites_words_and_keeps_the_rest_of_the_line() {
+        let checker = trained("1 tsp turmeric", "1 tsp tumeric", 0.9);
+
+        let (line, changes) = checker.correct("1/2 tsp Tumeric, ground");
+        assert_eq!(line, "1/2 tsp turmeric, ground");
+        assert_eq!(changes, ["Tumeric -> turmeric (0.91)"]);
+
+        let (line, changes) = checker.correct("2 cups turmeric");
+        assert_eq!(line, "2 cups turmeric");
+        assert!(changes.is_empty());
+    }
+}
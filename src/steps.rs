@@ -0,0 +1,101 @@
+/// Words that only make sense attached to the step before them, as when
+/// "preheat oven to 350" is followed by a step reading "f".
+const FRAGMENT_WORDS: &[&str] = &[
+    "f", "c", "degrees", "degree", "minutes", "minute", "mins", "min", "hours", "hour",
+];
+
+/// Whether a step is a piece of its neighbour rather than an instruction.
+fn is_fragment(step: &str) -> bool {
+    let step = step.trim();
+    if step.chars().filter(|c| c.is_alphanumeric()).count() <= 2 {
+        return true;
+    }
+    step.split_whitespace().all(|word| {
+        let word = word
+            .trim_matches(|c: char| !c.is_alphanumeric())
+            .to_lowercase();
+        word.is_empty() || word.parse::<f64>().is_ok() || FRAGMENT_WORDS.contains(&word.as_str())
+    })
+}
+
+/// Merges fragment steps into the step before them (or the one after, for
+/// a leading fragment). Returns the repaired steps and how many fragments
+/// were merged, or `None` when nothing needed repair.
+pub fn repair(steps: &[String]) -> Option<(Vec<String>, usize)> {
+    let mut repaired: Vec<String> = Vec::with_capacity(steps.len());
+    let mut pending: Option<String> = None;
+
+    for step in steps {
+        let step = step.trim();
+        if is_fragment(step) && (!repaired.is_empty() || steps.len() > 1) {
+            match repaired.last_mut() {
+                Some(previous) => {
+                    previous.push(' ');
+                    previous.push_str(step);
+                }
+                None => {
+                    let pending = pending.get_or_insert_with(String::new);
+                    if !pending.is_empty() {
+                        pending.push(' ');
+                    }
+                    pending.push_str(step);
+                }
+            }
+            continue;
+        }
+
+        match pending.take() {
+            Some(prefix) => repaired.push(format!("{} {}", prefix, step)),
+            None => repaired.push(step.to_string()),
+        }
+    }
+    if let Some(prefix) = pending {
+        repaired.push(prefix);
+    }
+
+    let merged = steps.len() - repaired.len();
+    (merged > 0).then_some((repaired, merged))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn steps(steps: &[&str]) -> Vec<String> {
+        steps.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn fragments_are_recognised() {
+        assert!(is_fragment("f"));
+        assert!(is_fragment(" 350 degrees. "));
+        assert!(is_fragment("15 minutes"));
+        assert!(!is_fragment("bake 20 minutes"));
+        assert!(!is_fragment("stir well"));
+    }
+
+    #[test]
+    fn fragments_join_the_step_before_them() {
+        let split = steps(&["preheat oven to 350", "f", "bake 20", " minutes "]);
+        assert_eq!(
+            repair(&split),
+            Some((steps(&["preheat oven to 350 f", "bake 20 minutes"]), 2))
+        );
+    }
+
+    #[test]
+    fn leading_fragments_join_the_step_after_them() {
+        assert_eq!(
+            repair(&steps(&["350", "degrees", "heat the oil"])),
+            Some((steps(&["350 degrees heat the oil"]), 2))
+        );
+        assert_eq!(repair(&steps(&["f", "c"])), Some((steps(&["f c"]), 1)));
+    }
+
+    #[test]
+    fn whole_steps_need_no_repair() {
+        assert_eq!(repair(&steps(&["chop the onion", "fry it"])), None);
+        assert_eq!(repair(&steps(&["f"])), None);
+        assert_eq!(repair(&[]), None);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::cancel::CancellationToken;
+use crate::interner::{Interner, Symbol};
+use crate::output;
+use crate::settings::Settings;
+use crate::Recipe;
+#[cfg(feature = "neo4j")]
+use neo4rs::{BoltType, Graph, Query};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+/// Title words too common to say anything about a recipe's tags.
+const STOP_WORDS: &[&str] = &[
+    "and", "with", "the", "for", "easy", "best", "recipe", "style", "quick", "homemade",
+];
+
+/// A tag proposed for a recipe, with the share of its evidence that
+/// co-occurs with the tag elsewhere in the corpus.
+#[derive(Debug, Clone, Serialize)]
+pub struct Suggestion {
+    pub recipe_id: i32,
+    pub tag: String,
+    /// Mean of P(tag | feature) over the recipe's known features, 0 to 1
+    pub confidence: f64,
+    /// Ingredients and title words the confidence rests on
+    pub features: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub suggestions: Vec<Suggestion>,
+    /// Tagged recipes the associations were learned from
+    pub learned_from: usize,
+    /// Recipes with at most `max_tags` tags
+    pub candidates: usize,
+}
+
+/// How suggestions are made; see `suggest`.
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// Recipes with this many tags or fewer get suggestions
+    pub max_tags: usize,
+    /// Fewest tagged recipes a feature needs before it counts as evidence
+    pub min_support: u32,
+    pub min_confidence: f64,
+    /// Most suggestions per recipe
+    pub per_recipe: usize,
+}
+
+/// `analyze suggest-tags`: suggests tags for the recipes in `input` and
+/// writes the suggestions to `out` as CSV.
+pub fn suggest_file(
+    input: &Path,
+    out: Box<dyn Write>,
+    settings: &Settings,
+    options: Options,
+    cancel: &CancellationToken,
+) -> Result<Report, Box<dyn Error>> {
+    let report = suggest(settings.recipes_in(input, cancel)?, settings, options)?;
+    output::write_csv(out, &report.suggestions)?;
+    eprintln!(
+        "{} suggestions for {} recipes with few tags, learned from {} tagged recipes",
+        report.suggestions.len(),
+        report.candidates,
+        report.learned_from
+    );
+    Ok(report)
+}
+
+/// The recipe's canonical ingredient names and title words, prefixed so an
+/// ingredient and a word with the same spelling stay apart.
+fn features(recipe: &Recipe, settings: &Settings) -> HashSet<String> {
+    let mut features: HashSet<String> = recipe
+        .ingredients
+        .iter()
+        .map(|line| format!("ingredient:{}", settings.parse_ingredient(line).name))
+        .collect();
+    features.extend(
+        recipe
+            .name
+            .to_lowercase()
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|word| word.len() > 2 && !STOP_WORDS.contains(word))
+            .map(|word| format!("word:{}", word)),
+    );
+    features
+}
+
+/// Learns how often each tag appears alongside each ingredient and title
+/// word across the tagged recipes, then proposes tags for recipes with few
+/// or none. A tag's confidence for a recipe is the mean, over the recipe's
+/// features seen in at least `min_support` tagged recipes, of the share of
+/// those recipes carrying the tag. Tags a recipe already has are not
+/// suggested again.
+pub fn suggest(
+    recipes: impl IntoIterator<Item = Result<Recipe, Box<dyn Error>>>,
+    settings: &Settings,
+    options: Options,
+) -> Result<Report, Box<dyn Error>> {
+    let mut report = Report::default();
+    let mut strings = Interner::default();
+    // feature -> tag -> recipes with both
+    let mut together: HashMap<Symbol, HashMap<Symbol, u32>> = HashMap::new();
+    let mut support: HashMap<Symbol, u32> = HashMap::new();
+    let mut candidates: Vec<(i32, Vec<Symbol>, HashSet<String>)> = Vec::new();
+
+    for recipe in recipes {
+        let recipe = recipe?;
+        let features: Vec<Symbol> = features(&recipe, settings)
+            .iter()
+            .map(|feature| strings.intern(feature))
+            .collect();
+        let tags: HashSet<String> = recipe
+            .tags
+            .iter()
+            .map(|tag| tag.trim().to_string())
+            .collect();
+
+        if !tags.is_empty() {
+            report.learned_from += 1;
+            let tag_symbols: Vec<Symbol> = tags.iter().map(|tag| strings.intern(tag)).collect();
+            for feature in &features {
+                *support.entry(*feature).or_insert(0) += 1;
+                let counts = together.entry(*feature).or_default();
+                for tag in &tag_symbols {
+                    *counts.entry(*tag).or_insert(0) += 1;
+                }
+            }
+        }
+        if tags.len() <= options.max_tags {
+            candidates.push((recipe.id, features, tags));
+        }
+    }
+    report.candidates = candidates.len();
+
+    for (recipe_id, features, tags) in candidates {
+        let known: Vec<Symbol> = features
+            .into_iter()
+            .filter(|feature| support.get(feature).copied().unwrap_or(0) >= options.min_support)
+            .collect();
+        if known.is_empty() {
+            continue;
+        }
+        let mut scores: HashMap<Symbol, f64> = HashMap::new();
+        for feature in &known {
+            let seen = support[feature] as f64;
+            for (tag, count) in &together[feature] {
+                *scores.entry(*tag).or_insert(0.0) += *count as f64 / seen;
+            }
+        }
+
+        let mut ranked: Vec<(&str, f64)> = scores
+            .into_iter()
+            .map(|(tag, score)| (strings.resolve(tag), score / known.len() as f64))
+            .filter(|(tag, confidence)| {
+                *confidence >= options.min_confidence && !tags.contains(*tag)
+            })
+            .collect();
+        ranked.sort_by(|(a_tag, a), (b_tag, b)| b.total_cmp(a).then(a_tag.cmp(b_tag)));
+        report
+            .suggestions
+            .extend(
+                ranked
+                    .into_iter()
+                    .take(options.per_recipe)
+                    .map(|(tag, confidence)| Suggestion {
+                        recipe_id,
+                        tag: tag.to_string(),
+                        confidence,
+                        features: known.len(),
+                    }),
+            );
+    }
+    Ok(report)
+}
+
+/// Writes `suggestions` as `(:Recipe)-[:SUGGESTED_TAG {confidence}]->(:Tag)`
+/// relationships, `batch_size` at a time, so they can be reviewed in the
+/// graph before being promoted to TAGGED. Recipes missing from the graph
+/// are skipped. Returns the number of suggestions sent.
+#[cfg(feature = "neo4j")]
+pub async fn write(
+    graph: &Graph,
+    settings: &Settings,
+    suggestions: &[Suggestion],
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut written = 0;
+    for batch in suggestions.chunks(batch_size.max(1)) {
+        cancel.check()?;
+        let rows: Vec<HashMap<String, BoltType>> = batch
+            .iter()
+            .map(|suggestion| {
+                let mut row = HashMap::new();
+                row.insert("recipe_id".to_string(), suggestion.recipe_id.into());
+                row.insert("tag".to_string(), suggestion.tag.clone().into());
+                row.insert(
+                    "key".to_string(),
+                    settings.key("Tag", &suggestion.tag).into(),
+                );
+                row.insert("confidence".to_string(), suggestion.confidence.into());
+                row
+            })
+            .collect();
+        graph
+            .run(
+                Query::new(
+                    "UNWIND $rows AS row MATCH (r:Recipe {id: row.recipe_id}) \
+                     MERGE (t:Tag {key: row.key}) ON CREATE SET t.name = row.tag \
+                     MERGE (r)-[s:SUGGESTED_TAG]->(t) SET s.confidence = row.confidence"
+                        .to_string(),
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        written += batch.len();
+        println!("{} suggestions written", written);
+    }
+    Ok(written)
+}
@@ -0,0 +1,251 @@
+#[cfg(feature = "neo4j")]
+use crate::audit::TypePolicy;
+use crate::fixtures;
+#[cfg(feature = "neo4j")]
+use crate::graph::GraphLoader;
+use crate::parser::python_list;
+#[cfg(feature = "neo4j")]
+use crate::settings::Settings;
+use crate::Recipe;
+use csv::{Reader, Writer};
+#[cfg(feature = "neo4j")]
+use neo4rs::{Graph, Query};
+use std::error::Error;
+
+/// The bundled corpora, by name:
+///
+/// - `empty`: the header and nothing else
+/// - `small_corpus`: ten synthetic recipes, as `fixtures generate` makes
+/// - `pathological_encoding`: combining marks, emoji sequences,
+///   right-to-left and CJK text, quotes of both kinds, escapes, embedded
+///   newlines, invisible characters and a recipe with every field empty
+pub const CORPORA: [&str; 3] = ["empty", "small_corpus", "pathological_encoding"];
+
+/// Seed for `small_corpus`, fixed so every build bundles the same recipes.
+const SMALL_CORPUS_SEED: u64 = 7;
+
+/// One RAW_recipes.csv row, with the step and ingredient counts filled in.
+fn row(
+    id: i32,
+    name: &str,
+    minutes: i32,
+    tags: &[&str],
+    steps: &[&str],
+    description: &str,
+    ingredients: &[&str],
+) -> Vec<String> {
+    vec![
+        name.to_string(),
+        id.to_string(),
+        minutes.to_string(),
+        "1".to_string(),
+        "2010-01-01".to_string(),
+        python_list(tags),
+        "[250.0, 10.0, 20.0, 5.0, 15.0, 8.0, 9.0]".to_string(),
+        steps.len().to_string(),
+        python_list(steps),
+        description.to_string(),
+        python_list(ingredients),
+        ingredients.len().to_string(),
+    ]
+}
+
+fn pathological_rows() -> Vec<Vec<String>> {
+    vec![
+        row(
+            900001,
+            // Decomposed: each accent is a combining mark after its letter
+            "cre\u{300}me bru\u{302}le\u{301}e",
+            75,
+            &["desserts", "french"],
+            &["heat the crème fraîche", "caramelize the sugar"],
+            "the same name precomposed and decomposed",
+            &["crème fraîche", "cre\u{300}me frai\u{302}che", "sugar"],
+        ),
+        row(
+            900002,
+            "👩\u{200d}🍳 pizza night 🍕",
+            40,
+            &["main-dish", "🍕"],
+            &["stretch the dough 🙌", "bake until the cheese bubbles 🔥"],
+            "emoji joined by zero-width joiners, with skin tones 👍🏽",
+            &["pizza dough", "mozzarella 🧀"],
+        ),
+        row(
+            900003,
+            "شوربة عدس",
+            30,
+            &["soups-stews", "middle-eastern"],
+            &["اطبخ العدس حتى ينضج"],
+            "right-to-left text with \u{200f}direction marks\u{200e}",
+            &["عدس", "עדשים", "cumin"],
+        ),
+        row(
+            900004,
+            "麻婆豆腐",
+            25,
+            &["asian", "chinese"],
+            &["切豆腐", "add 花椒 and simmer"],
+            "full-width\u{3000}space and CJK punctuation、。",
+            &["豆腐", "花椒", "ground pork"],
+        ),
+        row(
+            900005,
+            "mom's \"best\" pie",
+            90,
+            &["desserts", "grandma's"],
+            &["don't overmix", "add \"extra\" cinnamon", "cool\\then slice"],
+            "a description\nacross two lines, with a zero\u{200b}width space and a\u{a0}non-breaking one",
+            &["baker's chocolate", "flour", "salt"],
+        ),
+        row(
+            900006,
+            "\u{feff}\t padded  name \t",
+            15,
+            &[" spaced-tag "],
+            &["  step with\tinner tab  "],
+            "\u{feff}",
+            &["  salt  "],
+        ),
+        row(900007, "", 0, &[], &[], "", &[]),
+    ]
+}
+
+/// The corpus `name` as RAW_recipes.csv bytes, for tests that read files.
+pub fn corpus_csv(name: &str) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut out = Vec::new();
+    match name {
+        "empty" => fixtures::generate(&mut out, 0, SMALL_CORPUS_SEED)?,
+        "small_corpus" => fixtures::generate(&mut out, 10, SMALL_CORPUS_SEED)?,
+        "pathological_encoding" => {
+            let mut wtr = Writer::from_writer(&mut out);
+            wtr.write_record(fixtures::HEADER)?;
+            for record in pathological_rows() {
+                wtr.write_record(record)?;
+            }
+            wtr.flush()?;
+        }
+        other => {
+            return Err(format!(
+                "unknown corpus: {} (expected one of {})",
+                other,
+                CORPORA.join(", ")
+            )
+            .into())
+        }
+    }
+    Ok(out)
+}
+
+/// The corpus `name` parsed into recipes, in file order.
+pub fn recipes(name: &str) -> Result<Vec<Recipe>, Box<dyn Error>> {
+    let csv = corpus_csv(name)?;
+    let mut rdr = Reader::from_reader(csv.as_slice());
+    let mut recipes = Vec::new();
+    for result in rdr.deserialize() {
+        recipes.push(result?);
+    }
+    Ok(recipes)
+}
+
+/// Loads the corpus `name` into `graph` through `GraphLoader`, as `load`
+/// would with its defaults and no cleanup passes, and returns the ids
+/// written. Nothing already in the graph is removed; start from `clear`
+/// for a graph holding the corpus and nothing else.
+#[cfg(feature = "neo4j")]
+pub async fn load_fixture(graph: &Graph, name: &str) -> Result<Vec<i32>, Box<dyn Error>> {
+    let recipes = recipes(name)?;
+    let mut loader =
+        GraphLoader::sample(graph.clone(), TypePolicy::Fail, Settings::default()).await?;
+    let mut written = Vec::new();
+    for recipe in &recipes {
+        written.extend(loader.add_recipe(recipe).await?);
+    }
+    written.extend(loader.flush().await?);
+    Ok(written)
+}
+
+/// Deletes every node and relationship in `graph`, `batch_size` nodes per
+/// transaction. Only for scratch databases that tests own.
+#[cfg(feature = "neo4j")]
+pub async fn clear(graph: &Graph, batch_size: usize) -> Result<(), Box<dyn Error>> {
+    loop {
+        let mut result = graph
+            .execute(
+                Query::new(
+                    "MATCH (n) WITH n LIMIT $limit DETACH DELETE n RETURN count(*) AS deleted"
+                        .to_string(),
+                )
+                .param("limit", batch_size as i64),
+            )
+            .await?;
+        let deleted = match result.next().await? {
+            Some(row) => row.get::<i64>("deleted")?,
+            None => 0,
+        };
+        if deleted == 0 {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_python_list;
+    use csv::StringRecord;
+
+    #[test]
+    fn every_corpus_list_cell_parses() {
+        for name in CORPORA {
+            let csv = corpus_csv(name).unwrap();
+            let mut rdr = Reader::from_reader(csv.as_slice());
+            let headers = rdr.headers().unwrap().clone();
+            let column = |field: &str| headers.iter().position(|h| h == field).unwrap();
+            let records: Vec<StringRecord> = rdr.records().map(Result::unwrap).collect();
+            let recipes = recipes(name).unwrap();
+            assert_eq!(records.len(), recipes.len(), "{}", name);
+
+            for (record, recipe) in records.iter().zip(&recipes) {
+                let list = |field: &str| {
+                    let cell = &record[column(field)];
+                    let items = parse_python_list(cell).unwrap_or_else(|err| {
+                        panic!("{} {} {}: {:?}", name, &record[column("id")], field, err)
+                    });
+                    // Written back, the list is the cell it was read from
+                    assert_eq!(python_list(&items), cell, "{} {}", name, field);
+                    items
+                };
+                let count = |field: &str| record[column(field)].parse::<usize>().unwrap();
+
+                let steps = list("steps");
+                let ingredients = list("ingredients");
+                assert_eq!(steps.len(), count("n_steps"));
+                assert_eq!(ingredients.len(), count("n_ingredients"));
+                // The deserializer read the same lists, without falling
+                // back to a comma split
+                assert_eq!(list("tags"), recipe.tags);
+                assert_eq!(steps, recipe.steps);
+                assert_eq!(ingredients, recipe.ingredients);
+            }
+        }
+    }
+
+    #[test]
+    fn pathological_elements_survive_parsing() {
+        let recipes = recipes("pathological_encoding").unwrap();
+        let pie = recipes.iter().find(|recipe| recipe.id == 900005).unwrap();
+
+        assert_eq!(
+            pie.steps,
+            [
+                "don't overmix",
+                "add \"extra\" cinnamon",
+                "cool\\then slice"
+            ]
+        );
+        assert_eq!(pie.tags, ["desserts", "grandma's"]);
+        let empty = recipes.iter().find(|recipe| recipe.id == 900007).unwrap();
+        assert!(empty.steps.is_empty() && empty.ingredients.is_empty());
+    }
+}
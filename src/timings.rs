@@ -0,0 +1,96 @@
+use std::fmt::Write;
+use std::time::Duration;
+
+/// The steps every recipe goes through on its way into the graph.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stage {
+    /// Reading CSV records from the input
+    Read,
+    /// Column mapping and deserializing into a `Recipe`
+    Parse,
+    /// Cleanup, spelling, step repair, truncation and inference
+    Normalize,
+    /// Building the graph rows for a recipe
+    Serialize,
+    /// Sending a batch and committing its transaction
+    Commit,
+}
+
+pub const STAGES: [Stage; 5] = [
+    Stage::Read,
+    Stage::Parse,
+    Stage::Normalize,
+    Stage::Serialize,
+    Stage::Commit,
+];
+
+impl Stage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Read => "read",
+            Stage::Parse => "parse",
+            Stage::Normalize => "normalize",
+            Stage::Serialize => "serialize",
+            Stage::Commit => "commit",
+        }
+    }
+}
+
+/// Time spent in each `Stage`, kept per batch so a slow commit can be told
+/// apart from one slow batch.
+#[derive(Debug, Default)]
+pub struct Timings {
+    current: [Duration; 5],
+    batches: Vec<[Duration; 5]>,
+}
+
+impl Timings {
+    pub fn add(&mut self, stage: Stage, elapsed: Duration) {
+        self.current[stage as usize] += elapsed;
+    }
+
+    /// Closes the current batch; call after each commit.
+    pub fn end_batch(&mut self) {
+        self.batches.push(std::mem::take(&mut self.current));
+    }
+
+    /// A table of each stage's total, share of the whole, and mean and
+    /// slowest batch.
+    pub fn report(&self) -> String {
+        let mut out = format!(
+            "{:<10} {:>10} {:>7} {:>12} {:>12}\n",
+            "stage", "total s", "share", "mean ms", "max ms"
+        );
+        let batches = self.batches.len().max(1) as f64;
+        let total =
+            |index: usize| -> Duration { self.batches.iter().map(|batch| batch[index]).sum() };
+        let whole: f64 = (0..STAGES.len())
+            .map(|index| total(index).as_secs_f64())
+            .sum();
+
+        for (index, stage) in STAGES.iter().enumerate() {
+            let seconds = total(index).as_secs_f64();
+            let max = self
+                .batches
+                .iter()
+                .map(|batch| batch[index])
+                .max()
+                .unwrap_or_default();
+            let _ = writeln!(
+                out,
+                "{:<10} {:>10.2} {:>6.1}% {:>12.1} {:>12.1}",
+                stage.name(),
+                seconds,
+                if whole > 0.0 {
+                    100.0 * seconds / whole
+                } else {
+                    0.0
+                },
+                1000.0 * seconds / batches,
+                max.as_secs_f64() * 1000.0
+            );
+        }
+        let _ = write!(out, "{} batches", self.batches.len());
+        out
+    }
+}
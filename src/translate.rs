@@ -0,0 +1,378 @@
+use crate::cancel::CancellationToken;
+use crate::graph;
+use crate::output;
+use crate::server;
+use crate::settings::Settings;
+use neo4rs::{BoltType, Graph, Query};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+/// Texts sent to a translator in one call, so a large batch of recipes
+/// does not become one oversized request.
+const TEXTS_PER_CALL: usize = 50;
+
+/// The languages a run translates between, as the codes the translator
+/// takes, such as `en` and `es`.
+#[derive(Debug, Clone)]
+pub struct Languages {
+    pub from: String,
+    pub to: String,
+}
+
+/// A machine translation service. `translate` gets a batch of texts and
+/// returns their translations in the same order.
+pub trait Translator {
+    fn translate(
+        &mut self,
+        texts: &[String],
+        languages: &Languages,
+    ) -> impl Future<Output = Result<Vec<String>, Box<dyn Error>>>;
+}
+
+/// Runs `command` and returns its output as JSON, feeding it `input` on
+/// stdin.
+async fn run_json(command: &mut Command, input: &Value) -> Result<Value, Box<dyn Error>> {
+    let program = command
+        .as_std()
+        .get_program()
+        .to_string_lossy()
+        .into_owned();
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run {}: {}", program, err))?;
+    let mut stdin = child.stdin.take().ok_or("no stdin")?;
+    stdin.write_all(input.to_string().as_bytes()).await?;
+    drop(stdin);
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )
+        .into());
+    }
+    Ok(serde_json::from_slice(&output.stdout)?)
+}
+
+/// The strings of `value`, which must be an array of `expected` of them.
+fn translations(value: &Value, expected: usize) -> Result<Vec<String>, Box<dyn Error>> {
+    let texts: Vec<String> = value
+        .as_array()
+        .ok_or("translator did not return a list")?
+        .iter()
+        .map(|text| text.as_str().map(str::to_string))
+        .collect::<Option<_>>()
+        .ok_or("translator returned a list of non-strings")?;
+    if texts.len() != expected {
+        return Err(format!("translator returned {} texts for {}", texts.len(), expected).into());
+    }
+    Ok(texts)
+}
+
+/// A LibreTranslate server, called through `curl`.
+pub struct LibreTranslate {
+    url: String,
+    api_key: Option<String>,
+}
+
+impl LibreTranslate {
+    pub fn new(url: &str, api_key: Option<String>) -> LibreTranslate {
+        LibreTranslate {
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+        }
+    }
+}
+
+impl Translator for LibreTranslate {
+    async fn translate(
+        &mut self,
+        texts: &[String],
+        languages: &Languages,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let body = json!({
+            "q": texts,
+            "source": languages.from,
+            "target": languages.to,
+            "format": "text",
+            "api_key": self.api_key,
+        });
+        let mut curl = Command::new("curl");
+        curl.args(["--silent", "--show-error", "--fail"])
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--data-binary", "@-"])
+            .arg(format!("{}/translate", self.url));
+        let response = run_json(&mut curl, &body).await?;
+        translations(&response["translatedText"], texts.len())
+    }
+}
+
+/// Any program that reads `{"from", "to", "texts"}` as JSON on stdin and
+/// prints the translated texts as a JSON array, run through `sh -c`.
+pub struct CommandTranslator {
+    command: String,
+}
+
+impl CommandTranslator {
+    pub fn new(command: &str) -> CommandTranslator {
+        CommandTranslator {
+            command: command.to_string(),
+        }
+    }
+}
+
+impl Translator for CommandTranslator {
+    async fn translate(
+        &mut self,
+        texts: &[String],
+        languages: &Languages,
+    ) -> Result<Vec<String>, Box<dyn Error>> {
+        let input = json!({
+            "from": languages.from,
+            "to": languages.to,
+            "texts": texts,
+        });
+        let output = run_json(Command::new("sh").arg("-c").arg(&self.command), &input).await?;
+        translations(&output, texts.len())
+    }
+}
+
+/// Translations saved on disk by language pair and source text, so reruns
+/// only send text they have never seen.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: HashMap<String, HashMap<String, String>>,
+}
+
+impl Cache {
+    pub fn load(path: &Path) -> Result<Cache, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Cache::default());
+        }
+        Ok(serde_json::from_str(&output::read_text(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    fn pair(&mut self, languages: &Languages) -> &mut HashMap<String, String> {
+        self.entries
+            .entry(format!("{}>{}", languages.from, languages.to))
+            .or_default()
+    }
+}
+
+/// Translates whichever of `texts` the cache lacks, `TEXTS_PER_CALL` at a
+/// time, adding each call's results to the cache as it returns.
+async fn translate_missing(
+    translator: &mut impl Translator,
+    cache: &mut Cache,
+    texts: HashSet<String>,
+    languages: &Languages,
+    cancel: &CancellationToken,
+) -> Result<(), Box<dyn Error>> {
+    let known = cache.pair(languages);
+    let missing: Vec<String> = texts
+        .into_iter()
+        .filter(|text| !known.contains_key(text))
+        .collect();
+    for chunk in missing.chunks(TEXTS_PER_CALL) {
+        cancel.check()?;
+        let translated = translator.translate(chunk, languages).await?;
+        cache
+            .pair(languages)
+            .extend(chunk.iter().cloned().zip(translated));
+    }
+    Ok(())
+}
+
+/// One recipe's text as read from the graph.
+struct Source {
+    id: i64,
+    name: String,
+    description: Option<String>,
+    steps: Vec<String>,
+}
+
+/// The service a run sends texts to.
+#[derive(Debug, Clone)]
+pub enum Service {
+    /// A command reading JSON from stdin; see `CommandTranslator`
+    Command(String),
+    LibreTranslate {
+        url: String,
+        api_key: Option<String>,
+    },
+}
+
+/// `translate` with the translator for `service`.
+#[allow(clippy::too_many_arguments)]
+pub async fn translate_with(
+    graph: &Graph,
+    settings: &Settings,
+    service: &Service,
+    languages: &Languages,
+    cache_path: &Path,
+    refresh: bool,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    match service {
+        Service::Command(command) => {
+            let mut translator = CommandTranslator::new(command);
+            translate(
+                graph,
+                settings,
+                &mut translator,
+                languages,
+                cache_path,
+                refresh,
+                batch_size,
+                cancel,
+            )
+            .await
+        }
+        Service::LibreTranslate { url, api_key } => {
+            let mut translator = LibreTranslate::new(url, api_key.clone());
+            translate(
+                graph,
+                settings,
+                &mut translator,
+                languages,
+                cache_path,
+                refresh,
+                batch_size,
+                cancel,
+            )
+            .await
+        }
+    }
+}
+
+/// Writes a `(:RecipeTranslation {lang})` node for each recipe without one
+/// in `languages.to` (every recipe with `refresh`), linked from the recipe
+/// by `HAS_TRANSLATION`, holding its name, description and steps
+/// translated. Texts come from the cache at `cache_path` where it has
+/// them, otherwise from `translator`; the cache is saved after every
+/// batch. Blank texts are left as they are. Returns the number of recipes
+/// translated.
+#[allow(clippy::too_many_arguments)]
+pub async fn translate(
+    graph: &Graph,
+    settings: &Settings,
+    translator: &mut impl Translator,
+    languages: &Languages,
+    cache_path: &Path,
+    refresh: bool,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    graph::create_indexes(graph, settings).await?;
+    let server = server::detect(graph).await?;
+    let mut cache = Cache::load(cache_path)?;
+    let mut last_id = i64::MIN;
+    let mut translated = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(format!(
+            "MATCH (r:Recipe) WHERE r.id > $after AND ($refresh OR NOT {}) \
+             WITH r ORDER BY r.id LIMIT $limit \
+             RETURN r.id AS id, r.name AS name, r.description AS description, {} AS steps",
+            server.exists("(r)-[:HAS_TRANSLATION]->(:RecipeTranslation {lang: $lang})"),
+            graph::steps(&server)
+        ))
+        .param("after", last_id)
+        .param("refresh", refresh)
+        .param("lang", languages.to.clone())
+        .param("limit", batch_size as i64);
+
+        let mut sources = Vec::new();
+        let mut result = graph.execute(query).await?;
+        while let Some(row) = result.next().await? {
+            sources.push(Source {
+                id: row.get("id")?,
+                name: row.get::<Option<String>>("name")?.unwrap_or_default(),
+                description: row.get("description")?,
+                steps: row.get::<Option<Vec<String>>>("steps")?.unwrap_or_default(),
+            });
+        }
+        let Some(last) = sources.last() else {
+            break;
+        };
+        last_id = last.id;
+
+        let texts: HashSet<String> = sources
+            .iter()
+            .flat_map(|source| {
+                std::iter::once(&source.name)
+                    .chain(&source.description)
+                    .chain(&source.steps)
+            })
+            .filter(|text| !text.trim().is_empty())
+            .cloned()
+            .collect();
+        // Translations made before a failure or cancellation are kept
+        let done = translate_missing(translator, &mut cache, texts, languages, cancel).await;
+        cache.save(cache_path)?;
+        done?;
+
+        let known = cache.pair(languages);
+        let lookup = |text: &String| known.get(text).unwrap_or(text).clone();
+        let rows: Vec<HashMap<String, BoltType>> = sources
+            .iter()
+            .map(|source| {
+                let key = settings.key(
+                    "RecipeTranslation",
+                    &format!("{}/{}", source.id, languages.to),
+                );
+                HashMap::from([
+                    ("id".to_string(), source.id.into()),
+                    ("key".to_string(), key.into()),
+                    ("lang".to_string(), languages.to.clone().into()),
+                    ("name".to_string(), lookup(&source.name).into()),
+                    (
+                        "description".to_string(),
+                        source.description.as_ref().map(lookup).into(),
+                    ),
+                    (
+                        "steps".to_string(),
+                        source.steps.iter().map(lookup).collect::<Vec<_>>().into(),
+                    ),
+                ])
+            })
+            .collect();
+
+        translated += rows.len();
+        graph
+            .run(
+                Query::new(
+                    "UNWIND $rows AS row MATCH (r:Recipe {id: row.id}) \
+                     MERGE (t:RecipeTranslation {key: row.key}) \
+                     SET t.lang = row.lang, t.name = row.name, \
+                     t.description = row.description, t.steps = row.steps \
+                     MERGE (r)-[:HAS_TRANSLATION]->(t)"
+                        .to_string(),
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        println!("translated {} recipes to {}", translated, languages.to);
+    }
+
+    Ok(translated)
+}
@@ -0,0 +1,207 @@
+use crate::Recipe;
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// What to do with a field longer than its configured limit.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum OverflowPolicy {
+    /// Cut the text at the limit
+    #[default]
+    Truncate,
+    /// Skip the whole recipe
+    Reject,
+    /// Cut the text and write the full value to a file in this directory
+    Externalize(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TruncationOutcome {
+    Unchanged,
+    Truncated(Vec<String>),
+    Rejected(Vec<String>),
+}
+
+/// Per-field maximum lengths in characters. `None` means unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct TruncationConfig {
+    pub max_name: Option<usize>,
+    pub max_description: Option<usize>,
+    pub max_step: Option<usize>,
+    pub policy: OverflowPolicy,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "truncate" => Ok(OverflowPolicy::Truncate),
+            "reject" => Ok(OverflowPolicy::Reject),
+            "externalize" => Ok(OverflowPolicy::Externalize(PathBuf::from("overflow"))),
+            other => Err(format!("unknown overflow policy: {}", other)),
+        }
+    }
+}
+
+fn truncate_chars(text: &str, max: usize) -> Option<String> {
+    text.char_indices()
+        .nth(max)
+        .map(|(cut, _)| text[..cut].to_string())
+}
+
+impl TruncationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.max_name.is_some() || self.max_description.is_some() || self.max_step.is_some()
+    }
+
+    fn externalize(&self, recipe_id: i32, field: &str, text: &str) -> Result<(), Box<dyn Error>> {
+        if let OverflowPolicy::Externalize(dir) = &self.policy {
+            fs::create_dir_all(dir)?;
+            fs::write(dir.join(format!("{}.{}.txt", recipe_id, field)), text)?;
+        }
+        Ok(())
+    }
+
+    /// Enforces the limits on `recipe`, recording every shortened field in
+    /// `recipe.truncated_fields` so the loss stays visible in the graph.
+    pub fn apply(&self, recipe: &mut Recipe) -> Result<TruncationOutcome, Box<dyn Error>> {
+        let mut overflowing: Vec<(String, String)> = Vec::new();
+
+        if let Some(short) = self
+            .max_name
+            .and_then(|max| truncate_chars(&recipe.name, max))
+        {
+            overflowing.push(("name".to_string(), short));
+        }
+        if let Some(short) = self
+            .max_description
+            .and_then(|max| truncate_chars(&recipe.description, max))
+        {
+            overflowing.push(("description".to_string(), short));
+        }
+        if let Some(max) = self.max_step {
+            for (index, step) in recipe.steps.iter().enumerate() {
+                if let Some(short) = truncate_chars(step, max) {
+                    overflowing.push((format!("steps[{}]", index), short));
+                }
+            }
+        }
+
+        if overflowing.is_empty() {
+            return Ok(TruncationOutcome::Unchanged);
+        }
+
+        let fields: Vec<String> = overflowing.iter().map(|(field, _)| field.clone()).collect();
+        if self.policy == OverflowPolicy::Reject {
+            return Ok(TruncationOutcome::Rejected(fields));
+        }
+
+        for (field, short) in overflowing {
+            let full = match field.as_str() {
+                "name" => std::mem::replace(&mut recipe.name, short),
+                "description" => std::mem::replace(&mut recipe.description, short),
+                _ => {
+                    let index: usize = field
+                        .trim_start_matches("steps[")
+                        .trim_end_matches(']')
+                        .parse()?;
+                    std::mem::replace(&mut recipe.steps[index], short)
+                }
+            };
+            self.externalize(recipe.id, &field, &full)?;
+        }
+
+        recipe.truncated_fields.extend(fields.iter().cloned());
+        Ok(TruncationOutcome::Truncated(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testkit;
+
+    fn recipe(name: &str, steps: &[&str]) -> Recipe {
+        let mut recipe = testkit::recipes("small_corpus").unwrap().remove(0);
+        recipe.name = name.to_string();
+        recipe.description = String::new();
+        recipe.steps = steps.iter().map(|step| step.to_string()).collect();
+        recipe.truncated_fields.clear();
+        recipe
+    }
+
+    fn limits(max_name: usize, max_step: usize, policy: OverflowPolicy) -> TruncationConfig {
+        TruncationConfig {
+            max_name: Some(max_name),
+            max_description: None,
+            max_step: Some(max_step),
+            policy,
+        }
+    }
+
+    #[test]
+    fn cuts_on_char_boundaries() {
+        assert_eq!(truncate_chars("crème brûlée", 4).as_deref(), Some("crèm"));
+        assert_eq!(truncate_chars("日本料理", 2).as_deref(), Some("日本"));
+        // At or under the limit is left alone
+        assert_eq!(truncate_chars("crème", 5), None);
+        assert_eq!(truncate_chars("", 0), None);
+    }
+
+    #[test]
+    fn truncate_shortens_and_records_each_field() {
+        let mut recipe = recipe("crème brûlée", &["whisk", "bake slowly"]);
+
+        let outcome = limits(5, 5, OverflowPolicy::Truncate)
+            .apply(&mut recipe)
+            .unwrap();
+        assert_eq!(
+            outcome,
+            TruncationOutcome::Truncated(vec!["name".to_string(), "steps[1]".to_string()])
+        );
+        assert_eq!(recipe.name, "crème");
+        assert_eq!(recipe.steps, ["whisk", "bake "]);
+        assert_eq!(recipe.truncated_fields, ["name", "steps[1]"]);
+    }
+
+    #[test]
+    fn reject_leaves_the_recipe_as_it_was() {
+        let mut recipe = recipe("crème brûlée", &["whisk"]);
+
+        let outcome = limits(5, 5, OverflowPolicy::Reject)
+            .apply(&mut recipe)
+            .unwrap();
+        assert_eq!(
+            outcome,
+            TruncationOutcome::Rejected(vec!["name".to_string()])
+        );
+        assert_eq!(recipe.name, "crème brûlée");
+        assert!(recipe.truncated_fields.is_empty());
+    }
+
+    #[test]
+    fn within_the_limits_is_unchanged() {
+        let mut recipe = recipe("soup", &["stir"]);
+
+        let outcome = limits(5, 5, OverflowPolicy::Reject)
+            .apply(&mut recipe)
+            .unwrap();
+        assert_eq!(outcome, TruncationOutcome::Unchanged);
+        assert!(!TruncationConfig::default().is_enabled());
+    }
+
+    #[test]
+    fn externalize_writes_the_full_text() {
+        let dir = std::env::temp_dir().join(format!("overflow-{}", std::process::id()));
+        let mut recipe = recipe("crème brûlée", &["whisk"]);
+
+        limits(5, 5, OverflowPolicy::Externalize(dir.clone()))
+            .apply(&mut recipe)
+            .unwrap();
+        assert_eq!(recipe.name, "crème");
+        let full = fs::read_to_string(dir.join(format!("{}.name.txt", recipe.id))).unwrap();
+        assert_eq!(full, "crème brûlée");
+    }
+}
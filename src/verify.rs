@@ -0,0 +1,182 @@
+use crate::cancel::CancellationToken;
+use crate::graph;
+use crate::hashing;
+use crate::output;
+use crate::server;
+use crate::settings::Settings;
+use crate::warnings::Warnings;
+use crate::Recipe;
+use csv::Reader;
+use neo4rs::{Graph, Query};
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::time::Duration;
+
+/// Parses `1%` or `0.01` as a sampling fraction.
+pub fn parse_fraction(value: &str) -> Result<f64, String> {
+    let fraction = match value.strip_suffix('%') {
+        Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0),
+        None => value.trim().parse::<f64>(),
+    }
+    .map_err(|_| format!("invalid sample: {}", value))?;
+    if (0.0..=1.0).contains(&fraction) {
+        Ok(fraction)
+    } else {
+        Err(format!("sample must be between 0 and 100%: {}", value))
+    }
+}
+
+/// What the graph holds for a sampled recipe.
+struct Stored {
+    content_hash: Option<String>,
+    name: Option<String>,
+    minutes: Option<i64>,
+    steps: Vec<String>,
+    truncated_fields: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub sampled: usize,
+    pub matched: usize,
+    /// Recipe id and the fields that differ
+    pub diverged: Vec<(i64, Vec<&'static str>)>,
+    pub missing_from_input: Vec<i64>,
+    /// Loaded before content hashes existed; compared field by field only
+    pub unhashed: usize,
+}
+
+impl Report {
+    /// The counts, then one line per recipe that differs from `input` or
+    /// is missing from it.
+    pub fn summary(&self, input: &Path) -> String {
+        let mut lines = vec![format!(
+            "sampled {} recipes: {} match, {} diverged, {} missing from {}",
+            self.sampled,
+            self.matched,
+            self.diverged.len(),
+            self.missing_from_input.len(),
+            input.display()
+        )];
+        if self.unhashed > 0 {
+            lines.push(format!(
+                "{} sampled recipes predate content hashes and were compared field by field",
+                self.unhashed
+            ));
+        }
+        for (id, fields) in &self.diverged {
+            lines.push(format!("  {} differs in {}", id, fields.join(", ")));
+        }
+        for id in &self.missing_from_input {
+            lines.push(format!("  {} is not in the input", id));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Reads a random `fraction` of the graph's recipes in pages of
+/// `batch_size`, pausing `delay` between pages so a live database is not
+/// swamped, then re-parses `input` with `settings` and reports every sampled recipe whose
+/// stored content no longer matches its source row.
+pub async fn verify(
+    graph: &Graph,
+    settings: &Settings,
+    input: &Path,
+    fraction: f64,
+    batch_size: usize,
+    delay: Duration,
+    cancel: &CancellationToken,
+) -> Result<Report, Box<dyn Error>> {
+    let server = server::detect(graph).await?;
+    let mut sample: HashMap<i64, Stored> = HashMap::new();
+    let mut last_id = i64::MIN;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(format!(
+            "MATCH (r:Recipe) WHERE r.id > $after \
+             WITH r ORDER BY r.id LIMIT $limit \
+             RETURN r.id AS id, rand() < $fraction AS sampled, \
+                    r.content_hash AS content_hash, r.name AS name, \
+                    r.minutes AS minutes, {} AS steps, \
+                    coalesce(r.truncated_fields, []) AS truncated_fields",
+            graph::steps(&server)
+        ))
+        .param("after", last_id)
+        .param("limit", batch_size as i64)
+        .param("fraction", fraction);
+
+        let mut result = graph.execute(query).await?;
+        let mut rows = 0;
+        while let Some(row) = result.next().await? {
+            rows += 1;
+            last_id = row.get("id")?;
+            if row.get("sampled")? {
+                sample.insert(
+                    last_id,
+                    Stored {
+                        content_hash: row.get("content_hash")?,
+                        name: row.get("name")?,
+                        minutes: row.get("minutes")?,
+                        steps: row.get("steps")?,
+                        truncated_fields: row.get("truncated_fields")?,
+                    },
+                );
+            }
+        }
+        if rows == 0 {
+            break;
+        }
+        tokio::time::sleep(delay).await;
+    }
+
+    let mut report = Report {
+        sampled: sample.len(),
+        ..Report::default()
+    };
+    // Cleaned the same way the loader cleans, so hashes are comparable
+    let mut warnings = Warnings::default();
+    for result in settings.recipes(Reader::from_reader(output::open_source(input)?)) {
+        let mut recipe: Recipe = result?;
+        let Some(stored) = sample.remove(&i64::from(recipe.id)) else {
+            continue;
+        };
+        warnings.clean(&mut recipe);
+        // The loader hashes before repairing steps
+        let mut fields = Vec::new();
+        match &stored.content_hash {
+            Some(hash) if *hash != hashing::content_hash(&recipe) => fields.push("content_hash"),
+            Some(_) => {}
+            None => report.unhashed += 1,
+        }
+        warnings.repair_steps(&mut recipe);
+        // Fields the loader shortened are expected to differ
+        let truncated = |field: &str| {
+            stored
+                .truncated_fields
+                .iter()
+                .any(|name| name.split('[').next() == Some(field))
+        };
+        if !truncated("name") && stored.name.as_deref() != Some(recipe.name.as_str()) {
+            fields.push("name");
+        }
+        if stored.minutes != Some(i64::from(recipe.minutes)) {
+            fields.push("minutes");
+        }
+        if !truncated("steps") && stored.steps != recipe.steps {
+            fields.push("steps");
+        }
+
+        if fields.is_empty() {
+            report.matched += 1;
+        } else {
+            report.diverged.push((i64::from(recipe.id), fields));
+        }
+    }
+
+    report.missing_from_input = sample.into_keys().collect();
+    report.missing_from_input.sort_unstable();
+    report.diverged.sort_unstable();
+    Ok(report)
+}
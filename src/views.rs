@@ -0,0 +1,121 @@
+use crate::cancel::CancellationToken;
+use crate::graph;
+use crate::server::Server;
+use neo4rs::{BoltType, Graph, Query};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Denormalized snapshot of a recipe stored as JSON in `Recipe.view_json`,
+/// so API responses need a single property read instead of several
+/// traversals.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecipeView {
+    pub id: i64,
+    pub name: String,
+    pub description: String,
+    pub minutes: i64,
+    pub ingredients: Vec<IngredientView>,
+    pub steps: Vec<String>,
+    pub tags: Vec<String>,
+    pub rating: RatingSummary,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IngredientView {
+    pub name: String,
+    pub quantity_min: Option<f64>,
+    pub quantity_max: Option<f64>,
+    pub unit: Option<String>,
+    pub to_taste: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RatingSummary {
+    pub count: i64,
+    pub average: Option<f64>,
+}
+
+fn view_query(server: &Server) -> String {
+    format!(
+        "MATCH (r:Recipe) WHERE r.id > $after \
+         WITH r ORDER BY r.id LIMIT $limit \
+         CALL {{ WITH r \
+                OPTIONAL MATCH (r)-[c:CONTAINS]->(i:Ingredient) \
+                WITH c, i ORDER BY c.order \
+                RETURN collect(CASE WHEN i IS NULL THEN NULL ELSE {{ \
+                    name: i.name, quantity_min: c.quantity_min, quantity_max: c.quantity_max, \
+                    unit: c.unit, to_taste: coalesce(c.to_taste, false)}} END) AS ingredients }} \
+         CALL {{ WITH r \
+                OPTIONAL MATCH (r)-[:TAGGED]->(t:Tag) \
+                RETURN collect(t.name) AS tags }} \
+         CALL {{ WITH r \
+                OPTIONAL MATCH (:User)-[x:RATED]->(r) \
+                RETURN count(x) AS rating_count, avg(x.rating) AS rating_average }} \
+         RETURN r.id AS id, r.name AS name, r.description AS description, r.minutes AS minutes, \
+                {} AS steps, ingredients, tags, rating_count, rating_average \
+         ORDER BY id",
+        graph::steps(server)
+    )
+}
+
+/// Rebuilds `view_json` for every recipe in the graph. Returns the number of
+/// recipes refreshed.
+pub async fn materialize(
+    graph: &Graph,
+    server: &Server,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut last_id: i64 = i64::MIN;
+    let mut refreshed = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(view_query(server))
+            .param("after", last_id)
+            .param("limit", batch_size as i64);
+
+        let mut result = graph.execute(query).await?;
+        let mut rows: Vec<HashMap<String, BoltType>> = Vec::new();
+
+        while let Some(row) = result.next().await? {
+            let view = RecipeView {
+                id: row.get("id")?,
+                name: row.get::<Option<String>>("name")?.unwrap_or_default(),
+                description: row
+                    .get::<Option<String>>("description")?
+                    .unwrap_or_default(),
+                minutes: row.get::<Option<i64>>("minutes")?.unwrap_or(0),
+                ingredients: row.get("ingredients")?,
+                steps: row.get("steps")?,
+                tags: row.get("tags")?,
+                rating: RatingSummary {
+                    count: row.get("rating_count")?,
+                    average: row.get("rating_average")?,
+                },
+            };
+
+            let mut values = HashMap::new();
+            values.insert("id".to_string(), BoltType::from(view.id));
+            values.insert("view".to_string(), serde_json::to_string(&view)?.into());
+            rows.push(values);
+            last_id = view.id;
+        }
+
+        if rows.is_empty() {
+            break;
+        }
+
+        refreshed += rows.len();
+        let update = Query::new(
+            "UNWIND $rows AS row MATCH (r:Recipe {id: row.id}) SET r.view_json = row.view"
+                .to_string(),
+        )
+        .param("rows", rows);
+        graph.run(update).await?;
+        println!("materialized {} recipe views", refreshed);
+    }
+
+    Ok(refreshed)
+}
@@ -0,0 +1,186 @@
+use crate::Recipe;
+use serde::Serialize;
+
+/// Longest utterance, in words, before a step is split at a clause boundary.
+const MAX_WORDS: usize = 18;
+
+/// One thing for a voice assistant to say. `duration_seconds` is the timer a
+/// step asks for; `checkpoint` marks where the assistant should wait for the
+/// cook before moving on.
+#[derive(Debug, Serialize)]
+pub struct Utterance {
+    pub text: String,
+    pub ssml: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_seconds: Option<u32>,
+    pub checkpoint: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VoiceRecipe<'a> {
+    pub id: i32,
+    pub name: &'a str,
+    pub minutes: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<&'a str>,
+    pub ingredients: Vec<Utterance>,
+    pub steps: Vec<Utterance>,
+}
+
+fn ssml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// Seconds for the first "10 minutes" / "1.5 hours" / "30 seconds" in
+/// `text`. Ranges such as "20-25 minutes" take the upper bound.
+pub fn duration(text: &str) -> Option<u32> {
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .map(|word| word.trim_end_matches('.'))
+        .filter(|word| !word.is_empty())
+        .collect();
+
+    words.windows(2).find_map(|pair| {
+        let amount = pair[0].rsplit(['-', '–']).next()?.parse::<f32>().ok()?;
+        let scale = match pair[1].to_lowercase().trim_end_matches('s') {
+            "second" | "sec" => 1.0,
+            "minute" | "min" => 60.0,
+            "hour" | "hr" => 3600.0,
+            _ => return None,
+        };
+        Some((amount * scale).round() as u32)
+    })
+}
+
+/// The sentences of `step`, split at semicolons and at full stops followed
+/// by whitespace or the end, so "2.5 cups" stays whole.
+fn sentences(step: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = step.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        let ends = match c {
+            ';' => true,
+            '.' => chars.peek().is_none_or(|(_, next)| next.is_whitespace()),
+            _ => false,
+        };
+        if ends {
+            sentences.push(&step[start..index]);
+            start = index + c.len_utf8();
+        }
+    }
+    sentences.push(&step[start..]);
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Splits a step into utterances short enough to be spoken in one breath,
+/// breaking at sentence ends, then at commas and "then"/"and" once a chunk
+/// grows past `MAX_WORDS`.
+pub fn chunk(step: &str) -> Vec<String> {
+    let mut chunks = Vec::new();
+    for sentence in sentences(step) {
+        let mut current: Vec<&str> = Vec::new();
+        for word in sentence.split_whitespace() {
+            let breaks_before =
+                matches!(word, "then" | "and") || current.last().is_some_and(|w| w.ends_with(','));
+            if current.len() >= MAX_WORDS && breaks_before {
+                chunks.push(current.join(" ").trim_end_matches(',').to_string());
+                current.clear();
+            }
+            current.push(word);
+        }
+        if !current.is_empty() {
+            chunks.push(current.join(" ").trim_end_matches(',').to_string());
+        }
+    }
+    chunks
+}
+
+fn utterance(text: String, pause: &str) -> Utterance {
+    let duration_seconds = duration(&text);
+    let checkpoint = duration_seconds.is_some() || text.contains("until");
+    Utterance {
+        ssml: format!(
+            "<speak>{}<break time=\"{}\"/></speak>",
+            ssml_escape(&text),
+            pause
+        ),
+        text,
+        duration_seconds,
+        checkpoint,
+    }
+}
+
+impl<'a> VoiceRecipe<'a> {
+    pub fn from_recipe(recipe: &'a Recipe) -> VoiceRecipe<'a> {
+        VoiceRecipe {
+            id: recipe.id,
+            name: recipe.name_display.as_deref().unwrap_or(&recipe.name),
+            minutes: recipe.minutes,
+            source_url: recipe.source_url.as_deref(),
+            author: recipe.author.as_deref(),
+            license: recipe.license.as_deref(),
+            content_hash: recipe.content_hash.as_deref(),
+            ingredients: recipe
+                .ingredients
+                .iter()
+                .map(|ingredient| utterance(ingredient.clone(), "300ms"))
+                .collect(),
+            steps: recipe
+                .steps
+                .iter()
+                .flat_map(|step| chunk(step))
+                .map(|text| utterance(text, "700ms"))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_read_decimals_and_ranges() {
+        assert_eq!(duration("bake 1.5 hours."), Some(5400));
+        assert_eq!(duration("simmer for 20-25 minutes, stirring"), Some(1500));
+        assert_eq!(duration("rest 0.5 min. then slice"), Some(30));
+        assert_eq!(duration("Bake. 10 minutes."), Some(600));
+        assert_eq!(duration("add 2 cups flour"), None);
+    }
+
+    #[test]
+    fn decimals_do_not_end_sentences() {
+        assert_eq!(
+            chunk("Add 2.5 cups of flour. Stir; bake 1.5 hours."),
+            vec!["Add 2.5 cups of flour", "Stir", "bake 1.5 hours"]
+        );
+        assert_eq!(chunk("mix.stir"), vec!["mix.stir"]);
+        assert!(chunk(" . ; ").is_empty());
+    }
+
+    #[test]
+    fn long_sentences_break_at_clauses() {
+        let step = "combine the flour sugar salt baking soda baking powder cinnamon \
+                    nutmeg ginger cloves allspice and cardamom in a large bowl, \
+                    then whisk well";
+        let chunks = chunk(step);
+
+        assert_eq!(chunks.len(), 2, "{:?}", chunks);
+        assert!(chunks[1].starts_with("then"), "{:?}", chunks);
+    }
+}
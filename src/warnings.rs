@@ -0,0 +1,192 @@
+use crate::scrub::Category;
+use crate::steps;
+use crate::Recipe;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+/// A recoverable problem found while reading a recipe. Unlike errors these
+/// never stop a load; they are counted and reported at the end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    TrimmedWhitespace,
+    DroppedEmptyItem,
+    DedupedIngredient,
+    SpellCorrected,
+    MergedStepFragment,
+    MissingNumber,
+    Truncated,
+    Rejected,
+    Redacted,
+}
+
+impl WarningKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            WarningKind::TrimmedWhitespace => "trimmed whitespace",
+            WarningKind::DroppedEmptyItem => "dropped empty list item",
+            WarningKind::DedupedIngredient => "deduped ingredient",
+            WarningKind::SpellCorrected => "spell-corrected ingredient",
+            WarningKind::MergedStepFragment => "merged step fragment",
+            WarningKind::MissingNumber => "missing number",
+            WarningKind::Truncated => "truncated field",
+            WarningKind::Rejected => "rejected recipe",
+            WarningKind::Redacted => "redacted text",
+        }
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Warning {
+    pub recipe_id: i32,
+    pub kind: WarningKind,
+    pub detail: String,
+}
+
+/// Collects warnings across a run. Counts are kept for every warning; the
+/// individual entries are capped so a noisy file cannot exhaust memory.
+#[derive(Debug, Default)]
+pub struct Warnings {
+    counts: BTreeMap<WarningKind, usize>,
+    entries: Vec<Warning>,
+    redactions: BTreeMap<Category, usize>,
+}
+
+/// Individual warnings kept for inspection; counts keep going past this.
+const MAX_ENTRIES: usize = 1000;
+
+impl Warnings {
+    pub fn push(&mut self, recipe_id: i32, kind: WarningKind, detail: impl Into<String>) {
+        *self.counts.entry(kind).or_insert(0) += 1;
+        if self.entries.len() < MAX_ENTRIES {
+            self.entries.push(Warning {
+                recipe_id,
+                kind,
+                detail: detail.into(),
+            });
+        }
+    }
+
+    /// Records text redacted by the scrubbing pass, counted by category as
+    /// well as with the other warnings.
+    pub fn redacted(&mut self, recipe_id: i32, category: Category, field: &str) {
+        *self.redactions.entry(category).or_insert(0) += 1;
+        self.push(
+            recipe_id,
+            WarningKind::Redacted,
+            format!("{} in {}", category, field),
+        );
+    }
+
+    pub fn redactions(&self) -> &BTreeMap<Category, usize> {
+        &self.redactions
+    }
+
+    pub fn counts(&self) -> &BTreeMap<WarningKind, usize> {
+        &self.counts
+    }
+
+    pub fn entries(&self) -> &[Warning] {
+        &self.entries
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    /// Fixes what can be fixed in place and records a warning for each
+    /// change: surrounding whitespace, empty list items, repeated
+    /// ingredients (the first mention wins) and nutrition values that were
+    /// missing in the source.
+    pub fn clean(&mut self, recipe: &mut Recipe) {
+        let id = recipe.id;
+        for (field, value) in [
+            ("name", &mut recipe.name),
+            ("description", &mut recipe.description),
+        ] {
+            if value.trim().len() != value.len() {
+                *value = value.trim().to_string();
+                self.push(id, WarningKind::TrimmedWhitespace, field);
+            }
+        }
+
+        for (field, items) in [
+            ("ingredients", &mut recipe.ingredients),
+            ("steps", &mut recipe.steps),
+            ("tags", &mut recipe.tags),
+        ] {
+            for item in items.iter_mut() {
+                if item.trim().len() != item.len() {
+                    *item = item.trim().to_string();
+                    self.push(id, WarningKind::TrimmedWhitespace, field);
+                }
+            }
+            let before = items.len();
+            items.retain(|item| !item.is_empty());
+            for _ in items.len()..before {
+                self.push(id, WarningKind::DroppedEmptyItem, field);
+            }
+        }
+
+        let mut seen = HashSet::new();
+        let mut duplicates = Vec::new();
+        recipe.ingredients.retain(|ingredient| {
+            let first = seen.insert(ingredient.to_lowercase());
+            if !first {
+                duplicates.push(ingredient.clone());
+            }
+            first
+        });
+        for ingredient in duplicates {
+            self.push(id, WarningKind::DedupedIngredient, ingredient);
+        }
+
+        for (index, value) in recipe.nutrition.iter().enumerate() {
+            if value.is_nan() {
+                self.push(
+                    id,
+                    WarningKind::MissingNumber,
+                    format!("nutrition[{}]", index),
+                );
+            }
+        }
+    }
+
+    /// Merges step fragments into their neighbours, keeping the original
+    /// steps on the recipe when anything changed.
+    pub fn repair_steps(&mut self, recipe: &mut Recipe) {
+        if let Some((repaired, merged)) = steps::repair(&recipe.steps) {
+            for _ in 0..merged {
+                self.push(recipe.id, WarningKind::MergedStepFragment, "steps");
+            }
+            recipe.original_steps = std::mem::replace(&mut recipe.steps, repaired);
+        }
+    }
+
+    /// One line per category, for the end-of-run report. Redactions are
+    /// broken down by what was redacted.
+    pub fn summary(&self) -> String {
+        self.counts()
+            .iter()
+            .map(|(kind, count)| match kind {
+                WarningKind::Redacted => {
+                    let by_category: Vec<String> = self
+                        .redactions
+                        .iter()
+                        .map(|(category, count)| format!("{} {}", count, category))
+                        .collect();
+                    format!("  {}: {} ({})", kind, count, by_category.join(", "))
+                }
+                _ => format!("  {}: {}", kind, count),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
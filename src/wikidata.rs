@@ -0,0 +1,250 @@
+use crate::cancel::CancellationToken;
+use crate::output;
+use neo4rs::{BoltType, Graph, Query};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+const API: &str = "https://www.wikidata.org/w/api.php";
+
+/// Wikimedia asks API clients to identify themselves.
+const USER_AGENT: &str = concat!(
+    env!("CARGO_PKG_NAME"),
+    "/",
+    env!("CARGO_PKG_VERSION"),
+    " (ingredient enrichment)"
+);
+
+/// What an ingredient name resolved to on Wikidata.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entity {
+    pub qid: String,
+    /// Wikimedia Commons URL of the item's image (P18)
+    pub image: Option<String>,
+    /// "instance of" (P31)
+    pub instance_of: Vec<String>,
+    /// "subclass of" (P279) and, for plants and animals, "parent taxon"
+    /// (P171)
+    pub subclass_of: Vec<String>,
+}
+
+/// Lookups saved on disk by ingredient name, misses included, so reruns
+/// only query names they have never seen.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cache {
+    entries: HashMap<String, Option<Entity>>,
+}
+
+impl Cache {
+    pub fn load(path: &Path) -> Result<Cache, Box<dyn Error>> {
+        if !path.exists() {
+            return Ok(Cache::default());
+        }
+        Ok(serde_json::from_str(&output::read_text(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// Calls the Wikidata API through `curl`, at most one request per
+/// `interval`.
+pub struct Client {
+    interval: Duration,
+    last: Option<Instant>,
+}
+
+impl Client {
+    pub fn new(requests_per_second: f64) -> Client {
+        Client {
+            interval: Duration::from_secs_f64(1.0 / requests_per_second),
+            last: None,
+        }
+    }
+
+    async fn get(&mut self, params: &[(&str, &str)]) -> Result<Value, Box<dyn Error>> {
+        if let Some(last) = self.last {
+            tokio::time::sleep(self.interval.saturating_sub(last.elapsed())).await;
+        }
+        self.last = Some(Instant::now());
+
+        let mut curl = Command::new("curl");
+        curl.args(["--silent", "--show-error", "--fail", "--get"])
+            .args(["--user-agent", USER_AGENT]);
+        for (name, value) in params {
+            curl.arg("--data-urlencode")
+                .arg(format!("{}={}", name, value));
+        }
+        let output = curl
+            .arg(API)
+            .output()
+            .await
+            .map_err(|err| format!("cannot run curl: {}", err))?;
+        if !output.status.success() {
+            return Err(format!(
+                "Wikidata request failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+            .into());
+        }
+        Ok(serde_json::from_slice(&output.stdout)?)
+    }
+
+    /// The best English match for `name`, or `None` when Wikidata has none.
+    pub async fn resolve(&mut self, name: &str) -> Result<Option<Entity>, Box<dyn Error>> {
+        let search = self
+            .get(&[
+                ("action", "wbsearchentities"),
+                ("search", name),
+                ("language", "en"),
+                ("type", "item"),
+                ("limit", "1"),
+                ("format", "json"),
+            ])
+            .await?;
+        let Some(qid) = search["search"][0]["id"].as_str() else {
+            return Ok(None);
+        };
+
+        let entities = self
+            .get(&[
+                ("action", "wbgetentities"),
+                ("ids", qid),
+                ("props", "claims"),
+                ("format", "json"),
+            ])
+            .await?;
+        let claims = &entities["entities"][qid]["claims"];
+        let items = |property: &str| -> Vec<String> {
+            claims[property]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|claim| claim["mainsnak"]["datavalue"]["value"]["id"].as_str())
+                .map(str::to_string)
+                .collect()
+        };
+
+        let mut subclass_of = items("P279");
+        subclass_of.extend(items("P171"));
+        Ok(Some(Entity {
+            qid: qid.to_string(),
+            image: claims["P18"][0]["mainsnak"]["datavalue"]["value"]
+                .as_str()
+                .map(|file| {
+                    format!(
+                        "https://commons.wikimedia.org/wiki/Special:FilePath/{}",
+                        file.replace(' ', "_")
+                    )
+                }),
+            instance_of: items("P31"),
+            subclass_of,
+        }))
+    }
+}
+
+/// The entity for each of `names` found on Wikidata, from `cache` where it
+/// has the name unless `refresh` is set.
+async fn resolve_all(
+    client: &mut Client,
+    cache: &mut Cache,
+    names: Vec<String>,
+    refresh: bool,
+    cancel: &CancellationToken,
+) -> Result<Vec<(String, Entity)>, Box<dyn Error>> {
+    let mut found = Vec::new();
+    for name in names {
+        let entity = match cache.entries.get(&name) {
+            Some(entity) if !refresh => entity.clone(),
+            _ => {
+                cancel.check()?;
+                let entity = client.resolve(&name).await?;
+                cache.entries.insert(name.clone(), entity.clone());
+                entity
+            }
+        };
+        if let Some(entity) = entity {
+            found.push((name, entity));
+        }
+    }
+    Ok(found)
+}
+
+/// Resolves Ingredient nodes without a `wikidata_qid` (all of them with
+/// `refresh`) through the cache at `cache_path`, then the API, and sets
+/// the QID, image and taxonomy links on the ones found. The cache is saved
+/// after every batch. Returns the number of ingredients enriched.
+pub async fn enrich(
+    graph: &Graph,
+    client: &mut Client,
+    cache_path: &Path,
+    refresh: bool,
+    batch_size: usize,
+    cancel: &CancellationToken,
+) -> Result<usize, Box<dyn Error>> {
+    let mut cache = Cache::load(cache_path)?;
+    let mut last_name = String::new();
+    let mut enriched = 0;
+
+    loop {
+        cancel.check()?;
+        let query = Query::new(
+            "MATCH (i:Ingredient) WHERE i.name > $after AND ($refresh OR i.wikidata_qid IS NULL) \
+             RETURN i.name AS name ORDER BY name LIMIT $limit"
+                .to_string(),
+        )
+        .param("after", last_name.clone())
+        .param("refresh", refresh)
+        .param("limit", batch_size as i64);
+
+        let mut names = Vec::new();
+        let mut result = graph.execute(query).await?;
+        while let Some(row) = result.next().await? {
+            names.push(row.get::<String>("name")?);
+        }
+        let Some(last) = names.last() else {
+            break;
+        };
+        last_name = last.clone();
+
+        // Lookups made before a failure or cancellation are kept
+        let resolved = resolve_all(client, &mut cache, names, refresh, cancel).await;
+        cache.save(cache_path)?;
+        let rows: Vec<HashMap<String, BoltType>> = resolved?
+            .into_iter()
+            .map(|(name, entity)| {
+                let mut row = HashMap::new();
+                row.insert("name".to_string(), name.into());
+                row.insert("qid".to_string(), entity.qid.into());
+                row.insert("image".to_string(), entity.image.into());
+                row.insert("instance_of".to_string(), entity.instance_of.into());
+                row.insert("subclass_of".to_string(), entity.subclass_of.into());
+                row
+            })
+            .collect();
+
+        enriched += rows.len();
+        graph
+            .run(
+                Query::new(
+                    "UNWIND $rows AS row MATCH (i:Ingredient {name: row.name}) \
+                     SET i.wikidata_qid = row.qid, i.image_url = row.image, \
+                     i.wikidata_instance_of = row.instance_of, \
+                     i.wikidata_subclass_of = row.subclass_of"
+                        .to_string(),
+                )
+                .param("rows", rows),
+            )
+            .await?;
+        println!("enriched {} ingredients from Wikidata", enriched);
+    }
+
+    Ok(enriched)
+}